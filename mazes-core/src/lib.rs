@@ -0,0 +1,172 @@
+//! The grid pathfinding and maze generation of mazes for targets without the standard library, e.g. robots.
+//! Only an allocator is needed.
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+use alloc::{collections::BinaryHeap, vec, vec::Vec};
+use core::cmp::Reverse;
+
+use rand::{seq::SliceRandom, Rng};
+
+/// A map that only needs an allocator, for targets without the standard library such as robots:
+/// the cost of entering every block row by row, 0 for walls.
+///
+/// Portals, keys, one-way blocks and crossings don't exist here. The `Map` of mazes converts into it
+/// unless it has any of those blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostGrid {
+    width: usize,
+    height: usize,
+    costs: Vec<u8>,
+}
+
+/// The cheapest path through a [CostGrid], see [CostGrid::find_path]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridPath {
+    pub cost: u32,
+    /// The blocks from start to goal, both inclusive
+    pub steps: Vec<(usize, usize)>,
+}
+
+impl CostGrid {
+    /// A grid of walls only
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            costs: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The cost of entering the block, 0 for a wall and `None` outside of the grid
+    pub fn cost(&self, x: usize, y: usize) -> Option<u8> {
+        (x < self.width && y < self.height).then(|| self.costs[y * self.width + x])
+    }
+
+    /// Sets the cost of entering the block, 0 for a wall. Blocks outside of the grid are ignored.
+    pub fn set_cost(&mut self, x: usize, y: usize, cost: u8) {
+        if x < self.width && y < self.height {
+            self.costs[y * self.width + x] = cost;
+        }
+    }
+
+    /// Carves a perfect maze of `width` × `height` cells with the recursive backtracker,
+    /// like `generate_maze` of mazes. The grid has `2 * width + 1` × `2 * height + 1` blocks,
+    /// the cells and the passages between them cost 1.
+    pub fn maze(width: usize, height: usize, rng: &mut impl Rng) -> Self {
+        let mut grid = Self::new(2 * width + 1, 2 * height + 1);
+        if width == 0 || height == 0 {
+            return grid;
+        }
+        let mut visited = vec![false; width * height];
+        let mut stack = vec![(0usize, 0usize)];
+        visited[0] = true;
+        grid.set_cost(1, 1, 1);
+
+        while let Some(&(x, y)) = stack.last() {
+            let mut neighbors = [(0, -1), (1, 0), (0, 1), (-1, 0)]
+                .into_iter()
+                .filter_map(|(dx, dy)| {
+                    let next = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+                    (next.0 < width && next.1 < height && !visited[next.1 * width + next.0])
+                        .then_some(next)
+                })
+                .collect::<Vec<_>>();
+            neighbors.shuffle(rng);
+            let Some(&(next_x, next_y)) = neighbors.first() else {
+                stack.pop();
+                continue;
+            };
+            visited[next_y * width + next_x] = true;
+            // The passage lies between both cells
+            grid.set_cost(x + next_x + 1, y + next_y + 1, 1);
+            grid.set_cost(2 * next_x + 1, 2 * next_y + 1, 1);
+            stack.push((next_x, next_y));
+        }
+        grid
+    }
+
+    /// Finds the cheapest path with A*, estimating the cost by the Manhattan distance, since every step costs at least 1.
+    /// `None` if there is none or if the start or goal is a wall or outside of the grid.
+    pub fn find_path(&self, start: (usize, usize), goal: (usize, usize)) -> Option<GridPath> {
+        let walkable = |(x, y)| self.cost(x, y).is_some_and(|cost| cost > 0);
+        if !walkable(start) || !walkable(goal) {
+            return None;
+        }
+        let index = |(x, y): (usize, usize)| y * self.width + x;
+        let estimate = |(x, y): (usize, usize)| (x.abs_diff(goal.0) + y.abs_diff(goal.1)) as u32;
+
+        let mut costs = vec![u32::MAX; self.costs.len()];
+        let mut previous = vec![None; self.costs.len()];
+        let mut queue = BinaryHeap::new();
+        costs[index(start)] = 0;
+        queue.push(Reverse((estimate(start), 0, start)));
+
+        while let Some(Reverse((_, cost, position))) = queue.pop() {
+            if position == goal {
+                let mut steps = vec![goal];
+                while let Some(step) = previous[index(steps[steps.len() - 1])] {
+                    steps.push(step);
+                }
+                steps.reverse();
+                return Some(GridPath { cost, steps });
+            }
+            // Outdated entries of blocks that were reached cheaper in the meantime
+            if cost > costs[index(position)] {
+                continue;
+            }
+            let (x, y) = position;
+            for (dx, dy) in [(0, -1), (1, 0), (0, 1), (-1, 0)] {
+                let (Some(next_x), Some(next_y)) =
+                    (x.checked_add_signed(dx), y.checked_add_signed(dy))
+                else {
+                    continue;
+                };
+                let next = (next_x, next_y);
+                let Some(step_cost) = self.cost(next_x, next_y).filter(|cost| *cost > 0) else {
+                    continue;
+                };
+                let next_cost = cost + step_cost as u32;
+                if next_cost < costs[index(next)] {
+                    costs[index(next)] = next_cost;
+                    previous[index(next)] = Some(position);
+                    queue.push(Reverse((next_cost + estimate(next), next_cost, next)));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::mock::StepRng;
+
+    use super::*;
+
+    #[test]
+    fn mazes_connect_every_cell_once() {
+        let grid = CostGrid::maze(5, 4, &mut StepRng::new(7, 0x9E37_79B9_7F4A_7C15));
+
+        assert_eq!((grid.width(), grid.height()), (11, 9));
+        // Every cell and one passage less than there are cells
+        assert_eq!(
+            grid.costs.iter().filter(|cost| **cost > 0).count(),
+            2 * 20 - 1
+        );
+        let path = grid.find_path((1, 1), (9, 7)).unwrap();
+        assert_eq!(path.steps.first(), Some(&(1, 1)));
+        assert_eq!(path.steps.last(), Some(&(9, 7)));
+        assert_eq!(path.cost as usize, path.steps.len() - 1);
+        assert_eq!(grid.find_path((0, 0), (9, 7)), None);
+    }
+}
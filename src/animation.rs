@@ -0,0 +1,271 @@
+use std::{io::Write, time::Duration};
+
+use anyhow::anyhow;
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, GenericImage, Rgba, RgbaImage,
+};
+
+use crate::{
+    layers::{BACKGROUND, EXPLORED, FRONTIER, MARKERS, PATH},
+    Layers, Map, RenderOptions, SearchTrace,
+};
+
+/// How long the last frame with the whole exploration is shown
+const LAST_FRAME_DELAY: Duration = Duration::from_secs(3);
+
+/// The file format of an animation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnimationFormat {
+    #[default]
+    Gif,
+    /// Animated png, which keeps every color unlike gif
+    Apng,
+}
+
+/// How the exploration of a search is animated, see [Map::write_exploration]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationOptions {
+    pub format: AnimationFormat,
+    /// How many expansions each frame advances, 1 to show every step
+    pub expansions_per_frame: usize,
+    /// How long each frame is shown, except the last one which stays for three seconds
+    pub frame_delay: Duration,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            format: AnimationFormat::default(),
+            expansions_per_frame: 1,
+            frame_delay: Duration::from_millis(50),
+        }
+    }
+}
+
+impl Layers {
+    /// The search after `step` expansions in the default colors: the explored blocks, the frontier,
+    /// the path to the block expanded last and the start as a marker
+    pub fn at_step(trace: &SearchTrace, step: usize) -> Self {
+        let path = trace.path_at(step);
+        Self::default()
+            .explored(trace.explored(step), EXPLORED)
+            .frontier(trace.frontier(step), FRONTIER)
+            .markers(path.first().copied(), MARKERS)
+            .path(path, PATH)
+    }
+}
+
+impl Map {
+    /// Writes an animation of how the search explored the map, one frame every
+    /// [expansions_per_frame](AnimationOptions::expansions_per_frame) expansions as drawn by [Layers::at_step].
+    /// The last frame shows the whole exploration and the path to the destination.
+    pub fn write_exploration(
+        &self,
+        trace: &SearchTrace,
+        writer: impl Write,
+        animation: &AnimationOptions,
+        options: &RenderOptions,
+    ) -> anyhow::Result<()> {
+        self.write_race(std::slice::from_ref(trace), writer, animation, options)
+    }
+
+    /// Like [write_exploration](Self::write_exploration), but with the explorations of several searches
+    /// next to each other, all at the same number of expansions. Searches that are done keep their last frame,
+    /// so the ones that expand fewer states visibly finish first.
+    pub fn write_race(
+        &self,
+        traces: &[SearchTrace],
+        writer: impl Write,
+        animation: &AnimationOptions,
+        options: &RenderOptions,
+    ) -> anyhow::Result<()> {
+        if animation.expansions_per_frame == 0 {
+            return Err(anyhow!("Each frame must at least advance one expansion"));
+        }
+        if traces.is_empty() {
+            return Err(anyhow!("Please specify at least one search"));
+        }
+        let count = traces
+            .iter()
+            .map(SearchTrace::expansion_count)
+            .max()
+            .unwrap_or(0);
+        let mut steps: Vec<usize> = (0..count).step_by(animation.expansions_per_frame).collect();
+        steps.push(count);
+
+        let (panel_width, height) = self.image_size(options);
+        // The panels are separated by a gap as wide as a block
+        let gap = options.block_width as u32;
+        let width = traces.len() as u32 * (panel_width + gap) - gap;
+        let frames = steps.iter().enumerate().map(|(i, step)| {
+            let mut frame = RgbaImage::from_pixel(width, height, Rgba(BACKGROUND));
+            for (j, trace) in traces.iter().enumerate() {
+                let panel = self
+                    .layered_image(&Layers::at_step(trace, *step), options)
+                    .ok_or(anyhow!("The map is too large to be animated"))?;
+                frame.copy_from(&panel, j as u32 * (panel_width + gap), 0)?;
+            }
+            let delay = if i + 1 == steps.len() {
+                LAST_FRAME_DELAY
+            } else {
+                animation.frame_delay
+            };
+            Ok((frame, delay))
+        });
+        match animation.format {
+            AnimationFormat::Gif => write_gif(writer, frames),
+            AnimationFormat::Apng => write_apng(writer, (width, height), steps.len(), frames),
+        }
+    }
+}
+
+fn write_gif(
+    writer: impl Write,
+    frames: impl Iterator<Item = anyhow::Result<(RgbaImage, Duration)>>,
+) -> anyhow::Result<()> {
+    let mut encoder = GifEncoder::new_with_speed(writer, 30);
+    encoder.set_repeat(Repeat::Infinite)?;
+    for frame in frames {
+        let (image, delay) = frame?;
+        encoder.encode_frame(Frame::from_parts(
+            image,
+            0,
+            0,
+            Delay::from_saturating_duration(delay),
+        ))?;
+    }
+    Ok(())
+}
+
+fn write_apng(
+    writer: impl Write,
+    (width, height): (u32, u32),
+    frame_count: usize,
+    frames: impl Iterator<Item = anyhow::Result<(RgbaImage, Duration)>>,
+) -> anyhow::Result<()> {
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frame_count as u32, 0)?;
+    let mut png_writer = encoder.write_header()?;
+    for frame in frames {
+        let (image, delay) = frame?;
+        let millis = delay.as_millis().min(u16::MAX as u128) as u16;
+        png_writer.set_frame_delay(millis, 1000)?;
+        png_writer.write_image_data(image.as_raw())?;
+    }
+    png_writer.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{codecs::gif::GifDecoder, AnimationDecoder};
+
+    use super::*;
+    use crate::{SearchOptions, SolveAlgorithm};
+
+    fn trace() -> (Map, SearchTrace) {
+        let map = Map::from_rows(&["....", ".##.", "...."]);
+        let (start, goal) = (map.get_block(0, 0).unwrap(), map.get_block(3, 2).unwrap());
+        let (_, trace) = SolveAlgorithm::AStar
+            .solve_traced(&map, start, goal, &SearchOptions::default())
+            .unwrap();
+        (map, trace)
+    }
+
+    #[test]
+    fn gifs_have_a_frame_every_few_expansions() {
+        let (map, trace) = trace();
+        let animation = AnimationOptions {
+            expansions_per_frame: 2,
+            ..Default::default()
+        };
+        let mut gif = vec![];
+
+        map.write_exploration(&trace, &mut gif, &animation, &RenderOptions::default())
+            .unwrap();
+
+        let frames = GifDecoder::new(std::io::Cursor::new(gif))
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        assert_eq!(frames.len(), trace.expansion_count().div_ceil(2) + 1);
+        let last = map
+            .layered_image(
+                &Layers::at_step(&trace, trace.expansion_count()),
+                &RenderOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            frames.last().unwrap().buffer().dimensions(),
+            last.dimensions()
+        );
+    }
+
+    #[test]
+    fn races_show_the_searches_next_to_each_other() {
+        let (map, astar) = trace();
+        let (start, goal) = (map.get_block(0, 0).unwrap(), map.get_block(3, 2).unwrap());
+        let (_, dijkstra) = SolveAlgorithm::Dijkstra
+            .solve_traced(&map, start, goal, &SearchOptions::default())
+            .unwrap();
+        let options = RenderOptions {
+            block_width: 2,
+            border_width: 1,
+            ..Default::default()
+        };
+        let mut gif = vec![];
+
+        map.write_race(
+            &[astar.clone(), dijkstra.clone()],
+            &mut gif,
+            &AnimationOptions::default(),
+            &options,
+        )
+        .unwrap();
+
+        let frames = GifDecoder::new(std::io::Cursor::new(gif))
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        let longest = astar.expansion_count().max(dijkstra.expansion_count());
+        assert_eq!(frames.len(), longest + 1);
+        // Two panels of 4 blocks of 2 pixels and 3 borders with a gap of one block in between
+        assert_eq!(frames[0].buffer().dimensions(), (2 * 11 + 2, 8));
+        assert!(map
+            .write_race(&[], vec![], &AnimationOptions::default(), &options)
+            .is_err());
+    }
+
+    #[test]
+    fn apngs_keep_every_frame() {
+        let (map, trace) = trace();
+        let animation = AnimationOptions {
+            format: AnimationFormat::Apng,
+            ..Default::default()
+        };
+        let mut apng = vec![];
+
+        map.write_exploration(&trace, &mut apng, &animation, &RenderOptions::default())
+            .unwrap();
+
+        let reader = png::Decoder::new(apng.as_slice()).read_info().unwrap();
+        let control = reader.info().animation_control.unwrap();
+        assert_eq!(control.num_frames as usize, trace.expansion_count() + 1);
+        assert!(map
+            .write_exploration(
+                &trace,
+                vec![],
+                &AnimationOptions {
+                    expansions_per_frame: 0,
+                    ..Default::default()
+                },
+                &RenderOptions::default()
+            )
+            .is_err());
+    }
+}
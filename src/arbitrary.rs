@@ -0,0 +1,192 @@
+use proptest::{
+    arbitrary::Arbitrary,
+    collection::vec,
+    prelude::*,
+    sample::select,
+    strategy::{BoxedStrategy, Just},
+};
+
+use crate::{
+    generate, BlockType, Direction, GenOptions, KeyColor, Map, MazeAlgorithm, MazeMap, Region,
+    SearchOptions,
+};
+
+/// The largest width and height of arbitrary maps in blocks
+const MAX_BLOCKS: usize = 12;
+/// The largest width and height of arbitrary mazes in cells
+const MAX_CELLS: usize = 8;
+
+/// Terrain, walls, keys, doors and one-way blocks, but neither portals nor weave crossings,
+/// which are only valid in pairs and between passages.
+fn block_type() -> impl Strategy<Value = BlockType> {
+    let key_color = select(vec![KeyColor::Purple, KeyColor::Green, KeyColor::Blue]);
+    prop_oneof![
+        8 => Just(BlockType::Green),
+        4 => Just(BlockType::Black),
+        2 => select(vec![BlockType::Orange, BlockType::Blue, BlockType::Yellow]),
+        1 => key_color.clone().prop_map(BlockType::Key),
+        1 => key_color.prop_map(BlockType::Door),
+        1 => select(Direction::ALL.to_vec()).prop_map(BlockType::OneWay),
+    ]
+}
+
+fn region() -> impl Strategy<Value = Region> {
+    (0..MAX_BLOCKS, 0..MAX_BLOCKS, 0..MAX_BLOCKS, 0..MAX_BLOCKS)
+        .prop_map(|(x1, y1, x2, y2)| Region::new(x1, y1, x2, y2))
+}
+
+/// Maps of up to `max_blocks` x `max_blocks` blocks
+fn maps(max_blocks: usize) -> impl Strategy<Value = Map> {
+    (1..=max_blocks, 1..=max_blocks)
+        .prop_flat_map(|(width, height)| vec(vec(block_type(), width), height))
+        .prop_map(|rows| Map::from_fn(rows[0].len(), rows.len(), |x, y| rows[y][x]))
+}
+
+/// Maps of up to 12 x 12 blocks
+impl Arbitrary for Map {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Map>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        maps(MAX_BLOCKS).boxed()
+    }
+}
+
+/// Mazes of up to 8 x 8 cells of every algorithm, with and without loops.
+/// Each maze is generated from a seed, so shrinking keeps the maze.
+impl Arbitrary for MazeMap {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<MazeMap>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            1..=MAX_CELLS,
+            1..=MAX_CELLS,
+            select(MazeAlgorithm::ALL.to_vec()),
+            proptest::option::of(0.0..0.5),
+            any::<u64>(),
+        )
+            .prop_map(|(width, height, algorithm, loop_prob, seed)| {
+                let options = GenOptions {
+                    loop_prob,
+                    seed: Some(seed),
+                    ..Default::default()
+                };
+                generate(width, height, algorithm, &options)
+                    .expect("Mazes without a mask can always be generated")
+            })
+            .boxed()
+    }
+}
+
+/// Turn penalties and avoided and penalized regions within the size of arbitrary maps.
+/// Only options that keep the search optimal, so neither heuristic weights nor budgets.
+impl Arbitrary for SearchOptions {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<SearchOptions>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            0..5u32,
+            vec(region(), 0..2),
+            vec((region(), 0..10u32), 0..3),
+        )
+            .prop_map(|(turn_penalty, avoided, penalized)| {
+                let options = SearchOptions::default().turn_penalty(turn_penalty);
+                let options = avoided
+                    .into_iter()
+                    .fold(options, |options, region| options.avoid(region));
+                penalized
+                    .into_iter()
+                    .fold(options, |options, (region, cost)| {
+                        options.penalize(region, cost)
+                    })
+            })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Block, MazeError, SolveAlgorithm};
+
+    /// The cost of the cheapest path, checking that the solution is valid or that there really is no path
+    fn cheapest_cost(
+        algorithm: SolveAlgorithm,
+        map: &Map,
+        (start, destination): (Block, Block),
+        options: &SearchOptions,
+    ) -> Result<Option<u32>, TestCaseError> {
+        match algorithm.solve(map, start, destination, options) {
+            Ok(solution) => {
+                prop_assert!(solution.verify(map).is_ok());
+                Ok(Some(solution.cost()))
+            }
+            Err(error) => {
+                prop_assert_eq!(error.downcast_ref(), Some(&MazeError::NoPath));
+                Ok(None)
+            }
+        }
+    }
+
+    /// Two walkable blocks of the map, wrapping the positions around its size
+    fn endpoints(
+        map: &Map,
+        start: (usize, usize),
+        destination: (usize, usize),
+    ) -> Option<(Block, Block)> {
+        let block = |(x, y): (usize, usize)| map.get_block(x % map.width(), y % map.height());
+        Some((block(start)?, block(destination)?))
+            .filter(|(start, destination)| start.is_walkable() && destination.is_walkable())
+    }
+
+    proptest! {
+        #[test]
+        fn a_star_and_dijkstra_find_the_same_cost(
+            map: Map,
+            options: SearchOptions,
+            start: (usize, usize),
+            destination: (usize, usize),
+        ) {
+            let endpoints = endpoints(&map, start, destination);
+            prop_assume!(endpoints.is_some());
+            let endpoints = endpoints.unwrap();
+
+            prop_assert_eq!(
+                cheapest_cost(SolveAlgorithm::AStar, &map, endpoints, &options)?,
+                cheapest_cost(SolveAlgorithm::Dijkstra, &map, endpoints, &options)?
+            );
+        }
+
+        // IDA* tries every path when there is none, so it only gets tiny maps with a path
+        #[test]
+        fn ida_star_finds_the_same_cost_as_a_star(
+            map in maps(4),
+            options: SearchOptions,
+            start: (usize, usize),
+            destination: (usize, usize),
+        ) {
+            let endpoints = endpoints(&map, start, destination);
+            prop_assume!(endpoints.is_some());
+            let endpoints = endpoints.unwrap();
+
+            let cost = cheapest_cost(SolveAlgorithm::AStar, &map, endpoints, &options)?;
+            prop_assume!(cost.is_some());
+
+            prop_assert_eq!(
+                cheapest_cost(SolveAlgorithm::IdaStar, &map, endpoints, &options)?,
+                cost
+            );
+        }
+
+        #[test]
+        fn mazes_connect_every_cell(maze: MazeMap) {
+            let loops = maze.count_loops();
+            prop_assert_eq!(maze.is_perfect(), loops == 0);
+            let map = Map::from(maze);
+
+            prop_assert_eq!(map.components().len(), 1);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    str::FromStr,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+
+use crate::{
+    Block, CancellationToken, Map, MazeError, PackedMap, SearchOptions, SolveAlgorithm, SolveReport,
+};
+
+/// A named combination of search algorithm and options that [benchmark] compares
+#[derive(Debug, Clone)]
+pub struct Solver {
+    pub name: String,
+    pub algorithm: SolveAlgorithm,
+    pub options: SearchOptions,
+    /// Searches a [PackedMap] copy of the map instead of the map itself
+    pub packed: bool,
+}
+
+impl Solver {
+    pub fn new(name: &str, algorithm: SolveAlgorithm, options: SearchOptions) -> Self {
+        Self {
+            name: name.to_string(),
+            algorithm,
+            options,
+            packed: false,
+        }
+    }
+
+    /// The same solver on a [PackedMap]
+    pub fn packed(mut self) -> Self {
+        self.packed = true;
+        self
+    }
+
+    /// Every algorithm, A* additionally with weighted and greedy heuristics and on a [PackedMap]
+    pub fn all() -> Vec<Solver> {
+        vec![
+            Solver::new("astar", SolveAlgorithm::AStar, SearchOptions::default()),
+            Solver::new(
+                "astar w=1.5",
+                SolveAlgorithm::AStar,
+                SearchOptions::default().weight(1.5),
+            ),
+            Solver::new(
+                "astar w=3",
+                SolveAlgorithm::AStar,
+                SearchOptions::default().weight(3.0),
+            ),
+            Solver::new(
+                "greedy",
+                SolveAlgorithm::AStar,
+                SearchOptions::default().greedy(),
+            ),
+            Solver::new("idastar", SolveAlgorithm::IdaStar, SearchOptions::default()),
+            Solver::new(
+                "astar packed",
+                SolveAlgorithm::AStar,
+                SearchOptions::default(),
+            )
+            .packed(),
+        ]
+    }
+}
+
+/// Parses the name of a [SolveAlgorithm] or `greedy` for greedy best-first search
+impl FromStr for Solver {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "greedy" {
+            return Ok(Solver::new(
+                s,
+                SolveAlgorithm::AStar,
+                SearchOptions::default().greedy(),
+            ));
+        }
+        let algorithm = s
+            .parse::<SolveAlgorithm>()
+            .map_err(|error| anyhow!("{error} or greedy"))?;
+        Ok(Solver::new(s, algorithm, SearchOptions::default()))
+    }
+}
+
+/// How one [Solver] did on one map
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub solver: String,
+    pub duration: Duration,
+    /// The cost of the path and the statistics of the search, `None` if there is no path or the search timed out
+    pub solution: Option<(u32, SolveReport)>,
+    pub timed_out: bool,
+}
+
+/// Runs every solver from `start` to `goal` and measures how long it takes.
+/// Searches that take longer than `timeout` are cancelled.
+pub fn benchmark(
+    map: &Map,
+    start: Block,
+    goal: Block,
+    solvers: &[Solver],
+    timeout: Duration,
+) -> Vec<BenchResult> {
+    // Packing isn't part of the measured time
+    let packed = solvers
+        .iter()
+        .any(|solver| solver.packed)
+        .then(|| PackedMap::from(map));
+    solvers
+        .iter()
+        .map(|solver| {
+            let token = CancellationToken::new();
+            let options = solver.options.clone().cancellation(token.clone());
+            let (done, finished) = mpsc::channel::<()>();
+
+            std::thread::scope(|scope| {
+                scope.spawn(move || {
+                    // Also cancels once the search is done, which doesn't matter anymore
+                    let _ = finished.recv_timeout(timeout);
+                    token.cancel();
+                });
+                let started = Instant::now();
+                let result = match &packed {
+                    Some(packed) if solver.packed => solver
+                        .algorithm
+                        .solve_provider(packed, start, goal, &options)
+                        .map(|solution| (solution.cost(), solution.report().clone())),
+                    _ => solver
+                        .algorithm
+                        .solve(map, start, goal, &options)
+                        .map(|solution| (solution.cost(), solution.report().clone())),
+                };
+                let duration = started.elapsed();
+                drop(done);
+
+                BenchResult {
+                    solver: solver.name.clone(),
+                    duration,
+                    timed_out: result.as_ref().err().and_then(|e| e.downcast_ref())
+                        == Some(&MazeError::Cancelled),
+                    solution: result.ok(),
+                }
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::HeuristicWeight;
+
+    #[test]
+    fn optimal_solvers_agree_on_the_cost() {
+        let map = Map::from_rows(&["....#", ".##.#", ".b...", "..o#."]);
+        let start = map.get_block(0, 0).unwrap();
+        let goal = map.get_block(4, 3).unwrap();
+
+        let results = benchmark(&map, start, goal, &Solver::all(), Duration::from_secs(10));
+
+        let optimal_costs = results
+            .iter()
+            .filter_map(|result| result.solution.as_ref())
+            .filter(|(_, report)| report.is_optimal())
+            .map(|(cost, _)| *cost)
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), Solver::all().len());
+        assert!(results.iter().all(|result| !result.timed_out));
+        assert_eq!(optimal_costs.len(), 3);
+        assert!(optimal_costs.iter().all(|cost| *cost == optimal_costs[0]));
+    }
+
+    #[test]
+    fn solvers_parse_from_their_names() {
+        let greedy: Solver = "greedy".parse().unwrap();
+        assert_eq!(greedy.algorithm, SolveAlgorithm::AStar);
+        assert!(matches!(greedy.options.weight, HeuristicWeight::Greedy));
+        let dijkstra: Solver = "dijkstra".parse().unwrap();
+        assert_eq!(
+            (dijkstra.name.as_str(), dijkstra.algorithm),
+            ("dijkstra", SolveAlgorithm::Dijkstra)
+        );
+        assert!("bfs".parse::<Solver>().is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt::Display;
+
+use itertools::Itertools;
+
+use crate::{
+    directions::{compass, key_name, portal_name},
+    region_penalty, BlockType, Solution,
+};
+
+/// How much of the cost of a solution one type of block caused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerrainCost {
+    pub block_type: BlockType,
+    /// How many blocks of this type the path enters, the start doesn't count
+    pub blocks: usize,
+    pub cost: u32,
+}
+
+/// Where the cost of a solution comes from, see [Solution::cost_breakdown]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostBreakdown {
+    /// Every type of block the path enters, the most expensive first
+    pub terrain: Vec<TerrainCost>,
+    /// The extra cost of entering penalized regions
+    pub regions: u32,
+    /// The extra cost of turning
+    pub turns: u32,
+}
+
+impl CostBreakdown {
+    pub fn total(&self) -> u32 {
+        self.terrain.iter().map(|terrain| terrain.cost).sum::<u32>() + self.regions + self.turns
+    }
+
+    /// The breakdown as a JSON object with the terrain as an array of `{"block_type": .., "blocks": .., "cost": ..}`
+    pub fn to_json(&self) -> String {
+        let terrain = self
+            .terrain
+            .iter()
+            .map(|terrain| {
+                format!(
+                    r#"{{"block_type": "{}", "blocks": {}, "cost": {}}}"#,
+                    terrain_name(terrain.block_type),
+                    terrain.blocks,
+                    terrain.cost
+                )
+            })
+            .join(", ");
+        format!(
+            r#"{{"terrain": [{terrain}], "regions": {}, "turns": {}}}"#,
+            self.regions, self.turns
+        )
+    }
+}
+
+/// The name of a block type as in palettes, colors and directions spelled out
+fn terrain_name(block_type: BlockType) -> String {
+    match block_type {
+        BlockType::White => "white".to_string(),
+        BlockType::Black => "black".to_string(),
+        BlockType::Orange => "orange".to_string(),
+        BlockType::Blue => "blue".to_string(),
+        BlockType::Green => "green".to_string(),
+        BlockType::Yellow => "yellow".to_string(),
+        BlockType::Border => "border".to_string(),
+        BlockType::Solution => "solution".to_string(),
+        BlockType::StairsUp => "stairs-up".to_string(),
+        BlockType::StairsDown => "stairs-down".to_string(),
+        BlockType::BridgeHorizontal => "bridge-horizontal".to_string(),
+        BlockType::BridgeVertical => "bridge-vertical".to_string(),
+        BlockType::Key(color) => format!("{}-key", key_name(color)),
+        BlockType::Door(color) => format!("{}-door", key_name(color)),
+        BlockType::Portal(color) => format!("{}-portal", portal_name(color)),
+        BlockType::OneWay(direction) => format!("one-way-{}", compass(direction)),
+    }
+}
+
+impl Display for CostBreakdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let terrain = self.terrain.iter().map(|terrain| {
+            format!(
+                "{} {} ({} blocks)",
+                terrain_name(terrain.block_type),
+                terrain.cost,
+                terrain.blocks
+            )
+        });
+        let penalties = [("regions", self.regions), ("turns", self.turns)]
+            .into_iter()
+            .filter(|(_, cost)| *cost > 0)
+            .map(|(name, cost)| format!("{name} {cost}"));
+        let parts = terrain.chain(penalties).join(", ");
+        if parts.is_empty() {
+            f.write_str("nothing")
+        } else {
+            f.write_str(&parts)
+        }
+    }
+}
+
+impl Solution {
+    /// Splits the cost into the cost of each type of block the path enters, the region penalties and the turns
+    pub fn cost_breakdown(&self) -> CostBreakdown {
+        let mut terrain: Vec<TerrainCost> = vec![];
+        let mut regions = 0;
+        for block in self.path().iter().skip(1) {
+            regions += region_penalty(&self.region_penalties, *block);
+            let cost = block.speed() as u32;
+            match terrain
+                .iter_mut()
+                .find(|terrain| terrain.block_type == block.block_type())
+            {
+                Some(terrain) => {
+                    terrain.blocks += 1;
+                    terrain.cost += cost;
+                }
+                None => terrain.push(TerrainCost {
+                    block_type: block.block_type(),
+                    blocks: 1,
+                    cost,
+                }),
+            }
+        }
+        terrain.sort_by_key(|terrain| std::cmp::Reverse(terrain.cost));
+        let terrain_cost: u32 = terrain.iter().map(|terrain| terrain.cost).sum();
+        CostBreakdown {
+            terrain,
+            regions,
+            // Whatever the terrain and the regions don't explain was paid for turning
+            turns: self.cost().saturating_sub(terrain_cost + regions),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{a_star, a_star_with, Map, Region, SearchOptions};
+
+    #[test]
+    fn the_cost_is_split_by_terrain() {
+        let map = Map::from_rows(&[".bb.o"]);
+        let solution = a_star(
+            &map,
+            map.get_block(0, 0).unwrap(),
+            map.get_block(4, 0).unwrap(),
+        )
+        .unwrap();
+
+        let breakdown = solution.cost_breakdown();
+
+        assert_eq!(breakdown.total(), solution.cost());
+        assert_eq!(
+            breakdown.terrain,
+            [
+                TerrainCost {
+                    block_type: BlockType::Orange,
+                    blocks: 1,
+                    cost: 5
+                },
+                TerrainCost {
+                    block_type: BlockType::Blue,
+                    blocks: 2,
+                    cost: 4
+                },
+                TerrainCost {
+                    block_type: BlockType::Green,
+                    blocks: 1,
+                    cost: 1
+                },
+            ]
+        );
+        assert_eq!(
+            breakdown.to_string(),
+            "orange 5 (1 blocks), blue 4 (2 blocks), green 1 (1 blocks)"
+        );
+    }
+
+    #[test]
+    fn penalties_are_listed_separately() {
+        let map = Map::from_rows(&["...", "...", "..."]);
+        let options = SearchOptions::default()
+            .turn_penalty(3)
+            .penalize(Region::new(1, 0, 1, 2), 2);
+        let solution = a_star_with(
+            &map,
+            map.get_block(0, 0).unwrap(),
+            map.get_block(2, 2).unwrap(),
+            &options,
+        )
+        .unwrap();
+
+        let breakdown = solution.cost_breakdown();
+
+        assert_eq!(breakdown.total(), solution.cost());
+        assert_eq!((breakdown.regions, breakdown.turns), (2, 3));
+        assert!(breakdown
+            .to_json()
+            .ends_with(r#""regions": 2, "turns": 3}"#));
+    }
+}
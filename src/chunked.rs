@@ -0,0 +1,242 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Context};
+
+use crate::{BlockType, MapProvider};
+
+const MAGIC: &[u8; 4] = b"MZCK";
+const VERSION: u8 = 1;
+/// Magic bytes, version, chunk size, width and height
+const HEADER_LEN: u64 = 4 + 1 + 3 * 4;
+
+/// A map stored in a file as square chunks of blocks, of which only the most recently used ones are kept in memory.
+/// It is read through [MapProvider], so [a_star_provider](crate::a_star_provider) can search worlds
+/// that are far too large for a [Map](crate::Map).
+///
+/// Each block takes a single byte on disk and in memory. Portals are not paired up,
+/// since that would require reading the whole map, so they behave like ordinary blocks.
+///
+/// Blocks of chunks that fail to load, e.g. because the file changed after opening it, are outside of the map.
+/// The error is kept until [take_read_error](Self::take_read_error) is called.
+pub struct ChunkedMap {
+    file: RefCell<File>,
+    width: usize,
+    height: usize,
+    chunk_size: usize,
+    max_loaded_chunks: usize,
+    cache: RefCell<ChunkCache>,
+    read_error: RefCell<Option<anyhow::Error>>,
+}
+
+#[derive(Default)]
+struct ChunkCache {
+    chunks: HashMap<(usize, usize), Vec<u8>>,
+    /// The loaded chunks, the least recently used one first
+    order: VecDeque<(usize, usize)>,
+}
+
+impl ChunkedMap {
+    /// Writes the blocks of `map` into the chunked file format at `path`, in chunks of `chunk_size` × `chunk_size` blocks
+    pub fn write(map: &impl MapProvider, path: &Path, chunk_size: usize) -> anyhow::Result<()> {
+        if chunk_size == 0 {
+            return Err(anyhow!("The chunk size must be at least 1"));
+        }
+        let as_u32 = |value: usize| {
+            u32::try_from(value).map_err(|_| anyhow!("The map is too large for the chunked format"))
+        };
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&[VERSION])?;
+        for value in [chunk_size, map.width(), map.height()] {
+            file.write_all(&as_u32(value)?.to_le_bytes())?;
+        }
+
+        for chunk_y in 0..map.height().div_ceil(chunk_size) {
+            for chunk_x in 0..map.width().div_ceil(chunk_size) {
+                for y in chunk_y * chunk_size..(chunk_y + 1) * chunk_size {
+                    let row = (chunk_x * chunk_size..(chunk_x + 1) * chunk_size).map(|x| {
+                        // Chunks on the right and bottom edge are padded with walls
+                        map.block_type(x, y).unwrap_or(BlockType::Black).to_byte()
+                    });
+                    file.write_all(&row.collect::<Vec<_>>())?;
+                }
+            }
+        }
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Opens a map written by [write](Self::write). At most `max_loaded_chunks` chunks are kept in memory.
+    pub fn open(path: &Path, max_loaded_chunks: usize) -> anyhow::Result<Self> {
+        let mut file = File::open(path)
+            .with_context(|| format!("Failed to open the chunked map {}", path.display()))?;
+        let mut header = [0; HEADER_LEN as usize];
+        file.read_exact(&mut header)
+            .map_err(|_| anyhow!("The file is not a chunked map"))?;
+        if &header[..4] != MAGIC {
+            return Err(anyhow!("The file is not a chunked map"));
+        }
+        if header[4] != VERSION {
+            return Err(anyhow!("Unsupported chunked map version {}", header[4]));
+        }
+        let [chunk_size, width, height] = [5, 9, 13].map(|offset| {
+            u32::from_le_bytes(header[offset..offset + 4].try_into().expect("4 bytes")) as usize
+        });
+        if chunk_size == 0 {
+            return Err(anyhow!("The file is not a chunked map"));
+        }
+
+        let map = Self {
+            file: RefCell::new(file),
+            width,
+            height,
+            chunk_size,
+            max_loaded_chunks: max_loaded_chunks.max(1),
+            cache: RefCell::default(),
+            read_error: RefCell::default(),
+        };
+        let chunk_count = width.div_ceil(chunk_size) * height.div_ceil(chunk_size);
+        let expected_len = HEADER_LEN + (chunk_count * chunk_size * chunk_size) as u64;
+        if map.file.borrow().metadata()?.len() != expected_len {
+            return Err(anyhow!("The chunked map is truncated"));
+        }
+        Ok(map)
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// How many chunks are currently in memory
+    pub fn loaded_chunks(&self) -> usize {
+        self.cache.borrow().chunks.len()
+    }
+
+    /// The first error of reading a chunk since the last call, if any
+    pub fn take_read_error(&self) -> Option<anyhow::Error> {
+        self.read_error.borrow_mut().take()
+    }
+
+    /// Reads the blocks of a chunk from the file, row by row
+    fn read_chunk(&self, chunk: (usize, usize)) -> anyhow::Result<Vec<u8>> {
+        let len = self.chunk_size * self.chunk_size;
+        let index = chunk.1 * self.width.div_ceil(self.chunk_size) + chunk.0;
+        let mut bytes = vec![0; len];
+        let mut file = self.file.borrow_mut();
+        file.seek(SeekFrom::Start(HEADER_LEN + (index * len) as u64))?;
+        file.read_exact(&mut bytes)
+            .with_context(|| format!("Failed to read the chunk {} {}", chunk.0, chunk.1))?;
+        Ok(bytes)
+    }
+}
+
+impl MapProvider for ChunkedMap {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn block_type(&self, x: usize, y: usize) -> Option<BlockType> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let chunk = (x / self.chunk_size, y / self.chunk_size);
+        let mut cache = self.cache.borrow_mut();
+        if cache.order.back() != Some(&chunk) {
+            if let Some(position) = cache.order.iter().position(|loaded| *loaded == chunk) {
+                cache.order.remove(position);
+            } else {
+                let bytes = match self.read_chunk(chunk) {
+                    Ok(bytes) => bytes,
+                    Err(error) => {
+                        self.read_error.borrow_mut().get_or_insert(error);
+                        return None;
+                    }
+                };
+                if cache.order.len() == self.max_loaded_chunks {
+                    let evicted = cache.order.pop_front().expect("The cache is full");
+                    cache.chunks.remove(&evicted);
+                }
+                cache.chunks.insert(chunk, bytes);
+            }
+            cache.order.push_back(chunk);
+        }
+        let offset = (y % self.chunk_size) * self.chunk_size + x % self.chunk_size;
+        BlockType::from_byte(cache.chunks[&chunk][offset])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{a_star, a_star_provider, generate, GenOptions, Map, MazeAlgorithm, SearchOptions};
+
+    #[test]
+    fn chunked_maps_solve_like_the_map() {
+        let map =
+            Map::from(generate(20, 15, MazeAlgorithm::default(), &GenOptions::default()).unwrap());
+        let path = std::env::temp_dir().join(format!("mazes-chunked-{}.bin", std::process::id()));
+        ChunkedMap::write(&map, &path, 8).unwrap();
+        let chunked = ChunkedMap::open(&path, 4).unwrap();
+
+        for block in map.iter_blocks() {
+            assert_eq!(
+                chunked.block_type(block.x, block.y),
+                Some(block.block_type())
+            );
+        }
+        assert_eq!(chunked.block_type(map.width(), 0), None);
+        let start = map.get_block(1, 1).unwrap();
+        let goal = map.get_block(39, 29).unwrap();
+        let solution = a_star_provider(&chunked, start, goal, &SearchOptions::default()).unwrap();
+        assert_eq!(solution.cost(), a_star(&map, start, goal).unwrap().cost());
+        assert!(chunked.loaded_chunks() <= 4);
+        drop(chunked);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let path =
+            std::env::temp_dir().join(format!("mazes-not-chunked-{}.bin", std::process::id()));
+        std::fs::write(&path, b"MZCK").unwrap();
+
+        let result = ChunkedMap::open(&path, 4);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn chunks_that_fail_to_load_are_outside() {
+        let map = Map::from_fn(4, 4, |_, _| BlockType::White);
+        let path = std::env::temp_dir().join(format!("mazes-truncated-{}.bin", std::process::id()));
+        ChunkedMap::write(&map, &path, 2).unwrap();
+        let chunked = ChunkedMap::open(&path, 1).unwrap();
+        assert_eq!(chunked.block_type(0, 0), Some(BlockType::White));
+        assert!(chunked.take_read_error().is_none());
+
+        // Cut off the last chunks after opening
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(HEADER_LEN + 8)
+            .unwrap();
+
+        assert_eq!(chunked.block_type(3, 3), None);
+        assert!(chunked.take_read_error().is_some());
+        assert!(chunked.take_read_error().is_none());
+        drop(chunked);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
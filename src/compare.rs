@@ -0,0 +1,237 @@
+use std::{collections::HashMap, fmt::Display};
+
+#[cfg(feature = "image")]
+use image::RgbaImage;
+use itertools::Itertools;
+
+use crate::{Block, Solution};
+#[cfg(feature = "image")]
+use crate::{BlockType, RenderOptions};
+
+/// How two solutions differ, see [Solution::compare]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathDiff {
+    /// The cost of the other solution minus the cost of this one
+    pub cost_delta: i64,
+    /// How many blocks both paths start with
+    pub shared_prefix: usize,
+    /// How many blocks both paths end with
+    pub shared_suffix: usize,
+    /// The parts where the paths go separate ways, in the order of this path
+    pub segments: Vec<DivergentSegment>,
+}
+
+/// A part where two paths go separate ways. Both sides include the blocks where they split and meet again, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DivergentSegment {
+    pub ours: Vec<Block>,
+    pub theirs: Vec<Block>,
+    /// The cost of stepping along our side, without turn penalties
+    pub our_cost: u32,
+    pub their_cost: u32,
+}
+
+impl PathDiff {
+    /// Whether both paths visit the same blocks in the same order
+    pub fn is_identical(&self) -> bool {
+        self.segments.is_empty()
+    }
+}
+
+impl Display for PathDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_identical() {
+            return writeln!(f, "Both paths are identical");
+        }
+        writeln!(
+            f,
+            "The other path costs {:+}, both share the first {} and the last {} blocks and diverge {} times",
+            self.cost_delta,
+            self.shared_prefix,
+            self.shared_suffix,
+            self.segments.len()
+        )?;
+        for segment in &self.segments {
+            let (from, to) = (
+                segment.ours.first().or(segment.theirs.first()),
+                segment.ours.last().or(segment.theirs.last()),
+            );
+            let position = |block: Option<&Block>| {
+                block.map_or("-".to_string(), |block| format!("{} {}", block.x, block.y))
+            };
+            writeln!(
+                f,
+                "  from {} to {}: {} blocks costing {} instead of {} blocks costing {}",
+                position(from),
+                position(to),
+                segment.ours.len(),
+                segment.our_cost,
+                segment.theirs.len(),
+                segment.their_cost
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Solution {
+    /// Compares the path with the path of another solution between the same blocks, e.g. found by another algorithm.
+    ///
+    /// The paths are matched at the blocks both visit in the same order, picking the earliest
+    /// matching block of the other path, and everything in between is a [DivergentSegment].
+    pub fn compare(&self, other: &Solution) -> PathDiff {
+        let (ours, theirs) = (&self.path, &other.path);
+        let position = |block: &Block| (block.x, block.y);
+        let shared_prefix = ours
+            .iter()
+            .zip(theirs)
+            .take_while(|(a, b)| position(a) == position(b))
+            .count();
+        let shared_suffix = ours
+            .iter()
+            .rev()
+            .zip(theirs.iter().rev())
+            .take_while(|(a, b)| position(a) == position(b))
+            .count()
+            .min(ours.len().min(theirs.len()) - shared_prefix);
+
+        let mut their_indices: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (j, block) in theirs.iter().enumerate() {
+            their_indices.entry(position(block)).or_default().push(j);
+        }
+        let mut anchors = vec![];
+        let mut next = 0;
+        for (i, block) in ours.iter().enumerate() {
+            if let Some(j) = their_indices
+                .get(&position(block))
+                .and_then(|indices| indices.iter().find(|j| **j >= next))
+            {
+                anchors.push((i, *j));
+                next = j + 1;
+            }
+        }
+
+        // Sentinels before the start and after the end turn differing ends into segments as well
+        let bounds = std::iter::once(None)
+            .chain(anchors.into_iter().map(Some))
+            .chain(std::iter::once(None));
+        let segments = bounds
+            .tuple_windows()
+            .filter_map(|(from, to)| {
+                let (our_range, their_range) = match (from, to) {
+                    (Some((i, j)), Some((next_i, next_j)))
+                        if next_i == i + 1 && next_j == j + 1 =>
+                    {
+                        return None
+                    }
+                    (None, Some((0, 0))) => return None,
+                    (Some((i, j)), None) if i + 1 == ours.len() && j + 1 == theirs.len() => {
+                        return None
+                    }
+                    (from, to) => (
+                        from.map_or(0, |(i, _)| i)..to.map_or(ours.len(), |(i, _)| i + 1),
+                        from.map_or(0, |(_, j)| j)..to.map_or(theirs.len(), |(_, j)| j + 1),
+                    ),
+                };
+                let ours = ours[our_range].to_vec();
+                let theirs = theirs[their_range].to_vec();
+                Some(DivergentSegment {
+                    our_cost: step_cost(&ours),
+                    their_cost: step_cost(&theirs),
+                    ours,
+                    theirs,
+                })
+            })
+            .collect();
+
+        PathDiff {
+            cost_delta: other.cost as i64 - self.cost as i64,
+            shared_prefix,
+            shared_suffix,
+            segments,
+        }
+    }
+
+    /// Draws the map with both paths: blocks only on this path in the solution color,
+    /// blocks only on the other path in teal and blocks on both paths in a darker purple.
+    #[cfg(feature = "image")]
+    pub fn compare_image(&self, other: &Solution, options: &RenderOptions) -> Option<RgbaImage> {
+        const THEIRS: [u8; 4] = [0, 160, 160, 255];
+        const SHARED: [u8; 4] = [90, 40, 160, 255];
+        let mut on_path: HashMap<(usize, usize), (bool, bool)> = HashMap::new();
+        for block in &self.path {
+            on_path.entry((block.x, block.y)).or_default().0 = true;
+        }
+        for block in &other.path {
+            on_path.entry((block.x, block.y)).or_default().1 = true;
+        }
+        self.map
+            .to_image_colored(options, |block| match on_path.get(&(block.x, block.y)) {
+                Some((true, true)) => SHARED,
+                Some((true, false)) => options.palette.color(BlockType::Solution),
+                Some((false, true)) => THEIRS,
+                _ => options.palette.color(block.block_type()),
+            })
+    }
+}
+
+/// The cost of walking from the first to the last block
+fn step_cost(blocks: &[Block]) -> u32 {
+    blocks
+        .iter()
+        .skip(1)
+        .map(|block| block.speed() as u32)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{a_star, Map};
+
+    fn solve(rows: &[&str]) -> Solution {
+        let map = Map::from_rows(rows);
+        a_star(
+            &map,
+            map.get_block(0, 1).unwrap(),
+            map.get_block(6, 1).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn detours_are_divergent_segments() {
+        let top = solve(&["#.....#", "..###..", "#######"]);
+        let bottom = solve(&["#######", "..###..", "#.ooo.#"]);
+
+        let diff = top.compare(&bottom);
+
+        assert_eq!(diff.cost_delta, 12);
+        assert_eq!((diff.shared_prefix, diff.shared_suffix), (2, 2));
+        assert_eq!(diff.segments.len(), 1);
+        let segment = &diff.segments[0];
+        assert_eq!((segment.ours[0].x, segment.ours[0].y), (1, 1));
+        assert_eq!((segment.ours[6].x, segment.ours[6].y), (5, 1));
+        assert_eq!((segment.our_cost, segment.their_cost), (6, 18));
+        assert!(top.compare(&top).is_identical());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn both_paths_are_drawn() {
+        let top = solve(&["#.....#", "..###..", "#######"]);
+        let bottom = solve(&["#######", "..###..", "#.ooo.#"]);
+        let options = RenderOptions {
+            block_width: 1,
+            border_width: 0,
+            ..Default::default()
+        };
+
+        let image = top.compare_image(&bottom, &options).unwrap();
+
+        assert_eq!(image.get_pixel(3, 0).0, BlockType::Solution.to_rgba());
+        assert_eq!(image.get_pixel(3, 2).0, [0, 160, 160, 255]);
+        assert_eq!(image.get_pixel(0, 1).0, [90, 40, 160, 255]);
+        assert_eq!(image.get_pixel(3, 1).0, BlockType::Black.to_rgba());
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt::Display;
+
+use itertools::Itertools;
+
+use crate::{Direction, KeyColor, PortalColor, Solution};
+
+/// One step of the route a person can follow, see [Solution::directions]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// Walk this many blocks in a straight line
+    Go { direction: Direction, blocks: usize },
+    /// Change the heading before the next [Go](Instruction::Go)
+    Turn(Direction),
+    /// The next step enters a block that picks up a key
+    PickUp(KeyColor),
+    /// The next step passes a door with the key picked up before
+    Unlock(KeyColor),
+    /// The portal just entered moves the agent to its partner at these coordinates
+    Teleport {
+        color: PortalColor,
+        to: (usize, usize),
+    },
+}
+
+/// The compass direction, north is up
+pub(crate) fn compass(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Left => "west",
+        Direction::Up => "north",
+        Direction::Right => "east",
+        Direction::Down => "south",
+    }
+}
+
+pub(crate) fn key_name(color: KeyColor) -> &'static str {
+    match color {
+        KeyColor::Purple => "purple",
+        KeyColor::Green => "green",
+        KeyColor::Blue => "blue",
+    }
+}
+
+pub(crate) fn portal_name(color: PortalColor) -> &'static str {
+    match color {
+        PortalColor::Orange => "orange",
+        PortalColor::Yellow => "yellow",
+        PortalColor::Brown => "brown",
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Go { direction, blocks } => {
+                write!(f, "go {} {blocks}", compass(*direction))
+            }
+            Instruction::Turn(direction) => write!(f, "turn {}", compass(*direction)),
+            Instruction::PickUp(color) => write!(f, "pick up the {} key", key_name(*color)),
+            Instruction::Unlock(color) => write!(f, "unlock the {} door", key_name(*color)),
+            Instruction::Teleport { color, to: (x, y) } => {
+                write!(f, "take the {} portal to {x} {y}", portal_name(*color))
+            }
+        }
+    }
+}
+
+impl Solution {
+    /// The path as instructions for a person, e.g. `go north 5, turn east, go east 3`.
+    /// Straight steps are merged, keys, doors and portals on the way are called out where they are reached.
+    pub fn directions(&self) -> Vec<Instruction> {
+        let mut instructions = vec![];
+        let mut heading = None;
+        let mut keys = vec![];
+        for (from, to) in self.path().iter().tuple_windows() {
+            // Stepping into a portal lands on its partner, so the entered portal is a neighbor off the path
+            let entered_portal = || {
+                let color = to.portal()?;
+                Direction::ALL.into_iter().find(|direction| {
+                    let (x, y) = match direction {
+                        Direction::Left => (from.x.checked_sub(1), Some(from.y)),
+                        Direction::Up => (Some(from.x), from.y.checked_sub(1)),
+                        Direction::Right => (Some(from.x + 1), Some(from.y)),
+                        Direction::Down => (Some(from.x), Some(from.y + 1)),
+                    };
+                    x.zip(y)
+                        .and_then(|(x, y)| self.map().get_block(x, y))
+                        .is_some_and(|block| block.portal() == Some(color))
+                })
+            };
+            let step = Direction::between(*from, *to);
+            let Some(direction) = step.or_else(entered_portal) else {
+                heading = None;
+                continue;
+            };
+            if let Some(color) = to.door() {
+                instructions.push(Instruction::Unlock(color));
+            }
+            match instructions.last_mut() {
+                Some(Instruction::Go {
+                    direction: last,
+                    blocks,
+                }) if *last == direction => *blocks += 1,
+                _ => {
+                    if heading.is_some_and(|heading| heading != direction) {
+                        instructions.push(Instruction::Turn(direction));
+                    }
+                    instructions.push(Instruction::Go {
+                        direction,
+                        blocks: 1,
+                    });
+                }
+            }
+            heading = Some(direction);
+            if let Some(color) = to.portal().filter(|_| step.is_none()) {
+                instructions.push(Instruction::Teleport {
+                    color,
+                    to: (to.x, to.y),
+                });
+            }
+            if let Some(color) = to.key().filter(|color| !keys.contains(color)) {
+                keys.push(color);
+                instructions.push(Instruction::PickUp(color));
+            }
+        }
+        instructions
+    }
+
+    /// The [directions](Self::directions) as one line, separated by commas
+    pub fn directions_text(&self) -> String {
+        self.directions().iter().join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{a_star, Map};
+
+    fn directions(rows: &[&str], start: (usize, usize), goal: (usize, usize)) -> String {
+        let map = Map::from_rows(rows);
+        a_star(
+            &map,
+            map.get_block(start.0, start.1).unwrap(),
+            map.get_block(goal.0, goal.1).unwrap(),
+        )
+        .unwrap()
+        .directions_text()
+    }
+
+    #[test]
+    fn straight_steps_are_merged_between_turns() {
+        let rows = ["....", ".###", ".###", "...."];
+
+        assert_eq!(
+            directions(&rows, (0, 3), (3, 0)),
+            "go north 3, turn east, go east 3"
+        );
+        assert_eq!(directions(&rows, (0, 0), (0, 0)), "");
+    }
+
+    #[test]
+    fn keys_doors_and_portals_are_called_out() {
+        assert_eq!(
+            directions(&["1.A."], (1, 0), (3, 0)),
+            "go west 1, pick up the purple key, turn east, go east 1, unlock the purple door, go east 2"
+        );
+        assert_eq!(
+            directions(&["P.#P.", "..#.."], (1, 1), (4, 0)),
+            "go west 1, turn north, go north 1, take the orange portal to 3 0, turn east, go east 1"
+        );
+    }
+}
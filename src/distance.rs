@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+#[cfg(feature = "image")]
+use image::RgbaImage;
+
+#[cfg(feature = "image")]
+use crate::RenderOptions;
+use crate::{
+    search::{explore, SearchSpace},
+    Block, GridSpace, Map, SearchOptions, State,
+};
+
+/// The cost of the cheapest path from one block to every other block of a [Map], see [Map::distance_field]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistanceField {
+    origin: (usize, usize),
+    width: usize,
+    /// The cost row by row, `None` for blocks that can't be reached
+    costs: Vec<Option<u32>>,
+}
+
+impl DistanceField {
+    pub fn origin(&self) -> (usize, usize) {
+        self.origin
+    }
+
+    /// The cost of the cheapest path from the origin to the block at `x`, `y`, `None` if there is none
+    pub fn cost(&self, x: usize, y: usize) -> Option<u32> {
+        if x >= self.width {
+            return None;
+        }
+        self.costs.get(y * self.width + x).copied().flatten()
+    }
+
+    /// The cost of the block that is most expensive to reach
+    pub fn max_cost(&self) -> u32 {
+        self.costs.iter().flatten().max().copied().unwrap_or(0)
+    }
+}
+
+impl Map {
+    /// Runs Dijkstra from the block to every other one, following the same rules as the solvers:
+    /// keys picked up on the way open doors, portals teleport and weave crossings are only passed straight.
+    pub fn distance_field(&self, from: Block) -> anyhow::Result<DistanceField> {
+        self.distance_field_with(from, &SearchOptions::default())
+    }
+
+    /// Like [distance_field](Self::distance_field), but keeps out of the avoided regions and adds the penalties
+    /// and turn penalty of the options. The weight and the budget don't apply, every reachable block gets its cost.
+    pub fn distance_field_with(
+        &self,
+        from: Block,
+        options: &SearchOptions,
+    ) -> anyhow::Result<DistanceField> {
+        if from.x >= self.width() || self.get_block(from.x, from.y).is_none() {
+            return Err(anyhow!("Please specify coordinates within the map"));
+        }
+        let space = GridSpace {
+            map: self,
+            destination: from,
+            bound: None,
+            options,
+        };
+
+        let mut costs = vec![None; self.width() * self.height()];
+        for (state, cost) in explore(&space, State::new(from)) {
+            let known = &mut costs[state.location.y * self.width() + state.location.x];
+            if known.is_none_or(|known| cost < known) {
+                *known = Some(cost);
+            }
+        }
+        Ok(DistanceField {
+            origin: (from.x, from.y),
+            width: self.width(),
+            costs,
+        })
+    }
+
+    /// How many different cheapest paths lead from `start` to `destination`, 0 if there is none.
+    /// Counts beyond [u64::MAX] are capped there.
+    ///
+    /// Every step costs at least 1, so the steps between states whose costs in the Dijkstra field
+    /// differ by exactly the step cost form a DAG, whose paths are counted in the order of their cost.
+    pub fn count_shortest_paths(&self, start: Block, destination: Block) -> anyhow::Result<u64> {
+        let is_outside =
+            |block: Block| block.x >= self.width() || self.get_block(block.x, block.y).is_none();
+        if is_outside(start) || is_outside(destination) {
+            return Err(anyhow!("Please specify coordinates within the map"));
+        }
+        let options = SearchOptions::default();
+        let space = GridSpace {
+            map: self,
+            destination,
+            bound: None,
+            options: &options,
+        };
+
+        let costs = explore(&space, State::new(start));
+        let mut states = costs.iter().collect::<Vec<_>>();
+        states.sort_by_key(|(_, cost)| **cost);
+        let mut counts = HashMap::from([(State::new(start), 1u64)]);
+        for (state, cost) in &states {
+            let count = counts.get(*state).copied().unwrap_or(0);
+            for (next, step_cost) in space.successors(state) {
+                if costs.get(&next) == Some(&(**cost + step_cost)) {
+                    let next_count = counts.entry(next).or_insert(0);
+                    *next_count = next_count.saturating_add(count);
+                }
+            }
+        }
+
+        // The destination may be reached in several states, e.g. with different keys
+        let at_destination =
+            |state: &State| (state.location.x, state.location.y) == (destination.x, destination.y);
+        let Some(cheapest) = states
+            .iter()
+            .filter(|(state, _)| at_destination(state))
+            .map(|(_, cost)| **cost)
+            .min()
+        else {
+            return Ok(0);
+        };
+        Ok(states
+            .iter()
+            .filter(|(state, cost)| at_destination(state) && **cost == cheapest)
+            .map(|(state, _)| counts.get(*state).copied().unwrap_or(0))
+            .fold(0, u64::saturating_add))
+    }
+
+    /// Whether exactly one cheapest path leads from `start` to `destination`, see [count_shortest_paths](Self::count_shortest_paths)
+    pub fn has_unique_solution(&self, start: Block, destination: Block) -> anyhow::Result<bool> {
+        Ok(self.count_shortest_paths(start, destination)? == 1)
+    }
+
+    /// Colors every reachable block by its cost in the distance field, from the first color of the
+    /// [ramp](RenderOptions::ramp) at the origin to the last one at the farthest block.
+    /// The other blocks keep their color.
+    #[cfg(feature = "image")]
+    pub fn distance_image(
+        &self,
+        field: &DistanceField,
+        options: &RenderOptions,
+    ) -> Option<RgbaImage> {
+        let max_cost = field.max_cost().max(1) as f64;
+        self.to_image_colored(options, |block| match field.cost(block.x, block.y) {
+            Some(cost) => options.ramp.at(cost as f64 / max_cost),
+            None => options.palette.color(block.block_type()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{a_star, BlockType};
+
+    #[test]
+    fn distances_are_the_costs_of_the_cheapest_paths() {
+        let map = Map::from_rows(&["..o.#", ".#b..", "...#.", "##.#."]);
+        let start = map.get_block(0, 0).unwrap();
+
+        let field = map.distance_field(start).unwrap();
+
+        for block in map.walkable_blocks() {
+            let expected = a_star(&map, start, *block)
+                .ok()
+                .map(|solution| solution.cost());
+            assert_eq!(field.cost(block.x, block.y), expected, "{block:?}");
+        }
+        assert_eq!(field.cost(0, 0), Some(0));
+        assert_eq!(field.cost(4, 0), None);
+        assert_eq!(field.cost(9, 0), None);
+        assert_eq!(field.origin(), (0, 0));
+        assert_eq!(field.max_cost(), field.cost(4, 3).unwrap());
+    }
+
+    #[test]
+    fn doors_open_with_keys_picked_up_on_the_way() {
+        let map = Map::from_rows(&["1..A."]);
+        let start = map.get_block(2, 0).unwrap();
+
+        let field = map.distance_field(start).unwrap();
+
+        // Fetching the key first and coming back through the door
+        let beyond_door = map.get_block(4, 0).unwrap();
+        assert_eq!(
+            field.cost(4, 0),
+            Some(a_star(&map, start, beyond_door).unwrap().cost())
+        );
+        assert!(field.cost(4, 0) > Some(4));
+        assert!(map
+            .distance_field(Block::new(5, 0, BlockType::Green))
+            .is_err());
+    }
+
+    #[test]
+    fn shortest_paths_are_counted() {
+        // Around the pillar on either side, both equally cheap
+        let map = Map::from_rows(&["...", ".#.", "..."]);
+        let (start, destination) = (map.get_block(0, 0).unwrap(), map.get_block(2, 2).unwrap());
+        assert_eq!(map.count_shortest_paths(start, destination).unwrap(), 2);
+        assert!(!map.has_unique_solution(start, destination).unwrap());
+
+        // An open grid has as many as there are orders of the steps to the right and down
+        let map = Map::from_rows(&["....", "....", "...."]);
+        let destination = map.get_block(3, 2).unwrap();
+        assert_eq!(map.count_shortest_paths(start, destination).unwrap(), 10);
+
+        // The expensive side is no cheapest path, the wall makes the other one unreachable
+        let map = Map::from_rows(&["...", ".#y", "..#"]);
+        let (cheap, unreachable) = (map.get_block(2, 1).unwrap(), map.get_block(2, 2).unwrap());
+        assert!(map.has_unique_solution(start, cheap).unwrap());
+        assert_eq!(map.count_shortest_paths(start, unreachable).unwrap(), 0);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn the_image_runs_along_the_ramp() {
+        let map = Map::from_rows(&["...#"]);
+        let options = RenderOptions {
+            block_width: 1,
+            border_width: 0,
+            ramp: "#000000,#ffffff".parse().unwrap(),
+            ..Default::default()
+        };
+        let field = map.distance_field(map.get_block(0, 0).unwrap()).unwrap();
+
+        let image = map.distance_image(&field, &options).unwrap();
+
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [128, 128, 128, 255]);
+        assert_eq!(image.get_pixel(2, 0).0, [255, 255, 255, 255]);
+        assert_eq!(
+            image.get_pixel(3, 0).0,
+            options.palette.color(BlockType::Black)
+        );
+    }
+}
@@ -0,0 +1,509 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use anyhow::anyhow;
+use priority_queue::PriorityQueue;
+
+use crate::{
+    euclidean_distance, search::SearchSpace, Axis, Block, BlockType, GridSpace, KeyColor, Map,
+    MazeError, SearchOptions, Solution, SolveReport, State,
+};
+
+type Position = (usize, usize);
+/// Where the agent is and what it carries: its position, the passage of a weave crossing it moves along
+/// and one bit per [KeyColor] it has picked up, like a [State] without the block type
+type Vertex = (Position, Option<Axis>, u8);
+type Key = (u32, u32);
+
+const INFINITY: u32 = u32::MAX;
+
+/// An incremental planner that keeps its search state between queries, so that the path can be repaired
+/// cheaply when blocks change their type or the agent moves.
+///
+/// The search runs backwards from the goal, so only the parts of the map affected by a change are searched again.
+/// It follows the same rules as [a_star](crate::a_star): one-way blocks, portals, doors that need keys
+/// and weave crossings that can only be passed straight.
+///
+/// http://idm-lab.org/bib/abstracts/papers/aaai02b.pdf
+pub struct DStarLite {
+    map: Map,
+    options: SearchOptions,
+    /// Every portal with its partner
+    portals: HashMap<Position, Position>,
+    start: Vertex,
+    goal: Position,
+    /// The start position at the time of the last key modifier update
+    last: Position,
+    /// Compensates for the heuristic shrinking as the agent moves, so that queued keys stay valid
+    key_modifier: u32,
+    g: HashMap<Vertex, u32>,
+    rhs: HashMap<Vertex, u32>,
+    queue: PriorityQueue<Vertex, Reverse<Key>>,
+}
+
+impl DStarLite {
+    pub fn new(map: &Map, start: Block, goal: Block) -> anyhow::Result<Self> {
+        let (Some(start), Some(_)) = (
+            map.get_block(start.x, start.y),
+            map.get_block(goal.x, goal.y),
+        ) else {
+            return Err(anyhow!("Please specify coordinates within the map"));
+        };
+        let state = State::new(start);
+        let mut planner = Self {
+            map: map.clone(),
+            options: SearchOptions::default(),
+            portals: HashMap::new(),
+            start: ((start.x, start.y), state.layer, state.keys),
+            goal: (goal.x, goal.y),
+            last: (start.x, start.y),
+            key_modifier: 0,
+            g: HashMap::new(),
+            rhs: HashMap::new(),
+            queue: PriorityQueue::new(),
+        };
+        planner.update_portals();
+        // The goal may be reached with any keys and on either passage of a crossing
+        for vertex in planner.vertices_at(planner.goal) {
+            planner.update_vertex(vertex);
+        }
+        Ok(planner)
+    }
+
+    /// The map including all updates
+    pub fn map(&self) -> &Map {
+        &self.map
+    }
+
+    /// Changes the type of a block, e.g. when the agent discovers a wall.
+    pub fn update_cell(&mut self, x: usize, y: usize, block_type: BlockType) -> anyhow::Result<()> {
+        let block = self
+            .map
+            .get_block(x, y)
+            .ok_or(anyhow!("Please specify coordinates within the map"))?;
+        if block.block_type() == block_type {
+            return Ok(());
+        }
+        self.map.set_block_type(x, y, block_type)?;
+
+        // The steps out of the changed block and into it are affected. Changing a portal also redirects
+        // the steps into every other portal, which may now lead somewhere else.
+        let mut changed = vec![(x, y)];
+        if block.portal().is_some() || matches!(block_type, BlockType::Portal(_)) {
+            let had_portals = !self.portals.is_empty();
+            changed.extend(self.portals.keys().copied());
+            self.update_portals();
+            changed.extend(self.portals.keys().copied());
+            if had_portals == self.portals.is_empty() {
+                // The heuristic changed, so every queued key has to be calculated again
+                let queued = self
+                    .queue
+                    .iter()
+                    .map(|(vertex, _)| *vertex)
+                    .collect::<Vec<_>>();
+                for vertex in queued {
+                    let key = self.calculate_key(vertex);
+                    self.queue.change_priority(&vertex, Reverse(key));
+                }
+            }
+        }
+        let mut positions = changed.clone();
+        for (x, y) in changed {
+            positions.extend(
+                self.map
+                    .get_adjacent(x, y)
+                    .into_iter()
+                    .map(|neighbor| (neighbor.x, neighbor.y)),
+            );
+        }
+        positions.sort_unstable();
+        positions.dedup();
+        for position in positions {
+            for vertex in self.vertices_at(position) {
+                self.update_vertex(vertex);
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the agent to a new position. The next [replan](Self::replan) starts from there.
+    /// Keys on the way are picked up when the agent moves onto them, the same way as along a planned path.
+    pub fn move_to(&mut self, block: Block) -> anyhow::Result<()> {
+        let Some(block) = self.map.get_block(block.x, block.y) else {
+            return Err(anyhow!("Please specify coordinates within the map"));
+        };
+        let position = (block.x, block.y);
+        let keys = State {
+            keys: self.start.2,
+            ..State::new(block)
+        }
+        .with_key_of(block)
+        .keys;
+        self.start = self
+            .successors(self.start)
+            .into_iter()
+            .map(|(next, _)| next)
+            .find(|next| next.0 == position)
+            .unwrap_or((position, None, keys));
+        self.key_modifier += self.heuristic(self.last, position);
+        self.last = position;
+        Ok(())
+    }
+
+    /// Repairs the search after updates and returns the cheapest path from the current position to the goal.
+    pub fn replan(&mut self) -> anyhow::Result<Solution> {
+        let expanded = self.compute_shortest_path();
+        if self.g(self.start) == INFINITY {
+            return Err(MazeError::NoPath.into());
+        }
+
+        let mut vertex = self.start;
+        let mut states = vec![self.state(vertex)];
+        let mut cost = 0;
+        while vertex.0 != self.goal {
+            let (next, step_cost) = self
+                .successors(vertex)
+                .into_iter()
+                .min_by_key(|(next, step_cost)| step_cost.saturating_add(self.g(*next)))
+                .ok_or(MazeError::NoPath)?;
+            // Every vertex on the path has a cost, so the path can't be longer than there are of them
+            if states.len() > self.g.len() + 1 {
+                return Err(anyhow!("The planner is in an inconsistent state"));
+            }
+            cost += step_cost;
+            vertex = next;
+            states.push(self.state(vertex));
+        }
+
+        let report = SolveReport {
+            expanded,
+            suboptimality_bound: Some(1.0),
+        };
+        Ok(Solution::new(states, cost, self.map.clone(), report, 0))
+    }
+
+    /// The next block the agent should move to, if there is a path
+    pub fn next_step(&mut self) -> Option<Block> {
+        self.replan()
+            .ok()
+            .and_then(|solution| solution.states.get(1).map(|state| state.location))
+    }
+
+    fn block(&self, position: Position) -> Block {
+        self.map
+            .get_block(position.0, position.1)
+            .expect("Positions are always within the map")
+    }
+
+    fn state(&self, (position, layer, keys): Vertex) -> State {
+        State {
+            location: self.block(position),
+            layer,
+            keys,
+            heading: None,
+        }
+    }
+
+    fn update_portals(&mut self) {
+        self.portals = self
+            .map
+            .portal_pairs()
+            .into_iter()
+            .map(|(portal, partner)| ((portal.x, portal.y), (partner.x, partner.y)))
+            .collect();
+    }
+
+    /// Every vertex at the position: with any keys and, on a crossing, on either passage
+    fn vertices_at(&self, position: Position) -> Vec<Vertex> {
+        let layers = if self.block(position).crossing().is_some() {
+            vec![None, Some(Axis::Horizontal), Some(Axis::Vertical)]
+        } else {
+            vec![None]
+        };
+        (0..1u8 << KeyColor::ALL.len())
+            .flat_map(|keys| layers.iter().map(move |layer| (position, *layer, keys)))
+            .collect()
+    }
+
+    fn g(&self, vertex: Vertex) -> u32 {
+        *self.g.get(&vertex).unwrap_or(&INFINITY)
+    }
+
+    fn rhs(&self, vertex: Vertex) -> u32 {
+        *self.rhs.get(&vertex).unwrap_or(&INFINITY)
+    }
+
+    /// The straight line distance, or 0 once there are portals, which could shorten any path
+    fn heuristic(&self, from: Position, to: Position) -> u32 {
+        if self.portals.is_empty() {
+            euclidean_distance(self.block(from), self.block(to))
+        } else {
+            0
+        }
+    }
+
+    fn calculate_key(&self, vertex: Vertex) -> Key {
+        let min = self.g(vertex).min(self.rhs(vertex));
+        (
+            min.saturating_add(self.heuristic(self.start.0, vertex.0))
+                .saturating_add(self.key_modifier),
+            min,
+        )
+    }
+
+    /// The vertices the agent can step to and the cost of each step, the same ones the solvers search
+    fn successors(&self, vertex: Vertex) -> Vec<(Vertex, u32)> {
+        let state = self.state(vertex);
+        if !state.location.is_walkable() {
+            return vec![];
+        }
+        let space = GridSpace {
+            map: &self.map,
+            destination: state.location,
+            bound: None,
+            options: &self.options,
+        };
+        space
+            .successors(&state)
+            .into_iter()
+            .map(|(next, cost)| {
+                let position = (next.location.x, next.location.y);
+                ((position, next.layer, next.keys), cost)
+            })
+            .collect()
+    }
+
+    /// The vertices that may step onto the vertex. Some of them may not, which [update_vertex](Self::update_vertex)
+    /// finds out from their successors.
+    fn predecessors(&self, (position, _, keys): Vertex) -> Vec<Vertex> {
+        // The key of the block was either carried already or picked up by stepping onto it
+        let key_sets = match self.block(position).key() {
+            Some(color) => vec![keys, keys & !(1 << color as u8)],
+            None => vec![keys],
+        };
+        // Stepping onto the partner of a portal teleports onto the portal
+        let mut positions = vec![position];
+        positions.extend(self.portals.get(&position));
+        positions
+            .into_iter()
+            .flat_map(|(x, y)| self.map.get_adjacent(x, y))
+            .flat_map(|neighbor| {
+                let position = (neighbor.x, neighbor.y);
+                self.vertices_at(position)
+                    .into_iter()
+                    .filter(|(_, _, keys)| key_sets.contains(keys))
+            })
+            .collect()
+    }
+
+    fn update_vertex(&mut self, vertex: Vertex) {
+        let rhs = if vertex.0 == self.goal {
+            0
+        } else {
+            self.successors(vertex)
+                .into_iter()
+                .map(|(next, step_cost)| step_cost.saturating_add(self.g(next)))
+                .min()
+                .unwrap_or(INFINITY)
+        };
+        self.rhs.insert(vertex, rhs);
+        self.queue.remove(&vertex);
+        if self.g(vertex) != rhs {
+            let key = self.calculate_key(vertex);
+            self.queue.push(vertex, Reverse(key));
+        }
+    }
+
+    /// Returns how many vertices were expanded
+    fn compute_shortest_path(&mut self) -> usize {
+        let mut expanded = 0;
+        while let Some((&vertex, &Reverse(old_key))) = self.queue.peek() {
+            let start_key = self.calculate_key(self.start);
+            if old_key >= start_key && self.rhs(self.start) == self.g(self.start) {
+                break;
+            }
+            self.queue.pop();
+            expanded += 1;
+
+            let new_key = self.calculate_key(vertex);
+            if old_key < new_key {
+                self.queue.push(vertex, Reverse(new_key));
+            } else if self.g(vertex) > self.rhs(vertex) {
+                self.g.insert(vertex, self.rhs(vertex));
+                for predecessor in self.predecessors(vertex) {
+                    self.update_vertex(predecessor);
+                }
+            } else {
+                self.g.insert(vertex, INFINITY);
+                self.update_vertex(vertex);
+                for predecessor in self.predecessors(vertex) {
+                    self.update_vertex(predecessor);
+                }
+            }
+        }
+        expanded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{a_star, Direction, PortalColor};
+
+    #[test]
+    fn initial_plan_matches_a_star() {
+        let map = Map::from_rows(&["....#", ".##.#", ".b...", "..o#."]);
+        let start = map.get_block(0, 0).unwrap();
+        let goal = map.get_block(4, 3).unwrap();
+
+        let mut planner = DStarLite::new(&map, start, goal).unwrap();
+
+        assert_eq!(
+            planner.replan().unwrap().cost,
+            a_star(&map, start, goal).unwrap().cost
+        );
+    }
+
+    #[test]
+    fn replanning_avoids_new_walls() {
+        let map = Map::from_rows(&["....#", ".##.#", ".b...", "..o#."]);
+        let start = map.get_block(0, 0).unwrap();
+        let goal = map.get_block(4, 3).unwrap();
+        let mut planner = DStarLite::new(&map, start, goal).unwrap();
+        planner.replan().unwrap();
+
+        planner.update_cell(3, 1, BlockType::Black).unwrap();
+        let solution = planner.replan().unwrap();
+
+        let expected =
+            a_star(planner.map(), start, planner.map().get_block(4, 3).unwrap()).unwrap();
+        assert_eq!(solution.cost, expected.cost);
+        assert!(solution
+            .states
+            .iter()
+            .all(|state| state.location.x != 3 || state.location.y != 1));
+    }
+
+    #[test]
+    fn replanning_after_moving_continues_from_the_new_position() {
+        let map = Map::from_rows(&["....#", ".##.#", ".b...", "..o#."]);
+        let start = map.get_block(0, 0).unwrap();
+        let goal = map.get_block(4, 3).unwrap();
+        let mut planner = DStarLite::new(&map, start, goal).unwrap();
+
+        let next = planner.next_step().unwrap();
+        planner.move_to(next).unwrap();
+        planner.update_cell(4, 2, BlockType::Black).unwrap();
+
+        assert!(planner.replan().is_err());
+    }
+
+    /// Replans from `start` to `goal` and checks the cost against [a_star] on the updated map
+    fn assert_replans_like_a_star(
+        planner: &mut DStarLite,
+        start: (usize, usize),
+        goal: (usize, usize),
+    ) {
+        let map = planner.map().clone();
+        let expected = a_star(
+            &map,
+            map.get_block(start.0, start.1).unwrap(),
+            map.get_block(goal.0, goal.1).unwrap(),
+        )
+        .map(|solution| solution.cost)
+        .ok();
+        assert_eq!(
+            planner.replan().map(|solution| solution.cost).ok(),
+            expected
+        );
+    }
+
+    #[test]
+    fn replanning_follows_one_way_blocks() {
+        let map = Map::from_rows(&["..>..", ".###.", "....."]);
+        let mut planner = DStarLite::new(
+            &map,
+            map.get_block(0, 0).unwrap(),
+            map.get_block(4, 0).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(planner.replan().unwrap().cost, 4);
+
+        planner
+            .update_cell(2, 0, BlockType::OneWay(Direction::Left))
+            .unwrap();
+        assert_replans_like_a_star(&mut planner, (0, 0), (4, 0));
+        assert_eq!(planner.replan().unwrap().cost, 8);
+    }
+
+    #[test]
+    fn replanning_teleports_through_portals() {
+        let map = Map::from_rows(&["..#..", "..#..", "..#.."]);
+        let mut planner = DStarLite::new(
+            &map,
+            map.get_block(0, 2).unwrap(),
+            map.get_block(4, 2).unwrap(),
+        )
+        .unwrap();
+        assert!(planner.replan().is_err());
+
+        planner
+            .update_cell(0, 0, BlockType::Portal(PortalColor::Orange))
+            .unwrap();
+        planner
+            .update_cell(4, 0, BlockType::Portal(PortalColor::Orange))
+            .unwrap();
+        assert_replans_like_a_star(&mut planner, (0, 2), (4, 2));
+        let solution = planner.replan().unwrap();
+        assert!(solution
+            .states
+            .iter()
+            .any(|state| (state.location.x, state.location.y) == (4, 0)));
+
+        planner.update_cell(4, 0, BlockType::Green).unwrap();
+        assert!(planner.replan().is_err());
+    }
+
+    #[test]
+    fn replanning_fetches_keys_for_doors() {
+        let map = Map::from_rows(&["1..A."]);
+        let mut planner = DStarLite::new(
+            &map,
+            map.get_block(2, 0).unwrap(),
+            map.get_block(4, 0).unwrap(),
+        )
+        .unwrap();
+        assert_replans_like_a_star(&mut planner, (2, 0), (4, 0));
+        assert_eq!(planner.replan().unwrap().cost, 6);
+
+        // A key closer by saves the detour, without any the door stays shut
+        planner
+            .update_cell(1, 0, BlockType::Key(KeyColor::Purple))
+            .unwrap();
+        assert_replans_like_a_star(&mut planner, (2, 0), (4, 0));
+        assert_eq!(planner.replan().unwrap().cost, 4);
+        planner.update_cell(0, 0, BlockType::Green).unwrap();
+        planner.update_cell(1, 0, BlockType::Green).unwrap();
+        assert!(planner.replan().is_err());
+    }
+
+    #[test]
+    fn replanning_passes_weave_crossings_straight() {
+        let map = Map::from_rows(&["#.#", ".-.", "#.#"]);
+        let mut planner = DStarLite::new(
+            &map,
+            map.get_block(0, 1).unwrap(),
+            map.get_block(1, 0).unwrap(),
+        )
+        .unwrap();
+        // Turning on the crossing is not allowed
+        assert!(planner.replan().is_err());
+
+        planner.update_cell(1, 1, BlockType::Green).unwrap();
+        assert_replans_like_a_star(&mut planner, (0, 1), (1, 0));
+        assert_eq!(planner.replan().unwrap().cost, 2);
+        planner
+            .update_cell(1, 1, BlockType::BridgeVertical)
+            .unwrap();
+        assert!(planner.replan().is_err());
+    }
+}
@@ -0,0 +1,275 @@
+use std::{collections::HashMap, fmt::Display};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+
+use crate::{
+    search::{a_star_search, SearchSpace},
+    Block, BlockType, DistanceBound, Map, MazeError, State,
+};
+
+/// A block that is open for `open_for` ticks out of every `period` ticks, starting at tick `offset`.
+/// A door that opens every fourth tick for a single tick is `Schedule { period: 4, open_for: 1, offset: 0 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    pub period: usize,
+    pub open_for: usize,
+    pub offset: usize,
+}
+
+impl Schedule {
+    pub fn is_open(&self, tick: usize) -> bool {
+        (tick + self.period - self.offset % self.period) % self.period < self.open_for
+    }
+}
+
+/// A [Map] whose blocks may additionally be blocked on a [Schedule].
+#[derive(Debug, Clone)]
+pub struct DynamicMap {
+    map: Map,
+    schedules: HashMap<(usize, usize), Schedule>,
+}
+
+impl DynamicMap {
+    pub fn new(map: Map) -> Self {
+        Self {
+            map,
+            schedules: HashMap::new(),
+        }
+    }
+
+    pub fn map(&self) -> &Map {
+        &self.map
+    }
+
+    /// Makes the block at `x`, `y` only passable while the schedule is open.
+    pub fn set_schedule(&mut self, x: usize, y: usize, schedule: Schedule) -> anyhow::Result<()> {
+        if self.map.get_block(x, y).is_none() {
+            return Err(anyhow!("Please specify coordinates within the map"));
+        }
+        if schedule.period == 0 {
+            return Err(anyhow!(
+                "The period of a schedule must be at least one tick"
+            ));
+        }
+        self.schedules.insert((x, y), schedule);
+        Ok(())
+    }
+
+    pub fn is_open(&self, x: usize, y: usize, tick: usize) -> bool {
+        self.schedules
+            .get(&(x, y))
+            .is_none_or(|schedule| schedule.is_open(tick))
+    }
+
+    /// The map as it looks at `tick`: scheduled blocks that are closed are black.
+    pub fn at_tick(&self, tick: usize) -> Map {
+        let mut map = self.map.clone();
+        for (&(x, y), schedule) in &self.schedules {
+            if !schedule.is_open(tick) {
+                map.set_block_type(x, y, BlockType::Black)
+                    .expect("Schedules are only set within the map");
+            }
+        }
+        map
+    }
+
+    /// The number of ticks after which all schedules repeat
+    fn cycle(&self) -> usize {
+        self.schedules
+            .values()
+            .map(|schedule| schedule.period)
+            .fold(1, |cycle, period| cycle / gcd(cycle, period) * period)
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+pub struct DynamicSolution {
+    /// The block of the agent at every tick, starting at the start tick. Waiting repeats the block.
+    steps: Vec<Block>,
+    start_tick: usize,
+    cost: u32,
+    map: Map,
+}
+
+impl DynamicSolution {
+    pub fn steps(&self) -> &[Block] {
+        &self.steps
+    }
+
+    pub fn cost(&self) -> u32 {
+        self.cost
+    }
+
+    /// The tick at which the agent arrives at the destination
+    pub fn arrival_tick(&self) -> usize {
+        self.start_tick + self.steps.len() - 1
+    }
+
+    /// How many ticks the agent spends waiting
+    pub fn waits(&self) -> usize {
+        self.steps
+            .iter()
+            .tuple_windows()
+            .filter(|(a, b)| a == b)
+            .count()
+    }
+
+    pub fn to_solution_map(self) -> Map {
+        self.map
+    }
+}
+
+impl Display for DynamicSolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.map.to_string_with_locations(&[], false))?;
+        writeln!(
+            f,
+            "This solution cost {}, arrives at tick {} and waits {} ticks",
+            self.cost,
+            self.arrival_tick(),
+            self.waits()
+        )
+    }
+}
+
+/// Moving and waiting on a [DynamicMap]. Since all schedules repeat, only the phase within
+/// the cycle is part of the state, which keeps the state space finite.
+struct TimedSpace<'a> {
+    map: &'a DynamicMap,
+    destination: Block,
+    bound: DistanceBound,
+    cycle: usize,
+}
+
+impl SearchSpace for TimedSpace<'_> {
+    type State = (State, usize);
+
+    fn successors(&self, (state, phase): &(State, usize)) -> Vec<((State, usize), u32)> {
+        let next_phase = (phase + 1) % self.cycle;
+        let location = state.location;
+        let moves = self
+            .map
+            .map
+            .get_reachable(location.x, location.y)
+            .into_iter()
+            .filter(|block| state.can_move_to(*block))
+            .map(|block| (state.moved_to(block), block.speed() as u32));
+        // Waiting costs as much as a step on the cheapest terrain
+        let wait = std::iter::once((*state, 1));
+
+        moves
+            .chain(wait)
+            .filter(|(next, _)| {
+                self.map
+                    .is_open(next.location.x, next.location.y, next_phase)
+            })
+            .map(|(next, cost)| ((next, next_phase), cost))
+            .collect_vec()
+    }
+
+    fn heuristic(&self, (state, _): &(State, usize)) -> u32 {
+        self.bound.estimate(state.location)
+    }
+
+    fn is_goal(&self, (state, _): &(State, usize)) -> bool {
+        state.location == self.destination
+    }
+}
+
+/// Like [a_star](crate::a_star), but every step takes one tick and the agent may wait
+/// for a scheduled block to open. The agent never stands on a closed block after `start_tick`.
+pub fn a_star_dynamic(
+    map: &DynamicMap,
+    start: Block,
+    destination: Block,
+    start_tick: usize,
+) -> anyhow::Result<DynamicSolution> {
+    let cycle = map.cycle();
+    let space = TimedSpace {
+        map,
+        destination,
+        bound: DistanceBound::new(&map.map, destination),
+        cycle,
+    };
+    let path =
+        a_star_search(&space, (State::new(start), start_tick % cycle)).ok_or(MazeError::NoPath)?;
+    let steps = path
+        .states
+        .into_iter()
+        .map(|(state, _)| state.location)
+        .collect_vec();
+
+    let mut solution_map = map.map.clone();
+    solution_map.enter_solution(&steps);
+    Ok(DynamicSolution {
+        steps,
+        start_tick,
+        cost: path.cost,
+        map: solution_map,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_opens_periodically() {
+        let schedule = Schedule {
+            period: 4,
+            open_for: 2,
+            offset: 1,
+        };
+
+        let open = (0..8).filter(|tick| schedule.is_open(*tick)).collect_vec();
+
+        assert_eq!(open, vec![1, 2, 5, 6]);
+    }
+
+    #[test]
+    fn agent_waits_for_the_door() {
+        let mut map = DynamicMap::new(Map::from_rows(&["....."]));
+        let door = Schedule {
+            period: 5,
+            open_for: 1,
+            offset: 4,
+        };
+        map.set_schedule(2, 0, door).unwrap();
+        let start = map.map().get_block(0, 0).unwrap();
+        let destination = map.map().get_block(4, 0).unwrap();
+
+        let solution = a_star_dynamic(&map, start, destination, 0).unwrap();
+
+        assert_eq!(solution.waits(), 2);
+        assert_eq!(solution.arrival_tick(), 6);
+        for (tick, block) in solution.steps().iter().enumerate() {
+            assert!(map.is_open(block.x, block.y, tick));
+        }
+    }
+
+    #[test]
+    fn agent_takes_a_detour_when_waiting_is_more_expensive() {
+        let mut map = DynamicMap::new(Map::from_rows(&["...", ".#.", "..."]));
+        let closed_for_long = Schedule {
+            period: 20,
+            open_for: 1,
+            offset: 19,
+        };
+        map.set_schedule(1, 0, closed_for_long).unwrap();
+        let start = map.map().get_block(0, 0).unwrap();
+        let destination = map.map().get_block(2, 0).unwrap();
+
+        let solution = a_star_dynamic(&map, start, destination, 0).unwrap();
+
+        assert_eq!(solution.waits(), 0);
+        assert_eq!(solution.cost(), 6);
+    }
+}
@@ -0,0 +1,76 @@
+use std::fmt::Display;
+
+/// Errors callers may want to tell apart. They are returned inside [anyhow::Error],
+/// use `error.downcast_ref::<MazeError>()` to check for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MazeError {
+    /// The search was stopped through its [CancellationToken](crate::CancellationToken)
+    Cancelled,
+    /// The goal can't be reached from the start
+    NoPath,
+    /// There is no path within the [cost](crate::SearchOptions::max_cost) or [step](crate::SearchOptions::max_steps)
+    /// budget. Any path beyond it costs at least `frontier_cost`, the lowest estimate where the search had to stop,
+    /// if there is one at all.
+    PathNotFoundWithinBudget { frontier_cost: u32 },
+}
+
+impl Display for MazeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MazeError::Cancelled => f.write_str("The search was cancelled"),
+            MazeError::NoPath => f.write_str("There is no path"),
+            MazeError::PathNotFoundWithinBudget { frontier_cost } => write!(
+                f,
+                "There is no path within the budget, the cheapest one beyond it would cost at least {frontier_cost}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MazeError {}
+
+/// Why a path breaks the rules of the map, see [Solution::verify](crate::Solution::verify)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathError {
+    /// The path doesn't contain a single block
+    Empty,
+    /// The block is outside of the map or a wall
+    NotWalkable { x: usize, y: usize },
+    /// The blocks aren't neighbors connected by a step, a one-way block or a portal
+    InvalidStep {
+        from: (usize, usize),
+        to: (usize, usize),
+    },
+    /// The door is passed before a key of its color was picked up
+    LockedDoor { x: usize, y: usize },
+    /// The path turns on the weave crossing
+    TurnOnCrossing { x: usize, y: usize },
+    /// The reported cost differs from the cost of the steps
+    WrongCost { reported: u32, actual: u32 },
+}
+
+impl Display for PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathError::Empty => f.write_str("The path is empty"),
+            PathError::NotWalkable { x, y } => write!(f, "The block at {x} {y} is not walkable"),
+            PathError::InvalidStep { from, to } => write!(
+                f,
+                "The step from {} {} to {} {} is not possible",
+                from.0, from.1, to.0, to.1
+            ),
+            PathError::LockedDoor { x, y } => {
+                write!(f, "The door at {x} {y} is passed without its key")
+            }
+            PathError::TurnOnCrossing { x, y } => {
+                write!(f, "The path turns on the crossing at {x} {y}")
+            }
+            PathError::WrongCost { reported, actual } => write!(
+                f,
+                "The reported cost is {reported}, but the steps cost {actual}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
@@ -0,0 +1,187 @@
+use std::fmt::Display;
+
+use anyhow::anyhow;
+use itertools::Itertools;
+
+use crate::{Block, BlockType, DStarLite, Map, MazeError};
+
+/// The movement of an agent that could only see the blocks within a radius around itself.
+pub struct FogTrace {
+    steps: Vec<Block>,
+    /// The indices of the steps after which the agent discovered something new and replanned
+    replans: Vec<usize>,
+    cost: u32,
+    explored: Vec<bool>,
+    map: Map,
+}
+
+impl FogTrace {
+    /// The blocks the agent actually moved along, including start and goal
+    pub fn steps(&self) -> &[Block] {
+        &self.steps
+    }
+
+    pub fn replan_steps(&self) -> &[usize] {
+        &self.replans
+    }
+
+    pub fn cost(&self) -> u32 {
+        self.cost
+    }
+
+    pub fn is_explored(&self, x: usize, y: usize) -> bool {
+        x < self.map.width() && self.explored.get(y * self.map.width() + x) == Some(&true)
+    }
+
+    /// The map as far as the agent has seen it: unexplored blocks are white, the walked path is marked as solution.
+    pub fn to_explored_map(&self) -> Map {
+        let mut map = self.map.clone();
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                if !self.is_explored(x, y) {
+                    map.set_block_type(x, y, BlockType::White)
+                        .expect("The coordinates are within the map");
+                }
+            }
+        }
+        for step in &self.steps {
+            map.set_block_type(step.x, step.y, BlockType::Solution)
+                .expect("The agent stays within the map");
+        }
+        map
+    }
+}
+
+impl Display for FogTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_explored_map().to_string_with_locations(&[], false))?;
+        writeln!(
+            f,
+            "The agent walked {} steps costing {} and replanned {} times",
+            self.steps.len(),
+            self.cost,
+            self.replans.len()
+        )
+    }
+}
+
+/// Moves an agent from `start` to `goal` that only knows the blocks within `radius` of the positions it has visited.
+/// Unknown blocks are assumed to be walkable with the cheapest terrain until they are seen.
+pub fn solve_with_fog(
+    map: &Map,
+    start: Block,
+    goal: Block,
+    radius: usize,
+) -> anyhow::Result<FogTrace> {
+    let (width, height) = (map.width(), map.height());
+    let mut belief = map.clone();
+    for y in 0..height {
+        for x in 0..width {
+            belief.set_block_type(x, y, BlockType::Green)?;
+        }
+    }
+    let mut planner = DStarLite::new(&belief, start, goal)?;
+    let mut explored = vec![false; width * height];
+    let mut position = map
+        .get_block(start.x, start.y)
+        .ok_or(anyhow!("Please specify coordinates within the map"))?;
+    let mut steps = vec![position];
+    let mut replans = vec![];
+    let mut cost = 0;
+
+    loop {
+        let mut discovered = false;
+        for (x, y) in visible_positions(position, radius, width, height) {
+            if std::mem::replace(&mut explored[y * width + x], true) {
+                continue;
+            }
+            let actual = map
+                .get_block(x, y)
+                .expect("Visible positions are within the map");
+            if planner
+                .map()
+                .get_block(x, y)
+                .map(|block| block.block_type())
+                != Some(actual.block_type())
+            {
+                planner.update_cell(x, y, actual.block_type())?;
+                discovered = true;
+            }
+        }
+        if discovered {
+            replans.push(steps.len() - 1);
+        }
+
+        if position.x == goal.x && position.y == goal.y {
+            break;
+        }
+        // Each block can at most be discovered once, so the agent can't wander around forever
+        if steps.len() > 2 * width * height {
+            return Err(anyhow!("The agent is stuck"));
+        }
+
+        let next = planner.next_step().ok_or(MazeError::NoPath)?;
+        planner.move_to(next)?;
+        position = map
+            .get_block(next.x, next.y)
+            .expect("Steps are within the map");
+        cost += position.speed() as u32;
+        steps.push(position);
+    }
+
+    Ok(FogTrace {
+        steps,
+        replans,
+        cost,
+        explored,
+        map: map.clone(),
+    })
+}
+
+fn visible_positions(
+    center: Block,
+    radius: usize,
+    width: usize,
+    height: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    let xs = center.x.saturating_sub(radius)..(center.x + radius + 1).min(width);
+    let ys = center.y.saturating_sub(radius)..(center.y + radius + 1).min(height);
+    ys.cartesian_product(xs)
+        .map(|(y, x)| (x, y))
+        .filter(move |(x, y)| {
+            x.abs_diff(center.x).pow(2) + y.abs_diff(center.y).pow(2) <= radius.pow(2)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star;
+
+    #[test]
+    fn agent_replans_around_unseen_walls() {
+        let map = Map::from_rows(&[
+            "......", //
+            ".####.", ".#..#.", ".#..#.", "....#.",
+        ]);
+        let start = map.get_block(0, 0).unwrap();
+        let goal = map.get_block(2, 2).unwrap();
+
+        let trace = solve_with_fog(&map, start, goal, 1).unwrap();
+
+        assert_eq!(trace.steps().last().map(|b| (b.x, b.y)), Some((2, 2)));
+        assert!(!trace.replan_steps().is_empty());
+        assert!(trace.cost() >= a_star(&map, start, goal).unwrap().cost);
+    }
+
+    #[test]
+    fn agent_with_full_sight_walks_the_optimal_path() {
+        let map = Map::from_rows(&["....", ".##.", "b..o"]);
+        let start = map.get_block(0, 0).unwrap();
+        let goal = map.get_block(3, 2).unwrap();
+
+        let trace = solve_with_fog(&map, start, goal, 10).unwrap();
+
+        assert_eq!(trace.cost(), a_star(&map, start, goal).unwrap().cost);
+    }
+}
@@ -0,0 +1,543 @@
+use std::fmt::Write as _;
+
+use anyhow::anyhow;
+#[cfg(feature = "image")]
+use image::{Rgba, RgbaImage};
+use itertools::Itertools;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    map::{BlockType, IMAGE_BLOCK_WIDTH, IMAGE_BORDER_WIDTH},
+    maze_generation::{loop_count, Color, Wall},
+    search::{a_star_search, SearchSpace},
+    Block, GenOptions, MazeError, Palette,
+};
+
+/// Axial coordinates of a pointy-top hexagon. `r` is the row, `q` the diagonal column.
+///
+/// https://www.redblobgames.com/grids/hexagons/#coordinates-axial
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HexCoord {
+    pub q: i32,
+    pub r: i32,
+}
+
+impl HexCoord {
+    pub fn new(q: i32, r: i32) -> Self {
+        Self { q, r }
+    }
+
+    pub fn neighbor(&self, direction: HexDirection) -> HexCoord {
+        let (dq, dr) = direction.offset();
+        HexCoord::new(self.q + dq, self.r + dr)
+    }
+
+    /// The number of steps between two hexagons
+    pub fn distance(&self, other: &HexCoord) -> u32 {
+        let dq = self.q - other.q;
+        let dr = self.r - other.r;
+        ((dq.abs() + dr.abs() + (dq + dr).abs()) / 2) as u32
+    }
+}
+
+/// The six sides of a pointy-top hexagon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexDirection {
+    East,
+    NorthEast,
+    NorthWest,
+    West,
+    SouthWest,
+    SouthEast,
+}
+
+impl HexDirection {
+    pub const ALL: [HexDirection; 6] = [
+        HexDirection::East,
+        HexDirection::NorthEast,
+        HexDirection::NorthWest,
+        HexDirection::West,
+        HexDirection::SouthWest,
+        HexDirection::SouthEast,
+    ];
+
+    fn offset(&self) -> (i32, i32) {
+        match self {
+            HexDirection::East => (1, 0),
+            HexDirection::NorthEast => (1, -1),
+            HexDirection::NorthWest => (0, -1),
+            HexDirection::West => (-1, 0),
+            HexDirection::SouthWest => (-1, 1),
+            HexDirection::SouthEast => (0, 1),
+        }
+    }
+
+    fn index(&self) -> usize {
+        HexDirection::ALL
+            .iter()
+            .position(|direction| direction == self)
+            .expect("ALL contains every direction")
+    }
+
+    pub fn opposite(&self) -> HexDirection {
+        HexDirection::ALL[(self.index() + 3) % 6]
+    }
+
+    /// The angle of the side's normal in screen coordinates (y pointing down)
+    #[cfg(feature = "image")]
+    fn angle(&self) -> f64 {
+        -(self.index() as f64) * std::f64::consts::FRAC_PI_3
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HexCell {
+    pub coord: HexCoord,
+    pub walls: [Wall; 6],
+    pub color: Color,
+}
+
+impl HexCell {
+    fn new(coord: HexCoord) -> Self {
+        Self {
+            coord,
+            walls: [Wall::Closed; 6],
+            color: Color::Green,
+        }
+    }
+
+    pub fn wall(&self, direction: HexDirection) -> Wall {
+        self.walls[direction.index()]
+    }
+
+    fn speed(&self) -> u32 {
+        Block::new(0, 0, BlockType::from(self.color)).speed() as u32
+    }
+}
+
+/// A maze of hexagonal cells laid out in `height` rows of `width` cells, where every odd row is shifted half a cell to the right.
+#[derive(Debug, Clone)]
+pub struct HexMap {
+    width: usize,
+    height: usize,
+    cells: Vec<Vec<HexCell>>,
+}
+
+impl HexMap {
+    pub fn new(width: usize, height: usize) -> Self {
+        let cells = (0..height)
+            .map(|row| {
+                (0..width)
+                    .map(|column| HexCell::new(offset_to_axial(column, row)))
+                    .collect_vec()
+            })
+            .collect_vec();
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The coordinate of the cell in the given row and column
+    pub fn coord_at(&self, column: usize, row: usize) -> Option<HexCoord> {
+        self.cells.get(row)?.get(column).map(|cell| cell.coord)
+    }
+
+    pub fn get_cell(&self, coord: HexCoord) -> Option<&HexCell> {
+        let (column, row) = axial_to_offset(coord)?;
+        self.cells.get(row)?.get(column)
+    }
+
+    /// The position of the cell in row-major order
+    fn cell_index(&self, coord: HexCoord) -> Option<usize> {
+        let (column, row) = axial_to_offset(coord)?;
+        (column < self.width && row < self.height).then_some(row * self.width + column)
+    }
+
+    fn get_cell_mut(&mut self, coord: HexCoord) -> Option<&mut HexCell> {
+        let (column, row) = axial_to_offset(coord)?;
+        self.cells.get_mut(row)?.get_mut(column)
+    }
+
+    pub fn iter_cells(&self) -> impl Iterator<Item = &HexCell> {
+        self.cells.iter().flatten()
+    }
+
+    /// All neighbors within the map, regardless of walls
+    pub fn get_neighbors(&self, coord: HexCoord) -> Vec<(HexDirection, HexCoord)> {
+        HexDirection::ALL
+            .into_iter()
+            .map(|direction| (direction, coord.neighbor(direction)))
+            .filter(|(_, neighbor)| self.get_cell(*neighbor).is_some())
+            .collect_vec()
+    }
+
+    /// The neighbors that are not separated from `coord` by a wall
+    pub fn get_reachable(&self, coord: HexCoord) -> Vec<HexCoord> {
+        let Some(cell) = self.get_cell(coord) else {
+            return vec![];
+        };
+        self.get_neighbors(coord)
+            .into_iter()
+            .filter(|(direction, _)| cell.wall(*direction) == Wall::Open)
+            .map(|(_, neighbor)| neighbor)
+            .collect_vec()
+    }
+
+    fn open_wall(&mut self, coord: HexCoord, direction: HexDirection) -> anyhow::Result<()> {
+        let neighbor = coord.neighbor(direction);
+        self.get_cell_mut(coord)
+            .ok_or(anyhow!("The cell is not a part of the map"))?
+            .walls[direction.index()] = Wall::Open;
+        self.get_cell_mut(neighbor)
+            .ok_or(anyhow!("The neighbor is not a part of the map"))?
+            .walls[direction.opposite().index()] = Wall::Open;
+        Ok(())
+    }
+
+    /// Renders the maze as an SVG document. The cells of `highlighted` are drawn in the solution color.
+    pub fn to_svg(&self, highlighted: &[HexCoord]) -> String {
+        self.to_svg_with(highlighted, &Palette::default())
+    }
+
+    /// Like [to_svg](Self::to_svg), but in the colors of the palette
+    pub fn to_svg_with(&self, highlighted: &[HexCoord], palette: &Palette) -> String {
+        let [wall_r, wall_g, wall_b, _] = palette.color(BlockType::Black);
+        let size = IMAGE_BLOCK_WIDTH as f64 / 2.0 * 1.5;
+        let (width, height) = self.pixel_dimensions(size);
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.0}" height="{height:.0}" viewBox="0 0 {width:.0} {height:.0}">"#
+        );
+        svg.push('\n');
+
+        for cell in self.iter_cells() {
+            let (cx, cy) = self.center(cell.coord, size);
+            let corners = (0..6)
+                .map(|i| corner(cx, cy, size, i))
+                .map(|(x, y)| format!("{x:.2},{y:.2}"))
+                .join(" ");
+            let [r, g, b, _] = self.cell_rgba(cell, highlighted, palette);
+            let _ = writeln!(
+                svg,
+                r#"<polygon points="{corners}" fill="rgb({r},{g},{b})"/>"#
+            );
+        }
+
+        for cell in self.iter_cells() {
+            let (cx, cy) = self.center(cell.coord, size);
+            for direction in HexDirection::ALL {
+                if cell.wall(direction) == Wall::Open {
+                    continue;
+                }
+                // The side facing `direction` lies between the corners at its angle ±30°
+                let i = direction.index();
+                let (x1, y1) = corner(cx, cy, size, (6 - i) % 6);
+                let (x2, y2) = corner(cx, cy, size, (7 - i) % 6);
+                let _ = writeln!(
+                    svg,
+                    r#"<line x1="{x1:.2}" y1="{y1:.2}" x2="{x2:.2}" y2="{y2:.2}" stroke="rgb({wall_r},{wall_g},{wall_b})" stroke-width="{IMAGE_BORDER_WIDTH}" stroke-linecap="round"/>"#
+                );
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Renders the maze as an image. The cells of `highlighted` are drawn in the solution color.
+    #[cfg(feature = "image")]
+    pub fn to_image(&self, highlighted: &[HexCoord]) -> RgbaImage {
+        self.to_image_with(highlighted, &Palette::default())
+    }
+
+    /// Like [to_image](Self::to_image), but in the colors of the palette
+    #[cfg(feature = "image")]
+    pub fn to_image_with(&self, highlighted: &[HexCoord], palette: &Palette) -> RgbaImage {
+        let size = IMAGE_BLOCK_WIDTH as f64 / 2.0 * 1.5;
+        let (width, height) = self.pixel_dimensions(size);
+        let inner_radius = size * 3f64.sqrt() / 2.0;
+        let wall_width = IMAGE_BORDER_WIDTH as f64;
+
+        RgbaImage::from_fn(width.ceil() as u32, height.ceil() as u32, |px, py| {
+            let (x, y) = (px as f64 + 0.5, py as f64 + 0.5);
+            let Some(cell) = self.get_cell(self.pixel_to_coord(x, y, size)) else {
+                return Rgba(palette.color(BlockType::White));
+            };
+            let (cx, cy) = self.center(cell.coord, size);
+            let (side, distance) = HexDirection::ALL
+                .into_iter()
+                .map(|direction| {
+                    let angle = direction.angle();
+                    (direction, (x - cx) * angle.cos() + (y - cy) * angle.sin())
+                })
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .expect("A hexagon has six sides");
+            if distance > inner_radius - wall_width / 2.0 && cell.wall(side) == Wall::Closed {
+                return Rgba(palette.color(BlockType::Black));
+            }
+            Rgba(self.cell_rgba(cell, highlighted, palette))
+        })
+    }
+
+    fn cell_rgba(&self, cell: &HexCell, highlighted: &[HexCoord], palette: &Palette) -> [u8; 4] {
+        if highlighted.contains(&cell.coord) {
+            palette.color(BlockType::Solution)
+        } else {
+            palette.color(BlockType::from(cell.color))
+        }
+    }
+
+    fn pixel_dimensions(&self, size: f64) -> (f64, f64) {
+        let hex_width = 3f64.sqrt() * size;
+        let width =
+            hex_width * self.width as f64 + hex_width / 2.0 * (self.height > 1) as u8 as f64;
+        let height = size * 1.5 * self.height as f64 + size / 2.0;
+        (width, height)
+    }
+
+    /// The pixel position of the center of a cell, offset so that the top left hexagon touches the image border
+    fn center(&self, coord: HexCoord, size: f64) -> (f64, f64) {
+        let x = size * 3f64.sqrt() * (coord.q as f64 + coord.r as f64 / 2.0 + 0.5);
+        let y = size * 1.5 * coord.r as f64 + size;
+        (x, y)
+    }
+
+    #[cfg(feature = "image")]
+    fn pixel_to_coord(&self, x: f64, y: f64, size: f64) -> HexCoord {
+        let x = x - size * 3f64.sqrt() / 2.0;
+        let y = y - size;
+        let q = (3f64.sqrt() / 3.0 * x - y / 3.0) / size;
+        let r = (2.0 / 3.0 * y) / size;
+        cube_round(q, r)
+    }
+}
+
+/// Rows with odd index are shifted right by half a cell ("odd-r" layout).
+fn offset_to_axial(column: usize, row: usize) -> HexCoord {
+    HexCoord::new(
+        column as i32 - (row as i32 - (row as i32 & 1)) / 2,
+        row as i32,
+    )
+}
+
+fn axial_to_offset(coord: HexCoord) -> Option<(usize, usize)> {
+    let column = coord.q + (coord.r - (coord.r & 1)) / 2;
+    Some((
+        usize::try_from(column).ok()?,
+        usize::try_from(coord.r).ok()?,
+    ))
+}
+
+#[cfg(feature = "image")]
+fn cube_round(q: f64, r: f64) -> HexCoord {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+    HexCoord::new(rq as i32, rr as i32)
+}
+
+/// The i-th corner of a pointy-top hexagon in screen coordinates, counting clockwise from the upper right corner
+fn corner(cx: f64, cy: f64, size: f64, i: usize) -> (f64, f64) {
+    let angle = (60.0 * i as f64 - 30.0).to_radians();
+    (cx + size * angle.cos(), cy + size * angle.sin())
+}
+
+/// Generates a hexagonal maze with the recursive backtracker.
+pub fn generate_hex(width: usize, height: usize, options: &GenOptions) -> anyhow::Result<HexMap> {
+    if width == 0 || height == 0 {
+        return Err(anyhow!("The maze must at least have the dimensions 1x1"));
+    }
+    let mut map = HexMap::new(width, height);
+    let mut rng = options.rng();
+    let first = HexCoord::new(0, 0);
+    let mut visited = vec![false; width * height];
+    visited[0] = true;
+    let mut stack = vec![first];
+    let mut color: Color = rng.gen();
+
+    while let Some(current) = stack.pop() {
+        let candidates = map
+            .get_neighbors(current)
+            .into_iter()
+            .filter(|(_, neighbor)| {
+                map.cell_index(*neighbor)
+                    .is_some_and(|index| !visited[index])
+            })
+            .collect_vec();
+
+        if let Some((direction, next)) = candidates.choose(&mut rng).copied() {
+            stack.push(current);
+            map.open_wall(current, direction)?;
+            if let Some(cell) = map.get_cell_mut(current) {
+                cell.color = color;
+            }
+            if let Some(index) = map.cell_index(next).filter(|index| !visited[*index]) {
+                visited[index] = true;
+                stack.push(next);
+            }
+        } else {
+            // Dead ends have no passage of their own, they take the color of the branch that led there
+            if let Some(cell) = map.get_cell_mut(current) {
+                cell.color = color;
+            }
+            color = rng.gen();
+        }
+    }
+
+    // Every wall once, from the side of the first three directions
+    let closed = map
+        .iter_cells()
+        .flat_map(|cell| {
+            map.get_neighbors(cell.coord)
+                .into_iter()
+                .filter(|(direction, _)| {
+                    direction.index() < 3 && cell.wall(*direction) == Wall::Closed
+                })
+                .map(|(direction, _)| (cell.coord, direction))
+        })
+        .collect_vec();
+    let count = loop_count(options.loop_prob, closed.len());
+    for (coord, direction) in closed.choose_multiple(&mut rng, count) {
+        map.open_wall(*coord, *direction)?;
+    }
+
+    Ok(map)
+}
+
+pub struct HexSolution {
+    path: Vec<HexCoord>,
+    cost: u32,
+}
+
+impl HexSolution {
+    pub fn path(&self) -> &[HexCoord] {
+        &self.path
+    }
+
+    pub fn cost(&self) -> u32 {
+        self.cost
+    }
+}
+
+struct HexSpace<'a> {
+    map: &'a HexMap,
+    destination: HexCoord,
+}
+
+impl SearchSpace for HexSpace<'_> {
+    type State = HexCoord;
+
+    fn successors(&self, state: &HexCoord) -> Vec<(HexCoord, u32)> {
+        self.map
+            .get_reachable(*state)
+            .into_iter()
+            .filter_map(|coord| self.map.get_cell(coord))
+            .map(|cell| (cell.coord, cell.speed()))
+            .collect_vec()
+    }
+
+    fn heuristic(&self, state: &HexCoord) -> u32 {
+        // The cheapest terrain costs 1 per step
+        state.distance(&self.destination)
+    }
+
+    fn is_goal(&self, state: &HexCoord) -> bool {
+        *state == self.destination
+    }
+}
+
+/// Like [a_star](crate::a_star), but on a [HexMap]. Entering a cell costs the speed of its terrain color.
+pub fn a_star_hex(
+    map: &HexMap,
+    start: HexCoord,
+    destination: HexCoord,
+) -> anyhow::Result<HexSolution> {
+    if map.get_cell(start).is_none() || map.get_cell(destination).is_none() {
+        return Err(anyhow!("Please specify coordinates within the map"));
+    }
+    let space = HexSpace { map, destination };
+    let path = a_star_search(&space, start).ok_or(MazeError::NoPath)?;
+    Ok(HexSolution {
+        path: path.states,
+        cost: path.cost,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offset_and_axial_coordinates_round_trip() {
+        for row in 0..5 {
+            for column in 0..5 {
+                assert_eq!(
+                    axial_to_offset(offset_to_axial(column, row)),
+                    Some((column, row))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn inner_cell_has_six_neighbors() {
+        let map = HexMap::new(4, 4);
+        let coord = map.coord_at(1, 1).unwrap();
+        assert_eq!(map.get_neighbors(coord).len(), 6);
+    }
+
+    #[test]
+    fn generated_hex_maze_is_perfect() {
+        let map = generate_hex(6, 5, &GenOptions::default()).unwrap();
+        let open_walls = map
+            .iter_cells()
+            .flat_map(|cell| cell.walls)
+            .filter(|wall| *wall == Wall::Open)
+            .count();
+        // Every open wall is counted by both cells
+        assert_eq!(open_walls / 2, 6 * 5 - 1);
+
+        let start = map.coord_at(0, 0).unwrap();
+        for cell in map.iter_cells() {
+            assert!(a_star_hex(&map, start, cell.coord).is_ok());
+        }
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn pixel_center_maps_back_to_its_cell() {
+        let map = HexMap::new(5, 5);
+        let size = 15.0;
+        for cell in map.iter_cells() {
+            let (x, y) = map.center(cell.coord, size);
+            assert_eq!(map.pixel_to_coord(x, y, size), cell.coord);
+        }
+    }
+
+    #[test]
+    fn the_same_seed_generates_the_same_maze() {
+        let options = |seed| GenOptions {
+            seed: Some(seed),
+            loop_prob: Some(0.2),
+            ..Default::default()
+        };
+        let svg = |seed| generate_hex(6, 5, &options(seed)).unwrap().to_svg(&[]);
+
+        assert_eq!(svg(3), svg(3));
+        assert_ne!(svg(3), svg(4));
+    }
+}
@@ -0,0 +1,319 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+use priority_queue::PriorityQueue;
+
+use crate::{
+    search::{a_star_search, HeuristicWeight, Path, SearchSpace},
+    Axis, Block, DistanceBound, Map, MazeError, Solution, SolveReport, State,
+};
+
+/// Hierarchical pathfinding (HPA*) for repeated queries on large maps.
+///
+/// The map is split into square clusters. Every state on the border of a cluster that an agent can step
+/// into another cluster from, or arrive at from another cluster, becomes a node of an abstract graph.
+/// The cheapest paths between the nodes of each cluster are computed once, so queries only search
+/// the abstract graph and then refine its edges inside single clusters.
+///
+/// Since every border crossing is a node, the paths are as cheap as the ones of [a_star](crate::a_star).
+/// Keys and doors are not supported.
+pub struct HierarchicalPlanner<'a> {
+    map: &'a Map,
+    cluster_size: usize,
+    /// The abstract graph: every node with the nodes it leads to and the cost of getting there
+    edges: HashMap<State, Vec<(State, u32)>>,
+}
+
+impl<'a> HierarchicalPlanner<'a> {
+    /// Splits `map` into clusters of `cluster_size` × `cluster_size` blocks and builds the abstract graph
+    pub fn new(map: &'a Map, cluster_size: usize) -> anyhow::Result<Self> {
+        if cluster_size == 0 {
+            return Err(anyhow!("The cluster size must be at least 1"));
+        }
+        if map
+            .walkable_blocks()
+            .any(|block| block.key().is_some() || block.door().is_some())
+        {
+            return Err(anyhow!(
+                "Hierarchical pathfinding does not support keys and doors"
+            ));
+        }
+        let mut planner = Self {
+            map,
+            cluster_size,
+            edges: HashMap::new(),
+        };
+
+        // Steps between clusters
+        for state in map.walkable_blocks().flat_map(|block| states_of(*block)) {
+            for (next, cost) in planner.steps(&state) {
+                if !planner.same_cluster(state.location, next.location) {
+                    planner.edges.entry(state).or_default().push((next, cost));
+                    planner.edges.entry(next).or_default();
+                }
+            }
+        }
+        // Paths inside the clusters
+        let nodes = planner.edges.keys().copied().collect_vec();
+        for node in nodes {
+            let inner = planner
+                .cluster_costs(node)
+                .into_iter()
+                .filter(|(other, _)| *other != node && planner.edges.contains_key(other))
+                .collect_vec();
+            planner
+                .edges
+                .get_mut(&node)
+                .expect("Every node has edges")
+                .extend(inner);
+        }
+        Ok(planner)
+    }
+
+    pub fn cluster_size(&self) -> usize {
+        self.cluster_size
+    }
+
+    /// The number of nodes of the abstract graph
+    pub fn node_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// The number of edges of the abstract graph
+    pub fn edge_count(&self) -> usize {
+        self.edges.values().map(Vec::len).sum()
+    }
+
+    /// Finds the cheapest path from the start to the destination block, like [a_star](crate::a_star).
+    /// The [report](Solution::report) counts the states expanded by all searches of the query.
+    pub fn find_path(
+        &self,
+        start_block: Block,
+        destination_block: Block,
+    ) -> anyhow::Result<Solution> {
+        let start = State::new(start_block);
+        // The abstract graph plus edges from the start and into the destination.
+        // A start on a weave crossing is no node, since the agent may still leave it in any direction.
+        let mut extra: HashMap<State, Vec<(State, u32)>> = HashMap::new();
+        extra.insert(
+            start,
+            self.cluster_costs(start)
+                .into_iter()
+                .filter(|(state, _)| {
+                    self.edges.contains_key(state) || state.location == destination_block
+                })
+                .chain(
+                    self.steps(&start)
+                        .into_iter()
+                        .filter(|(next, _)| !self.same_cluster(start_block, next.location)),
+                )
+                .collect(),
+        );
+        let destination_cluster = self.cluster_of(destination_block);
+        for node in self.edges.keys() {
+            if self.cluster_of(node.location) == destination_cluster {
+                let into_destination = self
+                    .cluster_costs(*node)
+                    .into_iter()
+                    .filter(|(state, _)| state.location == destination_block)
+                    .collect_vec();
+                extra.entry(*node).or_default().extend(into_destination);
+            }
+        }
+
+        let space = AbstractSpace {
+            planner: self,
+            extra: &extra,
+            destination: destination_block,
+            bound: DistanceBound::new(self.map, destination_block),
+        };
+        let route = a_star_search(&space, start).ok_or(MazeError::NoPath)?;
+        let mut expanded = route.expanded;
+
+        // Refine every abstract edge into the steps it stands for
+        let mut states = vec![start];
+        for (from, to) in route.states.iter().tuple_windows() {
+            if !self.same_cluster(from.location, to.location) {
+                states.push(*to);
+                continue;
+            }
+            let inner = a_star_search(
+                &ClusterSpace {
+                    planner: self,
+                    cluster: self.cluster_of(from.location),
+                    goal: *to,
+                },
+                *from,
+            )
+            .expect("Abstract edges only connect states with a path between them");
+            expanded += inner.expanded;
+            states.extend(inner.states.into_iter().skip(1));
+        }
+
+        let path = Path {
+            states,
+            cost: route.cost,
+            expanded,
+        };
+        let report = SolveReport::new(&path, HeuristicWeight::Factor(1.0));
+        Ok(Solution::new(
+            path.states,
+            path.cost,
+            self.map.clone(),
+            report,
+            0,
+        ))
+    }
+
+    fn cluster_of(&self, block: Block) -> (usize, usize) {
+        (block.x / self.cluster_size, block.y / self.cluster_size)
+    }
+
+    fn same_cluster(&self, a: Block, b: Block) -> bool {
+        self.cluster_of(a) == self.cluster_of(b)
+    }
+
+    /// The steps an agent can take from `state`, with the cost of each
+    fn steps(&self, state: &State) -> Vec<(State, u32)> {
+        self.map
+            .get_reachable(state.location.x, state.location.y)
+            .into_iter()
+            .filter(|block| state.can_move_to(*block))
+            .map(|block| (state.moved_to(block), block.speed() as u32))
+            .collect()
+    }
+
+    /// The cost of the cheapest path from `from` to every state of its cluster, without leaving the cluster
+    fn cluster_costs(&self, from: State) -> HashMap<State, u32> {
+        let cluster = self.cluster_of(from.location);
+        let mut costs = HashMap::from([(from, 0)]);
+        let mut queue = PriorityQueue::new();
+        queue.push(from, Reverse(0));
+        while let Some((state, Reverse(cost))) = queue.pop() {
+            for (next, step_cost) in self.steps(&state) {
+                if self.cluster_of(next.location) != cluster {
+                    continue;
+                }
+                let next_cost = cost + step_cost;
+                if costs.get(&next).is_none_or(|known| next_cost < *known) {
+                    costs.insert(next, next_cost);
+                    queue.push(next, Reverse(next_cost));
+                }
+            }
+        }
+        costs
+    }
+}
+
+/// Every state an agent can be in on `block`: one per passage on a weave crossing
+fn states_of(block: Block) -> Vec<State> {
+    let state = State::new(block);
+    if block.crossing().is_some() {
+        [Axis::Horizontal, Axis::Vertical]
+            .into_iter()
+            .map(|axis| State {
+                layer: Some(axis),
+                ..state
+            })
+            .collect()
+    } else {
+        vec![state]
+    }
+}
+
+/// The abstract graph of a [HierarchicalPlanner] together with the edges of a single query
+struct AbstractSpace<'a> {
+    planner: &'a HierarchicalPlanner<'a>,
+    extra: &'a HashMap<State, Vec<(State, u32)>>,
+    destination: Block,
+    bound: DistanceBound,
+}
+
+impl SearchSpace for AbstractSpace<'_> {
+    type State = State;
+
+    fn successors(&self, state: &State) -> Vec<(State, u32)> {
+        let edges = self.planner.edges.get(state).into_iter().flatten();
+        let extra = self.extra.get(state).into_iter().flatten();
+        edges.chain(extra).copied().collect()
+    }
+
+    fn heuristic(&self, state: &State) -> u32 {
+        self.bound.estimate(state.location)
+    }
+
+    fn is_goal(&self, state: &State) -> bool {
+        state.location == self.destination
+    }
+}
+
+/// Moving inside a single cluster of a [HierarchicalPlanner] to a known state
+struct ClusterSpace<'a> {
+    planner: &'a HierarchicalPlanner<'a>,
+    cluster: (usize, usize),
+    goal: State,
+}
+
+impl SearchSpace for ClusterSpace<'_> {
+    type State = State;
+
+    fn successors(&self, state: &State) -> Vec<(State, u32)> {
+        self.planner
+            .steps(state)
+            .into_iter()
+            .filter(|(next, _)| self.planner.cluster_of(next.location) == self.cluster)
+            .collect()
+    }
+
+    fn heuristic(&self, _: &State) -> u32 {
+        // Portals inside the cluster would make distances overestimate, and clusters are small
+        0
+    }
+
+    fn is_goal(&self, state: &State) -> bool {
+        *state == self.goal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{a_star, generate, GenOptions, MazeAlgorithm};
+
+    #[test]
+    fn finds_the_same_costs_as_a_star() {
+        let options = GenOptions {
+            loop_prob: Some(0.2),
+            weave: Some(0.5),
+            seed: Some(11),
+            ..Default::default()
+        };
+        let map = Map::from(generate(12, 10, MazeAlgorithm::default(), &options).unwrap());
+        let planner = HierarchicalPlanner::new(&map, 5).unwrap();
+
+        for (start, goal) in [((1, 1), (23, 19)), ((23, 1), (1, 19)), ((3, 5), (5, 3))] {
+            let start = map.get_block(start.0, start.1).unwrap();
+            let goal = map.get_block(goal.0, goal.1).unwrap();
+
+            let solution = planner.find_path(start, goal).unwrap();
+
+            assert_eq!(solution.cost(), a_star(&map, start, goal).unwrap().cost());
+            assert_eq!(solution.verify(&map), Ok(()));
+        }
+    }
+
+    #[test]
+    fn follows_portals_and_one_way_blocks() {
+        let map = Map::from_rows(&["P.#....", "..#.>..", "###.#.P"]);
+        let planner = HierarchicalPlanner::new(&map, 2).unwrap();
+        let start = map.get_block(1, 1).unwrap();
+        let goal = map.get_block(3, 0).unwrap();
+
+        let solution = planner.find_path(start, goal).unwrap();
+
+        assert_eq!(solution.cost(), a_star(&map, start, goal).unwrap().cost());
+        assert_eq!(solution.verify(&map), Ok(()));
+        assert!(HierarchicalPlanner::new(&Map::from_rows(&["1A."]), 2).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use anyhow::anyhow;
+
+use crate::{a_star, Block, BlockType, Map, MazeError};
+
+/// How editing one block changes a route, see [Map::impact_of_block_change]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteImpact {
+    /// The index of the route in the routes passed in
+    pub route: usize,
+    /// The cost of the cheapest path before the edit, `None` if there was none
+    pub before: Option<u32>,
+    /// The cost of the cheapest path after the edit, `None` if there is none
+    pub after: Option<u32>,
+}
+
+impl RouteImpact {
+    /// Whether the route was possible before and isn't anymore or the other way around
+    pub fn changes_validity(&self) -> bool {
+        self.before.is_some() != self.after.is_some()
+    }
+}
+
+/// The cost of the cheapest path on the map, `None` if there is none
+fn cheapest_cost(map: &Map, (start, goal): (Block, Block)) -> anyhow::Result<Option<u32>> {
+    match a_star(map, start, goal) {
+        Ok(solution) => Ok(Some(solution.cost())),
+        Err(error) if error.downcast_ref() == Some(&MazeError::NoPath) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+impl Map {
+    /// Reports the routes between the start and goal blocks whose cost or validity changes
+    /// if the block at `x`, `y` becomes `new_type`, e.g. to update cached routes after an edit.
+    ///
+    /// The costs before the edit come from one [distance field](Map::distance_field) per start, shared by its routes.
+    /// Two more fields from the edited block, before and after the edit, bound the cost of going through it,
+    /// so that only the routes the edit can affect are solved again: those with a cheapest path that may cross
+    /// the block and those for which a detour through it may be cheaper. The fields don't track keys,
+    /// so on maps with keys or portals, which teleport past any distance, every route is solved again.
+    pub fn impact_of_block_change(
+        &self,
+        x: usize,
+        y: usize,
+        new_type: BlockType,
+        routes: &[(Block, Block)],
+    ) -> anyhow::Result<Vec<RouteImpact>> {
+        let old = self
+            .get_block(x, y)
+            .filter(|_| x < self.width())
+            .ok_or(anyhow!("Please specify coordinates within the map"))?;
+        let routes = routes
+            .iter()
+            .map(|(start, goal)| {
+                let block = |block: &Block| {
+                    self.get_block(block.x, block.y)
+                        .filter(|_| block.x < self.width())
+                        .ok_or(anyhow!("Please specify coordinates within the map"))
+                };
+                Ok((block(start)?, block(goal)?))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if old.block_type() == new_type {
+            return Ok(vec![]);
+        }
+        let mut edited = self.clone();
+        edited.set_block_type(x, y, new_type)?;
+        let new = Block::new(x, y, new_type);
+
+        let solves_everything = [old, new]
+            .iter()
+            .chain(self.iter_blocks())
+            .any(|block| block.portal().is_some() || block.key().is_some());
+        let through_old = self.distance_field(old)?;
+        let through_new = edited.distance_field(new)?;
+        let neighbors = self.get_adjacent(x, y);
+
+        let mut fields = HashMap::new();
+        let mut impacts = vec![];
+        for (index, &(start, goal)) in routes.iter().enumerate() {
+            let field = match fields.entry((start.x, start.y)) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(self.distance_field(start)?),
+            };
+            let before = field.cost(goal.x, goal.y);
+
+            // Lower bounds of the cost of the cheapest path through the block, before and after the edit
+            let through_before = field
+                .cost(x, y)
+                .zip(through_old.cost(goal.x, goal.y))
+                .map(|(to, from)| to + from);
+            let through_after = neighbors
+                .iter()
+                .filter_map(|neighbor| field.cost(neighbor.x, neighbor.y))
+                .min()
+                .zip(through_new.cost(goal.x, goal.y))
+                .filter(|_| new.is_walkable())
+                .map(|(to, from)| to + new.speed() as u32 + from);
+            let may_cross = through_before
+                .zip(before)
+                .is_some_and(|(through, cost)| through <= cost);
+            let detour_may_pay =
+                through_after.is_some_and(|through| before.is_none_or(|cost| through < cost));
+            let is_endpoint = [start, goal]
+                .iter()
+                .any(|block| (block.x, block.y) == (x, y));
+            if !(solves_everything || is_endpoint || may_cross || detour_may_pay) {
+                continue;
+            }
+
+            let after = cheapest_cost(&edited, (start, goal))?;
+            if before != after {
+                impacts.push(RouteImpact {
+                    route: index,
+                    before,
+                    after,
+                });
+            }
+        }
+        Ok(impacts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate, GenOptions, MazeAlgorithm};
+
+    #[test]
+    fn only_affected_routes_are_reported() {
+        let map = Map::from_rows(&["...", "#.#", "..."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+        let routes = [
+            (block(0, 0), block(0, 2)),
+            (block(0, 0), block(2, 0)),
+            (block(2, 2), block(0, 2)),
+        ];
+
+        // Blocking the only gap
+        let impacts = map
+            .impact_of_block_change(1, 1, BlockType::Black, &routes)
+            .unwrap();
+        assert_eq!(
+            impacts,
+            [RouteImpact {
+                route: 0,
+                before: Some(4),
+                after: None
+            }]
+        );
+        assert!(impacts[0].changes_validity());
+
+        // A shortcut
+        let impacts = map
+            .impact_of_block_change(0, 1, BlockType::Green, &routes)
+            .unwrap();
+        assert_eq!(
+            impacts,
+            [RouteImpact {
+                route: 0,
+                before: Some(4),
+                after: Some(2)
+            }]
+        );
+        assert!(!impacts[0].changes_validity());
+
+        assert!(map
+            .impact_of_block_change(1, 0, BlockType::Blue, &routes)
+            .unwrap()
+            .iter()
+            .map(|impact| impact.route)
+            .eq([0, 1]));
+        assert!(map
+            .impact_of_block_change(9, 0, BlockType::Blue, &routes)
+            .is_err());
+    }
+
+    #[test]
+    fn skipped_routes_keep_their_cost() {
+        let options = GenOptions {
+            loop_prob: Some(0.2),
+            seed: Some(4),
+            ..Default::default()
+        };
+        let map = Map::from(generate(6, 6, MazeAlgorithm::default(), &options).unwrap());
+        let walkable = map.walkable_blocks().copied().collect::<Vec<_>>();
+        let routes = walkable
+            .iter()
+            .step_by(7)
+            .zip(walkable.iter().rev().step_by(5))
+            .map(|(start, goal)| (*start, *goal))
+            .collect::<Vec<_>>();
+
+        for block in map.iter_blocks().filter(|block| block.x % 3 == 1) {
+            for new_type in [BlockType::Black, BlockType::Green, BlockType::Orange] {
+                let mut edited = map.clone();
+                edited.set_block_type(block.x, block.y, new_type).unwrap();
+                let expected = routes
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(route, &(start, goal))| {
+                        let before = cheapest_cost(&map, (start, goal)).unwrap();
+                        let after = cheapest_cost(&edited, (start, goal)).unwrap();
+                        (before != after).then_some(RouteImpact {
+                            route,
+                            before,
+                            after,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                let impacts = map
+                    .impact_of_block_change(block.x, block.y, new_type, &routes)
+                    .unwrap();
+
+                assert_eq!(impacts, expected, "{block:?} to {new_type:?}");
+            }
+        }
+    }
+}
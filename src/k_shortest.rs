@@ -0,0 +1,192 @@
+use std::collections::HashSet;
+
+#[cfg(feature = "image")]
+use image::RgbaImage;
+
+#[cfg(feature = "image")]
+use crate::{map::region_color, RenderOptions};
+use crate::{
+    search::{a_star_search, Path, SearchSpace},
+    Block, DistanceBound, GridSpace, Map, MazeError, SearchOptions, Solution, SolveReport, State,
+};
+
+/// The search space without some states and steps, so that [k_shortest_paths] finds paths it doesn't know yet
+struct RestrictedSpace<'a> {
+    grid: &'a GridSpace<'a>,
+    removed_states: HashSet<State>,
+    removed_steps: HashSet<(State, State)>,
+}
+
+impl SearchSpace for RestrictedSpace<'_> {
+    type State = State;
+
+    fn successors(&self, state: &State) -> Vec<(State, u32)> {
+        let mut successors = self.grid.successors(state);
+        successors.retain(|(next, _)| {
+            !self.removed_states.contains(next) && !self.removed_steps.contains(&(*state, *next))
+        });
+        successors
+    }
+
+    fn heuristic(&self, state: &State) -> u32 {
+        self.grid.heuristic(state)
+    }
+
+    fn is_goal(&self, state: &State) -> bool {
+        self.grid.is_goal(state)
+    }
+}
+
+/// Finds up to `k` different paths from the start to the destination block, from the cheapest to the most expensive,
+/// e.g. to offer alternative routes. Fewer paths are returned if there aren't as many.
+///
+/// Uses Yen's algorithm: every further path leaves one of the paths found so far at some block
+/// and takes the cheapest way from there that none of them takes. None of the paths visits a state twice.
+/// That takes a search per block of every path, so large `k` are slow on huge maps.
+/// The [suboptimality bound](SolveReport::suboptimality_bound) of each solution is its cost relative to the cheapest.
+pub fn k_shortest_paths(
+    map: &Map,
+    start_block: Block,
+    destination_block: Block,
+    k: usize,
+) -> anyhow::Result<Vec<Solution>> {
+    let options = SearchOptions::default();
+    let grid = GridSpace {
+        map,
+        destination: destination_block,
+        bound: Some(DistanceBound::new(map, destination_block)),
+        options: &options,
+    };
+    if k == 0 {
+        return Ok(vec![]);
+    }
+    let first = a_star_search(&grid, State::new(start_block)).ok_or(MazeError::NoPath)?;
+
+    let mut found: Vec<Path<State>> = vec![first];
+    let mut candidates: Vec<Path<State>> = vec![];
+    while found.len() < k {
+        let previous = &found[found.len() - 1];
+        for i in 0..previous.states.len() - 1 {
+            let root = &previous.states[..=i];
+            let removed_steps = found
+                .iter()
+                .filter(|path| path.states.len() > i + 1 && path.states[..=i] == *root)
+                .map(|path| (path.states[i], path.states[i + 1]))
+                .collect();
+            let space = RestrictedSpace {
+                grid: &grid,
+                removed_states: root[..i].iter().copied().collect(),
+                removed_steps,
+            };
+            let Some(spur) = a_star_search(&space, root[i]) else {
+                continue;
+            };
+            let mut states = root[..i].to_vec();
+            states.extend(spur.states);
+            let candidate = Path {
+                cost: step_costs(&grid, root) + spur.cost,
+                states,
+                expanded: spur.expanded,
+            };
+            if !candidates
+                .iter()
+                .any(|path| path.states == candidate.states)
+            {
+                candidates.push(candidate);
+            }
+        }
+
+        let Some(cheapest) = (0..candidates.len()).min_by_key(|i| candidates[*i].cost) else {
+            break;
+        };
+        found.push(candidates.swap_remove(cheapest));
+    }
+
+    let best_cost = found[0].cost;
+    Ok(found
+        .into_iter()
+        .map(|path| {
+            let report = SolveReport {
+                expanded: path.expanded,
+                suboptimality_bound: Some(if best_cost == 0 {
+                    1.0
+                } else {
+                    path.cost as f64 / best_cost as f64
+                }),
+            };
+            Solution::new(path.states, path.cost, map.clone(), report, 0)
+        })
+        .collect())
+}
+
+/// The cost of stepping along the states
+fn step_costs(grid: &GridSpace, states: &[State]) -> u32 {
+    states
+        .windows(2)
+        .map(|step| {
+            grid.successors(&step[0])
+                .into_iter()
+                .find(|(next, _)| *next == step[1])
+                .map_or(0, |(_, cost)| cost)
+        })
+        .sum()
+}
+
+impl Map {
+    /// Draws the paths of the solutions on the map, each in its own color. Blocks on several paths
+    /// take the color of the first of them, so pass the solutions from the most to the least important,
+    /// e.g. as [k_shortest_paths] returns them.
+    #[cfg(feature = "image")]
+    pub fn routes_image(&self, routes: &[Solution], options: &RenderOptions) -> Option<RgbaImage> {
+        let mut route_of = vec![vec![None; self.width()]; self.height()];
+        for (i, route) in routes.iter().enumerate().rev() {
+            for block in route.path() {
+                route_of[block.y][block.x] = Some(i);
+            }
+        }
+        self.to_image_colored(options, |block| match route_of[block.y][block.x] {
+            Some(i) => region_color(i),
+            None => options.palette.color(block.block_type()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star;
+
+    #[test]
+    fn alternatives_are_ranked_by_cost() {
+        let map = Map::from_rows(&["#.....#", "..#.#..", "#.ooo.#"]);
+        let (start, goal) = (map.get_block(0, 1).unwrap(), map.get_block(6, 1).unwrap());
+
+        let routes = k_shortest_paths(&map, start, goal, 10).unwrap();
+
+        assert_eq!(routes[0].cost(), a_star(&map, start, goal).unwrap().cost());
+        assert!(routes
+            .windows(2)
+            .all(|pair| pair[0].cost() <= pair[1].cost()));
+        assert!(routes.iter().all(|route| route.verify(&map).is_ok()));
+        let distinct: HashSet<_> = routes.iter().map(|route| route.path().to_vec()).collect();
+        assert_eq!(distinct.len(), routes.len());
+        // Along the top, along the bottom or switching sides in the middle
+        assert_eq!(
+            routes.iter().map(Solution::cost).collect::<Vec<_>>(),
+            [8, 18, 18, 20]
+        );
+        assert!(routes[0].report().is_optimal());
+        assert_eq!(routes[3].report().suboptimality_bound, Some(2.5));
+    }
+
+    #[test]
+    fn unreachable_destinations_fail() {
+        let map = Map::from_rows(&["..#.."]);
+        let (start, goal) = (map.get_block(0, 0).unwrap(), map.get_block(4, 0).unwrap());
+
+        let error = k_shortest_paths(&map, start, goal, 3).err().unwrap();
+
+        assert_eq!(error.downcast_ref(), Some(&MazeError::NoPath));
+        assert!(k_shortest_paths(&map, start, start, 0).unwrap().is_empty());
+    }
+}
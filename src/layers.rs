@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+
+use image::RgbaImage;
+
+use crate::{Map, RenderOptions, SearchTrace, Solution};
+
+pub(crate) const BACKGROUND: [u8; 4] = [255, 255, 255, 255];
+pub(crate) const EXPLORED: [u8; 4] = [70, 130, 220, 110];
+pub(crate) const FRONTIER: [u8; 4] = [240, 150, 30, 170];
+pub(crate) const PATH: [u8; 4] = [200, 30, 60, 220];
+pub(crate) const MARKERS: [u8; 4] = [0, 0, 0, 255];
+
+/// Blocks drawn in one color over the layers below
+#[derive(Debug, Clone, PartialEq)]
+struct Layer {
+    blocks: HashSet<(usize, usize)>,
+    color: [u8; 4],
+}
+
+/// The layers of an image drawn with [Map::layered_image]: the terrain of the map at the bottom,
+/// then the explored blocks, the frontier, the path and the markers on top.
+/// Every layer is optional and has its own RGBA color, whose alpha decides how much of the layers below shows through.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layers {
+    terrain: bool,
+    explored: Option<Layer>,
+    frontier: Option<Layer>,
+    path: Option<Layer>,
+    markers: Option<Layer>,
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        Self {
+            terrain: true,
+            explored: None,
+            frontier: None,
+            path: None,
+            markers: None,
+        }
+    }
+}
+
+impl Layers {
+    /// How a search explored the map until it found the solution: the explored blocks, the frontier
+    /// that was left, the path and its start and destination as markers, in the default colors
+    pub fn from_trace(trace: &SearchTrace, solution: &Solution) -> Self {
+        let step = trace.expansion_count();
+        let path = solution.path().iter().map(|block| (block.x, block.y));
+        let ends = [path.clone().next(), path.clone().next_back()];
+        Self::default()
+            .explored(trace.explored(step), EXPLORED)
+            .frontier(trace.frontier(step), FRONTIER)
+            .path(path, PATH)
+            .markers(ends.into_iter().flatten(), MARKERS)
+    }
+
+    /// Whether the blocks are drawn in the colors of the palette below the layers, otherwise on white
+    pub fn terrain(self, terrain: bool) -> Self {
+        Self { terrain, ..self }
+    }
+
+    pub fn explored(
+        self,
+        blocks: impl IntoIterator<Item = (usize, usize)>,
+        color: [u8; 4],
+    ) -> Self {
+        Self {
+            explored: Some(Layer::new(blocks, color)),
+            ..self
+        }
+    }
+
+    pub fn frontier(
+        self,
+        blocks: impl IntoIterator<Item = (usize, usize)>,
+        color: [u8; 4],
+    ) -> Self {
+        Self {
+            frontier: Some(Layer::new(blocks, color)),
+            ..self
+        }
+    }
+
+    pub fn path(self, blocks: impl IntoIterator<Item = (usize, usize)>, color: [u8; 4]) -> Self {
+        Self {
+            path: Some(Layer::new(blocks, color)),
+            ..self
+        }
+    }
+
+    /// Single blocks to point out, e.g. the start and the destination
+    pub fn markers(self, blocks: impl IntoIterator<Item = (usize, usize)>, color: [u8; 4]) -> Self {
+        Self {
+            markers: Some(Layer::new(blocks, color)),
+            ..self
+        }
+    }
+
+    /// The color of the block at `x`, `y` with every layer blended over the base color
+    fn color(&self, (x, y): (usize, usize), base: [u8; 4]) -> [u8; 4] {
+        [&self.explored, &self.frontier, &self.path, &self.markers]
+            .into_iter()
+            .flatten()
+            .filter(|layer| layer.blocks.contains(&(x, y)))
+            .fold(base, |below, layer| blend(layer.color, below))
+    }
+}
+
+impl Layer {
+    fn new(blocks: impl IntoIterator<Item = (usize, usize)>, color: [u8; 4]) -> Self {
+        Self {
+            blocks: blocks.into_iter().collect(),
+            color,
+        }
+    }
+}
+
+/// Draws `over` on top of `under` with the alpha of both
+pub(crate) fn blend(over: [u8; 4], under: [u8; 4]) -> [u8; 4] {
+    let (over_alpha, under_alpha) = (over[3] as f64 / 255.0, under[3] as f64 / 255.0);
+    let alpha = over_alpha + under_alpha * (1.0 - over_alpha);
+    if alpha == 0.0 {
+        return [0; 4];
+    }
+    let mut blended = [0; 4];
+    for i in 0..3 {
+        blended[i] = ((over[i] as f64 * over_alpha
+            + under[i] as f64 * under_alpha * (1.0 - over_alpha))
+            / alpha)
+            .round() as u8;
+    }
+    blended[3] = (alpha * 255.0).round() as u8;
+    blended
+}
+
+impl Map {
+    /// Composites the layers into one image, e.g. to show how a search explored the map, see [Layers::from_trace]
+    pub fn layered_image(&self, layers: &Layers, options: &RenderOptions) -> Option<RgbaImage> {
+        self.to_image_colored(options, |block| {
+            let base = if layers.terrain {
+                options.palette.color(block.block_type())
+            } else {
+                BACKGROUND
+            };
+            layers.color((block.x, block.y), base)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockType, SearchOptions, SolveAlgorithm};
+
+    fn options() -> RenderOptions {
+        RenderOptions {
+            block_width: 1,
+            border_width: 0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn layers_are_blended_in_order() {
+        let map = Map::from_rows(&["..#"]);
+        let layers = Layers::default()
+            .terrain(false)
+            .explored([(0, 0), (1, 0)], [0, 0, 255, 255])
+            .path([(1, 0)], [255, 0, 0, 128])
+            .markers([(2, 0)], [0, 0, 0, 0]);
+
+        let image = map.layered_image(&layers, &options()).unwrap();
+
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [128, 0, 127, 255]);
+        // Transparent layers leave the white background
+        assert_eq!(image.get_pixel(2, 0).0, BACKGROUND);
+
+        let terrain = map.layered_image(&Layers::default(), &options()).unwrap();
+        assert_eq!(terrain, map.to_image_with(&options()).unwrap());
+        assert_eq!(
+            terrain.get_pixel(2, 0).0,
+            options().palette.color(BlockType::Black)
+        );
+    }
+
+    #[test]
+    fn traces_show_the_explored_blocks_and_the_path() {
+        let map = Map::from_rows(&["....", "....", "...."]);
+        let (start, goal) = (map.get_block(0, 1).unwrap(), map.get_block(3, 1).unwrap());
+        let (solution, trace) = SolveAlgorithm::Dijkstra
+            .solve_traced(&map, start, goal, &SearchOptions::default())
+            .unwrap();
+
+        let layers = Layers::from_trace(&trace, &solution);
+        let image = map.layered_image(&layers, &options()).unwrap();
+
+        assert_eq!(image.get_pixel(0, 1).0, MARKERS);
+        assert_eq!(image.get_pixel(3, 1).0, MARKERS);
+        let green = options().palette.color(BlockType::Green);
+        assert_ne!(image.get_pixel(1, 1).0, green);
+        assert_ne!(image.get_pixel(1, 1).0, image.get_pixel(0, 0).0);
+    }
+}
@@ -1,85 +1,345 @@
+#[cfg(feature = "image")]
+mod animation;
+#[cfg(any(test, feature = "proptest"))]
+mod arbitrary;
+mod bench;
+mod breakdown;
+mod cancel;
+mod chunked;
+mod compare;
+mod directions;
+mod distance;
+mod dstar_lite;
+mod dynamic;
+mod error;
+mod fog;
+mod hex;
+mod hierarchical;
+mod impact;
+mod k_shortest;
+#[cfg(feature = "image")]
+mod layers;
 mod map;
+mod map3d;
 mod maze_generation;
+mod maze_solution;
+mod multi;
+mod openings;
+mod outcome;
+mod polar;
+mod provider;
+#[cfg(feature = "python")]
+mod python;
+mod region;
+mod scenario;
+mod search;
+mod smooth;
+mod theta_star;
+mod trace;
+mod verify;
+#[cfg(feature = "image")]
+mod viewer;
+#[cfg(feature = "wasm")]
+mod wasm;
 
-use std::cmp::Reverse;
-use std::collections::HashMap;
-use std::fmt::Display;
-use std::sync::Arc;
+use std::{
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
+#[cfg(feature = "image")]
+pub use animation::{AnimationFormat, AnimationOptions};
 use anyhow::anyhow;
-use anyhow::Ok;
+pub use bench::{benchmark, BenchResult, Solver};
+pub use breakdown::{CostBreakdown, TerrainCost};
+pub use cancel::CancellationToken;
+pub use chunked::ChunkedMap;
+pub use compare::{DivergentSegment, PathDiff};
+pub use directions::Instruction;
+pub use distance::DistanceField;
+pub use dstar_lite::DStarLite;
+pub use dynamic::{a_star_dynamic, DynamicMap, DynamicSolution, Schedule};
+pub use error::{MazeError, PathError};
+pub use fog::{solve_with_fog, FogTrace};
+pub use hex::{a_star_hex, generate_hex, HexCell, HexCoord, HexDirection, HexMap, HexSolution};
+pub use hierarchical::HierarchicalPlanner;
+pub use impact::RouteImpact;
 use itertools::Itertools;
+pub use k_shortest::k_shortest_paths;
+#[cfg(feature = "image")]
+pub use layers::Layers;
+pub use map::BitGrid;
 pub use map::Block;
+pub use map::BlockType;
+pub use map::ColorRamp;
+pub use map::Components;
+pub use map::Corridor;
+pub use map::Direction;
+pub use map::DownscalePolicy;
+pub use map::Edge;
+pub use map::FlowField;
+#[cfg(feature = "image")]
+pub use map::ImportOptions;
+pub use map::IsometricOptions;
+pub use map::KeyColor;
 pub use map::Map;
-pub use maze_generation::generate_maze;
-use priority_queue::PriorityQueue;
+pub use map::Metadata;
+pub use map::PackedMap;
+pub use map::PageSize;
+pub use map::Palette;
+pub use map::PortalColor;
+pub use map::RenderOptions;
+pub use map::RiverOptions;
+pub use map::TextTheme;
+pub use map::Unreachability;
+pub use map::ValidationFinding;
+pub use map::ValidationReport;
+pub use map::{TerrainBand, TerrainNoise};
+#[cfg(feature = "image")]
+pub use map::{Tile, Tileset};
+pub use map3d::{a_star_3d, generate_3d, Location3D, Map3D, Solution3D};
+pub use maze_generation::{
+    generate, generate_cave, generate_maze, generate_maze_iter, generate_parallel,
+    generate_unicursal, generate_with_difficulty, generate_with_progress, Axis, CarveEvent,
+    CarveEvents, CaveOptions, Cell, Color, ColoringStrategy, DifficultyRange, GenOptions,
+    Labyrinth, Mask, MazeAlgorithm, MazeAnalysis, MazeMap, SelectionPolicy, Symmetry, Wall,
+};
+pub use maze_solution::{a_star_maze, MazeSolution};
+pub use mazes_core::{CostGrid, GridPath};
+pub use multi::{solve_multi, MultiSolution};
+pub use openings::{suggest_openings, Opening};
+pub use outcome::SolveOutcome;
+pub use polar::{a_star_polar, generate_polar, PolarCell, PolarCoord, PolarMap, PolarSolution};
+pub use provider::{a_star_provider, MapProvider, ProviderSolution};
+pub use region::Region;
+pub use scenario::Scenario;
+pub use search::SolveReport;
+use search::{
+    ida_star_search, interruptible_search, Budget, HeuristicWeight, Interrupted, Observer, Path,
+    SearchSpace,
+};
+pub use smooth::SmoothedPath;
+pub use theta_star::{theta_star, AnyAnglePath};
+pub use trace::SearchTrace;
+#[cfg(feature = "image")]
+pub use viewer::Viewer;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct State {
     location: Block,
+    /// On a weave crossing: the axis of the passage the agent moves along.
+    /// Both passages of a crossing are separate states, since the agent cannot turn there.
+    layer: Option<Axis>,
+    /// One bit per [KeyColor] the agent has picked up
+    keys: u8,
+    /// The direction of the last step. Only tracked when turning costs extra.
+    heading: Option<Direction>,
 }
 
 impl State {
     fn new(location: Block) -> Self {
-        Self { location }
+        Self {
+            location,
+            layer: None,
+            keys: 0,
+            heading: None,
+        }
+        .with_key_of(location)
+    }
+
+    /// The state after moving from this state onto the neighboring `block`
+    fn moved_to(&self, block: Block) -> Self {
+        Self {
+            location: block,
+            layer: block.crossing().map(|_| axis_between(self.location, block)),
+            keys: self.keys,
+            heading: None,
+        }
+        .with_key_of(block)
+    }
+
+    fn with_key_of(mut self, block: Block) -> Self {
+        if let Some(color) = block.key() {
+            self.keys |= 1 << color as u8;
+        }
+        self
+    }
+
+    pub fn has_key(&self, color: KeyColor) -> bool {
+        self.keys & (1 << color as u8) != 0
+    }
+
+    /// Whether the agent may move onto the neighboring `block`:
+    /// it can't turn on a weave crossing and needs the matching key for a door.
+    fn can_move_to(&self, block: Block) -> bool {
+        self.layer
+            .is_none_or(|layer| axis_between(self.location, block) == layer)
+            && block.door().is_none_or(|color| self.has_key(color))
     }
 
     pub fn display_on_map(&self, map: &Map) -> String {
-        map.to_string_with_locations(&vec![self.location], false)
+        map.to_string_with_locations(&[self.location], false)
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
-struct Node {
-    state: State,
-    parent: Option<Arc<Node>>,
-    cost: u32,
+fn axis_between(from: Block, to: Block) -> Axis {
+    if from.y == to.y {
+        Axis::Horizontal
+    } else {
+        Axis::Vertical
+    }
 }
 
-impl Node {
-    fn new(state: State, parent: Option<Arc<Node>>, cost: u32) -> Self {
-        Self {
-            state,
-            parent,
-            cost,
-        }
-    }
+/// The straight line distance between two blocks, rounded down.
+fn euclidean_distance(from: Block, to: Block) -> u32 {
+    (((from.x as i32 - to.x as i32).pow(2) + (from.y as i32 - to.y as i32).pow(2)) as f64).sqrt()
+        as u32
+}
 
-    fn get_steps(&self) -> Vec<State> {
-        let mut solution: Vec<State> = Vec::new();
-        let mut node = self;
-        while let Some(parent) = &node.parent {
-            solution.push(node.state);
-            node = parent;
+/// A lower bound of the cost to the destination that stays admissible on maps with portals,
+/// where the straight line distance alone would overestimate.
+struct DistanceBound {
+    destination: Block,
+    /// Every portal entrance with a lower bound of the cost from stepping into it to the destination
+    portals: Vec<(Block, u32)>,
+}
+
+impl DistanceBound {
+    fn new(map: &impl MapProvider, destination: Block) -> Self {
+        let pairs = map.portal_pairs();
+        let mut bounds = pairs
+            .iter()
+            .map(|(_, exit)| euclidean_distance(*exit, destination))
+            .collect_vec();
+        // Every round allows chaining one more portal
+        for _ in 0..pairs.len() {
+            for (i, (_, exit)) in pairs.iter().enumerate() {
+                for (j, (entrance, _)) in pairs.iter().enumerate() {
+                    bounds[i] = bounds[i].min(euclidean_distance(*exit, *entrance) + bounds[j]);
+                }
+            }
         }
-        solution.push(node.state);
-        solution.reverse();
-        solution
-    }
 
-    fn euclidean_distance(&self, destination: Block) -> u32 {
-        (((self.state.location.x as i32 - destination.x as i32).pow(2)
-            + (self.state.location.y as i32 - destination.y as i32).pow(2)) as f64)
-            .sqrt() as u32
+        Self {
+            destination,
+            portals: pairs
+                .into_iter()
+                .map(|(entrance, _)| entrance)
+                .zip(bounds)
+                .collect_vec(),
+        }
     }
 
-    fn f(&self, destination: Block) -> u32 {
-        self.euclidean_distance(destination) + self.cost
+    fn estimate(&self, from: Block) -> u32 {
+        self.portals
+            .iter()
+            .map(|(entrance, bound)| euclidean_distance(from, *entrance) + bound)
+            .fold(euclidean_distance(from, self.destination), u32::min)
     }
 }
 
 pub struct Solution {
     states: Vec<State>,
+    path: Vec<Block>,
     map: Map,
     cost: u32,
+    report: SolveReport,
+    /// The extra cost of every turn that is contained in the cost
+    turn_penalty: u32,
+    /// The extra cost of entering a block in each region that is contained in the cost
+    region_penalties: Vec<(Region, u32)>,
 }
 
 impl Solution {
-    fn new(node: &Node, mut map: Map) -> Self {
-        let states = node.get_steps();
-        let cost = node.cost;
-        map.enter_solution(&states.iter().map(|state| state.location).collect_vec());
-        Self { states, map, cost }
+    pub fn cost(&self) -> u32 {
+        self.cost
+    }
+
+    /// How often the path changes its direction. Like in the search, teleporting through a portal
+    /// has no direction, so neither stepping into a portal nor out of its partner counts as a turn.
+    pub fn turns(&self) -> usize {
+        self.states
+            .iter()
+            .tuple_windows()
+            .map(|(from, to)| Direction::between(from.location, to.location))
+            .tuple_windows()
+            .filter(|(before, after)| before.is_some() && after.is_some() && before != after)
+            .count()
+    }
+
+    fn new(
+        states: Vec<State>,
+        cost: u32,
+        mut map: Map,
+        report: SolveReport,
+        turn_penalty: u32,
+    ) -> Self {
+        let path = states.iter().map(|state| state.location).collect_vec();
+        map.enter_solution(&path);
+        Self {
+            states,
+            path,
+            map,
+            cost,
+            report,
+            turn_penalty,
+            region_penalties: vec![],
+        }
+    }
+
+    fn with_region_penalties(mut self, penalties: &[(Region, u32)]) -> Self {
+        self.region_penalties = penalties.to_vec();
+        self
+    }
+
+    /// How much work the search took and how close the cost is guaranteed to be to the cheapest one
+    pub fn report(&self) -> &SolveReport {
+        &self.report
+    }
+
+    /// The blocks from start to destination, both inclusive
+    pub fn path(&self) -> &[Block] {
+        &self.path
+    }
+
+    /// Every block of the path with the cost of getting there from the start.
+    /// Turn penalties are not included.
+    fn costs_so_far(&self) -> impl Iterator<Item = (Block, u32)> + '_ {
+        self.path.iter().enumerate().scan(0, |cost, (i, block)| {
+            if i > 0 {
+                *cost += block.speed() as u32;
+            }
+            Some((*block, *cost))
+        })
+    }
+
+    /// The cost, its [breakdown](Self::cost_breakdown) and the path as a JSON array of
+    /// `{"x": .., "y": .., "cost_so_far": ..}` objects
+    pub fn to_json(&self) -> String {
+        let entries = self
+            .costs_so_far()
+            .map(|(block, cost)| {
+                format!(
+                    r#"{{"x": {}, "y": {}, "cost_so_far": {cost}}}"#,
+                    block.x, block.y
+                )
+            })
+            .join(",\n    ");
+        format!(
+            "{{\n  \"cost\": {},\n  \"cost_breakdown\": {},\n  \"path\": [\n    {entries}\n  ]\n}}\n",
+            self.cost,
+            self.cost_breakdown().to_json()
+        )
+    }
+
+    /// The path as CSV with a `x,y,cost_so_far` header
+    pub fn to_csv(&self) -> String {
+        let mut csv = "x,y,cost_so_far\n".to_string();
+        for (block, cost) in self.costs_so_far() {
+            csv += &format!("{},{},{cost}\n", block.x, block.y);
+        }
+        csv
     }
 
     pub fn as_sequence_of_maps(&self, map: &Map) -> Vec<String> {
@@ -89,6 +349,27 @@ impl Solution {
             .collect_vec()
     }
 
+    /// The solution map in the given theme followed by a summary
+    pub fn to_text_themed(&self, theme: TextTheme) -> String {
+        self.to_text_with(theme, &Palette::default())
+    }
+
+    /// Like [to_text_themed](Self::to_text_themed), but the colored themes use the colors of the palette
+    pub fn to_text_with(&self, theme: TextTheme, palette: &Palette) -> String {
+        format!(
+            "{}This solution cost {} and involves {} steps\nThe cost comes from {}\n",
+            self.map.to_text_with(theme, palette),
+            self.cost,
+            self.states.len(),
+            self.cost_breakdown()
+        )
+    }
+
+    /// The map with the blocks of the path turned into solution blocks, without giving up the solution
+    pub fn map(&self) -> &Map {
+        &self.map
+    }
+
     pub fn to_solution_map(self) -> Map {
         self.map
     }
@@ -96,49 +377,681 @@ impl Solution {
 
 impl Display for Solution {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.map.to_string_with_locations(&vec![], false))?;
-        f.write_fmt(format_args!(
-            "This solution cost {} and involves {} steps\n",
-            self.cost,
-            self.states.len()
-        ))
+        f.write_str(&self.to_text_themed(TextTheme::Emoji))
+    }
+}
+
+/// Changes how [a_star_with] weighs paths.
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    turn_penalty: u32,
+    weight: HeuristicWeight,
+    cancellation: Option<CancellationToken>,
+    avoided: Vec<Region>,
+    penalties: Vec<(Region, u32)>,
+    budget: Budget<u32>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            turn_penalty: 0,
+            weight: HeuristicWeight::Factor(1.0),
+            cancellation: None,
+            avoided: vec![],
+            penalties: vec![],
+            budget: Budget::default(),
+        }
     }
 }
 
+impl SearchOptions {
+    /// Weighted A*: orders the search by `cost + weight * heuristic`. A weight above one finds a path
+    /// faster on large maps, which costs at most `weight` times as much as the cheapest one.
+    pub fn weight(mut self, weight: f64) -> Self {
+        self.weight = HeuristicWeight::Factor(weight);
+        self
+    }
+
+    /// Lets the search fail with [MazeError::Cancelled] once the token is cancelled
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Greedy best-first search: only follows the heuristic. Usually the fastest, but without any guarantee on the cost.
+    pub fn greedy(mut self) -> Self {
+        self.weight = HeuristicWeight::Greedy;
+        self
+    }
+
+    /// Adds the penalty to the cost of every step that changes the direction, e.g. for vehicles.
+    pub fn turn_penalty(mut self, penalty: u32) -> Self {
+        self.turn_penalty = penalty;
+        self
+    }
+
+    /// Never steps onto a block in the region, as if it were a wall. The start may lie in it, but not the destination.
+    pub fn avoid(mut self, region: Region) -> Self {
+        self.avoided.push(region);
+        self
+    }
+
+    /// Adds the cost to every step onto a block in the region, so that paths only cross it
+    /// when going around is more expensive. The costs of overlapping regions add up.
+    pub fn penalize(mut self, region: Region, extra_cost: u32) -> Self {
+        self.penalties.push((region, extra_cost));
+        self
+    }
+
+    /// Fails with [MazeError::PathNotFoundWithinBudget] instead of finding a path that costs more,
+    /// e.g. for an agent with limited fuel. The search never looks beyond the budget.
+    pub fn max_cost(mut self, max_cost: u32) -> Self {
+        self.budget.max_cost = Some(max_cost);
+        self
+    }
+
+    /// Fails with [MazeError::PathNotFoundWithinBudget] instead of finding a path with more steps.
+    /// Only limits the cheapest way to each block, so a more expensive path with fewer steps may be missed.
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.budget.max_steps = Some(max_steps);
+        self
+    }
+
+    fn is_avoided(&self, block: Block) -> bool {
+        self.avoided
+            .iter()
+            .any(|region| region.contains(block.x, block.y))
+    }
+}
+
+/// Moving between neighboring blocks of a single [Map] or any other [MapProvider]
+struct GridSpace<'a, M = Map> {
+    map: &'a M,
+    destination: Block,
+    /// `None` to search without a heuristic
+    bound: Option<DistanceBound>,
+    options: &'a SearchOptions,
+}
+
+impl<M: MapProvider> SearchSpace for GridSpace<'_, M> {
+    type State = State;
+
+    fn successors(&self, state: &State) -> Vec<(State, u32)> {
+        self.map
+            .get_reachable(state.location.x, state.location.y)
+            .into_iter()
+            .filter(|block| state.can_move_to(*block) && !self.options.is_avoided(*block))
+            .map(|block| {
+                let mut next = state.moved_to(block);
+                let mut cost =
+                    block.speed() as u32 + region_penalty(&self.options.penalties, block);
+                if self.options.turn_penalty > 0 {
+                    // Teleporting through a portal has no direction and resets the heading
+                    next.heading = Direction::between(state.location, block);
+                    if state.heading.is_some()
+                        && next.heading.is_some()
+                        && state.heading != next.heading
+                    {
+                        cost += self.options.turn_penalty;
+                    }
+                }
+                (next, cost)
+            })
+            .collect_vec()
+    }
+
+    fn heuristic(&self, state: &State) -> u32 {
+        self.bound
+            .as_ref()
+            .map_or(0, |bound| bound.estimate(state.location))
+    }
+
+    fn is_goal(&self, state: &State) -> bool {
+        state.location == self.destination
+    }
+
+    fn budget(&self) -> Budget<u32> {
+        self.options.budget
+    }
+}
+
+/// The sum of the extra costs of the regions that contain the block
+fn region_penalty(penalties: &[(Region, u32)], block: Block) -> u32 {
+    penalties
+        .iter()
+        .filter(|(region, _)| region.contains(block.x, block.y))
+        .map(|(_, extra_cost)| extra_cost)
+        .sum()
+}
+
+/// Finds the cheapest path from the start to the destination block.
+/// Doors can only be passed after picking up a key of the same color, so the path may detour to collect keys.
+/// Stepping onto a portal moves the agent to its partner.
 pub fn a_star(map: &Map, start_block: Block, destination_block: Block) -> anyhow::Result<Solution> {
-    let first_state = State::new(start_block);
-    let first_node = Arc::new(Node::new(first_state, None, 0));
-
-    let mut frontier: PriorityQueue<Arc<Node>, Reverse<u32>> = PriorityQueue::new();
-    let mut reached: HashMap<State, Arc<Node>> = HashMap::new();
-
-    let f = first_node.f(destination_block);
-
-    frontier.push(first_node, Reverse(f));
-
-    while !frontier.is_empty() {
-        let (node, _) = frontier.pop().ok_or(anyhow!("Frontier is empty"))?;
-        if node.state.location == destination_block {
-            return Ok(Solution::new(&node, (*map).clone()));
-        }
-        for action in map.get_reachable(node.state.location.x, node.state.location.y) {
-            let new_state = State::new(action);
-            let child = Arc::new(Node::new(
-                new_state,
-                Some(node.clone()),
-                node.cost + new_state.location.speed() as u32,
-            ));
-            if !reached.contains_key(&new_state) {
-                reached.insert(new_state, child.clone());
-                frontier.push(child.clone(), Reverse(child.f(destination_block)));
-            } else if child.cost < reached[&child.state].cost {
-                // Remove old (worse) node
-                frontier.remove(&reached[&child.state]);
-                reached.insert(child.state, child.clone());
-                frontier.push(child.clone(), Reverse(child.f(destination_block)));
-            }
+    a_star_with(
+        map,
+        start_block,
+        destination_block,
+        &SearchOptions::default(),
+    )
+}
+
+/// Like [a_star], but with [SearchOptions] that change the cost of a path.
+pub fn a_star_with(
+    map: &Map,
+    start_block: Block,
+    destination_block: Block,
+    options: &SearchOptions,
+) -> anyhow::Result<Solution> {
+    SolveAlgorithm::AStar.solve(map, start_block, destination_block, options)
+}
+
+/// Searches any [MapProvider] with the algorithm, after checking that it supports the options.
+/// IDA* expands states over and over, so the observer only sees A* and Dijkstra.
+fn search_grid<M: MapProvider>(
+    map: &M,
+    algorithm: SolveAlgorithm,
+    start_block: Block,
+    destination_block: Block,
+    options: &SearchOptions,
+    observer: &mut impl Observer<State>,
+) -> anyhow::Result<(Path<State>, SolveReport)> {
+    match (algorithm, options.weight) {
+        (SolveAlgorithm::AStar, HeuristicWeight::Factor(weight))
+            if weight.is_nan() || weight < 1.0 =>
+        {
+            return Err(anyhow!("The heuristic weight must be at least 1"));
+        }
+        (SolveAlgorithm::IdaStar, weight) if weight != HeuristicWeight::Factor(1.0) => {
+            return Err(anyhow!("IDA* does not support a heuristic weight"));
+        }
+        (SolveAlgorithm::Dijkstra, weight) if weight != HeuristicWeight::Factor(1.0) => {
+            return Err(anyhow!("Dijkstra does not support a heuristic weight"));
+        }
+        _ => {}
+    }
+    let space = GridSpace {
+        map,
+        destination: destination_block,
+        bound: (algorithm != SolveAlgorithm::Dijkstra)
+            .then(|| DistanceBound::new(map, destination_block)),
+        options,
+    };
+    let start = State::new(start_block);
+    let should_stop = || options.is_cancelled();
+    let path = match algorithm {
+        SolveAlgorithm::IdaStar => ida_star_search(&space, start, should_stop),
+        SolveAlgorithm::AStar | SolveAlgorithm::Dijkstra => {
+            interruptible_search(&space, start, options.weight, should_stop, observer)
+        }
+    };
+    let path = found_path(path)?;
+    let report = SolveReport::new(&path, options.weight);
+    Ok((path, report))
+}
+
+fn found_path<S>(result: Result<Option<Path<S>>, Interrupted>) -> anyhow::Result<Path<S>> {
+    match result {
+        Ok(Some(path)) => Ok(path),
+        Ok(None) => Err(MazeError::NoPath.into()),
+        Err(Interrupted::Stopped) => Err(MazeError::Cancelled.into()),
+        Err(Interrupted::OverBudget { frontier_cost }) => {
+            Err(MazeError::PathNotFoundWithinBudget { frontier_cost }.into())
+        }
+    }
+}
+
+/// The heuristic weights [a_star_anytime] searches with, from the fastest to the optimal one
+const ANYTIME_WEIGHTS: [f64; 6] = [5.0, 3.0, 2.0, 1.5, 1.2, 1.0];
+
+/// Searches with decreasing heuristic weights until the `budget` runs out and returns the cheapest path found.
+/// The first searches are fast but may find expensive paths, later ones tighten the
+/// [suboptimality bound](SolveReport::suboptimality_bound), which is one once the path is optimal.
+/// Fails only if not even the fastest search finished within the budget.
+pub fn a_star_anytime(
+    map: &Map,
+    start_block: Block,
+    destination_block: Block,
+    budget: Duration,
+) -> anyhow::Result<Solution> {
+    let deadline = Instant::now() + budget;
+    let space = GridSpace {
+        map,
+        destination: destination_block,
+        bound: Some(DistanceBound::new(map, destination_block)),
+        options: &SearchOptions::default(),
+    };
+
+    let mut best: Option<(Path<State>, f64)> = None;
+    let mut expanded = 0;
+    for weight in ANYTIME_WEIGHTS {
+        let result = interruptible_search(
+            &space,
+            State::new(start_block),
+            HeuristicWeight::Factor(weight),
+            || Instant::now() >= deadline,
+            &mut (),
+        );
+        let Ok(path) = result else {
+            break;
+        };
+        let Some(path) = path else {
+            return Err(MazeError::NoPath.into());
+        };
+        expanded += path.expanded;
+        // Every finished search bounds the cost of the cheapest path found so far
+        best = match best {
+            Some((best_path, _)) if best_path.cost <= path.cost => Some((best_path, weight)),
+            _ => Some((path, weight)),
+        };
+    }
+
+    let (path, bound) = best.ok_or(anyhow!("No path was found within the time budget"))?;
+    let report = SolveReport {
+        expanded,
+        suboptimality_bound: Some(bound),
+    };
+    Ok(Solution::new(
+        path.states,
+        path.cost,
+        map.clone(),
+        report,
+        0,
+    ))
+}
+
+/// Like [a_star_with], but with iterative deepening A*, which only keeps the current path in memory.
+/// Meant for maps too large to remember every reached block. Always finds the cheapest path,
+/// so a heuristic weight is rejected.
+pub fn ida_star(
+    map: &Map,
+    start_block: Block,
+    destination_block: Block,
+    options: &SearchOptions,
+) -> anyhow::Result<Solution> {
+    SolveAlgorithm::IdaStar.solve(map, start_block, destination_block, options)
+}
+
+/// Like [a_star_with], but without a heuristic: the states are expanded in order of their cost alone.
+/// Much slower, but its cost doesn't depend on the heuristic being admissible,
+/// so it's a reference for the cheapest cost.
+pub fn dijkstra(
+    map: &Map,
+    start_block: Block,
+    destination_block: Block,
+    options: &SearchOptions,
+) -> anyhow::Result<Solution> {
+    SolveAlgorithm::Dijkstra.solve(map, start_block, destination_block, options)
+}
+
+/// The search algorithm used to solve a [Map]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SolveAlgorithm {
+    /// Fast, but remembers every reached state
+    #[default]
+    AStar,
+    /// Needs very little memory, but expands states several times
+    IdaStar,
+    /// Doesn't use a heuristic, so it expands far more states than A*
+    Dijkstra,
+}
+
+impl SolveAlgorithm {
+    pub const ALL: [SolveAlgorithm; 3] = [
+        SolveAlgorithm::AStar,
+        SolveAlgorithm::IdaStar,
+        SolveAlgorithm::Dijkstra,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            SolveAlgorithm::AStar => "astar",
+            SolveAlgorithm::IdaStar => "idastar",
+            SolveAlgorithm::Dijkstra => "dijkstra",
+        }
+    }
+
+    pub fn solve(
+        self,
+        map: &Map,
+        start_block: Block,
+        destination_block: Block,
+        options: &SearchOptions,
+    ) -> anyhow::Result<Solution> {
+        let (path, report) =
+            search_grid(map, self, start_block, destination_block, options, &mut ())?;
+        Ok(Solution::new(
+            path.states,
+            path.cost,
+            map.clone(),
+            report,
+            options.turn_penalty,
+        )
+        .with_region_penalties(&options.penalties))
+    }
+}
+
+impl Display for SolveAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SolveAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SolveAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == s)
+            .ok_or(anyhow!(
+                "Unknown solve algorithm '{s}'. Possible values: {}",
+                SolveAlgorithm::ALL.iter().join(", ")
+            ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_star_goes_straight_through_crossings() {
+        let map = Map::from_rows(&["#.#", ".-.", "#.#"]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+
+        assert!(a_star(&map, block(0, 1), block(2, 1)).is_ok());
+        assert!(a_star(&map, block(1, 0), block(1, 2)).is_ok());
+        assert!(a_star(&map, block(0, 1), block(1, 0)).is_err());
+    }
+
+    #[test]
+    fn solution_exports_the_cost_so_far() {
+        let map = Map::from_rows(&[".b."]);
+        let solution = a_star(
+            &map,
+            map.get_block(0, 0).unwrap(),
+            map.get_block(2, 0).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(solution.path().len(), 3);
+        assert_eq!(solution.to_csv(), "x,y,cost_so_far\n0,0,0\n1,0,2\n2,0,3\n");
+        assert!(solution
+            .to_json()
+            .contains(r#"{"x": 2, "y": 0, "cost_so_far": 3}"#));
+    }
+
+    #[test]
+    fn a_star_fetches_the_key_before_passing_the_door() {
+        let map = Map::from_rows(&["..A..", "#####", "1...."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+
+        let error = a_star(&map, block(0, 0), block(4, 0)).err().unwrap();
+        assert_eq!(error.downcast_ref(), Some(&MazeError::NoPath));
+
+        let map = Map::from_rows(&["1.A..", "#####", "....."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+        let solution = a_star(&map, block(1, 0), block(4, 0)).unwrap();
+
+        assert_eq!(
+            solution.states.first().map(|s| s.location),
+            Some(block(1, 0))
+        );
+        assert!(solution
+            .states
+            .iter()
+            .any(|state| state.location == block(0, 0)));
+        assert!(solution.states.last().unwrap().has_key(KeyColor::Purple));
+        assert_eq!(solution.cost, 5);
+    }
+
+    #[test]
+    fn a_star_steps_through_portals() {
+        let map = Map::from_rows(&["..........", ".P......P.", ".........."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+
+        let solution = a_star(&map, block(0, 1), block(9, 1)).unwrap();
+
+        assert_eq!(solution.cost, 2);
+        assert_eq!(solution.states[1].location, block(8, 1));
+        assert!(DistanceBound::new(&map, block(9, 1)).estimate(block(0, 1)) <= 2);
+    }
+
+    #[test]
+    fn a_portal_without_partner_is_an_ordinary_block() {
+        let map = Map::from_rows(&[".P.Q"]);
+
+        assert_eq!(map.get_reachable(0, 0), vec![map.get_block(1, 0).unwrap()]);
+    }
+
+    #[test]
+    fn a_star_follows_one_way_blocks() {
+        let map = Map::from_rows(&["..>..", ".###.", "....."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+
+        let there = a_star(&map, block(0, 0), block(4, 0)).unwrap();
+        let back = a_star(&map, block(4, 0), block(0, 0)).unwrap();
+
+        assert_eq!(there.cost, 4);
+        assert_eq!(back.cost, 8);
+    }
+
+    #[test]
+    fn turn_penalty_prefers_straight_paths() {
+        let map = Map::from_rows(&["....", "....", "...."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+        let options = SearchOptions::default().turn_penalty(10);
+
+        let solution = a_star_with(&map, block(0, 0), block(3, 2), &options).unwrap();
+
+        assert_eq!(solution.turns(), 1);
+        assert_eq!(solution.cost(), 5 + 10);
+
+        // Coming out of the portal in the opposite direction is no turn
+        let map = Map::from_rows(&["P..", "###", "P.."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+        let solution = a_star(&map, block(2, 0), block(2, 2)).unwrap();
+        assert_eq!(solution.path().len(), 5);
+        assert_eq!(solution.turns(), 0);
+    }
+
+    #[test]
+    fn avoided_and_penalized_regions_are_detoured() {
+        let map = Map::from_rows(&[".....", ".....", "....."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+        let through_middle = |block: &Block| block.x == 2 && block.y < 2;
+
+        let avoiding = SearchOptions::default().avoid(Region::new(2, 0, 2, 1));
+        let solution = a_star_with(&map, block(0, 0), block(4, 0), &avoiding).unwrap();
+        assert!(!solution.path().iter().any(through_middle));
+        assert_eq!(solution.cost(), 8);
+
+        let penalizing = SearchOptions::default().penalize(Region::new(2, 0, 2, 1), 3);
+        let solution = dijkstra(&map, block(0, 0), block(4, 0), &penalizing).unwrap();
+        assert_eq!(solution.cost(), 4 + 3);
+        assert!(solution.path().iter().any(through_middle));
+        assert!(solution.verify(&map).is_ok());
+
+        let walled_in = SearchOptions::default().avoid(Region::new(2, 0, 2, 2));
+        let error = a_star_with(&map, block(0, 0), block(4, 0), &walled_in)
+            .err()
+            .unwrap();
+        assert_eq!(error.downcast_ref(), Some(&MazeError::NoPath));
+    }
+
+    #[test]
+    fn budgets_reject_paths_beyond_them() {
+        let map = Map::from_rows(&["..y..", ".###.", "....."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+        let (start, destination) = (block(0, 0), block(4, 0));
+
+        for algorithm in SolveAlgorithm::ALL {
+            let within = SearchOptions::default().max_cost(8);
+            assert_eq!(
+                algorithm
+                    .solve(&map, start, destination, &within)
+                    .unwrap()
+                    .cost(),
+                8
+            );
+
+            let too_little = SearchOptions::default().max_cost(7);
+            let error = algorithm
+                .solve(&map, start, destination, &too_little)
+                .err()
+                .unwrap();
+            assert_eq!(
+                error.downcast_ref(),
+                Some(&MazeError::PathNotFoundWithinBudget { frontier_cost: 8 }),
+                "{algorithm}"
+            );
+        }
+
+        // The cheapest path takes 8 steps, the one through the yellow block only 4
+        let few_steps = SearchOptions::default().max_steps(7);
+        let solution = a_star_with(&map, start, destination, &few_steps).unwrap();
+        assert_eq!((solution.cost(), solution.path().len()), (10, 5));
+        let error = a_star_with(
+            &map,
+            start,
+            destination,
+            &SearchOptions::default().max_steps(3),
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(MazeError::PathNotFoundWithinBudget { .. })
+        ));
+
+        // The cheapest way to the block behind the orange one takes too many steps to go on from there,
+        // only the more expensive way through it fits
+        let map = Map::from_rows(&["...##", ".o...", "#####"]);
+        let (start, destination) = (map.get_block(0, 1).unwrap(), map.get_block(4, 1).unwrap());
+        for algorithm in SolveAlgorithm::ALL {
+            let solution = algorithm
+                .solve(
+                    &map,
+                    start,
+                    destination,
+                    &SearchOptions::default().max_steps(5),
+                )
+                .unwrap();
+            assert_eq!(
+                (solution.cost(), solution.path().len()),
+                (8, 5),
+                "{algorithm}"
+            );
         }
     }
 
-    Err(anyhow!("There is no path"))
+    #[test]
+    fn weighted_search_stays_within_its_bound() {
+        let map = Map::from(generate_maze(15, 15, Some(0.3)).unwrap());
+        let block = |x, y| map.get_block(x, y).unwrap();
+        let (start, destination) = (block(1, 1), block(29, 29));
+        let optimal = a_star(&map, start, destination).unwrap();
+
+        let weighted = a_star_with(
+            &map,
+            start,
+            destination,
+            &SearchOptions::default().weight(2.0),
+        )
+        .unwrap();
+        let greedy =
+            a_star_with(&map, start, destination, &SearchOptions::default().greedy()).unwrap();
+
+        assert_eq!(weighted.report().suboptimality_bound, Some(2.0));
+        assert!(weighted.cost() <= 2 * optimal.cost());
+        assert_eq!(greedy.report().suboptimality_bound, None);
+        assert!(greedy.cost() >= optimal.cost());
+    }
+
+    #[test]
+    fn ida_star_finds_the_same_cost_as_a_star() {
+        let map = Map::from(generate_maze(8, 8, Some(0.2)).unwrap());
+        let block = |x, y| map.get_block(x, y).unwrap();
+        let options = SearchOptions::default();
+
+        for destination in [block(15, 15), block(1, 15), block(15, 1)] {
+            let expected = a_star(&map, block(1, 1), destination).unwrap();
+            let solution = ida_star(&map, block(1, 1), destination, &options).unwrap();
+
+            assert_eq!(solution.cost(), expected.cost());
+            assert_eq!(
+                solution.states.last().map(|s| s.location),
+                Some(destination)
+            );
+        }
+    }
+
+    #[test]
+    fn ida_star_reports_unreachable_destinations() {
+        let map = Map::from_rows(&["..#.."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+
+        assert!(ida_star(&map, block(0, 0), block(4, 0), &SearchOptions::default()).is_err());
+    }
+
+    #[test]
+    fn dijkstra_expands_more_but_finds_the_same_cost() {
+        let map = Map::from_rows(&["1.#..", "..A.>", "#P#.#", "o.P.b"]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+        let options = SearchOptions::default().turn_penalty(2);
+
+        let expected = a_star_with(&map, block(0, 3), block(4, 1), &options).unwrap();
+        let solution = dijkstra(&map, block(0, 3), block(4, 1), &options).unwrap();
+
+        assert_eq!(solution.cost(), expected.cost());
+        assert!(solution.report().expanded >= expected.report().expanded);
+    }
+
+    #[test]
+    fn anytime_search_with_enough_time_is_optimal() {
+        let map = Map::from(generate_maze(10, 10, Some(0.3)).unwrap());
+        let block = |x, y| map.get_block(x, y).unwrap();
+
+        let solution =
+            a_star_anytime(&map, block(1, 1), block(19, 19), Duration::from_secs(60)).unwrap();
+
+        assert!(solution.report().is_optimal());
+        assert_eq!(
+            solution.cost(),
+            a_star(&map, block(1, 1), block(19, 19)).unwrap().cost()
+        );
+    }
+
+    #[test]
+    fn anytime_search_without_time_finds_nothing() {
+        let map = Map::from(generate_maze(60, 60, None).unwrap());
+        let block = |x, y| map.get_block(x, y).unwrap();
+
+        assert!(a_star_anytime(&map, block(1, 1), block(119, 119), Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn cancelled_searches_fail_with_cancelled() {
+        let map = Map::from(generate_maze(10, 10, None).unwrap());
+        let block = |x, y| map.get_block(x, y).unwrap();
+        let token = CancellationToken::new();
+        let options = SearchOptions::default().cancellation(token.clone());
+        token.cancel();
+
+        for algorithm in SolveAlgorithm::ALL {
+            let error = algorithm
+                .solve(&map, block(1, 1), block(19, 19), &options)
+                .err()
+                .unwrap();
+            assert_eq!(error.downcast_ref(), Some(&MazeError::Cancelled));
+        }
+    }
 }
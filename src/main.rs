@@ -1,31 +1,223 @@
-use std::{fs::File, io::Write, num::ParseIntError, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::File,
+    io::{BufWriter, Cursor, Read, Write},
+    num::ParseIntError,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use clap::{Args, Parser, Subcommand};
-use mazes::{a_star, generate_maze, Block, Map};
-use promptly::{prompt, prompt_opt};
+use crossterm::{cursor, execute, style::Print};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    codecs::png::PngEncoder,
+    Delay, Frame, ImageError, ImageFormat, RgbaImage,
+};
+use itertools::Itertools;
+use mazes::{
+    a_star, a_star_with, benchmark, dijkstra, generate, generate_cave, generate_maze_iter,
+    generate_parallel, generate_unicursal, generate_with_difficulty, generate_with_progress,
+    k_shortest_paths, solve_with_fog, suggest_openings, theta_star, AnimationFormat,
+    AnimationOptions, Block, BlockType, CarveEvent, CaveOptions, ColorRamp, ColoringStrategy,
+    DifficultyRange, GenOptions, HierarchicalPlanner, ImportOptions, IsometricOptions, Layers, Map,
+    Mask, MazeAlgorithm, MazeError, MazeMap, Metadata, PageSize, Palette, Region, RenderOptions,
+    RiverOptions, Scenario, SearchOptions, SearchTrace, SelectionPolicy, Solution, SolveAlgorithm,
+    SolveOutcome, Solver, Symmetry, TerrainBand, TerrainNoise, TextTheme, Tileset,
+};
+use promptly::{prompt, prompt_opt, Promptable, ReadlineError};
+use rand::{rngs::StdRng, SeedableRng};
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 #[command(propagate_version = true)]
+#[command(
+    after_help = "Exit codes: 0 success, 1 unexpected failure, 2 invalid input or missing argument, 3 no path found, 4 reading or writing a file failed"
+)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Fail instead of prompting for missing arguments
+    #[arg(long, global = true)]
+    no_interactive: bool,
+    /// Only print errors and never prompt
+    #[arg(long, short, global = true)]
+    quiet: bool,
 }
 
+/// How the CLI talks to the user
+#[derive(Debug, Clone, Copy)]
+struct Interaction {
+    prompts: bool,
+    quiet: bool,
+}
+
+impl Interaction {
+    /// Asks for a required argument that wasn't given, or fails when prompts are disabled
+    fn require<T: Promptable>(&self, argument: &'static str, message: &str) -> anyhow::Result<T> {
+        if !self.prompts {
+            return Err(MissingArgument(argument).into());
+        }
+        Ok(prompt(message)?)
+    }
+
+    /// Results written to stdout must not be mixed with other output
+    fn with_results_on_stdout(self, results_on_stdout: bool) -> Self {
+        Self {
+            quiet: self.quiet || results_on_stdout,
+            ..self
+        }
+    }
+
+    /// Asks for an optional argument that wasn't given, unless prompts are disabled
+    /// or the output is quiet, e.g. because results are written to stdout
+    fn optional<T: Promptable>(&self, message: &str) -> anyhow::Result<Option<T>> {
+        if !self.prompts || self.quiet {
+            return Ok(None);
+        }
+        Ok(prompt_opt(message)?)
+    }
+}
+
+/// A required argument wasn't given and prompts are disabled
+#[derive(Debug)]
+struct MissingArgument(&'static str);
+
+impl Display for MissingArgument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Please specify {}", self.0)
+    }
+}
+
+impl std::error::Error for MissingArgument {}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Solve a maze given as a png file
-    Solve(SolveArgs),
+    Solve(Box<SolveArgs>),
     /// Generate a maze and optionally save it as a png file
-    Gen(GenArgs),
+    Gen(Box<GenArgs>),
+    /// Compare the runtime, expanded states and path cost of all solvers on mazes of several sizes
+    Bench(BenchArgs),
+    /// Serve generating and solving over HTTP: `POST /generate` and `POST /solve` with JSON bodies
+    Serve(ServeArgs),
+    /// Show how several solvers explore the same map side by side, one expansion after the other
+    Race(RaceArgs),
+    /// Open a map in a window, click to set the start and the destination and press Tab to switch the algorithm
+    #[cfg(feature = "gui")]
+    View(ViewArgs),
+}
+
+#[cfg(feature = "gui")]
+#[derive(Args)]
+struct ViewArgs {
+    /// The path of the map, in any format `solve` reads
+    #[arg(long, short)]
+    path: PathBuf,
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(Args)]
+struct RaceArgs {
+    /// The path of the map, in any format `solve` reads
+    #[arg(long, short)]
+    path: PathBuf,
+    /// The x coordinate of the start, the first walkable block by default
+    #[arg(long, requires = "start_y")]
+    start_x: Option<usize>,
+    #[arg(long, requires = "start_x")]
+    start_y: Option<usize>,
+    /// The x coordinate of the destination, the last walkable block by default
+    #[arg(long, requires = "dest_y")]
+    dest_x: Option<usize>,
+    #[arg(long, requires = "dest_x")]
+    dest_y: Option<usize>,
+    /// The solvers that race, comma separated: astar, dijkstra or greedy
+    #[arg(long, value_delimiter = ',', default_value = "astar,dijkstra,greedy")]
+    algorithms: Vec<Solver>,
+    /// Save the race as animation instead of showing it in the terminal, an animated png if the path ends
+    /// in .png or .apng and a gif otherwise
+    #[arg(long)]
+    gif: Option<PathBuf>,
+    /// How many expansions each frame advances
+    #[arg(long, default_value_t = 1)]
+    expansions_per_frame: usize,
+    /// How many frames per second the terminal shows
+    #[arg(long, default_value_t = 10.0)]
+    speed: f64,
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    /// The port to listen on
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+    /// The address to listen on, e.g. 0.0.0.0 to accept requests from other machines
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+    /// The largest width and height in blocks that requests may generate or solve
+    #[arg(long, default_value_t = 1001)]
+    max_size: usize,
+    /// How many requests are answered at the same time, further ones wait for a free worker
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    workers: u16,
+    /// The largest request body in bytes, larger requests are answered with status 413
+    #[arg(long, default_value_t = 4 << 20)]
+    max_body_size: u64,
+}
+
+#[derive(Args)]
+struct BenchArgs {
+    /// The widths and heights of the generated mazes in blocks
+    #[arg(long, value_delimiter = ',', default_values_t = [21, 101, 301])]
+    sizes: Vec<usize>,
+    /// Benchmark these maps instead of generated mazes
+    #[arg(long, num_args = 1..)]
+    maps: Vec<PathBuf>,
+    /// Benchmark the problems of a MovingAI .scen file instead and check the costs against their optimal lengths.
+    /// The .map files are looked up next to the .scen file, costs that don't fit are marked with `!`
+    #[arg(long, conflicts_with = "maps")]
+    scen: Option<PathBuf>,
+    /// The probability of loops in the generated mazes, which gives the solvers a choice of paths
+    #[arg(long, value_parser = between_0_1, default_value_t = 0.1)]
+    loop_prob: f64,
+    /// Generates the same mazes and picks the same random pairs every time
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Solve between this many random pairs of connected blocks on each map instead of between the outermost blocks.
+    /// The blocks of a pair are at least half the width and height of the map apart
+    #[arg(long)]
+    random_pairs: Option<usize>,
+    /// Cancel searches that take longer than this many seconds
+    #[arg(long, default_value_t = 10.0)]
+    timeout: f64,
 }
 
 #[derive(Args)]
 struct SolveArgs {
-    /// The path of the map on which the agent shall move
+    /// The path of the map on which the agent shall move, an image, a binary .maze file, a run-length encoded
+    /// .rle or .rle.gz file, a MovingAI .map file or text in the ascii theme. `-` reads stdin
     #[arg(long, short)]
     path: Option<PathBuf>,
+    /// The label of the initial position of the agent, instead of its coordinates
+    #[arg(long, conflicts_with_all = ["start_x", "start_y"])]
+    start: Option<String>,
+    /// The label of the desired destination of the agent, instead of its coordinates
+    #[arg(long, conflicts_with_all = ["dest_x", "dest_y"])]
+    dest: Option<String>,
     /// The x coordinate of the initial position of the agent
     #[arg(long)]
     start_x: Option<usize>,
@@ -38,15 +230,298 @@ struct SolveArgs {
     /// The y coordinate of the desired destination of the agent (origin is in the top left)
     #[arg(long)]
     dest_y: Option<usize>,
-    /// The path where to store the solution as txt
+    /// The path where to store the solution as txt, `-` for stdout
     #[arg(long)]
     txt: Option<PathBuf>,
-    /// The path where to store the solution as png
+    /// The path where to store the solution as png, `-` for stdout
     #[arg(long)]
     png: Option<PathBuf>,
+    /// The path where to store the coordinates of the solution with the cost so far as json, `-` for stdout
+    #[arg(long)]
+    json: Option<PathBuf>,
+    /// The path where to store the coordinates of the solution with the cost so far as csv, `-` for stdout
+    #[arg(long)]
+    csv: Option<PathBuf>,
+    /// The path where to store an image with every connected region of the map in its own color
+    #[arg(long)]
+    components: Option<PathBuf>,
+    /// The path where to store an image of the map with its bottlenecks highlighted: the blocks whose removal
+    /// would split the region they are in, `-` for stdout
+    #[arg(long)]
+    bottlenecks: Option<PathBuf>,
+    /// If the goal can't be reached, the path where to store an image of the region of the start,
+    /// the region of the goal and the fewest walls that separate them highlighted, `-` for stdout
+    #[arg(long)]
+    explain: Option<PathBuf>,
+    /// Print up to this many walls that would shorten the path or make the destination reachable if opened
+    #[arg(long)]
+    suggest_openings: Option<usize>,
+    /// Print how many different cheapest paths lead to the destination, puzzles usually have exactly one
+    #[arg(long)]
+    count_solutions: bool,
+    /// The path where to store the map with an arrow towards the destination on every block, `-` for stdout
+    #[arg(long)]
+    flow_field: Option<PathBuf>,
+    /// The path where to store an image with every block colored by the cost of reaching it from the start,
+    /// along the colors of --ramp, `-` for stdout
+    #[arg(long)]
+    distances: Option<PathBuf>,
+    /// The path where to store an image of how the search explored the map: the expanded blocks,
+    /// the frontier it left and the path on top of the terrain, `-` for stdout
+    #[arg(long)]
+    exploration: Option<PathBuf>,
+    /// The path where to store an animation of the search exploring the map, an animated png if it ends
+    /// in .png or .apng and a gif otherwise, `-` for a gif on stdout
+    #[arg(long)]
+    exploration_animation: Option<PathBuf>,
+    /// How many expansions each frame of --exploration-animation advances
+    #[arg(long, default_value_t = 1, requires = "exploration_animation")]
+    expansions_per_frame: usize,
+    #[command(flatten)]
+    pdf: PdfArgs,
+    #[command(flatten)]
+    isometric: IsometricArgs,
+    /// Replay the solution in place in the terminal, showing the agent moving step by step
+    #[arg(long, default_value = "false")]
+    animate: bool,
+    /// How many steps per second the animation shows
+    #[arg(long, default_value_t = 10.0, requires = "animate")]
+    speed: f64,
+    /// Also print the path pulled straight where the agent can move in a line, with its waypoints and cost
+    #[arg(long, default_value = "false")]
+    smooth: bool,
+    /// Also print the shortest path of straight lines in any direction (Theta*), ignoring terrain costs
+    #[arg(long, default_value = "false")]
+    any_angle: bool,
+    /// If the destination can't be reached, follow the path to the reachable block nearest to it instead of failing
+    #[arg(long, default_value = "false")]
+    best_effort: bool,
+    /// Also print the route as directions, e.g. go north 5, turn east, go east 3
+    #[arg(long, default_value = "false")]
+    directions: bool,
     /// If present the solution is printed step by step
     #[arg[long, default_value = "false"]]
     verbose_solution: bool,
+    /// Only let the agent see blocks within this radius and replan whenever it discovers something new.
+    /// The png shows the explored part of the map.
+    #[arg(long)]
+    fog: Option<usize>,
+    /// Fill all dead ends with walls before searching, which only leaves the paths between start and destination
+    /// in perfect mazes
+    #[arg(long, default_value = "false", conflicts_with = "fog")]
+    prune: bool,
+    /// Solve with A* on the corridors between junctions instead of the blocks, which expands far fewer states.
+    /// Maps with anything but plain terrain are still searched block by block
+    #[arg(long, default_value = "false", conflicts_with_all = ["fog", "clusters", "best_effort", "turn_penalty", "weight", "greedy", "avoid", "penalty", "max_cost", "max_steps"])]
+    corridors: bool,
+    /// Also solve with A* and Dijkstra, verify both paths and fail if their costs differ
+    #[arg(long, default_value = "false", conflicts_with = "fog")]
+    check: bool,
+    /// The path where to store an image with the paths of A* and Dijkstra from the check in different colors
+    #[arg(long, requires = "check")]
+    check_png: Option<PathBuf>,
+    /// The path where to store an image with the cheapest alternative routes, each in its own color, `-` for stdout.
+    /// Ignores turn penalties and the heuristic weight
+    #[arg(long, conflicts_with = "fog")]
+    alternatives: Option<PathBuf>,
+    /// How many routes the alternatives image shows at most
+    #[arg(long, default_value_t = 3, requires = "alternatives")]
+    alternative_count: usize,
+    /// Solve with hierarchical pathfinding on square clusters of this size instead of the algorithm,
+    /// which pays off for many queries on huge maps. Doesn't support keys, doors and turn penalties.
+    #[arg(long, conflicts_with_all = ["fog", "turn_penalty", "weight", "greedy", "avoid", "penalty", "max_cost", "max_steps"])]
+    clusters: Option<usize>,
+    /// The extra cost of every change of direction
+    #[arg(long, default_value_t = 0)]
+    turn_penalty: u32,
+    /// Never step onto a block of this rectangle given as x1,y1,x2,y2 (or x,y for a single block), can be repeated
+    #[arg(long)]
+    avoid: Vec<Region>,
+    /// Add an extra cost to every step onto a block of the rectangle, given as x1,y1,x2,y2=cost, can be repeated
+    #[arg(long, value_parser = parse_penalty)]
+    penalty: Vec<(Region, u32)>,
+    /// Fail instead of finding a path that costs more, e.g. for an agent with limited fuel
+    #[arg(long)]
+    max_cost: Option<u32>,
+    /// Fail instead of finding a path with more steps
+    #[arg(long)]
+    max_steps: Option<usize>,
+    /// Weighs the heuristic to find a path faster, which costs at most this factor times the cheapest one
+    #[arg(long, conflicts_with = "greedy")]
+    weight: Option<f64>,
+    /// Only follow the heuristic: the fastest search, but the path may be much more expensive
+    #[arg(long, default_value = "false")]
+    greedy: bool,
+    /// The search algorithm (astar, idastar, dijkstra). idastar needs far less memory on huge maps, but is slower
+    #[arg(long, short, default_value_t = SolveAlgorithm::default())]
+    algorithm: SolveAlgorithm,
+    /// Read the map as plain black and white image, e.g. a scan, where this many pixels in each direction make up a block
+    #[arg(long)]
+    monochrome: Option<usize>,
+    /// How far (as euclidean distance in RGB) a pixel of the map may be off the nearest block color
+    #[arg(long, default_value_t = ImportOptions::default().tolerance)]
+    color_tolerance: f64,
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+/// How maps are drawn as images
+#[derive(Args)]
+struct RenderArgs {
+    /// The width and height of a block in pixels
+    #[arg(long, default_value_t = RenderOptions::default().block_width)]
+    cell_size: usize,
+    /// The width of the borders between blocks in pixels
+    #[arg(long, default_value_t = RenderOptions::default().border_width)]
+    wall_width: usize,
+    /// Colors of block types as comma separated <block type>=#rrggbb, e.g. border=#000000,white=#ffffff,
+    /// optionally starting with a predefined palette: default, colorblind or grayscale for printing,
+    /// e.g. grayscale,border=#ffffff. Applies to images, PDFs and the ansi, truecolor and emoji themes.
+    /// When solving, the map is read with these colors as well.
+    #[arg(long)]
+    palette: Option<Palette>,
+    /// The colors of gradients such as the distances image, as comma separated #rrggbb from near to far
+    #[arg(long)]
+    ramp: Option<ColorRamp>,
+    /// How maps are printed (auto, emoji, ascii, box, ansi, truecolor). Emoji don't line up in every terminal,
+    /// auto picks the most colorful theme the terminal supports.
+    #[arg(long, default_value_t = TextTheme::default())]
+    theme: TextTheme,
+    /// A png with square tiles that png maps are drawn with instead of flat colors, in a row or wrapping:
+    /// wall, floor, green, blue, orange, yellow, path, start and goal. Fully transparent tiles are left out.
+    #[arg(long)]
+    tileset: Option<PathBuf>,
+    /// The size of the tiles of --tileset in pixels, by default the height of the tileset
+    #[arg(long, requires = "tileset")]
+    tile_size: Option<u32>,
+}
+
+impl RenderArgs {
+    fn options(&self) -> RenderOptions {
+        RenderOptions {
+            block_width: self.cell_size,
+            border_width: self.wall_width,
+            palette: self.palette.clone().unwrap_or_default(),
+            ramp: self.ramp.clone().unwrap_or_default(),
+        }
+    }
+
+    fn tileset(&self) -> anyhow::Result<Option<Tileset>> {
+        let Some(path) = &self.tileset else {
+            return Ok(None);
+        };
+        let sheet = image::open(path)
+            .with_context(|| format!("Reading {}", path.display()))?
+            .into_rgba8();
+        let tile_size = self.tile_size.unwrap_or(sheet.height());
+        Ok(Some(Tileset::from_sheet(&sheet, tile_size)?))
+    }
+}
+
+/// How maps are drawn in isometric projection
+#[derive(Args)]
+struct IsometricArgs {
+    /// The path where to save the map in isometric projection with raised walls, as svg if it ends in .svg
+    /// and as png otherwise, `-` for png on stdout. The diamonds are twice as wide as --cell-size.
+    #[arg(long)]
+    isometric: Option<PathBuf>,
+    /// How high the walls of --isometric rise in pixels
+    #[arg(long, default_value_t = IsometricOptions::default().wall_height, requires = "isometric")]
+    wall_height: u32,
+}
+
+impl IsometricArgs {
+    /// Writes the isometric projection if --isometric is given
+    fn save(&self, map: &Map, render: &RenderArgs) -> anyhow::Result<()> {
+        let Some(path) = &self.isometric else {
+            return Ok(());
+        };
+        let options = IsometricOptions {
+            tile_width: 2 * render.cell_size as u32,
+            wall_height: self.wall_height,
+            palette: render.options().palette,
+        };
+        if has_extension(path, "svg") {
+            write_output(path, &map.to_isometric_svg(&options))
+        } else {
+            let image = map
+                .to_isometric_image(&options)
+                .ok_or(anyhow!("Failed to create image"))?;
+            save_rgba_image(&image, path)
+        }
+    }
+}
+
+/// How maps are printed as PDF
+#[derive(Args)]
+struct PdfArgs {
+    /// The path where to save the map as vector PDF, with the solution on a second page, `-` for stdout
+    #[arg(long)]
+    pdf: Option<PathBuf>,
+    /// The paper size of the PDF (a4, a5, letter or <width>x<height> in millimeters)
+    #[arg(long, default_value = "a4", requires = "pdf")]
+    page_size: PageSize,
+    /// The blank margin around the map in the PDF in millimeters
+    #[arg(long, default_value_t = 10.0, requires = "pdf")]
+    margin: f64,
+}
+
+impl PdfArgs {
+    /// Writes the PDF if --pdf is given
+    fn save(&self, map: &Map, solution: &Solution, render: &RenderArgs) -> anyhow::Result<()> {
+        if let Some(path) = &self.pdf {
+            let palette = render.options().palette;
+            let pdf = map.to_pdf_with_solution(self.page_size, self.margin, &palette, solution)?;
+            let mut output = create_output(path)?;
+            output.write_all(&pdf)?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_penalty(s: &str) -> Result<(Region, u32), String> {
+    let (region, cost) = s
+        .split_once('=')
+        .ok_or(format!("'{s}' is not of the form x1,y1,x2,y2=cost"))?;
+    let region = region
+        .parse::<Region>()
+        .map_err(|error| error.to_string())?;
+    let cost = cost
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{cost}' is not a cost"))?;
+    Ok((region, cost))
+}
+
+fn parse_metadata_entry(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or(format!("'{s}' is not of the form key=value"))?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+fn parse_waypoint(s: &str) -> Result<(usize, usize), String> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or(format!("'{s}' is not of the form x,y"))?;
+    let coordinate = |c: &str| {
+        c.trim()
+            .parse()
+            .map_err(|_| format!("'{c}' is not a coordinate"))
+    };
+    Ok((coordinate(x)?, coordinate(y)?))
+}
+
+fn parse_count_range(s: &str) -> Result<RangeInclusive<usize>, String> {
+    let (min, max) = s.split_once('-').unwrap_or((s, s));
+    let bound = |bound: &str, default| match bound.trim() {
+        "" => Ok(default),
+        bound => bound
+            .parse()
+            .map_err(|_| format!("'{bound}' of '{s}' is not a count")),
+    };
+    Ok(bound(min, 0)?..=bound(max, usize::MAX)?)
 }
 
 fn between_0_1(s: &str) -> Result<f64, String> {
@@ -68,39 +543,227 @@ struct GenArgs {
     /// The height of the generated maze in blocks
     #[arg(long)]
     height: Option<usize>,
-    /// The probability that a loop occurs as decimal number between 0 and 1
+    /// The fraction between 0 and 1 of the walls a perfect maze keeps closed that are opened to create loops
     #[arg(long, short, value_parser = between_0_1)]
     loop_prob: Option<f64>,
-    /// The path where to save the generated map as png
+    /// The path where to save the generated map as image, as binary map with the extension .maze
+    /// or as run-length encoded text with .rle or .rle.gz or as MovingAI map with .map, `-` for png on stdout
     #[arg(long, short)]
     path: Option<PathBuf>,
+    /// The algorithm used to carve the maze (backtracker, hunt-and-kill, aldous-broder, growing-tree, sidewinder, binary-tree)
+    #[arg(long, short, default_value_t = MazeAlgorithm::default())]
+    algorithm: MazeAlgorithm,
+    /// How the growing-tree algorithm picks its next cell (newest, oldest, random, mixed:<probability of newest>)
+    #[arg(long, default_value_t = SelectionPolicy::default())]
+    selection_policy: SelectionPolicy,
+    /// The colors of the cells, which decide the terrain costs
+    /// (per-branch, single:<color>, gradient, checkerboard[:<color>,<color>], palette:<color>,..).
+    /// The colors are green, blue, orange and yellow
+    #[arg(long, default_value_t = ColoringStrategy::default())]
+    coloring: ColoringStrategy,
+    /// The probability that a passage tunnels under a corridor (weave maze) as decimal number between 0 and 1
+    #[arg(long, value_parser = between_0_1)]
+    weave: Option<f64>,
+    /// Copy the passages of one part of the maze to the others (none, mirror, mirror-both, rotate, rotate-quarter).
+    /// Not supported with masks, multiple threads and the sidewinder and binary-tree algorithms
+    #[arg(long, default_value_t = Symmetry::default())]
+    symmetry: Symmetry,
+    /// Generate a unicursal labyrinth: a single path without branches from the border to the center,
+    /// labeled entrance and center. The width and height in cells must be even, loops and weaving are ignored
+    #[arg(long, conflicts_with_all = ["animate", "animate_gif", "count", "mask"])]
+    unicursal: bool,
+    /// Generate an open cave with a cellular automaton instead of a maze
+    #[arg(long, conflicts_with_all = ["unicursal", "animate", "animate_gif", "count", "mask", "dot"])]
+    cave: bool,
+    /// The fraction between 0 and 1 of the blocks of a --cave that start as walls [default: 0.45]
+    #[arg(long, requires = "cave", value_parser = between_0_1)]
+    cave_fill: Option<f64>,
+    /// How often the automaton of a --cave smooths the walls [default: 4]
+    #[arg(long, requires = "cave")]
+    cave_iterations: Option<usize>,
+    /// Paint the terrain with smooth noise instead of the coloring: patches of roads, grass, mud and swamps
+    #[arg(long)]
+    noise_terrain: bool,
+    /// The size in blocks of the patches of --noise-terrain [default: 12]
+    #[arg(long, requires = "noise_terrain")]
+    noise_scale: Option<f64>,
+    /// The terrain of --noise-terrain by the noise value between 0 and 1, as <color>:<bound> separated by commas,
+    /// the last bound may be left out [default: green:0.35,blue:0.6,orange:0.7,yellow]
+    #[arg(long, requires = "noise_terrain", value_delimiter = ',')]
+    terrain_bands: Vec<TerrainBand>,
+    /// Carve a river of yellow terrain across the map from left to right, crossed by bridges
+    #[arg(long)]
+    river: bool,
+    /// How many blocks wide the --river is [default: 2]
+    #[arg(long, requires = "river")]
+    river_width: Option<usize>,
+    /// How many bridges cross the --river, only where there is ground on both banks [default: 2]
+    #[arg(long, requires = "river")]
+    river_bridges: Option<usize>,
+    /// Make the --river impassable, so that it can only be crossed on the bridges. This may cut off parts of a maze
+    #[arg(long, requires = "river")]
+    river_impassable: bool,
+    /// Carve a road of green terrain from the left to the right of the map through random waypoints
+    #[arg(long)]
+    road: bool,
+    /// Carve a road of green terrain through these blocks instead, as x,y separated by spaces
+    #[arg(long, num_args = 2.., value_parser = parse_waypoint, conflicts_with = "road")]
+    road_through: Vec<(usize, usize)>,
+    /// Generate mazes until the cells on the solution lie within this range, as min-max where either bound may be left out
+    #[arg(long, value_parser = parse_count_range, conflicts_with_all = ["animate", "animate_gif", "count", "threads"])]
+    solution_length: Option<RangeInclusive<usize>>,
+    /// Generate mazes until the number of dead ends lies within this range, as min-max
+    #[arg(long, value_parser = parse_count_range, conflicts_with_all = ["animate", "animate_gif", "count", "threads"])]
+    dead_ends: Option<RangeInclusive<usize>>,
+    /// Generate mazes until the number of cells on the solution with more than one way on lies within this range, as min-max
+    #[arg(long, value_parser = parse_count_range, conflicts_with_all = ["animate", "animate_gif", "count", "threads"])]
+    decision_points: Option<RangeInclusive<usize>>,
+    /// How many mazes to generate at most for --solution-length, --dead-ends and --decision-points
+    #[arg(long, default_value_t = 100)]
+    attempts: usize,
+    /// A png (dark pixels are masked out) or txt file (X and # are masked out) with one pixel / character per cell.
+    /// The maze is only carved within the available cells and takes the size of the mask.
+    #[arg(long, short)]
+    mask: Option<PathBuf>,
+    /// Also solve the maze from its top left to its bottom right cell, or a labyrinth from its entrance to the center,
+    /// and save the solution as png at this path
+    #[arg(long)]
+    solve: Option<PathBuf>,
+    /// The path where to save the cells and passages of the maze as Graphviz graph, `-` for stdout
+    #[arg(long)]
+    dot: Option<PathBuf>,
+    #[command(flatten)]
+    pdf: PdfArgs,
+    #[command(flatten)]
+    isometric: IsometricArgs,
+    /// Show the maze being carved in the terminal. Not supported with multiple threads
+    #[arg(long, default_value = "false")]
+    animate: bool,
+    /// The path where to save a gif of the maze being carved. Not supported with multiple threads
+    #[arg(long)]
+    animate_gif: Option<PathBuf>,
+    /// How many passages per second the animations carve
+    #[arg(long, default_value_t = 50.0)]
+    speed: f64,
+    /// Generate the maze in this many horizontal bands at the same time, which speeds up huge mazes.
+    /// The bands are connected by a single passage each. Not supported with difficulty targets
+    #[arg(long, default_value_t = 1)]
+    threads: usize,
+    /// Generates the same maze every time. With --count the mazes use the following seeds
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Generate this many mazes into --out-dir instead of a single one, each with its own seed, skipping mazes that repeat an earlier one
+    #[arg(long, requires = "out_dir")]
+    count: Option<usize>,
+    /// The directory for the mazes of --count and a manifest.json with their seeds and parameters
+    #[arg(long, requires = "count")]
+    out_dir: Option<PathBuf>,
+    /// The file name of each maze of --count, {seed} and {index} are replaced
+    #[arg(long, default_value = "maze_{seed}.png")]
+    name_template: String,
+    /// The path where to save a small preview png of the maze, see --thumbnail-size
+    #[arg(long)]
+    thumbnail: Option<PathBuf>,
+    /// Also save a preview png next to every maze of --count as thumb_<file name>.png and list it in the manifest
+    #[arg(long, default_value = "false", requires = "count")]
+    thumbnails: bool,
+    /// The largest width and height of --thumbnail and --thumbnails in pixels.
+    /// Larger mazes merge blocks to their most common type.
+    #[arg(long, default_value_t = 256)]
+    thumbnail_size: u32,
+    /// Generate this many mazes of --count at the same time
+    #[arg(long, default_value_t = 1)]
+    jobs: usize,
+    /// The title stored in the metadata of the maze
+    #[arg(long)]
+    title: Option<String>,
+    /// The author stored in the metadata of the maze
+    #[arg(long)]
+    author: Option<String>,
+    /// Further metadata of the maze as key=value, can be repeated
+    #[arg(long, value_parser = parse_metadata_entry)]
+    meta: Vec<(String, String)>,
+    #[command(flatten)]
+    render: RenderArgs,
 }
 
-fn main() {
+fn main() -> ExitCode {
     let cli = Cli::parse();
 
-    if let Err(e) = run(&cli) {
-        eprintln!("{e}");
+    match run(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            exit_code(&e)
+        }
     }
 }
 
 fn run(cli: &Cli) -> anyhow::Result<()> {
+    let interaction = Interaction {
+        prompts: !cli.no_interactive && !cli.quiet,
+        quiet: cli.quiet,
+    };
     match &cli.command {
-        Commands::Solve(solve_args) => solve(&solve_args),
-        Commands::Gen(gen_args) => gen(&gen_args),
+        Commands::Solve(solve_args) => solve(solve_args, interaction),
+        Commands::Gen(gen_args) => gen(gen_args, interaction),
+        Commands::Bench(bench_args) => bench(bench_args),
+        Commands::Serve(serve_args) => serve(serve_args, interaction),
+        Commands::Race(race_args) => race(race_args),
+        #[cfg(feature = "gui")]
+        Commands::View(view_args) => view(view_args),
     }
 }
 
-fn gen(args: &GenArgs) -> anyhow::Result<()> {
-    let width: usize = args
-        .width
-        .ok_or("No width arg specified")
-        .or_else(|_| prompt("Specify the width of the maze"))?;
+/// Lets scripts tell failures apart, see the exit codes in the help
+fn exit_code(error: &anyhow::Error) -> ExitCode {
+    let is_io_error = error.chain().any(|cause| {
+        cause.is::<std::io::Error>() || matches!(cause.downcast_ref(), Some(ImageError::IoError(_)))
+    });
+    if matches!(
+        error.downcast_ref(),
+        Some(MazeError::NoPath | MazeError::PathNotFoundWithinBudget { .. })
+    ) {
+        ExitCode::from(3)
+    } else if is_io_error {
+        ExitCode::from(4)
+    } else if error.is::<ReadlineError>() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::from(2)
+    }
+}
 
-    let height: usize = args
-        .height
+fn gen(args: &GenArgs, interaction: Interaction) -> anyhow::Result<()> {
+    let interaction = interaction.with_results_on_stdout(
+        [
+            &args.path,
+            &args.solve,
+            &args.dot,
+            &args.pdf.pdf,
+            &args.isometric.isometric,
+            &args.thumbnail,
+        ]
+        .into_iter()
+        .flatten()
+        .any(|path| is_std_stream(path)),
+    );
+    let mask = args.mask.as_ref().map(load_mask).transpose()?;
+
+    // A mask defines the size of the maze in cells, each cell is two blocks wide
+    let width: usize = mask
+        .as_ref()
+        .map(|mask| mask.width() * 2 + 1)
+        .or(args.width)
         .ok_or("No width arg specified")
-        .or_else(|_| prompt("Specify the height of the maze"))?;
+        .or_else(|_| interaction.require("--width", "Specify the width of the maze"))?;
+
+    let height: usize = mask
+        .as_ref()
+        .map(|mask| mask.height() * 2 + 1)
+        .or(args.height)
+        .ok_or("No height arg specified")
+        .or_else(|_| interaction.require("--height", "Specify the height of the maze"))?;
 
     let loop_prob: Option<f64> = args.loop_prob;
 
@@ -112,123 +775,1810 @@ fn gen(args: &GenArgs) -> anyhow::Result<()> {
 
     let loop_prob = loop_prob.unwrap_or(0.0);
 
-    if loop_prob >= 1.0 || loop_prob < 0.0 {
+    if !(0.0..1.0).contains(&loop_prob) {
         return Err(anyhow!("Please specify a loop probability between 0 and 1"));
     }
 
-    let maze_map = generate_maze(width / 2, height / 2, Some(loop_prob))?;
-    let map = Map::from(maze_map);
+    let options = GenOptions {
+        loop_prob: Some(loop_prob),
+        selection_policy: args.selection_policy,
+        coloring: args.coloring.clone(),
+        weave: args.weave,
+        mask,
+        seed: args.seed,
+        symmetry: args.symmetry,
+    };
+    if let (Some(count), Some(out_dir)) = (args.count, &args.out_dir) {
+        return gen_batch(args, interaction, (width, height), &options, count, out_dir);
+    }
+
+    let mut carved = vec![];
+    let mut map = if args.cave {
+        let defaults = CaveOptions::default();
+        let cave_options = CaveOptions {
+            fill: args.cave_fill.unwrap_or(defaults.fill),
+            iterations: args.cave_iterations.unwrap_or(defaults.iterations),
+            seed: args.seed,
+        };
+        generate_cave(width, height, &cave_options)?
+    } else if args.unicursal {
+        if args.threads > 1 {
+            return Err(anyhow!(
+                "Unicursal labyrinths don't support multiple threads"
+            ));
+        }
+        let labyrinth = generate_unicursal(width / 2, height / 2, args.algorithm, &options)?;
+        if let Some(path) = &args.dot {
+            write_output(path, &labyrinth.maze().to_dot())?;
+        }
+        Map::from(labyrinth)
+    } else {
+        let maze_map =
+            generate_from_args(args, interaction, (width, height), &options, &mut carved)?;
+        if !interaction.quiet && loop_prob > 0.0 {
+            println!("Opened {} loops", maze_map.count_loops());
+        }
+        if let Some(path) = &args.dot {
+            write_output(path, &maze_map.to_dot())?;
+        }
+        Map::from(maze_map)
+    };
+    paint_noise_terrain(&mut map, args, args.seed)?;
+    carve_features(&mut map, args, args.seed)?;
+    add_metadata(&mut map, args)?;
 
-    println!("{map}");
+    if let Some(path) = &args.animate_gif {
+        save_generation_gif(&map, &carved, &args.render.options(), args.speed, path)?;
+    }
+    if args.animate {
+        animate_generation(&map, &carved, args.render.theme, args.speed)?;
+    } else if !interaction.quiet {
+        map.write_text_with(
+            std::io::stdout().lock(),
+            true,
+            args.render.theme,
+            &args.render.options().palette,
+        )?;
+    }
 
     let path: Option<PathBuf> = if let Some(p) = &args.path {
         Some(p.clone())
     } else {
-        prompt_opt("Enter the path where to save the map as png")?
+        interaction.optional("Enter the path where to save the map as png")?
     };
 
-    println!("Generating the image...");
+    if !interaction.quiet {
+        println!("Saving the image...");
+    }
+    save_map_image(
+        &map,
+        None,
+        &path.ok_or(anyhow!("No path specified. Discarding the image"))?,
+        &args.render,
+    )?;
+    args.isometric.save(&map, &args.render)?;
+    if let Some(path) = &args.thumbnail {
+        save_thumbnail(&map, path, args)?;
+    }
+
+    if args.solve.is_some() || args.pdf.pdf.is_some() {
+        // Labyrinths lead from their entrance to the center
+        let (start, goal) = match (map.label("entrance"), map.label("center")) {
+            (Some(entrance), Some(center)) if args.unicursal => (entrance, center),
+            _ => outermost_blocks(&map)?,
+        };
+        let solution = a_star(&map, start, goal)?;
+        if !interaction.quiet {
+            println!(
+                "Solved from {} {} to {} {} with a cost of {}",
+                start.x,
+                start.y,
+                goal.x,
+                goal.y,
+                solution.cost()
+            );
+        }
+        args.pdf.save(&map, &solution, &args.render)?;
+        if let Some(path) = &args.solve {
+            save_map_image(&map, Some(solution), path, &args.render)?;
+        }
+    }
 
-    let image = map.to_image().ok_or(anyhow!("Failed to create image"))?;
+    Ok(())
+}
 
-    println!("Saving the image...");
-    image.save(path.ok_or(anyhow!("No path specified. Discarding the image"))?)?;
+/// Generates the maze of the arguments with an animation, multiple threads or a progress indicator,
+/// recording the carved passages for animations
+fn generate_from_args(
+    args: &GenArgs,
+    interaction: Interaction,
+    (width, height): (usize, usize),
+    options: &GenOptions,
+    carved: &mut Vec<CarveEvent>,
+) -> anyhow::Result<MazeMap> {
+    let target = DifficultyRange {
+        solution_length: args.solution_length.clone(),
+        dead_ends: args.dead_ends.clone(),
+        decision_points: args.decision_points.clone(),
+    };
+    Ok(
+        if target.solution_length.is_some()
+            || target.dead_ends.is_some()
+            || target.decision_points.is_some()
+        {
+            let (maze_map, analysis) = generate_with_difficulty(
+                width / 2,
+                height / 2,
+                args.algorithm,
+                options,
+                &target,
+                args.attempts,
+            )?;
+            if !interaction.quiet {
+                println!(
+                "The solution leads through {} cells and {} decision points, the maze has {} dead ends",
+                analysis.solution_length, analysis.decision_points, analysis.dead_ends
+            );
+            }
+            maze_map
+        } else if args.animate || args.animate_gif.is_some() {
+            if args.threads > 1 {
+                return Err(anyhow!("Animations don't support multiple threads"));
+            }
+            let mut events = generate_maze_iter(width / 2, height / 2, args.algorithm, options)?;
+            carved.extend(events.by_ref());
+            events.into_maze()
+        } else if args.threads > 1 {
+            generate_parallel(width / 2, height / 2, args.algorithm, options, args.threads)?
+        } else {
+            let maze_map = generate_with_progress(
+                width / 2,
+                height / 2,
+                args.algorithm,
+                options,
+                &mut |percent| {
+                    if !interaction.quiet {
+                        eprint!("\rCarving the maze... {percent}%")
+                    }
+                },
+            )?;
+            if !interaction.quiet {
+                eprintln!();
+            }
+            maze_map
+        },
+    )
+}
 
+/// The first and last walkable block. Masked out corners are walls, so in a maze these are the outermost cells.
+fn outermost_blocks(map: &Map) -> anyhow::Result<(Block, Block)> {
+    let start = *map
+        .walkable_blocks()
+        .next()
+        .ok_or(anyhow!("The maze has no walkable block"))?;
+    let goal = *map
+        .walkable_blocks()
+        .last()
+        .ok_or(anyhow!("The maze has no walkable block"))?;
+    Ok((start, goal))
+}
+
+#[cfg(feature = "gui")]
+fn view(args: &ViewArgs) -> anyhow::Result<()> {
+    let import_options = ImportOptions {
+        palette: args.render.palette.clone().unwrap_or_default(),
+        ..Default::default()
+    };
+    let map = load_map(&args.path, &import_options, None)?;
+    mazes::Viewer::new(map, args.render.options()).show()
+}
+
+fn race(args: &RaceArgs) -> anyhow::Result<()> {
+    let import_options = ImportOptions {
+        palette: args.render.palette.clone().unwrap_or_default(),
+        ..Default::default()
+    };
+    let map = load_map(&args.path, &import_options, None)?;
+    let (first, last) = outermost_blocks(&map)?;
+    let block = |x: Option<usize>, y: Option<usize>, default: Block| match x.zip(y) {
+        Some((x, y)) => map
+            .get_block(x, y)
+            .ok_or(anyhow!("Please specify coordinates within the map")),
+        None => Ok(default),
+    };
+    let start = block(args.start_x, args.start_y, first)?;
+    let goal = block(args.dest_x, args.dest_y, last)?;
+
+    let mut solutions = vec![];
+    let mut traces = vec![];
+    for solver in &args.algorithms {
+        let (solution, trace) =
+            solver
+                .algorithm
+                .solve_traced(&map, start, goal, &solver.options)?;
+        solutions.push(solution);
+        traces.push(trace);
+    }
+
+    match &args.gif {
+        Some(path) => {
+            let animation = AnimationOptions {
+                format: animation_format(path),
+                expansions_per_frame: args.expansions_per_frame,
+                ..Default::default()
+            };
+            map.write_race(
+                &traces,
+                create_output(path)?,
+                &animation,
+                &args.render.options(),
+            )?;
+        }
+        None => animate_race(&map, &args.algorithms, &traces, args)?,
+    }
+    for ((solver, solution), trace) in args.algorithms.iter().zip(&solutions).zip(&traces) {
+        println!(
+            "{:<12} expanded {:>8} states, path cost {}",
+            solver.name,
+            trace.expansion_count(),
+            solution.cost()
+        );
+    }
     Ok(())
 }
 
-fn solve(args: &SolveArgs) -> anyhow::Result<()> {
+/// Prints the explorations of the searches next to each other and redraws them in place every frame:
+/// `*` for the path to the state expanded last, `+` for the frontier and `:` for explored blocks
+fn animate_race(
+    map: &Map,
+    solvers: &[Solver],
+    traces: &[SearchTrace],
+    args: &RaceArgs,
+) -> anyhow::Result<()> {
+    if args.expansions_per_frame == 0 {
+        return Err(anyhow!("Each frame must at least advance one expansion"));
+    }
+    let delay = step_delay(args.speed)?;
+    let theme = args.render.theme;
+    let panel_width = map.width() * theme.block_width();
+    let count = traces
+        .iter()
+        .map(SearchTrace::expansion_count)
+        .max()
+        .unwrap_or(0);
+    let mut steps = (0..count).step_by(args.expansions_per_frame).collect_vec();
+    steps.push(count);
+
+    with_hidden_cursor(std::io::stdout().lock(), |out| {
+        for (i, step) in steps.iter().enumerate() {
+            if i > 0 {
+                execute!(out, cursor::MoveUp(map.height() as u16 + 1))?;
+            }
+            let names = solvers
+                .iter()
+                .map(|solver| format!("{:<panel_width$}", solver.name))
+                .join("  ");
+            writeln!(out, "{names}")?;
+            let marks = traces
+                .iter()
+                .map(|trace| {
+                    let mut marks = HashMap::new();
+                    for (positions, mark) in [
+                        (trace.explored(*step), ':'),
+                        (trace.frontier(*step), '+'),
+                        (trace.path_at(*step), '*'),
+                    ] {
+                        for position in positions {
+                            marks.insert(position, mark);
+                        }
+                    }
+                    marks
+                })
+                .collect_vec();
+            for y in 0..map.height() {
+                let row = marks
+                    .iter()
+                    .map(|marks| {
+                        (0..map.width())
+                            .map(|x| match marks.get(&(x, y)) {
+                                Some(mark) => mark.to_string().repeat(theme.block_width()),
+                                None => map.text_at(x, y, theme).unwrap_or_default(),
+                            })
+                            .collect::<String>()
+                    })
+                    .join("  ");
+                writeln!(out, "{row}")?;
+            }
+            out.flush()?;
+            std::thread::sleep(delay);
+        }
+        Ok(())
+    })
+}
+
+fn bench(args: &BenchArgs) -> anyhow::Result<()> {
+    if args.timeout <= 0.0 || args.timeout.is_nan() {
+        return Err(anyhow!("Please specify a positive timeout"));
+    }
+    if let Some(scen) = &args.scen {
+        return bench_scenarios(scen, args);
+    }
+    let maps = if args.maps.is_empty() {
+        args.sizes
+            .iter()
+            .map(|size| {
+                let options = GenOptions {
+                    loop_prob: Some(args.loop_prob),
+                    seed: args.seed,
+                    ..Default::default()
+                };
+                let maze = generate(size / 2, size / 2, MazeAlgorithm::default(), &options)?;
+                Ok((format!("{size}x{size}"), Map::from(maze)))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?
+    } else {
+        args.maps
+            .iter()
+            .map(|path| {
+                Ok((
+                    path.display().to_string(),
+                    Map::from_image(&image::open(path)?)?,
+                ))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+
+    let timeout = Duration::from_secs_f64(args.timeout);
+    println!(
+        "{:<16} {:<12} {:>12} {:>10} {:>8}",
+        "map", "solver", "time", "expanded", "cost"
+    );
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    for (name, map) in &maps {
+        let pairs = match args.random_pairs {
+            Some(count) => (0..count)
+                .map(|_| {
+                    map.random_pair_at_least(&mut rng, (map.width() + map.height()) / 2)
+                        .ok_or(anyhow!("{name} has no blocks that are far enough apart"))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+            None => vec![outermost_blocks(map)?],
+        };
+        for (start, goal) in pairs {
+            for result in benchmark(map, start, goal, &Solver::all(), timeout) {
+                let (expanded, cost) = match &result.solution {
+                    Some((cost, report)) => (report.expanded.to_string(), cost.to_string()),
+                    None if result.timed_out => ("timeout".to_string(), "-".to_string()),
+                    None => ("-".to_string(), "no path".to_string()),
+                };
+                println!(
+                    "{name:<16} {:<12} {:>12} {expanded:>10} {cost:>8}",
+                    result.solver,
+                    format!("{:.2?}", result.duration)
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs every solver on every scenario and marks the costs that aren't the [expected](Scenario::expected_cost) ones,
+/// e.g. of the solvers that don't guarantee optimal paths
+fn bench_scenarios(path: &Path, args: &BenchArgs) -> anyhow::Result<()> {
+    let scenarios = Scenario::parse_all(&std::fs::read_to_string(path)?)?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let timeout = Duration::from_secs_f64(args.timeout);
+    println!(
+        "{:<16} {:>6} {:<12} {:>12} {:>10} {:>8} {:>10}",
+        "map", "scen", "solver", "time", "expanded", "cost", "optimal"
+    );
+    for (name, scenarios) in &scenarios.iter().enumerate().chunk_by(|(_, s)| &s.map) {
+        let map_path = directory.join(name);
+        // The maps are often stored next to the scenarios without the directories of the benchmark
+        let map_path = if map_path.exists() {
+            map_path
+        } else {
+            directory.join(Path::new(name).file_name().unwrap_or_default())
+        };
+        let map = Map::from_movingai(
+            &std::fs::read_to_string(&map_path)
+                .with_context(|| format!("Reading {}", map_path.display()))?,
+        )?;
+        for (index, scenario) in scenarios {
+            let block = |(x, y)| {
+                map.get_block(x, y)
+                    .ok_or(anyhow!("Scenario {index} lies outside of {name}"))
+            };
+            let (start, goal) = (block(scenario.start)?, block(scenario.goal)?);
+            let expected = scenario.expected_cost(&map).ok();
+            for result in benchmark(&map, start, goal, &Solver::all(), timeout) {
+                let (expanded, cost) = match &result.solution {
+                    Some((cost, report)) => {
+                        let check = if expected == Some(*cost) { "" } else { "!" };
+                        (report.expanded.to_string(), format!("{cost}{check}"))
+                    }
+                    None if result.timed_out => ("timeout".to_string(), "-".to_string()),
+                    None => ("-".to_string(), "no path".to_string()),
+                };
+                println!(
+                    "{name:<16} {index:>6} {:<12} {:>12} {expanded:>10} {cost:>8} {:>10.2}",
+                    result.solver,
+                    format!("{:.2?}", result.duration),
+                    scenario.optimal_length
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Answers requests on a fixed number of worker threads until the process is stopped.
+///
+/// `POST /generate` takes `{"width": .., "height": .., "algorithm": .., "seed": .., "loop_prob": .., "format": ..}`
+/// with the size in blocks like `gen` and returns the map as png for the format `png`
+/// or as `{"width": .., "height": .., "rows": [..], "labels": {..}, "metadata": {..}}` with one row of
+/// [ASCII](TextTheme::Ascii) blocks per string, the named blocks as `"name": [x, y]` and the metadata as strings.
+///
+/// `POST /solve` takes `{"map": {"rows": [..], "labels": {..}, "metadata": {..}}, "start": [x, y], "dest": [x, y], "algorithm": ..,
+/// "turn_penalty": ..}`, where the start and destination can also be labels of the map,
+/// and returns `{"cost": .., "path": [{"x": .., "y": .., "cost_so_far": ..}, ..], "map": ..}`,
+/// where `map` has the path drawn into it.
+///
+/// Failures are answered with `{"error": ..}`, with status 422 if there is no path, 413 for too large bodies
+/// and 400 for invalid requests.
+fn serve(args: &ServeArgs, interaction: Interaction) -> anyhow::Result<()> {
+    let server = Server::http((args.host.as_str(), args.port))
+        .map_err(|e| anyhow!("Failed to listen on {}:{}: {e}", args.host, args.port))?;
+    if !interaction.quiet {
+        println!("Listening on http://{}:{}", args.host, args.port);
+    }
+    // The workers take turns receiving from the shared listener
+    std::thread::scope(|scope| {
+        for _ in 0..args.workers {
+            scope.spawn(|| {
+                for request in server.incoming_requests() {
+                    respond(request, args);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn respond(mut request: Request, args: &ServeArgs) {
+    let path = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let response = match (request.method(), path.as_str()) {
+        (Method::Post, "/generate" | "/solve") => {
+            match read_body(request.as_reader(), args.max_body_size) {
+                Ok(Some(body)) => post_response(&path, &body, args.max_size),
+                Ok(None) => json_response(
+                    413,
+                    &json!({"error": format!("The request must not be larger than {} bytes", args.max_body_size)}),
+                ),
+                Err(e) => json_response(
+                    400,
+                    &json!({"error": format!("Failed to read the request: {e}")}),
+                ),
+            }
+        }
+        (_, "/generate" | "/solve") => {
+            json_response(405, &json!({"error": format!("Please POST to {path}")}))
+        }
+        _ => json_response(404, &json!({"error": format!("There is no {path}")})),
+    };
+    // The client may be gone already, which leaves nobody to tell
+    let _ = request.respond(response);
+}
+
+/// The body, `None` if it is longer than `limit` bytes. Reads at most one byte beyond the limit.
+fn read_body(reader: impl Read, limit: u64) -> std::io::Result<Option<String>> {
+    let mut body = String::new();
+    reader
+        .take(limit.saturating_add(1))
+        .read_to_string(&mut body)?;
+    Ok((body.len() as u64 <= limit).then_some(body))
+}
+
+/// Answers `POST /generate` or `POST /solve`, turning failures into `{"error": ..}`
+fn post_response(path: &str, body: &str, max_size: usize) -> Response<Cursor<Vec<u8>>> {
+    let result = if path == "/generate" {
+        generate_response(body, max_size)
+    } else {
+        solve_response(body, max_size)
+    };
+    result.unwrap_or_else(|e| {
+        let status = if e.downcast_ref::<MazeError>() == Some(&MazeError::NoPath) {
+            422
+        } else {
+            400
+        };
+        json_response(status, &json!({"error": e.to_string()}))
+    })
+}
+
+fn json_response(status: u16, value: &Value) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(value.to_string())
+        .with_status_code(status)
+        .with_header(content_type("application/json"))
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).expect("Content types are valid headers")
+}
+
+/// The field of a JSON request, `None` if it is missing or null
+fn field<'a, T>(
+    params: &'a Value,
+    name: &str,
+    expected: &str,
+    convert: impl FnOnce(&'a Value) -> Option<T>,
+) -> anyhow::Result<Option<T>> {
+    params
+        .get(name)
+        .filter(|value| !value.is_null())
+        .map(|value| convert(value).ok_or(anyhow!("\"{name}\" must be {expected}")))
+        .transpose()
+}
+
+fn generate_response(body: &str, max_size: usize) -> anyhow::Result<Response<Cursor<Vec<u8>>>> {
+    let params: Value = serde_json::from_str(body)?;
+    let size = |name| -> anyhow::Result<usize> {
+        let size = field(&params, name, "a number of blocks", Value::as_u64)?
+            .ok_or(anyhow!("Please specify \"{name}\""))?;
+        if !(3..=max_size as u64).contains(&size) {
+            return Err(anyhow!("\"{name}\" must be between 3 and {max_size}"));
+        }
+        Ok(size as usize)
+    };
+    let (width, height) = (size("width")?, size("height")?);
+    let algorithm = field(&params, "algorithm", "a string", Value::as_str)?
+        .map_or(Ok(MazeAlgorithm::default()), str::parse)?;
+    let loop_prob = field(&params, "loop_prob", "a number", Value::as_f64)?;
+    if loop_prob.is_some_and(|loop_prob| !(0.0..1.0).contains(&loop_prob)) {
+        return Err(anyhow!("Please specify a loop probability between 0 and 1"));
+    }
+    let options = GenOptions {
+        loop_prob,
+        seed: field(&params, "seed", "a non-negative integer", Value::as_u64)?,
+        ..Default::default()
+    };
+    let map = Map::from(generate(width / 2, height / 2, algorithm, &options)?);
+
+    match field(&params, "format", "a string", Value::as_str)?.unwrap_or("json") {
+        "json" => Ok(json_response(200, &map.to_json_value())),
+        "png" => {
+            let mut png = vec![];
+            map.write_png(&mut png)?;
+            Ok(Response::from_data(png).with_header(content_type("image/png")))
+        }
+        format => Err(anyhow!("Unknown format '{format}', expected json or png")),
+    }
+}
+
+fn solve_response(body: &str, max_size: usize) -> anyhow::Result<Response<Cursor<Vec<u8>>>> {
+    let params: Value = serde_json::from_str(body)?;
+    let map = Map::from_json_value(&params["map"])?;
+    if map.width() > max_size || map.height() > max_size {
+        return Err(anyhow!(
+            "The map must not be larger than {max_size}x{max_size}"
+        ));
+    }
+    let block = |name| -> anyhow::Result<Block> {
+        if let Some(label) = params[name].as_str() {
+            return map
+                .label(label)
+                .ok_or(anyhow!("The map has no label \"{label}\""));
+        }
+        let coordinates = field(&params, name, "[x, y] or a label", |value| {
+            match value.as_array()?.as_slice() {
+                [x, y] => Some((x.as_u64()?, y.as_u64()?)),
+                _ => None,
+            }
+        })?
+        .ok_or(anyhow!("Please specify \"{name}\""))?;
+        map.get_block(coordinates.0 as usize, coordinates.1 as usize)
+            .ok_or(anyhow!("Please specify coordinates within the map"))
+    };
+    let (start, dest) = (block("start")?, block("dest")?);
+    let algorithm = field(&params, "algorithm", "a string", Value::as_str)?
+        .map_or(Ok(SolveAlgorithm::default()), str::parse)?;
+    let turn_penalty = field(&params, "turn_penalty", "a non-negative integer", |value| {
+        u32::try_from(value.as_u64()?).ok()
+    })?;
+    let options = SearchOptions::default().turn_penalty(turn_penalty.unwrap_or(0));
+
+    let solution = algorithm.solve(&map, start, dest, &options)?;
+    let exported: Value = serde_json::from_str(&solution.to_json())?;
+    Ok(json_response(
+        200,
+        &json!({
+            "cost": solution.cost(),
+            "cost_breakdown": exported["cost_breakdown"],
+            "path": exported["path"],
+            "map": solution.to_solution_map().to_json_value(),
+        }),
+    ))
+}
+
+/// Generates `count` mazes with consecutive seeds and writes them together with a manifest into `out_dir`.
+fn gen_batch(
+    args: &GenArgs,
+    interaction: Interaction,
+    (width, height): (usize, usize),
+    options: &GenOptions,
+    count: usize,
+    out_dir: &Path,
+) -> anyhow::Result<()> {
+    let template = &args.name_template;
+    if count > 1 && !template.contains("{seed}") && !template.contains("{index}") {
+        return Err(anyhow!(
+            "Please use {{seed}} or {{index}} in the name template to tell the mazes apart"
+        ));
+    }
+    std::fs::create_dir_all(out_dir)?;
+
+    let first_seed = args.seed.unwrap_or_else(rand::random);
+    let mazes = (0..count)
+        .map(|index| {
+            let seed = first_seed.wrapping_add(index as u64);
+            let name = template
+                .replace("{seed}", &seed.to_string())
+                .replace("{index}", &index.to_string());
+            (seed, name)
+        })
+        .collect_vec();
+
+    let next = AtomicUsize::new(0);
+    // The first maze of each layout by index, and the layout and whether it was saved for every maze
+    let first_of_layout: Mutex<HashMap<u64, usize>> = Mutex::default();
+    let layouts: Mutex<Vec<Option<(u64, bool)>>> = Mutex::new(vec![None; count]);
+    let generate_next = || -> anyhow::Result<()> {
+        loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some((seed, name)) = mazes.get(index) else {
+                break;
+            };
+            let options = GenOptions {
+                seed: Some(*seed),
+                ..options.clone()
+            };
+            let maze_map = if args.threads > 1 {
+                generate_parallel(
+                    width / 2,
+                    height / 2,
+                    args.algorithm,
+                    &options,
+                    args.threads,
+                )?
+            } else {
+                generate(width / 2, height / 2, args.algorithm, &options)?
+            };
+            let mut map = Map::from(maze_map);
+            paint_noise_terrain(&mut map, args, Some(*seed))?;
+            carve_features(&mut map, args, Some(*seed))?;
+            add_metadata(&mut map, args)?;
+            let layout = map.canonical_hash();
+            // Mazes that turn out to duplicate an earlier one after being saved are removed at the end
+            let is_duplicate = {
+                let mut first_of_layout = first_of_layout.lock().expect("No worker panics");
+                let first = first_of_layout.entry(layout).or_insert(index);
+                *first = (*first).min(index);
+                *first < index
+            };
+            if !is_duplicate {
+                save_image(&map, &out_dir.join(name), &args.render.options())?;
+                if args.thumbnails {
+                    save_thumbnail(&map, &out_dir.join(thumbnail_name(name)), args)?;
+                }
+                if !interaction.quiet {
+                    println!("Saved {name}");
+                }
+            }
+            layouts.lock().expect("No worker panics")[index] = Some((layout, !is_duplicate));
+        }
+        Ok(())
+    };
+    std::thread::scope(|scope| {
+        let workers = (0..args.jobs.clamp(1, count.max(1)))
+            .map(|_| scope.spawn(generate_next))
+            .collect_vec();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("A generator thread panicked"))
+            .collect::<anyhow::Result<Vec<_>>>()
+    })?;
+
+    let first_of_layout = first_of_layout.into_inner().expect("No worker panics");
+    let layouts = layouts.into_inner().expect("No worker panics");
+    let mut manifest_mazes = vec![];
+    for (((seed, name), layout), index) in mazes.iter().zip(layouts).zip(0..) {
+        let (layout, saved) = layout.expect("Every maze was generated");
+        let first = first_of_layout[&layout];
+        if first != index {
+            let first_name = &mazes[first].1;
+            if saved {
+                std::fs::remove_file(out_dir.join(name))?;
+                if args.thumbnails {
+                    std::fs::remove_file(out_dir.join(thumbnail_name(name)))?;
+                }
+            }
+            if !interaction.quiet {
+                println!("Skipped {name}, it is the same maze as {first_name}");
+            }
+            manifest_mazes.push(json!({"seed": seed, "duplicate_of": first_name}));
+            continue;
+        }
+        let mut entry = json!({"seed": seed, "file": name, "hash": format!("{layout:016x}")});
+        if args.thumbnails {
+            entry["thumbnail"] = json!(thumbnail_name(name));
+        }
+        manifest_mazes.push(entry);
+    }
+    let manifest = json!({
+        "algorithm": args.algorithm.to_string(),
+        "width": width,
+        "height": height,
+        "loop_prob": options.loop_prob.unwrap_or(0.0),
+        "selection_policy": options.selection_policy.to_string(),
+        "coloring": options.coloring.to_string(),
+        "weave": options.weave.unwrap_or(0.0),
+        "symmetry": options.symmetry.to_string(),
+        "mask": args.mask.as_ref().map(|mask| mask.display().to_string()),
+        "mazes": manifest_mazes,
+    });
+    let manifest = serde_json::to_string_pretty(&manifest)? + "\n";
+    write_output(&out_dir.join("manifest.json"), &manifest)
+}
+
+/// Repaints the terrain with noise if the arguments ask for it
+fn paint_noise_terrain(map: &mut Map, args: &GenArgs, seed: Option<u64>) -> anyhow::Result<()> {
+    if !args.noise_terrain {
+        return Ok(());
+    }
+    let defaults = TerrainNoise::default();
+    let noise = TerrainNoise {
+        scale: args.noise_scale.unwrap_or(defaults.scale),
+        bands: if args.terrain_bands.is_empty() {
+            defaults.bands
+        } else {
+            args.terrain_bands.clone()
+        },
+        seed,
+        ..defaults
+    };
+    map.paint_terrain(&noise)
+}
+
+/// Carves the river and then the road of the arguments, so that the road fords the river
+fn carve_features(map: &mut Map, args: &GenArgs, seed: Option<u64>) -> anyhow::Result<()> {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    if args.river {
+        let defaults = RiverOptions::default();
+        let options = RiverOptions {
+            width: args.river_width.unwrap_or(defaults.width),
+            bridges: args.river_bridges.unwrap_or(defaults.bridges),
+            impassable: args.river_impassable,
+            ..defaults
+        };
+        map.carve_river(&options, &mut rng)?;
+    }
+    if args.road {
+        map.carve_random_road(&mut rng)?;
+    } else if !args.road_through.is_empty() {
+        map.carve_road(&args.road_through)?;
+    }
+    Ok(())
+}
+
+/// Adds the title, author and further entries of the arguments and the current time
+/// to the metadata the generator recorded
+fn add_metadata(map: &mut Map, args: &GenArgs) -> anyhow::Result<()> {
+    let metadata = map.metadata_mut();
+    metadata.title.clone_from(&args.title);
+    metadata.author.clone_from(&args.author);
+    metadata.created = Some(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
+    for (key, value) in &args.meta {
+        metadata.set(key, value)?;
+    }
+    Ok(())
+}
+
+/// The file name of the thumbnail of a maze of a batch
+fn thumbnail_name(name: &str) -> String {
+    let stem = Path::new(name)
+        .file_stem()
+        .map_or(name.into(), |stem| stem.to_string_lossy());
+    format!("thumb_{stem}.png")
+}
+
+fn save_thumbnail(map: &Map, path: &Path, args: &GenArgs) -> anyhow::Result<()> {
+    let image = map
+        .thumbnail_with(args.thumbnail_size, &args.render.options().palette)
+        .ok_or(anyhow!("Please specify a thumbnail size of at least 1"))?;
+    save_rgba_image(&image, path)
+}
+
+/// Streams png files to disk or to stdout for `-`, `.maze` files get the [binary format](Map::to_bytes),
+/// `.map` files the [MovingAI format](Map::to_movingai), `.rle` and `.gz` files [run-length encoded text](Map::write_rle)
+/// and other formats are encoded in memory by the image crate.
+fn save_image(map: &Map, path: &Path, options: &RenderOptions) -> anyhow::Result<()> {
+    if has_extension(path, "maze") {
+        let mut output = create_output(path)?;
+        output.write_all(&map.to_bytes())?;
+        output.flush()?;
+        Ok(())
+    } else if has_extension(path, "map") {
+        let mut output = create_output(path)?;
+        output.write_all(map.to_movingai().as_bytes())?;
+        output.flush()?;
+        Ok(())
+    } else if has_extension(path, "rle") {
+        Ok(map.write_rle(create_output(path)?)?)
+    } else if has_extension(path, "gz") {
+        Ok(map.write_rle_gzip(create_output(path)?)?)
+    } else if is_std_stream(path)
+        || path
+            .extension()
+            .is_none_or(|extension| extension.eq_ignore_ascii_case("png"))
+    {
+        map.write_png_with(create_output(path)?, options)
+    } else {
+        map.to_image_with(options)
+            .ok_or(anyhow!("Failed to create image"))?
+            .save(path)?;
+        Ok(())
+    }
+}
+
+/// Like [save_image], but drawn with the tiles of --tileset when it is given and the path is an image
+fn save_map_image(
+    map: &Map,
+    solution: Option<Solution>,
+    path: &Path,
+    render: &RenderArgs,
+) -> anyhow::Result<()> {
+    let is_image = !["maze", "map", "rle", "gz"]
+        .into_iter()
+        .any(|extension| has_extension(path, extension));
+    match (render.tileset()?.filter(|_| is_image), solution) {
+        (Some(tileset), solution) => {
+            let image = match &solution {
+                Some(solution) => map.to_image_with_tileset_and_solution(&tileset, solution),
+                None => map.to_image_with_tileset(&tileset),
+            }
+            .ok_or(anyhow!("Failed to create image"))?;
+            save_rgba_image(&image, path)
+        }
+        (None, Some(solution)) => save_image(&solution.to_solution_map(), path, &render.options()),
+        (None, None) => save_image(map, path, &render.options()),
+    }
+}
+
+/// Like [save_image], for images that aren't a rendered map
+fn save_rgba_image(image: &RgbaImage, path: &Path) -> anyhow::Result<()> {
+    if is_std_stream(path) {
+        image.write_with_encoder(PngEncoder::new(create_output(path)?))?;
+    } else {
+        image.save(path)?;
+    }
+    Ok(())
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|actual| actual.eq_ignore_ascii_case(extension))
+}
+
+/// `-` stands for stdin or stdout instead of a file
+fn is_std_stream(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// The file at `path` or stdout for `-`
+fn create_output(path: &Path) -> anyhow::Result<Box<dyn Write>> {
+    if is_std_stream(path) {
+        Ok(Box::new(std::io::stdout().lock()))
+    } else {
+        Ok(Box::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+fn write_output(path: &Path, contents: &str) -> anyhow::Result<()> {
+    let mut output = create_output(path)?;
+    output.write_all(contents.as_bytes())?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Reads an image, a binary map, a run-length encoded map, a MovingAI map or a map in the ASCII theme from a file or from stdin for `-`
+fn load_map(
+    path: &Path,
+    import_options: &ImportOptions,
+    monochrome: Option<usize>,
+) -> anyhow::Result<Map> {
+    let bytes = if is_std_stream(path) {
+        let mut bytes = vec![];
+        std::io::stdin().read_to_end(&mut bytes)?;
+        bytes
+    } else {
+        std::fs::read(path)?
+    };
+    // Only data with the magic bytes of the binary format can be read as binary map
+    if let Ok(map) = Map::from_bytes(&bytes) {
+        return Ok(map);
+    }
+    if bytes.starts_with(b"mazes-rle") || bytes.starts_with(&[0x1f, 0x8b]) {
+        return Map::read_rle(bytes.as_slice());
+    }
+    if bytes.starts_with(b"type ") {
+        return Map::from_movingai(std::str::from_utf8(&bytes)?);
+    }
+    if image::guess_format(&bytes).is_err() {
+        let text = std::str::from_utf8(&bytes)
+            .map_err(|_| anyhow!("The map is neither an image nor text"))?;
+        return Map::from_text(text);
+    }
+
+    let img = image::load_from_memory(&bytes)?;
+    let mut map = match monochrome {
+        Some(block_size) => Map::from_monochrome_image(&img, block_size),
+        None => Map::from_image_with(&img, import_options),
+    }?;
+    if image::guess_format(&bytes)? == ImageFormat::Png {
+        *map.metadata_mut() = Metadata::from_png(&bytes)?;
+    }
+    Ok(map)
+}
+
+fn load_mask(path: &PathBuf) -> anyhow::Result<Mask> {
+    if path.extension().is_some_and(|extension| extension == "txt") {
+        Mask::from_text(&std::fs::read_to_string(path)?)
+    } else {
+        Ok(Mask::from_image(&image::open(path)?))
+    }
+}
+
+fn solve(args: &SolveArgs, interaction: Interaction) -> anyhow::Result<()> {
     let path: PathBuf = if let Some(p) = &args.path {
         p.clone()
     } else {
-        prompt("Enter the path to the map as png")?
+        interaction.require("--path", "Enter the path to the map as png")?
     };
 
-    let img = image::open(path)?;
-    let map: Map = Map::from(img);
+    let import_options = ImportOptions {
+        tolerance: args.color_tolerance,
+        palette: args.render.palette.clone().unwrap_or_default(),
+    };
+    let map = load_map(&path, &import_options, args.monochrome)?;
+    let interaction = Interaction {
+        // Stdin is taken by the map
+        prompts: interaction.prompts && !is_std_stream(&path),
+        ..interaction
+    }
+    .with_results_on_stdout(
+        [
+            &args.txt,
+            &args.png,
+            &args.json,
+            &args.csv,
+            &args.components,
+            &args.explain,
+            &args.bottlenecks,
+            &args.flow_field,
+            &args.distances,
+            &args.exploration,
+            &args.exploration_animation,
+            &args.pdf.pdf,
+            &args.isometric.isometric,
+            &args.check_png,
+            &args.alternatives,
+        ]
+        .into_iter()
+        .flatten()
+        .any(|path| is_std_stream(path)),
+    );
 
-    println!("{map}");
+    if !interaction.quiet {
+        map.write_text_with(
+            std::io::stdout().lock(),
+            true,
+            args.render.theme,
+            &args.render.options().palette,
+        )?;
+    }
 
     let start_line: String = args
-        .start_y
-        .and_then(|y| args.start_x.map(|x| format!("{x} {y}")))
+        .start
+        .clone()
+        .or_else(|| {
+            args.start_y
+                .and_then(|y| args.start_x.map(|x| format!("{x} {y}")))
+        })
         .ok_or("No start x y arg specified")
-        .or_else(|_| prompt("Enter the start as x y"))?;
+        .or_else(|_| {
+            interaction.require(
+                "--start or --start-x and --start-y",
+                "Enter the start as x y or label",
+            )
+        })?;
 
     let start_block = parse_block(&start_line, &map)?;
 
     let destination_line: String = args
-        .dest_y
-        .and_then(|y| args.dest_x.map(|x| format!("{x} {y}")))
+        .dest
+        .clone()
+        .or_else(|| {
+            args.dest_y
+                .and_then(|y| args.dest_x.map(|x| format!("{x} {y}")))
+        })
         .ok_or("No dest x y arg specified")
-        .or_else(|_| prompt("Enter the destination as x y"))?;
+        .or_else(|_| {
+            interaction.require(
+                "--dest or --dest-x and --dest-y",
+                "Enter the destination as x y or label",
+            )
+        })?;
 
     let destination_block = parse_block(&destination_line, &map)?;
 
-    if let Ok(solution) = a_star(&map, start_block, destination_block) {
-        let solution_file = args
-            .txt
-            .as_ref()
-            .cloned()
-            .unwrap_or(PathBuf::from("solution.txt".to_string()));
+    let validation = map.validate_route(
+        (start_block.x, start_block.y),
+        (destination_block.x, destination_block.y),
+    );
+    if !interaction.quiet {
+        for finding in validation.findings() {
+            eprintln!("Warning: {finding}");
+        }
+    }
+    let unreachability = map.explain_unreachable(
+        (start_block.x, start_block.y),
+        (destination_block.x, destination_block.y),
+    );
+    if let Some(unreachability) = &unreachability {
+        if !interaction.quiet {
+            eprintln!("{unreachability}");
+        }
+        if let Some(path) = &args.explain {
+            let image = map
+                .unreachability_image(unreachability, &args.render.options())
+                .ok_or(anyhow!("Failed to create image"))?;
+            save_rgba_image(&image, path)?;
+        }
+    }
+
+    if args.count_solutions && !interaction.quiet {
+        match map.count_shortest_paths(start_block, destination_block)? {
+            u64::MAX => println!("There are at least {} cheapest paths", u64::MAX),
+            count => println!(
+                "There {} {count} cheapest path{}",
+                if count == 1 { "is" } else { "are" },
+                if count == 1 { "" } else { "s" }
+            ),
+        }
+    }
+
+    if let Some(k) = args.suggest_openings.filter(|_| !interaction.quiet) {
+        for opening in suggest_openings(&map, start_block, destination_block, k)? {
+            match opening.saving {
+                Some(saving) => println!(
+                    "Opening the wall at {} {} saves {saving}, the path costs {}",
+                    opening.x, opening.y, opening.cost
+                ),
+                None => println!(
+                    "Opening the wall at {} {} makes the destination reachable at a cost of {}",
+                    opening.x, opening.y, opening.cost
+                ),
+            }
+        }
+    }
 
-        let mut file = File::create(solution_file)?;
+    if let Some(path) = &args.components {
+        let image = map
+            .components_image(&args.render.options())
+            .ok_or(anyhow!("Failed to create image"))?;
+        save_rgba_image(&image, path)?;
+    }
 
-        let solution_seq = solution.as_sequence_of_maps(&map);
-        let solution_str = solution.to_string();
+    if let Some(path) = &args.bottlenecks {
+        let image = map
+            .bottlenecks_image(&args.render.options())
+            .ok_or(anyhow!("Failed to create image"))?;
+        save_rgba_image(&image, path)?;
+    }
 
-        if args.verbose_solution || args.txt.is_some() {
-            for state in solution_seq {
-                if args.txt.is_some() {
-                    file.write_all(format!("{}\n", state).as_bytes())?;
-                }
-                if args.verbose_solution {
-                    println!("{}", state);
+    if let Some(path) = &args.flow_field {
+        write_output(path, &map.flow_field(destination_block)?.to_text(&map))?;
+    }
+
+    if let Some(path) = &args.distances {
+        let image = map
+            .distance_image(&map.distance_field(start_block)?, &args.render.options())
+            .ok_or(anyhow!("Failed to create image"))?;
+        save_rgba_image(&image, path)?;
+    }
+
+    if let Some(radius) = args.fog {
+        return solve_fogged(
+            args,
+            interaction,
+            &map,
+            start_block,
+            destination_block,
+            radius,
+        );
+    }
+
+    let map = if args.prune {
+        let pruned = map.pruned(&[start_block, destination_block]);
+        if !interaction.quiet {
+            println!(
+                "Filled {} dead end blocks",
+                map.walkable_blocks().count() - pruned.walkable_blocks().count()
+            );
+        }
+        pruned
+    } else {
+        map
+    };
+    let mut options = constrained_options(args);
+    if let Some(weight) = args.weight {
+        options = options.weight(weight);
+    }
+    if args.greedy {
+        options = options.greedy();
+    }
+    if args.check {
+        check_optimality(&map, start_block, destination_block, args, &interaction)?;
+    }
+    let solution = match args.clusters {
+        Some(cluster_size) => {
+            let planner = HierarchicalPlanner::new(&map, cluster_size)?;
+            if !interaction.quiet {
+                println!(
+                    "The abstract graph has {} nodes and {} edges",
+                    planner.node_count(),
+                    planner.edge_count()
+                );
+            }
+            planner.find_path(start_block, destination_block)?
+        }
+        None if args.corridors => map.solve_on_corridors(start_block, destination_block)?,
+        None if args.best_effort => {
+            let outcome =
+                args.algorithm
+                    .solve_or_approach(&map, start_block, destination_block, &options)?;
+            match outcome {
+                SolveOutcome::Solved(solution) => solution,
+                SolveOutcome::Unreachable {
+                    best_effort,
+                    remaining_distance,
+                } => {
+                    if !interaction.quiet {
+                        let end = best_effort.path().last().unwrap_or(&start_block);
+                        eprintln!(
+                            "The destination can't be reached, the path ends at {} {}, {remaining_distance} steps away from it",
+                            end.x, end.y
+                        );
+                    }
+                    best_effort
                 }
             }
         }
+        None => args
+            .algorithm
+            .solve(&map, start_block, destination_block, &options)?,
+    };
+    if args.exploration.is_some() || args.exploration_animation.is_some() {
+        let (solution, trace) =
+            args.algorithm
+                .solve_traced(&map, start_block, destination_block, &options)?;
+        if let Some(path) = &args.exploration {
+            let image = map
+                .layered_image(
+                    &Layers::from_trace(&trace, &solution),
+                    &args.render.options(),
+                )
+                .ok_or(anyhow!("Failed to create image"))?;
+            save_rgba_image(&image, path)?;
+        }
+        if let Some(path) = &args.exploration_animation {
+            let animation = AnimationOptions {
+                format: animation_format(path),
+                expansions_per_frame: args.expansions_per_frame,
+                ..Default::default()
+            };
+            map.write_exploration(
+                &trace,
+                create_output(path)?,
+                &animation,
+                &args.render.options(),
+            )?;
+        }
+    }
+    args.pdf.save(&map, &solution, &args.render)?;
+    args.isometric.save(solution.map(), &args.render)?;
+    let mut file = args.txt.as_deref().map(create_output).transpose()?;
+
+    let solution_seq = solution.as_sequence_of_maps(&map);
+    let solution_str = solution.to_text_with(args.render.theme, &args.render.options().palette);
 
-        if args.txt.is_some() {
-            file.write_all(format!("{}\n", solution_str).as_bytes())?;
+    if args.verbose_solution || args.txt.is_some() {
+        for state in solution_seq {
+            if let Some(file) = file.as_mut() {
+                file.write_all(format!("{}\n", state).as_bytes())?;
+            }
+            if args.verbose_solution {
+                println!("{}", state);
+            }
         }
+    }
+
+    if let Some(file) = file.as_mut() {
+        file.write_all(format!("{}\n", solution_str).as_bytes())?;
+        file.flush()?;
+    }
+    if args.animate {
+        animate(&map, solution.path(), args.render.theme, args.speed)?;
+    } else if !interaction.quiet {
         println!("{solution_str}");
+    }
+    if !interaction.quiet {
+        if args.turn_penalty > 0 {
+            println!("The path turns {} times", solution.turns());
+        }
+        let report = solution.report();
+        match report.suboptimality_bound {
+            Some(bound) if bound > 1.0 => println!(
+                "Expanded {} states, the cost is at most {bound} times the cheapest",
+                report.expanded
+            ),
+            Some(_) => println!("Expanded {} states, the cost is minimal", report.expanded),
+            None => println!(
+                "Expanded {} states, the cost may be far from minimal",
+                report.expanded
+            ),
+        }
+        if args.smooth {
+            println!("{}", solution.smoothed(&map));
+        }
+        if args.any_angle {
+            println!("{}", theta_star(&map, start_block, destination_block)?);
+        }
+        if args.directions {
+            println!("Directions: {}", solution.directions_text());
+        }
+    }
 
-        if args.png.is_some() {
-            let path: PathBuf = args
-                .png
-                .as_ref()
-                .ok_or("Not png path arg specified")
-                .cloned()
-                .or_else(|_| prompt("Enter the path where the map should be saved"))?;
-            //prompt("Enter the path where the map should be saved")?;
-            solution
-                .to_solution_map()
-                .to_image()
-                .ok_or(anyhow!("Failed to create image"))?
-                .save(path)?;
+    if let Some(path) = &args.alternatives {
+        let routes =
+            k_shortest_paths(&map, start_block, destination_block, args.alternative_count)?;
+        if !interaction.quiet {
+            println!(
+                "The alternative routes cost {}",
+                routes.iter().map(|route| route.cost()).join(", ")
+            );
         }
+        let image = map
+            .routes_image(&routes, &args.render.options())
+            .ok_or(anyhow!("Failed to create image"))?;
+        save_rgba_image(&image, path)?;
+    }
+
+    if let Some(path) = &args.json {
+        write_output(path, &solution.to_json())?;
+    }
+    if let Some(path) = &args.csv {
+        write_output(path, &solution.to_csv())?;
+    }
+
+    let png: Option<PathBuf> = if let Some(p) = &args.png {
+        Some(p.clone())
     } else {
-        eprintln!("No path found 😢");
+        interaction.optional("Enter the path where to save the solution as png")?
+    };
+    if let Some(path) = png {
+        save_map_image(&map, Some(solution), &path, &args.render)?;
+    }
+
+    Ok(())
+}
+
+/// Prints the map once and then only redraws the blocks the agent leaves and enters.
+fn animate(
+    map: &Map,
+    path: &[Block],
+    theme: TextTheme,
+    steps_per_second: f64,
+) -> anyhow::Result<()> {
+    let delay = step_delay(steps_per_second)?;
+    with_hidden_cursor(std::io::stdout().lock(), |out| {
+        play_path(out, map, path, theme, delay)
+    })
+}
+
+/// The frames of [animate]: the map, then the agent moving one block per step
+fn play_path(
+    out: &mut impl Write,
+    map: &Map,
+    path: &[Block],
+    theme: TextTheme,
+    delay: Duration,
+) -> anyhow::Result<()> {
+    let mut frame = map.clone();
+    frame.write_text(&mut *out, false, theme)?;
+    for (i, block) in path.iter().enumerate() {
+        if i > 0 {
+            // Restore the block the agent left
+            draw_block(out, &frame, theme, (path[i - 1].x, path[i - 1].y))?;
+        }
+        frame.set_block_type(block.x, block.y, BlockType::Solution)?;
+        draw_block(out, &frame, theme, (block.x, block.y))?;
+        frame.set_block_type(block.x, block.y, block.block_type())?;
+        std::thread::sleep(delay);
+    }
+    Ok(())
+}
+
+/// Prints the map as solid wall and then uncovers the passages in the order they were carved.
+fn animate_generation(
+    map: &Map,
+    carved: &[CarveEvent],
+    theme: TextTheme,
+    steps_per_second: f64,
+) -> anyhow::Result<()> {
+    let delay = step_delay(steps_per_second)?;
+    with_hidden_cursor(std::io::stdout().lock(), |out| {
+        play_carving(out, map, carved, theme, delay)
+    })?;
+    Ok(())
+}
+
+/// The frames of [animate_generation], returns the map as it was drawn in the end
+fn play_carving(
+    out: &mut impl Write,
+    map: &Map,
+    carved: &[CarveEvent],
+    theme: TextTheme,
+    delay: Duration,
+) -> anyhow::Result<Map> {
+    let mut frame = map.clone();
+    frame.fill_rect((0, 0), (map.width(), map.height()), BlockType::Black)?;
+    frame.write_text(&mut *out, false, theme)?;
+    for event in carved {
+        for (x, y) in event.blocks() {
+            frame.set_block_type(x, y, map[(x, y)].block_type())?;
+            draw_block(out, &frame, theme, (x, y))?;
+        }
+        std::thread::sleep(delay);
+    }
+    // Blocks no passage leads through, e.g. the only cell of a 1x1 maze
+    for block in map.iter_blocks() {
+        if frame[(block.x, block.y)].block_type() != block.block_type() {
+            frame.set_block_type(block.x, block.y, block.block_type())?;
+            draw_block(out, &frame, theme, (block.x, block.y))?;
+        }
+    }
+    Ok(frame)
+}
+
+/// Writes a gif that uncovers the passages in the order they were carved.
+/// Several passages share a frame when the speed exceeds what gif delays can show.
+fn save_generation_gif(
+    map: &Map,
+    carved: &[CarveEvent],
+    options: &RenderOptions,
+    steps_per_second: f64,
+    path: &Path,
+) -> anyhow::Result<()> {
+    const MAX_FRAMES_PER_SECOND: f64 = 50.0;
+    step_delay(steps_per_second)?;
+    let steps_per_frame = (steps_per_second / MAX_FRAMES_PER_SECOND).ceil() as usize;
+    let frame_delay = Delay::from_saturating_duration(Duration::from_secs_f64(
+        steps_per_frame as f64 / steps_per_second,
+    ));
+
+    let mut encoder = GifEncoder::new_with_speed(create_output(path)?, 30);
+    encoder.set_repeat(Repeat::Infinite)?;
+    let mut frame = map.clone();
+    frame.fill_rect((0, 0), (map.width(), map.height()), BlockType::Black)?;
+    let mut render = |frame: &Map, delay: Delay| -> anyhow::Result<()> {
+        let image = frame
+            .to_image_with(options)
+            .ok_or(anyhow!("The map is too large to be saved as gif"))?;
+        encoder.encode_frame(Frame::from_parts(image, 0, 0, delay))?;
+        Ok(())
     };
+    render(&frame, frame_delay)?;
+    for events in carved.chunks(steps_per_frame) {
+        for (x, y) in events.iter().flat_map(CarveEvent::blocks) {
+            frame.set_block_type(x, y, map[(x, y)].block_type())?;
+        }
+        render(&frame, frame_delay)?;
+    }
+    render(map, Delay::from_saturating_duration(Duration::from_secs(3)))
+}
+
+/// An animated png for paths ending in .png or .apng, a gif otherwise
+fn animation_format(path: &Path) -> AnimationFormat {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("png" | "apng") => AnimationFormat::Apng,
+        _ => AnimationFormat::Gif,
+    }
+}
+
+/// The time between two steps of an animation
+fn step_delay(steps_per_second: f64) -> anyhow::Result<Duration> {
+    if steps_per_second <= 0.0 || steps_per_second.is_nan() {
+        return Err(anyhow!("Please specify a positive speed"));
+    }
+    Ok(Duration::from_secs_f64(1.0 / steps_per_second))
+}
+
+/// Shows the cursor of the terminal again once it's dropped
+struct HiddenCursor<W: Write>(W);
+
+impl<W: Write> Drop for HiddenCursor<W> {
+    fn drop(&mut self) {
+        // There is nowhere to report the error to while unwinding
+        let _ = execute!(self.0, cursor::Show);
+    }
+}
+
+/// Runs an animation with the cursor hidden and shows it again,
+/// even if the animation returns early with an error or panics.
+fn with_hidden_cursor<W: Write, T>(
+    out: W,
+    animation: impl FnOnce(&mut W) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let mut guard = HiddenCursor(out);
+    execute!(guard.0, cursor::Hide)?;
+    animation(&mut guard.0)
+}
+
+/// Redraws a single block of a map that was printed right above the cursor.
+/// The cursor is moved relative to the end of the map, so the map must fit into the terminal.
+fn draw_block(
+    out: &mut impl Write,
+    frame: &Map,
+    theme: TextTheme,
+    (x, y): (usize, usize),
+) -> anyhow::Result<()> {
+    let text = frame
+        .text_at(x, y, theme)
+        .ok_or(anyhow!("Please specify coordinates within the map"))?;
+    execute!(
+        out,
+        cursor::SavePosition,
+        cursor::MoveUp((frame.height() - y) as u16),
+        cursor::MoveToColumn((x * theme.block_width()) as u16),
+        Print(text),
+        cursor::RestorePosition
+    )?;
+    Ok(())
+}
+
+/// The options that change the cost of a path or where it may go, but not how the search works
+fn constrained_options(args: &SolveArgs) -> SearchOptions {
+    let mut options = SearchOptions::default().turn_penalty(args.turn_penalty);
+    if let Some(max_cost) = args.max_cost {
+        options = options.max_cost(max_cost);
+    }
+    if let Some(max_steps) = args.max_steps {
+        options = options.max_steps(max_steps);
+    }
+    let options = args
+        .avoid
+        .iter()
+        .fold(options, |options, region| options.avoid(*region));
+    args.penalty
+        .iter()
+        .fold(options, |options, (region, cost)| {
+            options.penalize(*region, *cost)
+        })
+}
+
+/// Solves with A* and Dijkstra and fails if either path is invalid or A* misses the cheapest cost,
+/// which would mean that the heuristic overestimates on this map
+fn check_optimality(
+    map: &Map,
+    start_block: Block,
+    destination_block: Block,
+    args: &SolveArgs,
+    interaction: &Interaction,
+) -> anyhow::Result<()> {
+    let options = constrained_options(args);
+    let a_star_solution = a_star_with(map, start_block, destination_block, &options)?;
+    let dijkstra_solution = dijkstra(map, start_block, destination_block, &options)?;
+    a_star_solution
+        .verify(map)
+        .context("The path of A* is invalid")?;
+    dijkstra_solution
+        .verify(map)
+        .context("The path of Dijkstra is invalid")?;
+
+    if let Some(path) = &args.check_png {
+        let image = a_star_solution
+            .compare_image(&dijkstra_solution, &args.render.options())
+            .ok_or(anyhow!("Failed to create image"))?;
+        save_rgba_image(&image, path)?;
+    }
 
+    if a_star_solution.cost() != dijkstra_solution.cost() {
+        let theme = args.render.theme;
+        let column_width = map.width() * theme.block_width();
+        eprintln!("{:<column_width$}   Dijkstra", "A*");
+        for (left, right) in a_star_solution
+            .to_text_themed(theme)
+            .lines()
+            .zip(dijkstra_solution.to_text_themed(theme).lines())
+        {
+            eprintln!("{left}   {right}");
+        }
+        eprint!("{}", a_star_solution.compare(&dijkstra_solution));
+        return Err(anyhow!(
+            "A* found a path costing {}, but Dijkstra found one costing {}",
+            a_star_solution.cost(),
+            dijkstra_solution.cost()
+        ));
+    }
+    if !interaction.quiet {
+        println!(
+            "A* and Dijkstra agree on a cost of {}",
+            a_star_solution.cost()
+        );
+    }
     Ok(())
 }
 
+fn solve_fogged(
+    args: &SolveArgs,
+    interaction: Interaction,
+    map: &Map,
+    start: Block,
+    destination: Block,
+    radius: usize,
+) -> anyhow::Result<()> {
+    let trace = solve_with_fog(map, start, destination, radius)?;
+    if !interaction.quiet {
+        println!("{trace}");
+    }
+
+    if let Some(path) = &args.txt {
+        write_output(path, &trace.to_string())?;
+    }
+    if let Some(path) = &args.png {
+        save_image(&trace.to_explored_map(), path, &args.render.options())?;
+    }
+
+    Ok(())
+}
+
+/// The block at the coordinates `x y` or with the label
 fn parse_block(line: &str, map: &Map) -> anyhow::Result<Block> {
+    if let Some(block) = map.label(line.trim()) {
+        return Ok(block);
+    }
     let coords: Result<Vec<usize>, ParseIntError> = line
         .split(" ")
         .map(|string_num| string_num.parse::<usize>())
         .collect();
 
-    let coords = coords.map_err(|_| anyhow!("Please specify valid usize numbers"))?;
+    let coords =
+        coords.map_err(|_| anyhow!("Please specify valid usize numbers or a label of the map"))?;
 
     if coords.len() != 2 {
         return Err(anyhow!("Please specify two coordinates"));
     }
 
     map.get_block(
-        *coords.get(0).expect("Can't happen"),
+        *coords.first().expect("Can't happen"),
         *coords.get(1).expect("Can't happen"),
     )
     .ok_or(anyhow!("Please specify coordinates within the map"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The status and JSON body of a response
+    fn parse(response: Response<Cursor<Vec<u8>>>) -> (u16, Value) {
+        let status = response.status_code().0;
+        let body = serde_json::from_slice(&response.into_reader().into_inner()).unwrap();
+        (status, body)
+    }
+
+    #[test]
+    fn generated_maps_are_solved_over_http() {
+        let (status, map) = parse(post_response(
+            "/generate",
+            r#"{"width": 9, "height": 7, "seed": 1}"#,
+            100,
+        ));
+        assert_eq!(status, 200);
+        assert_eq!(map["width"], 9);
+
+        let request = json!({"map": map, "start": [1, 1], "dest": [7, 5]});
+        let (status, solution) = parse(post_response("/solve", &request.to_string(), 100));
+        assert_eq!(status, 200);
+        let path = solution["path"].as_array().unwrap();
+        assert_eq!(path.last().unwrap()["cost_so_far"], solution["cost"]);
+
+        let walled_in = json!({"map": {"rows": [".#."]}, "start": [0, 0], "dest": [2, 0]});
+        assert_eq!(
+            parse(post_response("/solve", &walled_in.to_string(), 100)).0,
+            422
+        );
+    }
+
+    #[test]
+    fn malformed_and_oversized_requests_are_rejected() {
+        for (path, body) in [
+            ("/generate", "{\"width\": "),
+            ("/generate", r#"{"width": 5000, "height": 9}"#),
+            (
+                "/solve",
+                r#"{"map": {"rows": 3}, "start": [0, 0], "dest": [1, 0]}"#,
+            ),
+            (
+                "/solve",
+                r#"{"map": {"rows": [".."]}, "start": [0, 0], "dest": [5, 0]}"#,
+            ),
+        ] {
+            let (status, body) = parse(post_response(path, body, 100));
+            assert_eq!(status, 400, "{body}");
+            assert!(body["error"].is_string());
+        }
+
+        assert_eq!(read_body("1234".as_bytes(), 4).unwrap().unwrap(), "1234");
+        assert_eq!(read_body("12345".as_bytes(), 4).unwrap(), None);
+    }
+
+    #[test]
+    fn solutions_are_only_saved_when_asked_for_without_prompts() {
+        let dir = std::env::temp_dir().join(format!("mazes-solve-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (map, png) = (dir.join("map.txt"), dir.join("solution.png"));
+        std::fs::write(&map, "#####\n#...#\n#####\n").unwrap();
+        let solve = |extra: &[&str]| {
+            let args = [
+                "mazes",
+                "--no-interactive",
+                "--quiet",
+                "solve",
+                "--path",
+                map.to_str().unwrap(),
+                "--start-x",
+                "1",
+                "--start-y",
+                "1",
+            ];
+            run(&Cli::try_parse_from(args.iter().chain(extra)).unwrap())
+        };
+
+        solve(&["--dest-x", "3", "--dest-y", "1"]).unwrap();
+        assert!(!png.exists());
+        let png_args = [
+            "--dest-x",
+            "3",
+            "--dest-y",
+            "1",
+            "--png",
+            png.to_str().unwrap(),
+        ];
+        solve(&png_args).unwrap();
+        assert!(png.exists());
+        let error = solve(&[]).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(error.is::<MissingArgument>());
+        assert_eq!(exit_code(&error), ExitCode::from(2));
+    }
+
+    #[test]
+    fn generated_mazes_are_saved_unsolved_and_solved() {
+        let dir = std::env::temp_dir().join(format!("mazes-gen-solve-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (unsolved, solved) = (dir.join("maze.png"), dir.join("solved.png"));
+        let args = [
+            "mazes",
+            "--quiet",
+            "gen",
+            "--width",
+            "15",
+            "--height",
+            "11",
+            "--path",
+            unsolved.to_str().unwrap(),
+            "--solve",
+            solved.to_str().unwrap(),
+        ];
+
+        run(&Cli::try_parse_from(args).unwrap()).unwrap();
+
+        let maze = load_map(&unsolved, &ImportOptions::default(), None).unwrap();
+        let solution_map = load_map(&solved, &ImportOptions::default(), None).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let (start, goal) = outermost_blocks(&maze).unwrap();
+        assert_eq!((start.x, start.y, goal.x, goal.y), (1, 1, 13, 9));
+        let solution = a_star(&maze, start, goal).unwrap();
+        let marked = solution_map
+            .iter_blocks()
+            .filter(|block| block.block_type() == BlockType::Solution)
+            .count();
+        assert_eq!(marked, solution.path().len());
+    }
+
+    #[test]
+    fn difficulty_targets_reject_multiple_threads() {
+        let gen = |extra: &[&str]| {
+            let args = ["mazes", "gen", "--width", "21", "--height", "21"];
+            Cli::try_parse_from(args.iter().chain(extra))
+        };
+
+        assert!(gen(&["--threads", "4", "--dead-ends", "1-9"]).is_err());
+        assert!(gen(&["--decision-points", "1-", "--threads", "2"]).is_err());
+        assert!(gen(&["--dead-ends", "1-9"]).is_ok());
+        assert!(gen(&["--threads", "4"]).is_ok());
+    }
+
+    #[test]
+    fn animations_redraw_in_place_and_show_the_cursor_again() {
+        let map = Map::from_text("#####\n#...#\n#####\n").unwrap();
+        let path = (1..4).map(|x| map.get_block(x, 1).unwrap()).collect_vec();
+        let mut out = vec![];
+
+        with_hidden_cursor(&mut out, |out| {
+            play_path(out, &map, &path, TextTheme::Ascii, Duration::ZERO)
+        })
+        .unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        // The map once, then the first block and a restored and a new block for every further step
+        assert!(text.contains("#...#"));
+        assert_eq!(text.matches("\u{1b}7").count(), 2 * path.len() - 1);
+        assert!(text.ends_with("\u{1b}[?25h"));
+
+        // The carving ends on the finished maze
+        let mut events =
+            generate_maze_iter(4, 3, MazeAlgorithm::default(), &GenOptions::default()).unwrap();
+        let carved = events.by_ref().collect_vec();
+        let map = Map::from(events.into_maze());
+        let frame =
+            play_carving(&mut vec![], &map, &carved, TextTheme::Ascii, Duration::ZERO).unwrap();
+        assert_eq!(
+            frame.to_text_themed(TextTheme::Ascii),
+            map.to_text_themed(TextTheme::Ascii)
+        );
+    }
+
+    #[test]
+    fn the_cursor_comes_back_when_animations_fail() {
+        let mut out = vec![];
+        let result = with_hidden_cursor(&mut out, |_| -> anyhow::Result<()> {
+            Err(anyhow!("The terminal is gone"))
+        });
+        assert!(result.is_err());
+        assert!(out.ends_with(b"\x1b[?25h"));
+
+        let mut out = vec![];
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            with_hidden_cursor(&mut out, |_| -> anyhow::Result<()> {
+                panic!("Interrupted")
+            })
+        }));
+        assert!(result.is_err());
+        assert!(out.ends_with(b"\x1b[?25h"));
+    }
+}
@@ -1,17 +1,72 @@
-use std::fmt::Display;
-
-use image::{DynamicImage, Rgba, RgbaImage};
+mod binary;
+mod bottlenecks;
+mod components;
+mod compose;
+mod cost_grid;
+mod explain;
+mod features;
+mod flow;
+mod graph;
+mod hash;
+#[cfg(feature = "image")]
+mod import;
+mod isometric;
+#[cfg(feature = "json")]
+mod json;
+mod labels;
+mod metadata;
+mod movingai;
+mod packed;
+mod pdf;
+mod prune;
+mod reach;
+mod render;
+mod rle;
+mod sample;
+mod terrain;
+mod text;
+#[cfg(feature = "image")]
+mod tileset;
+mod validate;
+
+use std::{collections::BTreeMap, fmt::Display, ops::Index};
+
+use anyhow::anyhow;
+#[cfg(feature = "image")]
+use image::DynamicImage;
 use itertools::Itertools;
-#[cfg(debug_assertions)]
-use std::time::Instant;
-
-use crate::maze_generation::{Cell, Color, MazeMap, Wall};
-
-const IMAGE_BORDER_WIDTH: usize = 3;
-const IMAGE_BLOCK_WIDTH: usize = 20;
 
+use crate::maze_generation::{Axis, Cell, Color, MazeMap, Wall};
+
+#[cfg(feature = "image")]
+pub(crate) use components::region_color;
+pub use components::Components;
+pub use compose::DownscalePolicy;
+pub use explain::Unreachability;
+pub use features::RiverOptions;
+pub use flow::FlowField;
+pub use graph::Edge;
+#[cfg(feature = "image")]
+pub use import::ImportOptions;
+pub use isometric::IsometricOptions;
+pub use metadata::Metadata;
+pub use packed::PackedMap;
+pub use pdf::PageSize;
+pub use prune::Corridor;
+pub use reach::BitGrid;
+pub use render::{ColorRamp, Palette, RenderOptions};
+pub use terrain::{TerrainBand, TerrainNoise};
+pub use text::TextTheme;
+#[cfg(feature = "image")]
+pub use tileset::{Tile, Tileset};
+pub use validate::{ValidationFinding, ValidationReport};
+
+pub(crate) const IMAGE_BORDER_WIDTH: usize = 3;
+pub(crate) const IMAGE_BLOCK_WIDTH: usize = 20;
+
+/// The terrain of a block. Every type has its own color in images and text.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
-enum BlockType {
+pub enum BlockType {
     White,
     Black,
     Orange,
@@ -20,27 +75,150 @@ enum BlockType {
     Yellow,
     Border,
     Solution,
+    StairsUp,
+    StairsDown,
+    /// A weave crossing where the horizontal passage runs over the vertical one
+    BridgeHorizontal,
+    /// A weave crossing where the vertical passage runs over the horizontal one
+    BridgeVertical,
+    /// Picking up a key allows passing all doors of the same color
+    Key(KeyColor),
+    /// Only passable with the key of the same color
+    Door(KeyColor),
+    /// Stepping onto a portal moves the agent to the other portal of the same color
+    Portal(PortalColor),
+    /// A one-way door or conveyor belt: can't be entered against its direction and can only be left along it
+    OneWay(Direction),
 }
 
-impl BlockType {
-    fn from_rgba(rgba: &Rgba<u8>) -> Self {
-        let red = *rgba.0.first().expect("Rgba needs to have red");
-        let green = *rgba.0.get(1).expect("Rgba needs to have green");
-        let blue = *rgba.0.get(2).expect("Rgba needs to have blue");
-
-        match (red, green, blue) {
-            (0, 0, 0) => BlockType::Black,
-            (255, 255, 255) => BlockType::White,
-            (200, 113, 55) => BlockType::Orange,
-            (255, 255, 0) => BlockType::Yellow,
-            (0, 255, 0) => BlockType::Green,
-            (0, 0, 255) => BlockType::Blue,
-            (138, 74, 243) => BlockType::Solution,
-            _ => BlockType::Border,
+/// A direction on the grid, the origin is in the top left
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+pub enum Direction {
+    Left,
+    Up,
+    Right,
+    Down,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Left,
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+    ];
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Up => Direction::Down,
+            Direction::Right => Direction::Left,
+            Direction::Down => Direction::Up,
+        }
+    }
+
+    pub fn clockwise(self) -> Self {
+        match self {
+            Direction::Left => Direction::Up,
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+        }
+    }
+
+    /// The direction of a step between two neighboring blocks
+    pub fn between(from: Block, to: Block) -> Option<Self> {
+        match (
+            to.x as isize - from.x as isize,
+            to.y as isize - from.y as isize,
+        ) {
+            (-1, 0) => Some(Direction::Left),
+            (0, -1) => Some(Direction::Up),
+            (1, 0) => Some(Direction::Right),
+            (0, 1) => Some(Direction::Down),
+            _ => None,
         }
     }
+}
+
+/// The color that matches a key to its doors
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+pub enum KeyColor {
+    Purple,
+    Green,
+    Blue,
+}
+
+/// The color that pairs two portals
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash)]
+pub enum PortalColor {
+    Orange,
+    Yellow,
+    Brown,
+}
+
+impl PortalColor {
+    pub const ALL: [PortalColor; 3] =
+        [PortalColor::Orange, PortalColor::Yellow, PortalColor::Brown];
+
+    fn rgb(self) -> [u8; 3] {
+        match self {
+            PortalColor::Orange => [255, 140, 0],
+            PortalColor::Yellow => [200, 200, 0],
+            PortalColor::Brown => [120, 60, 20],
+        }
+    }
+}
+
+impl KeyColor {
+    pub const ALL: [KeyColor; 3] = [KeyColor::Purple, KeyColor::Green, KeyColor::Blue];
+
+    /// The color of the key. Doors use a darker shade of it.
+    fn rgb(self) -> [u8; 3] {
+        match self {
+            KeyColor::Purple => [128, 0, 128],
+            KeyColor::Green => [0, 128, 0],
+            KeyColor::Blue => [0, 0, 128],
+        }
+    }
+}
+
+impl BlockType {
+    /// Every block type, including all colors and directions
+    pub(crate) fn all() -> impl Iterator<Item = BlockType> {
+        [
+            BlockType::White,
+            BlockType::Black,
+            BlockType::Orange,
+            BlockType::Blue,
+            BlockType::Green,
+            BlockType::Yellow,
+            BlockType::Border,
+            BlockType::Solution,
+            BlockType::StairsUp,
+            BlockType::StairsDown,
+            BlockType::BridgeHorizontal,
+            BlockType::BridgeVertical,
+        ]
+        .into_iter()
+        .chain(KeyColor::ALL.into_iter().map(BlockType::Key))
+        .chain(KeyColor::ALL.into_iter().map(BlockType::Door))
+        .chain(PortalColor::ALL.into_iter().map(BlockType::Portal))
+        .chain(Direction::ALL.into_iter().map(BlockType::OneWay))
+    }
 
-    fn to_rgba(&self) -> [u8; 4] {
+    /// A number per block type for binary formats, its position in [all](Self::all)
+    pub(crate) fn to_byte(self) -> u8 {
+        Self::all()
+            .position(|block_type| block_type == self)
+            .expect("Every block type is part of all") as u8
+    }
+
+    pub(crate) fn from_byte(byte: u8) -> Option<Self> {
+        Self::all().nth(byte as usize)
+    }
+
+    pub(crate) fn to_rgba(self) -> [u8; 4] {
         match self {
             BlockType::White => [255, 255, 255, 0],
             BlockType::Black => [0, 0, 0, 255],
@@ -50,12 +228,26 @@ impl BlockType {
             BlockType::Yellow => [255, 255, 0, 255],
             BlockType::Border => [255, 0, 0, 255],
             BlockType::Solution => [138, 74, 243, 255],
+            BlockType::StairsUp => [0, 255, 255, 255],
+            BlockType::StairsDown => [255, 0, 255, 255],
+            BlockType::BridgeHorizontal => [150, 75, 0, 255],
+            BlockType::BridgeVertical => [100, 50, 0, 255],
+            BlockType::Key(color) => {
+                let [r, g, b] = color.rgb();
+                [r, g, b, 255]
+            }
+            BlockType::Door(color) => {
+                let [r, g, b] = color.rgb();
+                [r / 2, g / 2, b / 2, 255]
+            }
+            BlockType::Portal(color) => {
+                let [r, g, b] = color.rgb();
+                [r, g, b, 255]
+            }
+            // Nearly the same pink, so that the direction survives importing the image
+            BlockType::OneWay(direction) => [255, 192, 200 + direction as u8, 255],
         }
     }
-
-    fn is_border(&self) -> bool {
-        *self == BlockType::Border
-    }
 }
 
 impl Display for BlockType {
@@ -69,6 +261,23 @@ impl Display for BlockType {
             BlockType::Yellow => "🟨",
             BlockType::Border => "🟥",
             BlockType::Solution => "🤖",
+            BlockType::StairsUp => "🔼",
+            BlockType::StairsDown => "🔽",
+            BlockType::BridgeHorizontal => "🟰",
+            BlockType::BridgeVertical => "🪜",
+            BlockType::Key(KeyColor::Purple) => "🟣",
+            BlockType::Key(KeyColor::Green) => "🟢",
+            BlockType::Key(KeyColor::Blue) => "🔵",
+            BlockType::Door(KeyColor::Purple) => "💜",
+            BlockType::Door(KeyColor::Green) => "💚",
+            BlockType::Door(KeyColor::Blue) => "💙",
+            BlockType::Portal(PortalColor::Orange) => "🟠",
+            BlockType::Portal(PortalColor::Yellow) => "🟡",
+            BlockType::Portal(PortalColor::Brown) => "🟤",
+            BlockType::OneWay(Direction::Left) => "⏪",
+            BlockType::OneWay(Direction::Up) => "⏫",
+            BlockType::OneWay(Direction::Right) => "⏩",
+            BlockType::OneWay(Direction::Down) => "⏬",
         };
         f.write_str(s)
     }
@@ -93,14 +302,68 @@ pub struct Block {
 }
 
 impl Block {
-    fn new(x: usize, y: usize, block_type: BlockType) -> Self {
+    pub(crate) fn new(x: usize, y: usize, block_type: BlockType) -> Self {
         Self { x, y, block_type }
     }
 
+    pub fn block_type(&self) -> BlockType {
+        self.block_type
+    }
+
+    /// The axis of the upper passage if this block is a weave crossing
+    pub fn crossing(&self) -> Option<Axis> {
+        match self.block_type {
+            BlockType::BridgeHorizontal => Some(Axis::Horizontal),
+            BlockType::BridgeVertical => Some(Axis::Vertical),
+            _ => None,
+        }
+    }
+
     pub fn is_walkable(&self) -> bool {
         !(self.block_type == BlockType::Black || self.block_type == BlockType::White)
     }
 
+    /// Whether this block can only be passed with a key
+    pub fn door(&self) -> Option<KeyColor> {
+        match self.block_type {
+            BlockType::Door(color) => Some(color),
+            _ => None,
+        }
+    }
+
+    /// The only direction in which a one-way block can be passed
+    pub fn one_way(&self) -> Option<Direction> {
+        match self.block_type {
+            BlockType::OneWay(direction) => Some(direction),
+            _ => None,
+        }
+    }
+
+    /// Whether the agent may step from this block onto the neighboring `to` block
+    pub(crate) fn allows_step_to(&self, to: Block) -> bool {
+        let Some(direction) = Direction::between(*self, to) else {
+            return false;
+        };
+        self.one_way().is_none_or(|exit| exit == direction)
+            && to
+                .one_way()
+                .is_none_or(|entry| entry != direction.opposite())
+    }
+
+    pub fn portal(&self) -> Option<PortalColor> {
+        match self.block_type {
+            BlockType::Portal(color) => Some(color),
+            _ => None,
+        }
+    }
+
+    pub fn key(&self) -> Option<KeyColor> {
+        match self.block_type {
+            BlockType::Key(color) => Some(color),
+            _ => None,
+        }
+    }
+
     /// The smaller the better!!!
     pub fn speed(&self) -> usize {
         match self.block_type {
@@ -112,6 +375,14 @@ impl Block {
             BlockType::Yellow => 7,
             BlockType::Border => usize::MAX,
             BlockType::Solution => usize::MAX,
+            BlockType::StairsUp => 3,
+            BlockType::StairsDown => 3,
+            BlockType::BridgeHorizontal => 2,
+            BlockType::BridgeVertical => 2,
+            BlockType::Key(_) => 1,
+            BlockType::Door(_) => 1,
+            BlockType::Portal(_) => 1,
+            BlockType::OneWay(_) => 1,
         }
     }
 }
@@ -127,12 +398,15 @@ pub struct Map {
     width: usize,
     height: usize,
     blocks: Vec<Vec<Block>>,
+    /// Named blocks, see [Map::set_label]
+    labels: BTreeMap<String, (usize, usize)>,
+    metadata: Metadata,
 }
 
 impl Map {
     pub fn new(blocks: Vec<Vec<Block>>) -> Self {
         let width = blocks
-            .get(0)
+            .first()
             .expect("A map must at least have a height of 1")
             .len();
         let height = blocks.len();
@@ -141,6 +415,95 @@ impl Map {
             width,
             height,
             blocks,
+            labels: BTreeMap::new(),
+            metadata: Metadata::default(),
+        }
+    }
+
+    /// The number of blocks per row
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The number of rows
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// All blocks row by row, starting at the top left
+    pub fn iter_blocks(&self) -> impl Iterator<Item = &Block> {
+        self.blocks.iter().flatten()
+    }
+
+    /// The rows of blocks from top to bottom
+    pub fn iter_rows(&self) -> impl Iterator<Item = &[Block]> {
+        self.blocks.iter().map(Vec::as_slice)
+    }
+
+    /// All blocks an agent can stand on
+    pub fn walkable_blocks(&self) -> impl Iterator<Item = &Block> {
+        self.iter_blocks().filter(|block| block.is_walkable())
+    }
+
+    /// Changes the terrain of the block at `x`, `y`.
+    pub fn set_block_type(
+        &mut self,
+        x: usize,
+        y: usize,
+        block_type: BlockType,
+    ) -> anyhow::Result<()> {
+        let block = self
+            .blocks
+            .get_mut(y)
+            .and_then(|row| row.get_mut(x))
+            .ok_or(anyhow!("Please specify coordinates within the map"))?;
+        block.block_type = block_type;
+        Ok(())
+    }
+
+    /// Changes the terrain of all blocks in the `width` x `height` rectangle whose top left corner is at `x`, `y`.
+    pub fn fill_rect(
+        &mut self,
+        (x, y): (usize, usize),
+        (width, height): (usize, usize),
+        block_type: BlockType,
+    ) -> anyhow::Result<()> {
+        if x + width > self.width || y + height > self.height {
+            return Err(anyhow!(
+                "A {width}x{height} rectangle at {x} {y} does not fit into a {}x{} map",
+                self.width,
+                self.height
+            ));
+        }
+        for row in &mut self.blocks[y..y + height] {
+            for block in &mut row[x..x + width] {
+                block.block_type = block_type;
+            }
+        }
+        Ok(())
+    }
+
+    /// Turns a walkable block into a wall and a wall into green terrain. Returns the new type of the block.
+    pub fn toggle_wall(&mut self, x: usize, y: usize) -> anyhow::Result<BlockType> {
+        let block = self
+            .get_block(x, y)
+            .ok_or(anyhow!("Please specify coordinates within the map"))?;
+        let toggled = if block.is_walkable() {
+            BlockType::Black
+        } else {
+            BlockType::Green
+        };
+        self.set_block_type(x, y, toggled)?;
+        Ok(toggled)
+    }
+
+    /// Turns the blocks of an entered solution back into green terrain, so the map can be solved again.
+    /// Their original terrain isn't known anymore.
+    pub fn clear_solution(&mut self) {
+        for block in self.blocks.iter_mut().flatten() {
+            if block.block_type == BlockType::Solution {
+                block.block_type = BlockType::Green;
+            }
         }
     }
 
@@ -150,7 +513,45 @@ impl Map {
             .and_then(|row: &Vec<Block>| row.get(x).cloned())
     }
 
+    /// The walkable blocks the agent can reach in one step, respecting one-way blocks.
+    /// Stepping onto a portal leads to its partner instead.
     pub fn get_reachable(&self, x: usize, y: usize) -> Vec<Block> {
+        let Some(from) = self.get_block(x, y) else {
+            return vec![];
+        };
+        self.get_adjacent(x, y)
+            .into_iter()
+            .filter(|block| from.allows_step_to(*block))
+            .map(|block| {
+                if block.portal().is_some() {
+                    self.portal_partner(block.x, block.y).unwrap_or(block)
+                } else {
+                    block
+                }
+            })
+            .collect_vec()
+    }
+
+    /// The other portal of the same color, if the block at `x`, `y` is a portal with exactly one partner.
+    /// A portal without a partner is an ordinary block.
+    pub fn portal_partner(&self, x: usize, y: usize) -> Option<Block> {
+        let color = self.get_block(x, y)?.portal()?;
+        self.iter_blocks()
+            .filter(|block| block.portal() == Some(color) && (block.x, block.y) != (x, y))
+            .exactly_one()
+            .ok()
+            .copied()
+    }
+
+    /// Every portal that has a partner together with that partner
+    pub(crate) fn portal_pairs(&self) -> Vec<(Block, Block)> {
+        self.iter_blocks()
+            .filter_map(|block| Some((*block, self.portal_partner(block.x, block.y)?)))
+            .collect_vec()
+    }
+
+    /// The walkable blocks next to `x`, `y`, ignoring portals and one-way blocks
+    pub(crate) fn get_adjacent(&self, x: usize, y: usize) -> Vec<Block> {
         let mut reachable_blocks = vec![];
 
         // To the left
@@ -180,7 +581,7 @@ impl Map {
             .collect_vec()
     }
 
-    pub fn enter_solution(&mut self, locations: &Vec<Block>) {
+    pub fn enter_solution(&mut self, locations: &[Block]) {
         self.blocks
             .iter_mut()
             .map(|row| {
@@ -191,121 +592,41 @@ impl Map {
             })
             .collect_vec();
     }
-
-    pub fn to_string_with_locations(&self, locations: &Vec<Block>, with_numbers: bool) -> String {
-        let mut res = "".to_string();
-        if with_numbers {
-            res += "  ";
-            for i in 0..self.width {
-                res += &format!("{:>2}", i);
-            }
-            res += "\n";
-        }
-        for (i, row) in self.blocks.iter().enumerate() {
-            if with_numbers {
-                res += &format!("{:>2}", i);
-            }
-            for block in row {
-                let mut block = *block;
-                if let Some(_) = locations.iter().find(|location| **location == block) {
-                    block.block_type = BlockType::Solution;
-                }
-                res += &block.to_string();
-            }
-            res += "\n";
-        }
-        res
-    }
-
-    pub fn to_image(self) -> Option<RgbaImage> {
-        #[cfg(debug_assertions)]
-        let now = Instant::now();
-        let image_width: u32 = self.width as u32 * IMAGE_BLOCK_WIDTH as u32
-            + (self.width as u32 - 1) * IMAGE_BORDER_WIDTH as u32;
-        let image_height: u32 = self.height as u32 * IMAGE_BLOCK_WIDTH as u32
-            + (self.height as u32 - 1) * IMAGE_BORDER_WIDTH as u32;
-        let border_rows = (0..IMAGE_BORDER_WIDTH)
-            .map(|_| (0..image_width).map(|_| BlockType::Border).collect_vec())
-            .collect_vec();
-        let buffer_vec = self
-            .blocks
-            .into_iter()
-            .map(|block_row| block_row.iter().map(|block| block.block_type).collect_vec())
-            .map(|block_row| expand_block_row(&block_row))
-            .intersperse(border_rows)
-            .flatten()
-            .flatten()
-            .map(|block_type| block_type.to_rgba())
-            .flatten()
-            .collect();
-
-        #[cfg(debug_assertions)]
-        println!("Image generation took {:.2?}", now.elapsed());
-
-        RgbaImage::from_vec(image_width, image_height, buffer_vec)
-    }
 }
 
-fn expand_block_row(block_row: &Vec<BlockType>) -> Vec<Vec<BlockType>> {
-    let expanded_row = block_row
-        .into_iter()
-        .intersperse(&BlockType::Border)
-        .flat_map(|block_type| {
-            if block_type.is_border() {
-                0..IMAGE_BORDER_WIDTH
-            } else {
-                0..IMAGE_BLOCK_WIDTH
-            }
-            .map(|_| block_type.clone())
-        })
-        .collect_vec();
+impl Index<(usize, usize)> for Map {
+    type Output = Block;
 
-    (0..IMAGE_BLOCK_WIDTH)
-        .map(|_| expanded_row.clone())
-        .collect_vec()
+    /// The block at `(x, y)`.
+    ///
+    /// # Panics
+    /// If the coordinates are outside the map, use [Map::get_block] to handle that case.
+    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+        &self.blocks[y][x]
+    }
 }
 
 impl Display for Map {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.to_string_with_locations(&vec![], true))?;
+        f.write_str(&self.to_string_with_locations(&[], true))?;
         Ok(())
     }
 }
 
+#[cfg(feature = "image")]
 impl From<DynamicImage> for Map {
-    fn from(mut img: DynamicImage) -> Self {
-        // let mut img = image::open("./images/lageplan.png").expect("Error opening the image");
-        let rgba8_img = img
-            .as_mut_rgba8()
-            .expect("Failed to convert image to rgba8.");
-
-        // Every pixel row of a chunk belongs to the same block.
-        let row_chunks = rgba8_img
-            .rows()
-            .map(|row| row.map(BlockType::from_rgba).collect_vec())
-            .chunk_by(is_border_row);
-
-        let blocks = row_chunks
-            .into_iter()
-            .filter(|(is_border_row, _)| !is_border_row)
-            .map(|(_, chunk)| {
-                // Take the third pixel row to get pure colors (on the edges of each block are "blurred pixels" due to compression)
-                chunk
-                    .skip(2)
-                    .take(1)
-                    .nth(0)
-                    .expect("Each row must at least have a height of 5 pixels")
-            })
-            .enumerate()
-            .map(|(block_row_y, row)| get_blocks_from_pixel_row(block_row_y, &row))
-            .collect_vec();
-
-        Map::new(blocks)
+    /// Reads the map with [Map::from_image].
+    ///
+    /// # Panics
+    /// If the image doesn't contain a map.
+    fn from(img: DynamicImage) -> Self {
+        Map::from_image(&img).expect("The image must contain a map")
     }
 }
 
 impl From<MazeMap> for Map {
     fn from(value: MazeMap) -> Self {
+        let metadata = value.metadata().clone();
         let mut block_rows = value
             .cells
             .into_iter()
@@ -313,39 +634,40 @@ impl From<MazeMap> for Map {
             .collect_vec();
         block_rows.push(
             (0..(value.width * 2 + 1))
-                .map(|i| Block::new(i, value.height, BlockType::Black))
+                .map(|i| Block::new(i, value.height * 2, BlockType::Black))
                 .collect_vec(),
         );
         Self {
             width: value.width * 2 + 1,
             height: value.height * 2 + 1,
             blocks: block_rows,
+            labels: BTreeMap::new(),
+            metadata,
         }
     }
 }
 
 // Each cell row can be expanded in 3 block rows. One of those is shared between two cell_rows.
 // Therefore each cell row gets expanded into two block_row: The top and the middle block row.
-fn expand_cell_row(cell_row: &Vec<Cell>) -> Vec<Vec<Block>> {
-    let mut block_rows = vec![];
-    block_rows.push(get_top_block_row_of_cell_row(cell_row));
-    block_rows.push(get_middle_block_row_of_cell_row(cell_row));
-    block_rows
+fn expand_cell_row(cell_row: &[Cell]) -> Vec<Vec<Block>> {
+    vec![
+        get_top_block_row_of_cell_row(cell_row),
+        get_middle_block_row_of_cell_row(cell_row),
+    ]
 }
 
-fn get_top_block_row_of_cell_row(cell_row: &Vec<Cell>) -> Vec<Block> {
+fn get_top_block_row_of_cell_row(cell_row: &[Cell]) -> Vec<Block> {
     let mut block_row = vec![];
 
     let y = cell_row
         .first()
         .expect("The MazeMap must at least have a width of 1")
         .y
-        * 2
-        + 0;
+        * 2;
 
     for cell in cell_row {
         // Top left block is always black
-        block_row.push(Block::new(cell.x * 2 + 0, y, BlockType::Black));
+        block_row.push(Block::new(cell.x * 2, y, BlockType::Black));
         let block_type = if cell.top == Wall::Open {
             BlockType::from(cell.color)
         } else {
@@ -354,12 +676,12 @@ fn get_top_block_row_of_cell_row(cell_row: &Vec<Cell>) -> Vec<Block> {
         block_row.push(Block::new(cell.x * 2 + 1, y, block_type));
     }
 
-    block_row.push(Block::new(cell_row.len(), y, BlockType::Black));
+    block_row.push(Block::new(cell_row.len() * 2, y, BlockType::Black));
 
     block_row
 }
 
-fn get_middle_block_row_of_cell_row(cell_row: &Vec<Cell>) -> Vec<Block> {
+fn get_middle_block_row_of_cell_row(cell_row: &[Cell]) -> Vec<Block> {
     let mut block_row = vec![];
 
     let y = cell_row
@@ -376,9 +698,15 @@ fn get_middle_block_row_of_cell_row(cell_row: &Vec<Cell>) -> Vec<Block> {
             BlockType::Black
         };
 
-        block_row.push(Block::new(cell.x * 2 + 0, y, block_type));
+        block_row.push(Block::new(cell.x * 2, y, block_type));
 
-        block_row.push(Block::new(cell.x * 2 + 1, y, cell.color.into()));
+        let block_type = match cell.crossing {
+            _ if cell.masked => BlockType::Black,
+            Some(Axis::Horizontal) => BlockType::BridgeHorizontal,
+            Some(Axis::Vertical) => BlockType::BridgeVertical,
+            None => cell.color.into(),
+        };
+        block_row.push(Block::new(cell.x * 2 + 1, y, block_type));
     }
 
     let last_cell = cell_row
@@ -391,26 +719,137 @@ fn get_middle_block_row_of_cell_row(cell_row: &Vec<Cell>) -> Vec<Block> {
         BlockType::Black
     };
 
-    block_row.push(Block::new(cell_row.len(), y, block_type));
+    block_row.push(Block::new(cell_row.len() * 2, y, block_type));
 
     block_row
 }
 
-fn is_border_row(row: &Vec<BlockType>) -> bool {
-    row.iter().all(|block| block.is_border())
+#[cfg(test)]
+impl Map {
+    /// Builds a map from text, one character per block: `#` is a wall, `o`, `b`, `y` are orange, blue and yellow,
+    /// `u` and `d` are stairs, `-` and `|` are crossings with the horizontal or vertical passage on top,
+    /// `1`, `2`, `3` are purple, green and blue keys, `A`, `B`, `C` are the matching doors,
+    /// `P`, `Q`, `R` are orange, yellow and brown portals, `<`, `^`, `>`, `v` are one-way blocks
+    /// and everything else is green.
+    pub(crate) fn from_rows(rows: &[&str]) -> Map {
+        Map::new(
+            rows.iter()
+                .enumerate()
+                .map(|(y, row)| {
+                    row.chars()
+                        .enumerate()
+                        .map(|(x, c)| {
+                            let block_type = match c {
+                                '#' => BlockType::Black,
+                                'o' => BlockType::Orange,
+                                'b' => BlockType::Blue,
+                                'y' => BlockType::Yellow,
+                                'u' => BlockType::StairsUp,
+                                'd' => BlockType::StairsDown,
+                                '-' => BlockType::BridgeHorizontal,
+                                '|' => BlockType::BridgeVertical,
+                                '1'..='3' => {
+                                    BlockType::Key(KeyColor::ALL[c as usize - '1' as usize])
+                                }
+                                'A'..='C' => {
+                                    BlockType::Door(KeyColor::ALL[c as usize - 'A' as usize])
+                                }
+                                'P'..='R' => {
+                                    BlockType::Portal(PortalColor::ALL[c as usize - 'P' as usize])
+                                }
+                                '<' => BlockType::OneWay(Direction::Left),
+                                '^' => BlockType::OneWay(Direction::Up),
+                                '>' => BlockType::OneWay(Direction::Right),
+                                'v' => BlockType::OneWay(Direction::Down),
+                                _ => BlockType::Green,
+                            };
+                            Block::new(x, y, block_type)
+                        })
+                        .collect_vec()
+                })
+                .collect_vec(),
+        )
+    }
 }
 
-fn get_blocks_from_pixel_row(block_row_y: usize, pixel_row: &Vec<BlockType>) -> Vec<Block> {
-    pixel_row
-        .split(|block| block.is_border())
-        .filter(|pixel_block| pixel_block.len() > 2)
-        .map(|pixel_block| {
-            // Get the third pixel of the block to get pure color (on the edges of each block are blurred colors due to compression)
-            pixel_block
-                .get(2)
-                .expect("Each block must have at least a width of 3 pixels")
-        })
-        .enumerate()
-        .map(|(block_x, block_type)| Block::new(block_x, block_row_y, *block_type))
-        .collect_vec()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn streamed_png_matches_the_image() {
+        let map = Map::from_rows(&["#.o", "b>1", "P.#"]);
+        let mut png = vec![];
+
+        map.write_png(&mut png).unwrap();
+
+        let decoded = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(decoded, map.to_image().unwrap());
+    }
+
+    #[test]
+    fn accessors_expose_the_blocks() {
+        let map = Map::from_rows(&["#.o", "b##"]);
+
+        assert_eq!((map.width(), map.height()), (3, 2));
+        assert_eq!(map.iter_blocks().count(), 6);
+        assert_eq!(map.iter_rows().nth(1).unwrap()[0], map[(0, 1)]);
+        assert_eq!(map[(2, 0)].block_type(), BlockType::Orange);
+        assert_eq!(
+            map.walkable_blocks()
+                .map(|block| (block.x, block.y))
+                .collect_vec(),
+            vec![(1, 0), (2, 0), (0, 1)]
+        );
+    }
+
+    #[test]
+    fn blocks_of_generated_maps_know_their_position() {
+        let map = Map::from(crate::generate_maze(4, 3, None).unwrap());
+
+        for (y, row) in map.iter_rows().enumerate() {
+            for (x, block) in row.iter().enumerate() {
+                assert_eq!((block.x, block.y), (x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn editing_changes_the_blocks() {
+        let mut map = Map::from_rows(&["....", "....", "...."]);
+
+        map.fill_rect((1, 0), (2, 2), BlockType::Orange).unwrap();
+        map.set_block_type(3, 2, BlockType::Blue).unwrap();
+
+        assert_eq!(
+            map.to_string(),
+            Map::from_rows(&[".oo.", ".oo.", "...b"]).to_string()
+        );
+        assert!(map.fill_rect((2, 2), (3, 1), BlockType::Black).is_err());
+        assert!(map.set_block_type(4, 0, BlockType::Black).is_err());
+    }
+
+    #[test]
+    fn toggling_a_wall_twice_makes_it_walkable() {
+        let mut map = Map::from_rows(&["o"]);
+
+        assert_eq!(map.toggle_wall(0, 0).unwrap(), BlockType::Black);
+        assert_eq!(map.toggle_wall(0, 0).unwrap(), BlockType::Green);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn special_blocks_survive_an_image_round_trip() {
+        let map = Map::from_rows(&["1AB", "2C3", "PQR", "<^>", "v.."]);
+
+        let image = DynamicImage::ImageRgba8(map.clone().to_image().unwrap());
+        let imported = Map::from(image);
+
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                assert_eq!(imported.get_block(x, y), map.get_block(x, y));
+            }
+        }
+    }
 }
@@ -0,0 +1,307 @@
+use anyhow::anyhow;
+use itertools::Itertools;
+
+use crate::Solution;
+
+use super::{Block, BlockType, Map};
+
+const MAGIC: &[u8; 4] = b"MZMP";
+const VERSION: u8 = 1;
+/// Set in the flags if a path follows the blocks
+const HAS_PATH: u8 = 1;
+/// Set in the flags if labels follow the blocks and the path
+const HAS_LABELS: u8 = 2;
+/// Set in the flags if metadata follows everything else
+const HAS_METADATA: u8 = 4;
+
+impl Map {
+    /// Encodes the map in a compact binary format that keeps every block type, unlike images with custom palettes.
+    ///
+    /// The format starts with the magic bytes `MZMP`, a version byte, a flags byte and the width and height
+    /// as little endian `u32`. A palette of the block types that occur follows, sorted by their number,
+    /// and then the blocks row by row as runs of a palette index and a length.
+    /// [Labels](Self::set_label) follow as their number and each name with its length and the coordinates,
+    /// the [metadata](Self::metadata) comes last as the number of entries and each key and value with its length.
+    /// The same map always results in the same bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encode(None)
+    }
+
+    /// Reads a map written by [to_bytes](Self::to_bytes) or [Solution::to_bytes].
+    /// A stored path is ignored, see [from_bytes_with_path](Self::from_bytes_with_path).
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Map> {
+        Ok(Map::decode(bytes)?.0)
+    }
+
+    /// Like [from_bytes](Self::from_bytes), but also returns the path from start to destination
+    /// that [Solution::to_bytes] stored, which is empty for maps without one.
+    pub fn from_bytes_with_path(bytes: &[u8]) -> anyhow::Result<(Map, Vec<Block>)> {
+        Map::decode(bytes)
+    }
+
+    fn encode(&self, path: Option<&[Block]>) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        let mut flags = 0;
+        if path.is_some() {
+            flags |= HAS_PATH;
+        }
+        if !self.labels.is_empty() {
+            flags |= HAS_LABELS;
+        }
+        let metadata = self.metadata.entries();
+        if !metadata.is_empty() {
+            flags |= HAS_METADATA;
+        }
+        bytes.push(flags);
+        for value in [self.width, self.height] {
+            bytes.extend((value as u32).to_le_bytes());
+        }
+
+        let palette = self
+            .iter_blocks()
+            .map(|block| block.block_type.to_byte())
+            .sorted()
+            .dedup()
+            .collect_vec();
+        bytes.push(palette.len() as u8);
+        bytes.extend(&palette);
+        for (block_type, run) in &self
+            .iter_blocks()
+            .chunk_by(|block| block.block_type.to_byte())
+        {
+            let index = palette
+                .binary_search(&block_type)
+                .expect("Every block type is part of the palette");
+            bytes.push(index as u8);
+            write_varint(&mut bytes, run.count());
+        }
+
+        if let Some(path) = path {
+            write_varint(&mut bytes, path.len());
+            for block in path {
+                write_varint(&mut bytes, block.x);
+                write_varint(&mut bytes, block.y);
+            }
+        }
+        if !self.labels.is_empty() {
+            write_varint(&mut bytes, self.labels.len());
+            for (name, (x, y)) in &self.labels {
+                write_varint(&mut bytes, name.len());
+                bytes.extend(name.as_bytes());
+                write_varint(&mut bytes, *x);
+                write_varint(&mut bytes, *y);
+            }
+        }
+        if !metadata.is_empty() {
+            write_varint(&mut bytes, metadata.len());
+            for text in metadata.iter().flat_map(|(key, value)| [key, value]) {
+                write_varint(&mut bytes, text.len());
+                bytes.extend(text.as_bytes());
+            }
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> anyhow::Result<(Map, Vec<Block>)> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(anyhow!("The data is not a binary map"));
+        }
+        let version = reader.byte()?;
+        if version != VERSION {
+            return Err(anyhow!("Unsupported binary map version {version}"));
+        }
+        let flags = reader.byte()?;
+        let [width, height] = [(); 2].map(|_| {
+            reader
+                .take(4)
+                .map(|value| u32::from_le_bytes(value.try_into().expect("4 bytes")) as usize)
+        });
+        let (width, height) = (width?, height?);
+        if width == 0 || height == 0 {
+            return Err(anyhow!("The map must at least have one block"));
+        }
+
+        let palette_len = reader.byte()? as usize;
+        let palette = reader
+            .take(palette_len)?
+            .iter()
+            .map(|byte| BlockType::from_byte(*byte).ok_or(anyhow!("Unknown block type {byte}")))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let len = width
+            .checked_mul(height)
+            .ok_or(anyhow!("The map is too large"))?;
+        let mut block_types = Vec::with_capacity(len.min(bytes.len() * 8));
+        while block_types.len() < len {
+            let index = reader.byte()? as usize;
+            let block_type = *palette
+                .get(index)
+                .ok_or(anyhow!("The palette has no block type {index}"))?;
+            let run = reader.varint()?;
+            if run == 0 || run > len - block_types.len() {
+                return Err(anyhow!("A run of blocks exceeds the map"));
+            }
+            block_types.extend(std::iter::repeat_n(block_type, run));
+        }
+        let mut map = Map::from_fn(width, height, |x, y| block_types[y * width + x]);
+
+        let mut path = vec![];
+        if flags & HAS_PATH != 0 {
+            for _ in 0..reader.varint()? {
+                let (x, y) = (reader.varint()?, reader.varint()?);
+                path.push(
+                    map.get_block(x, y)
+                        .ok_or(anyhow!("The path leaves the map at {x} {y}"))?,
+                );
+            }
+        }
+        if flags & HAS_LABELS != 0 {
+            for _ in 0..reader.varint()? {
+                let name = reader.text()?;
+                let (x, y) = (reader.varint()?, reader.varint()?);
+                let block = map
+                    .get_block(x, y)
+                    .ok_or(anyhow!("The label '{name}' is outside of the map"))?;
+                map.set_label(name, block)?;
+            }
+        }
+        if flags & HAS_METADATA != 0 {
+            for _ in 0..reader.varint()? {
+                let (key, value) = (reader.text()?, reader.text()?);
+                map.metadata.set(key, value)?;
+            }
+        }
+        if reader.position != bytes.len() {
+            return Err(anyhow!("The binary map is followed by unexpected data"));
+        }
+        Ok((map, path))
+    }
+}
+
+impl Solution {
+    /// Encodes the map like [Map::to_bytes] together with the path. The blocks of the path keep their terrain,
+    /// so [Map::from_bytes_with_path] returns the map as it was before solving.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut map = self.map.clone();
+        for block in &self.path {
+            map.blocks[block.y][block.x].block_type = block.block_type;
+        }
+        map.encode(Some(&self.path))
+    }
+}
+
+/// Unsigned LEB128: seven bits per byte, the lowest first, with the high bit set on all but the last byte
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let taken = self
+            .position
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.position..end))
+            .ok_or(anyhow!("The binary map is truncated"))?;
+        self.position += len;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// UTF-8 text preceded by its length in bytes
+    fn text(&mut self) -> anyhow::Result<&'a str> {
+        let len = self.varint()?;
+        std::str::from_utf8(self.take(len)?)
+            .map_err(|_| anyhow!("The binary map contains text that is not UTF-8"))
+    }
+
+    fn varint(&mut self) -> anyhow::Result<usize> {
+        let mut value = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as usize)
+                .checked_shl(shift)
+                .ok_or(anyhow!("A number of the binary map is too large"))?;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(anyhow!("A number of the binary map is too large"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star;
+
+    #[test]
+    fn every_block_type_survives_a_round_trip() {
+        let map = Map::from_rows(&["#.obyud-|", "123ABCPQR", "<^>v....."]);
+
+        let bytes = map.to_bytes();
+
+        assert_eq!(
+            Map::from_bytes(&bytes).unwrap().to_string(),
+            map.to_string()
+        );
+        assert_eq!(bytes, Map::from_bytes(&bytes).unwrap().to_bytes());
+    }
+
+    #[test]
+    fn runs_keep_uniform_areas_small() {
+        let map = Map::from_fn(1000, 1000, |_, y| {
+            if y % 2 == 0 {
+                BlockType::Black
+            } else {
+                BlockType::Green
+            }
+        });
+
+        assert!(map.to_bytes().len() < 3 * 1000 + 100);
+    }
+
+    #[test]
+    fn solutions_keep_their_terrain_and_path() {
+        let map = Map::from_rows(&["..o..", ".###.", "..b.."]);
+        let solution = a_star(
+            &map,
+            map.get_block(0, 0).unwrap(),
+            map.get_block(4, 2).unwrap(),
+        )
+        .unwrap();
+
+        let (read, path) = Map::from_bytes_with_path(&solution.to_bytes()).unwrap();
+
+        assert_eq!(read.to_string(), map.to_string());
+        assert_eq!(path, solution.path());
+        assert!(Map::from_bytes_with_path(&map.to_bytes())
+            .unwrap()
+            .1
+            .is_empty());
+    }
+
+    #[test]
+    fn corrupted_data_is_rejected() {
+        let bytes = Map::from_rows(&["#.", ".#"]).to_bytes();
+
+        assert!(Map::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Map::from_bytes(&[&bytes[..], &[0]].concat()).is_err());
+        assert!(Map::from_bytes(b"PNG").is_err());
+        let mut newer = bytes.clone();
+        newer[4] = VERSION + 1;
+        assert!(Map::from_bytes(&newer).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "image")]
+use image::RgbaImage;
+
+#[cfg(feature = "image")]
+use super::RenderOptions;
+use super::{Block, Map};
+
+/// The color of the bottlenecks in [Map::bottlenecks_image]
+#[cfg(feature = "image")]
+const BOTTLENECK: [u8; 4] = [255, 0, 255, 255];
+
+impl Map {
+    /// The walkable blocks whose removal splits the region they are in, the choke points every path between
+    /// the parts has to pass, row by row. Like in [components](Map::components), portals connect to their partner
+    /// and one-way blocks and doors are treated like ordinary blocks.
+    pub fn bottlenecks(&self) -> Vec<Block> {
+        let partners: HashMap<(usize, usize), (usize, usize)> = self
+            .portal_pairs()
+            .into_iter()
+            .map(|(portal, partner)| ((portal.x, portal.y), (partner.x, partner.y)))
+            .collect();
+        let index = |(x, y): (usize, usize)| y * self.width + x;
+        let mut neighbors = vec![vec![]; self.width * self.height];
+        for block in self.walkable_blocks().filter(|block| block.x < self.width) {
+            let mut adjacent = self
+                .get_adjacent(block.x, block.y)
+                .into_iter()
+                .map(|neighbor| (neighbor.x, neighbor.y))
+                .chain(partners.get(&(block.x, block.y)).copied())
+                .filter(|(x, _)| *x < self.width)
+                .map(index)
+                .collect::<Vec<_>>();
+            // A portal next to its partner is connected twice
+            adjacent.sort_unstable();
+            adjacent.dedup();
+            neighbors[index((block.x, block.y))] = adjacent;
+        }
+
+        // Tarjan's depth-first search, iterative so that long corridors don't overflow the stack.
+        // A block is a bottleneck if none of the blocks below it in the search tree reach above it
+        // without passing it, the root if it has more than one child.
+        let mut discovered = vec![usize::MAX; neighbors.len()];
+        let mut lowest = vec![usize::MAX; neighbors.len()];
+        let mut is_bottleneck = vec![false; neighbors.len()];
+        let mut time = 0;
+        for root in self
+            .walkable_blocks()
+            .filter(|block| block.x < self.width)
+            .map(|block| index((block.x, block.y)))
+        {
+            if discovered[root] != usize::MAX {
+                continue;
+            }
+            discovered[root] = time;
+            lowest[root] = time;
+            time += 1;
+            let mut root_children = 0;
+            // The block, its parent and how many of its neighbors were looked at
+            let mut stack = vec![(root, None, 0)];
+            while let Some((node, parent, next)) = stack.last_mut() {
+                let (node, parent) = (*node, *parent);
+                if let Some(&neighbor) = neighbors[node].get(*next) {
+                    *next += 1;
+                    if discovered[neighbor] == usize::MAX {
+                        discovered[neighbor] = time;
+                        lowest[neighbor] = time;
+                        time += 1;
+                        stack.push((neighbor, Some(node), 0));
+                    } else if Some(neighbor) != parent {
+                        lowest[node] = lowest[node].min(discovered[neighbor]);
+                    }
+                    continue;
+                }
+                stack.pop();
+                let Some(parent) = parent else {
+                    continue;
+                };
+                lowest[parent] = lowest[parent].min(lowest[node]);
+                if parent == root {
+                    root_children += 1;
+                } else if lowest[node] >= discovered[parent] {
+                    is_bottleneck[parent] = true;
+                }
+            }
+            is_bottleneck[root] = root_children > 1;
+        }
+
+        self.walkable_blocks()
+            .filter(|block| block.x < self.width && is_bottleneck[index((block.x, block.y))])
+            .copied()
+            .collect()
+    }
+
+    /// Draws the map with its [bottlenecks](Map::bottlenecks) highlighted
+    #[cfg(feature = "image")]
+    pub fn bottlenecks_image(&self, options: &RenderOptions) -> Option<RgbaImage> {
+        let bottlenecks = self
+            .bottlenecks()
+            .into_iter()
+            .map(|block| (block.x, block.y))
+            .collect::<std::collections::HashSet<_>>();
+        self.to_image_colored(options, |block| {
+            if bottlenecks.contains(&(block.x, block.y)) {
+                BOTTLENECK
+            } else {
+                options.palette.color(block.block_type)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_corridor_between_two_rooms_is_a_bottleneck() {
+        let map = Map::from_rows(&["..#..", "..#..", ".....", "..#.."]);
+
+        let bottlenecks = map
+            .bottlenecks()
+            .into_iter()
+            .map(|block| (block.x, block.y))
+            .collect::<Vec<_>>();
+
+        // The corridor and the blocks on both ends where it leaves the rooms
+        assert_eq!(bottlenecks, [(1, 2), (2, 2), (3, 2)]);
+        // Every block of a dead end corridor but its tip is one
+        let map = Map::from_rows(&["...", "#.#", "#.#", "#.#"]);
+        let bottlenecks = map
+            .bottlenecks()
+            .into_iter()
+            .map(|block| (block.x, block.y))
+            .collect::<Vec<_>>();
+        assert_eq!(bottlenecks, [(1, 0), (1, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn portals_bypass_the_choke_point() {
+        let map = Map::from_rows(&["P..#", "##.#", "#P.#"]);
+
+        assert!(map.bottlenecks().is_empty());
+
+        // Without them the blocks form a path, where all but both ends are bottlenecks
+        let map = Map::from_rows(&["...#", "##.#", "#..#"]);
+        assert_eq!(map.bottlenecks().len(), 4);
+    }
+}
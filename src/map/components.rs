@@ -0,0 +1,174 @@
+use std::collections::{HashMap, VecDeque};
+
+#[cfg(feature = "image")]
+use image::RgbaImage;
+
+use super::Map;
+#[cfg(feature = "image")]
+use super::RenderOptions;
+
+/// The walkable blocks of a [Map] labeled by the connected region they belong to.
+/// Portals connect to their partner, one-way blocks and doors are treated like ordinary blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Components {
+    width: usize,
+    /// The region of every block row by row, `None` for blocks that aren't walkable
+    labels: Vec<Option<usize>>,
+    sizes: Vec<usize>,
+}
+
+impl Components {
+    /// The region of the block at `x`, `y`, if it is walkable
+    pub fn label(&self, x: usize, y: usize) -> Option<usize> {
+        if x >= self.width {
+            return None;
+        }
+        self.labels.get(y * self.width + x).copied().flatten()
+    }
+
+    /// The number of blocks of each region, indexed by label
+    pub fn sizes(&self) -> &[usize] {
+        &self.sizes
+    }
+
+    /// The number of regions
+    pub fn len(&self) -> usize {
+        self.sizes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sizes.is_empty()
+    }
+
+    /// Whether both blocks are walkable and in the same region, which is necessary for a path between them
+    pub fn connected(&self, a: (usize, usize), b: (usize, usize)) -> bool {
+        self.label(a.0, a.1)
+            .is_some_and(|label| self.label(b.0, b.1) == Some(label))
+    }
+
+    /// The label of the region with the most blocks
+    pub fn largest(&self) -> Option<usize> {
+        (0..self.len()).max_by_key(|label| (self.sizes[*label], usize::MAX - label))
+    }
+
+    /// The coordinates of every walkable block with its label
+    pub(crate) fn iter_labels(&self) -> impl Iterator<Item = ((usize, usize), usize)> + '_ {
+        self.labels
+            .iter()
+            .enumerate()
+            .filter_map(|(i, label)| label.map(|label| ((i % self.width, i / self.width), label)))
+    }
+}
+
+impl Map {
+    /// Labels the walkable blocks by the connected region they belong to.
+    pub fn components(&self) -> Components {
+        let partners: HashMap<(usize, usize), (usize, usize)> = self
+            .portal_pairs()
+            .into_iter()
+            .map(|(portal, partner)| ((portal.x, portal.y), (partner.x, partner.y)))
+            .collect();
+        let index = |(x, y): (usize, usize)| y * self.width + x;
+        let mut labels = vec![None; self.width * self.height];
+        let mut sizes = vec![];
+
+        // Blocks of ragged rows beyond the width aren't labeled
+        for block in self.walkable_blocks().filter(|block| block.x < self.width) {
+            if labels[index((block.x, block.y))].is_some() {
+                continue;
+            }
+            let label = sizes.len();
+            let mut size = 0;
+            let mut queue = VecDeque::from([(block.x, block.y)]);
+            labels[index((block.x, block.y))] = Some(label);
+            while let Some((x, y)) = queue.pop_front() {
+                size += 1;
+                let neighbors = self
+                    .get_adjacent(x, y)
+                    .into_iter()
+                    .map(|neighbor| (neighbor.x, neighbor.y))
+                    .chain(partners.get(&(x, y)).copied())
+                    .filter(|(x, _)| *x < self.width);
+                for neighbor in neighbors {
+                    if labels[index(neighbor)].is_none() {
+                        labels[index(neighbor)] = Some(label);
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+
+        Components {
+            width: self.width,
+            labels,
+            sizes,
+        }
+    }
+
+    /// Draws every connected region in its own color. Blocks that aren't walkable keep their color.
+    #[cfg(feature = "image")]
+    pub fn components_image(&self, options: &RenderOptions) -> Option<RgbaImage> {
+        let components = self.components();
+        self.to_image_colored(options, |block| match components.label(block.x, block.y) {
+            Some(label) => region_color(label),
+            None => options.palette.color(block.block_type),
+        })
+    }
+}
+
+/// A distinct color for each region, spreading the hues by the golden ratio.
+#[cfg(feature = "image")]
+pub(crate) fn region_color(label: usize) -> [u8; 4] {
+    // Starts at green, so that the first region stands out from the red borders
+    let hue = (0.33 + label as f64 * 0.618_033_988_75).fract() * 6.0;
+    let (saturation, value) = (0.65, 0.95);
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as usize {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let channel = |c: f64| ((c + value - chroma) * 255.0).round() as u8;
+    [channel(r), channel(g), channel(b), 255]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separated_regions_get_different_labels() {
+        let map = Map::from_rows(&["..#.", "###.", "P#.P"]);
+
+        let components = map.components();
+
+        assert_eq!(components.sizes(), [2, 5]);
+        assert!(components.connected((3, 0), (3, 2)));
+        assert!(!components.connected((0, 0), (3, 0)));
+        assert_eq!(components.label(1, 1), None);
+        // The portals connect the bottom left block to the right region
+        assert!(components.connected((0, 2), (2, 2)));
+        assert_eq!(components.largest(), Some(1));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn regions_are_drawn_in_distinct_colors() {
+        let map = Map::from_rows(&[".#."]);
+        let options = RenderOptions {
+            block_width: 1,
+            border_width: 0,
+            ..RenderOptions::default()
+        };
+
+        let image = map.components_image(&options).unwrap();
+
+        assert_ne!(image.get_pixel(0, 0), image.get_pixel(2, 0));
+        assert_eq!(image.get_pixel(1, 0).0, [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,405 @@
+use anyhow::anyhow;
+use itertools::Itertools;
+use rand::{seq::IteratorRandom, Rng};
+
+use super::{Block, BlockType, Direction, Map};
+
+/// Decides the type of a block when [Map::downscale] merges several blocks into one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DownscalePolicy {
+    /// Any wall keeps the merged block a wall, so no passage appears that wasn't there.
+    /// Thin passages may close.
+    #[default]
+    PreserveWalls,
+    /// Any walkable block keeps the merged block walkable with the most common walkable type,
+    /// so passages stay open. Thin walls may disappear.
+    PreservePassages,
+    /// The most common type wins, ties go to the type that comes first
+    Majority,
+}
+
+impl DownscalePolicy {
+    fn merge(self, blocks: &[Block]) -> BlockType {
+        let most_common = |blocks: &mut dyn Iterator<Item = &Block>| {
+            let mut counts: Vec<(BlockType, usize)> = vec![];
+            for block in blocks {
+                match counts
+                    .iter_mut()
+                    .find(|(block_type, _)| *block_type == block.block_type)
+                {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((block.block_type, 1)),
+                }
+            }
+            // Iterating in reverse lets the first of several equally common types win
+            counts
+                .into_iter()
+                .rev()
+                .max_by_key(|(_, count)| *count)
+                .map(|(block_type, _)| block_type)
+        };
+        let merged = match self {
+            DownscalePolicy::PreserveWalls => blocks
+                .iter()
+                .find(|block| block.block_type == BlockType::Black)
+                .map(|block| block.block_type)
+                .or_else(|| most_common(&mut blocks.iter())),
+            DownscalePolicy::PreservePassages => {
+                most_common(&mut blocks.iter().filter(|block| block.is_walkable()))
+                    .or_else(|| most_common(&mut blocks.iter()))
+            }
+            DownscalePolicy::Majority => most_common(&mut blocks.iter()),
+        };
+        merged.expect("Every merged block covers at least one block")
+    }
+}
+
+impl Map {
+    /// Builds a map where every block gets the type returned for its coordinates
+    pub(crate) fn from_fn(
+        width: usize,
+        height: usize,
+        block_type: impl Fn(usize, usize) -> BlockType,
+    ) -> Map {
+        Map::new(
+            (0..height)
+                .map(|y| {
+                    (0..width)
+                        .map(|x| Block::new(x, y, block_type(x, y)))
+                        .collect_vec()
+                })
+                .collect_vec(),
+        )
+    }
+
+    fn block_type_at(&self, x: usize, y: usize) -> BlockType {
+        self.blocks[y][x].block_type
+    }
+
+    /// Copies all blocks of `other` into this map, with the top left corner of `other` at `offset`.
+    pub fn paste(&mut self, other: &Map, offset: (usize, usize)) -> anyhow::Result<()> {
+        let (offset_x, offset_y) = offset;
+        if offset_x + other.width > self.width || offset_y + other.height > self.height {
+            return Err(anyhow!(
+                "A {}x{} map does not fit into a {}x{} map at {offset_x} {offset_y}",
+                other.width,
+                other.height,
+                self.width,
+                self.height
+            ));
+        }
+        for (y, row) in other.blocks.iter().enumerate() {
+            for (x, block) in row.iter().enumerate() {
+                self.blocks[offset_y + y][offset_x + x].block_type = block.block_type;
+            }
+        }
+        Ok(())
+    }
+
+    /// Mirrors the map along the vertical axis (left becomes right)
+    pub fn hflip(&self) -> Map {
+        Map::from_fn(self.width, self.height, |x, y| {
+            match self.block_type_at(self.width - 1 - x, y) {
+                BlockType::OneWay(direction @ (Direction::Left | Direction::Right)) => {
+                    BlockType::OneWay(direction.opposite())
+                }
+                block_type => block_type,
+            }
+        })
+    }
+
+    /// Mirrors the map along the horizontal axis (top becomes bottom)
+    pub fn vflip(&self) -> Map {
+        Map::from_fn(self.width, self.height, |x, y| {
+            match self.block_type_at(x, self.height - 1 - y) {
+                BlockType::OneWay(direction @ (Direction::Up | Direction::Down)) => {
+                    BlockType::OneWay(direction.opposite())
+                }
+                block_type => block_type,
+            }
+        })
+    }
+
+    /// The `width` x `height` blocks with the top left corner at `x`, `y` as a map of their own
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> anyhow::Result<Map> {
+        if width == 0 || height == 0 {
+            return Err(anyhow!("The map must at least have the dimensions 1x1"));
+        }
+        if x + width > self.width || y + height > self.height {
+            return Err(anyhow!(
+                "A {width}x{height} rectangle at {x} {y} does not fit into a {}x{} map",
+                self.width,
+                self.height
+            ));
+        }
+        Ok(Map::from_fn(width, height, |cx, cy| {
+            self.block_type_at(x + cx, y + cy)
+        }))
+    }
+
+    /// Turns every block into `factor` x `factor` blocks of the same type.
+    /// Keys, doors and portals are copied to every one of them.
+    pub fn upscale(&self, factor: usize) -> anyhow::Result<Map> {
+        if factor == 0 {
+            return Err(anyhow!("Please specify a factor of at least 1"));
+        }
+        Ok(Map::from_fn(
+            self.width * factor,
+            self.height * factor,
+            |x, y| self.block_type_at(x / factor, y / factor),
+        ))
+    }
+
+    /// Merges every `factor` x `factor` blocks into one, using `policy` to pick its type.
+    /// Blocks at the right and bottom edge are merged with fewer blocks if the size isn't divisible by `factor`.
+    pub fn downscale(&self, factor: usize, policy: DownscalePolicy) -> anyhow::Result<Map> {
+        if factor == 0 {
+            return Err(anyhow!("Please specify a factor of at least 1"));
+        }
+        Ok(Map::from_fn(
+            self.width.div_ceil(factor),
+            self.height.div_ceil(factor),
+            |x, y| {
+                let blocks = self.blocks[y * factor..((y + 1) * factor).min(self.height)]
+                    .iter()
+                    .flat_map(|row| &row[x * factor..((x + 1) * factor).min(self.width)])
+                    .copied()
+                    .collect_vec();
+                policy.merge(&blocks)
+            },
+        ))
+    }
+
+    /// Rotates the map by 90 degrees clockwise
+    pub fn rotate90(&self) -> Map {
+        Map::from_fn(self.height, self.width, |x, y| {
+            // Crossings keep their upper passage, which now runs along the other axis
+            match self.block_type_at(y, self.height - 1 - x) {
+                BlockType::BridgeHorizontal => BlockType::BridgeVertical,
+                BlockType::BridgeVertical => BlockType::BridgeHorizontal,
+                BlockType::OneWay(direction) => BlockType::OneWay(direction.clockwise()),
+                block_type => block_type,
+            }
+        })
+    }
+
+    fn has_solid_border(&self) -> bool {
+        let solid = |x: usize, y: usize| !self.blocks[y][x].is_walkable();
+        (0..self.width).all(|x| solid(x, 0) && solid(x, self.height - 1))
+            && (0..self.height).all(|y| solid(0, y) && solid(self.width - 1, y))
+    }
+
+    /// Arranges the `tiles` (rows of maps) into one map. All tiles of a row must have the same height
+    /// and all tiles of a column the same width.
+    ///
+    /// If every tile is surrounded by a solid wall, neighboring tiles share their border walls and
+    /// `openings_per_seam` passages are opened through each shared wall to connect the tiles.
+    pub fn tile(tiles: &[Vec<Map>], openings_per_seam: usize) -> anyhow::Result<Map> {
+        let first_row = tiles
+            .first()
+            .filter(|row| !row.is_empty())
+            .ok_or(anyhow!("There must at least be one tile"))?;
+        if tiles.iter().any(|row| row.len() != first_row.len()) {
+            return Err(anyhow!("All rows of tiles must have the same length"));
+        }
+        let column_widths = first_row.iter().map(|tile| tile.width).collect_vec();
+        let row_heights = tiles.iter().map(|row| row[0].height).collect_vec();
+        for row in tiles {
+            for (column, tile) in row.iter().enumerate() {
+                if tile.width != column_widths[column] || tile.height != row[0].height {
+                    return Err(anyhow!(
+                        "All tiles of a column must have the same width and all tiles of a row the same height"
+                    ));
+                }
+            }
+        }
+
+        let overlap = usize::from(tiles.iter().flatten().all(Map::has_solid_border));
+        let offsets = |sizes: &[usize]| {
+            sizes
+                .iter()
+                .scan(0, |offset, size| {
+                    let current = *offset;
+                    *offset += size - overlap;
+                    Some(current)
+                })
+                .collect_vec()
+        };
+        let x_offsets = offsets(&column_widths);
+        let y_offsets = offsets(&row_heights);
+        let width = x_offsets.last().expect("Not empty") + column_widths.last().expect("Not empty");
+        let height = y_offsets.last().expect("Not empty") + row_heights.last().expect("Not empty");
+
+        let mut map = Map::from_fn(width, height, |_, _| BlockType::Black);
+        for (row, y_offset) in tiles.iter().zip(&y_offsets) {
+            for (tile, x_offset) in row.iter().zip(&x_offsets) {
+                map.paste(tile, (*x_offset, *y_offset))?;
+            }
+        }
+
+        if overlap == 1 {
+            let mut rng = rand::thread_rng();
+            // Vertical seams between horizontally neighboring tiles
+            for &seam_x in x_offsets.iter().skip(1) {
+                for (&y_offset, &tile_height) in y_offsets.iter().zip(&row_heights) {
+                    let segment = (y_offset + 1..y_offset + tile_height - 1).map(|y| (seam_x, y));
+                    map.open_seam(segment, (1, 0), openings_per_seam, &mut rng);
+                }
+            }
+            // Horizontal seams between vertically neighboring tiles
+            for &seam_y in y_offsets.iter().skip(1) {
+                for (&x_offset, &tile_width) in x_offsets.iter().zip(&column_widths) {
+                    let segment = (x_offset + 1..x_offset + tile_width - 1).map(|x| (x, seam_y));
+                    map.open_seam(segment, (0, 1), openings_per_seam, &mut rng);
+                }
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// Opens up to `count` blocks of the seam that have walkable blocks on both sides (along `direction`).
+    fn open_seam<R: Rng>(
+        &mut self,
+        seam: impl Iterator<Item = (usize, usize)>,
+        direction: (usize, usize),
+        count: usize,
+        rng: &mut R,
+    ) {
+        let (dx, dy) = direction;
+        let candidates = seam
+            .filter(|&(x, y)| {
+                self.blocks[y - dy][x - dx].is_walkable()
+                    && self.blocks[y + dy][x + dx].is_walkable()
+            })
+            .choose_multiple(rng, count);
+        for (x, y) in candidates {
+            // The opening gets the terrain of the block before it
+            self.blocks[y][x].block_type = self.blocks[y - dy][x - dx].block_type;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{a_star, generate_maze};
+
+    fn types(map: &Map) -> Vec<Vec<BlockType>> {
+        map.blocks
+            .iter()
+            .map(|row| row.iter().map(|block| block.block_type).collect_vec())
+            .collect_vec()
+    }
+
+    #[test]
+    fn four_rotations_are_the_identity() {
+        let map = Map::from(generate_maze(4, 3, None).unwrap());
+        let rotated = map.rotate90();
+        assert_eq!((rotated.width, rotated.height), (map.height, map.width));
+        assert_eq!(
+            types(&rotated.rotate90().rotate90().rotate90()),
+            types(&map)
+        );
+    }
+
+    #[test]
+    fn flipping_twice_is_the_identity() {
+        let map = Map::from(generate_maze(4, 3, None).unwrap());
+        assert_eq!(types(&map.hflip().hflip()), types(&map));
+        assert_eq!(types(&map.vflip().vflip()), types(&map));
+        assert_eq!(
+            map.hflip().get_block(0, 1).map(|block| block.block_type),
+            map.get_block(map.width - 1, 1)
+                .map(|block| block.block_type)
+        );
+    }
+
+    #[test]
+    fn one_way_blocks_turn_with_the_map() {
+        let map = Map::from_rows(&[">.", "^."]);
+        let block_type = |map: &Map, x, y| map.get_block(x, y).unwrap().block_type;
+
+        assert_eq!(
+            block_type(&map.rotate90(), 1, 0),
+            BlockType::OneWay(Direction::Down)
+        );
+        assert_eq!(
+            block_type(&map.rotate90(), 0, 0),
+            BlockType::OneWay(Direction::Right)
+        );
+        assert_eq!(
+            block_type(&map.hflip(), 1, 0),
+            BlockType::OneWay(Direction::Left)
+        );
+        assert_eq!(
+            block_type(&map.vflip(), 0, 0),
+            BlockType::OneWay(Direction::Down)
+        );
+    }
+
+    #[test]
+    fn crop_extracts_the_rectangle() {
+        let map = Map::from_rows(&["#o#.", "b..#", "###."]);
+
+        assert_eq!(
+            map.crop(1, 0, 2, 2).unwrap().to_string(),
+            Map::from_rows(&["o#", ".."]).to_string()
+        );
+        assert!(map.crop(3, 0, 2, 1).is_err());
+    }
+
+    #[test]
+    fn downscaling_an_upscaled_map_restores_it() {
+        let map = Map::from(generate_maze(4, 3, None).unwrap());
+        let upscaled = map.upscale(3).unwrap();
+        assert_eq!((upscaled.width, upscaled.height), (27, 21));
+
+        for policy in [
+            DownscalePolicy::PreserveWalls,
+            DownscalePolicy::PreservePassages,
+            DownscalePolicy::Majority,
+        ] {
+            assert_eq!(types(&upscaled.downscale(3, policy).unwrap()), types(&map));
+        }
+    }
+
+    #[test]
+    fn downscale_policies_decide_between_walls_and_passages() {
+        let map = Map::from_rows(&["#..o", "..oo", "###."]);
+        let downscaled = |policy| map.downscale(2, policy).unwrap().to_string();
+
+        assert_eq!(
+            downscaled(DownscalePolicy::PreserveWalls),
+            Map::from_rows(&["#o", "##"]).to_string()
+        );
+        assert_eq!(
+            downscaled(DownscalePolicy::PreservePassages),
+            Map::from_rows(&[".o", "#."]).to_string()
+        );
+        assert_eq!(
+            downscaled(DownscalePolicy::Majority),
+            Map::from_rows(&[".o", "##"]).to_string()
+        );
+    }
+
+    #[test]
+    fn paste_rejects_maps_that_do_not_fit() {
+        let mut map = Map::from(generate_maze(2, 2, None).unwrap());
+        let other = Map::from(generate_maze(3, 3, None).unwrap());
+        assert!(map.paste(&other, (0, 0)).is_err());
+    }
+
+    #[test]
+    fn tiles_share_walls_and_are_connected() {
+        let tile = || Map::from(generate_maze(3, 3, None).unwrap());
+        let tiles = vec![vec![tile(), tile()], vec![tile(), tile()]];
+
+        let map = Map::tile(&tiles, 1).unwrap();
+
+        assert_eq!((map.width, map.height), (13, 13));
+        let start = map.get_block(1, 1).unwrap();
+        let destination = map.get_block(11, 11).unwrap();
+        assert!(a_star(&map, start, destination).is_ok());
+    }
+}
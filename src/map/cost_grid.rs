@@ -0,0 +1,70 @@
+use anyhow::anyhow;
+use mazes_core::CostGrid;
+
+use super::{BlockType, Map};
+
+/// Converts a map for the pathfinding of `mazes-core`, which runs without the standard library.
+/// Fails for maps with keys, doors, portals, one-way blocks or crossings, which `mazes-core` doesn't know,
+/// so that a path through the grid is always a valid one on the map.
+/// Terrain that is too expensive for a byte becomes a wall.
+impl TryFrom<&Map> for CostGrid {
+    type Error = anyhow::Error;
+
+    fn try_from(map: &Map) -> anyhow::Result<Self> {
+        let mut grid = CostGrid::new(map.width, map.height);
+        for block in map.iter_blocks().filter(|block| block.is_walkable()) {
+            if matches!(
+                block.block_type,
+                BlockType::Key(_)
+                    | BlockType::Door(_)
+                    | BlockType::Portal(_)
+                    | BlockType::OneWay(_)
+                    | BlockType::BridgeHorizontal
+                    | BlockType::BridgeVertical
+            ) {
+                return Err(anyhow!(
+                    "The block at {} {} can't be converted, mazes-core has no keys, doors, portals, one-way blocks or crossings",
+                    block.x,
+                    block.y
+                ));
+            }
+            let cost = u8::try_from(block.speed()).unwrap_or(0);
+            grid.set_cost(block.x, block.y, cost);
+        }
+        Ok(grid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star;
+
+    #[test]
+    fn cost_grids_find_the_same_costs() {
+        let map = Map::from_rows(&["..o.#", ".#y..", "b..#.", "##..."]);
+        let grid = CostGrid::try_from(&map).unwrap();
+
+        let path = grid.find_path((0, 0), (4, 3)).unwrap();
+
+        let solution = a_star(
+            &map,
+            map.get_block(0, 0).unwrap(),
+            map.get_block(4, 3).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(path.cost, solution.cost());
+        assert_eq!(path.steps.len(), solution.path().len());
+        assert_eq!(grid.cost(4, 0), Some(0));
+    }
+
+    #[test]
+    fn maps_with_rules_beyond_costs_are_rejected() {
+        for rows in [["1.A.."], ["P...P"], [".>..."], ["..|.."]] {
+            assert!(
+                CostGrid::try_from(&Map::from_rows(&rows)).is_err(),
+                "{rows:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+};
+
+#[cfg(feature = "image")]
+use image::RgbaImage;
+use itertools::Itertools;
+
+#[cfg(feature = "image")]
+use super::{region_color, RenderOptions};
+use super::{BlockType, Components, Map};
+
+/// The color of the walls that separate the start from the goal in [Map::unreachability_image]
+#[cfg(feature = "image")]
+const SEPARATING_WALL: [u8; 4] = [255, 40, 200, 255];
+
+/// Why there is no path between two blocks, see [Map::explain_unreachable]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unreachability {
+    /// The walkable blocks connected to the start
+    pub start_region: Vec<(usize, usize)>,
+    /// The walkable blocks connected to the goal
+    pub goal_region: Vec<(usize, usize)>,
+    /// The fewest walls that separate both regions, row by row: every way from one to the other
+    /// that breaks through walls leads through at least one of them
+    pub separating_walls: Vec<(usize, usize)>,
+    /// The walls to remove on one path from the start to the goal that has to break through the fewest walls,
+    /// in the order they are passed
+    pub walls_to_open: Vec<(usize, usize)>,
+}
+
+impl Display for Unreachability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The start is in a region of {} blocks, the goal in one of {} blocks",
+            self.start_region.len(),
+            self.goal_region.len()
+        )?;
+        if self.walls_to_open.is_empty() {
+            return f.write_str(", no wall can be opened to connect them");
+        }
+        let positions =
+            |walls: &[(usize, usize)]| walls.iter().map(|(x, y)| format!("{x} {y}")).join(", ");
+        write!(
+            f,
+            ", separated by the {} walls at {}. Opening the {} walls at {} would connect them",
+            self.separating_walls.len(),
+            positions(&self.separating_walls),
+            self.walls_to_open.len(),
+            positions(&self.walls_to_open)
+        )
+    }
+}
+
+impl Map {
+    /// Explains why the goal can't be reached from the start: the connected regions both are in, the fewest
+    /// walls that separate them and the fewest walls to open on a path between them. `None` if either block
+    /// isn't walkable or both are in the same region, where only locked doors or one-way blocks can stand in the way.
+    pub fn explain_unreachable(
+        &self,
+        start: (usize, usize),
+        goal: (usize, usize),
+    ) -> Option<Unreachability> {
+        let components = self.components();
+        let start_label = components.label(start.0, start.1)?;
+        let goal_label = components.label(goal.0, goal.1)?;
+        if start_label == goal_label {
+            return None;
+        }
+        let region = |label| {
+            components
+                .iter_labels()
+                .filter(|(_, other)| *other == label)
+                .map(|(position, _)| position)
+                .collect_vec()
+        };
+        Some(Unreachability {
+            start_region: region(start_label),
+            goal_region: region(goal_label),
+            separating_walls: self.minimum_wall_cut(&components, start_label, goal_label),
+            walls_to_open: self
+                .fewest_walls_between(start, |(x, y)| components.label(x, y) == Some(goal_label)),
+        })
+    }
+
+    /// The fewest walls whose removal from the map, walls and walkable blocks alike, disconnects both regions.
+    ///
+    /// By the max-flow min-cut theorem, these are as many as there are ways from one region to the other
+    /// that don't share a wall. Every wall is split into an entry and an exit connected with capacity one,
+    /// everything else has unlimited capacity and each region is a single node. Once no further way is left,
+    /// the cut consists of the walls whose entry the start region still reaches, but not their exit.
+    fn minimum_wall_cut(
+        &self,
+        components: &Components,
+        start_label: usize,
+        goal_label: usize,
+    ) -> Vec<(usize, usize)> {
+        const UNLIMITED: u32 = u32::MAX;
+        let walls = self
+            .iter_blocks()
+            .filter(|block| block.x < self.width && block.block_type == BlockType::Black)
+            .map(|block| (block.x, block.y))
+            .collect_vec();
+        let wall_index: HashMap<(usize, usize), usize> = walls
+            .iter()
+            .enumerate()
+            .map(|(index, position)| (*position, index))
+            .collect();
+        let regions = components.len();
+        // The node flow enters a block through and the one it leaves it through, the same one for regions
+        let node = |position: (usize, usize)| match wall_index.get(&position) {
+            Some(index) => Some((regions + 2 * index, regions + 2 * index + 1)),
+            None => components
+                .label(position.0, position.1)
+                .map(|label| (label, label)),
+        };
+
+        // Every edge is followed by its reverse, so that `edge ^ 1` is the other one
+        let mut targets = vec![];
+        let mut capacities = vec![];
+        let mut edges = vec![vec![]; regions + 2 * walls.len()];
+        let mut connect = |from: usize, to: usize, capacity: u32| {
+            edges[from].push(targets.len());
+            targets.push(to);
+            capacities.push(capacity);
+            edges[to].push(targets.len());
+            targets.push(from);
+            capacities.push(0);
+        };
+        for index in 0..walls.len() {
+            connect(regions + 2 * index, regions + 2 * index + 1, 1);
+        }
+        for (x, y) in (0..self.height)
+            .cartesian_product(0..self.width)
+            .map(|(y, x)| (x, y))
+        {
+            let Some((entry, exit)) = node((x, y)) else {
+                continue;
+            };
+            for neighbor in [(x + 1, y), (x, y + 1)]
+                .into_iter()
+                .filter(|(x, _)| *x < self.width)
+            {
+                if let Some((neighbor_entry, neighbor_exit)) = node(neighbor) {
+                    if entry != neighbor_entry {
+                        connect(exit, neighbor_entry, UNLIMITED);
+                        connect(neighbor_exit, entry, UNLIMITED);
+                    }
+                }
+            }
+        }
+
+        // Augment along the shortest ways until the goal can't be reached anymore
+        let reachable = |capacities: &[u32], parents: &mut Vec<Option<usize>>| {
+            parents.fill(None);
+            let mut seen = vec![false; edges.len()];
+            seen[start_label] = true;
+            let mut queue = VecDeque::from([start_label]);
+            while let Some(current) = queue.pop_front() {
+                for &edge in &edges[current] {
+                    let target = targets[edge];
+                    if capacities[edge] > 0 && !seen[target] {
+                        seen[target] = true;
+                        parents[target] = Some(edge);
+                        queue.push_back(target);
+                    }
+                }
+            }
+            seen
+        };
+        let mut parents = vec![None; edges.len()];
+        while reachable(&capacities, &mut parents)[goal_label] {
+            let mut way = vec![];
+            let mut current = goal_label;
+            while let Some(edge) = parents[current] {
+                way.push(edge);
+                current = targets[edge ^ 1];
+            }
+            let Some(flow) = way.iter().map(|edge| capacities[*edge]).min() else {
+                break;
+            };
+            if flow == UNLIMITED {
+                // The regions touch, no walls separate them
+                return vec![];
+            }
+            for edge in way {
+                capacities[edge] -= flow;
+                capacities[edge ^ 1] += flow;
+            }
+        }
+        let seen = reachable(&capacities, &mut parents);
+        walls
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| seen[regions + 2 * index] && !seen[regions + 2 * index + 1])
+            .map(|(_, position)| position)
+            .sorted_by_key(|(x, y)| (*y, *x))
+            .collect()
+    }
+
+    /// The walls on the path from `start` to a goal block that crosses the fewest walls (0-1 BFS).
+    /// Walking costs nothing and portals teleport, every wall costs one.
+    fn fewest_walls_between(
+        &self,
+        start: (usize, usize),
+        is_goal: impl Fn((usize, usize)) -> bool,
+    ) -> Vec<(usize, usize)> {
+        let index = |(x, y): (usize, usize)| y * self.width + x;
+        let partners: HashMap<(usize, usize), (usize, usize)> = self
+            .portal_pairs()
+            .into_iter()
+            .map(|(portal, partner)| ((portal.x, portal.y), (partner.x, partner.y)))
+            .collect();
+        let mut walls = vec![u32::MAX; self.width * self.height];
+        let mut parents = vec![None; self.width * self.height];
+        walls[index(start)] = 0;
+        let mut queue = VecDeque::from([start]);
+        while let Some(position) = queue.pop_front() {
+            if is_goal(position) {
+                let mut separating = vec![];
+                let mut current = Some(position);
+                while let Some(block) = current {
+                    if self.blocks[block.1][block.0].block_type == BlockType::Black {
+                        separating.push(block);
+                    }
+                    current = parents[index(block)];
+                }
+                separating.reverse();
+                return separating;
+            }
+            let (x, y) = position;
+            let adjacent = [
+                x.checked_sub(1).map(|x| (x, y)),
+                y.checked_sub(1).map(|y| (x, y)),
+                Some((x + 1, y)),
+                Some((x, y + 1)),
+            ];
+            let teleport = partners.get(&position).copied();
+            let neighbors = adjacent
+                .into_iter()
+                .chain([teleport])
+                .flatten()
+                .filter(|(x, _)| *x < self.width);
+            for neighbor in neighbors {
+                let Some(block) = self.get_block(neighbor.0, neighbor.1) else {
+                    continue;
+                };
+                let cost = match block.block_type {
+                    BlockType::Black => 1,
+                    _ if block.is_walkable() => 0,
+                    _ => continue,
+                };
+                let total = walls[index(position)] + cost;
+                if total < walls[index(neighbor)] {
+                    walls[index(neighbor)] = total;
+                    parents[index(neighbor)] = Some(position);
+                    if cost == 0 {
+                        queue.push_front(neighbor);
+                    } else {
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+        vec![]
+    }
+
+    /// Draws the region of the start and the region of the goal each in its own color
+    /// and the walls that separate them highlighted
+    #[cfg(feature = "image")]
+    pub fn unreachability_image(
+        &self,
+        unreachability: &Unreachability,
+        options: &RenderOptions,
+    ) -> Option<RgbaImage> {
+        let set = |blocks: &[(usize, usize)]| {
+            blocks
+                .iter()
+                .copied()
+                .collect::<std::collections::HashSet<_>>()
+        };
+        let (walls, start_region, goal_region) = (
+            set(&unreachability.separating_walls),
+            set(&unreachability.start_region),
+            set(&unreachability.goal_region),
+        );
+        self.to_image_colored(options, |block| {
+            let position = (block.x, block.y);
+            if walls.contains(&position) {
+                SEPARATING_WALL
+            } else if start_region.contains(&position) {
+                region_color(0)
+            } else if goal_region.contains(&position) {
+                region_color(1)
+            } else {
+                options.palette.color(block.block_type)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_thinnest_wall_is_found() {
+        // A thick wall at the top and a single wall block in the middle
+        let map = Map::from_rows(&["..##..", "..##..", "..#...", "..##.."]);
+
+        let unreachability = map.explain_unreachable((0, 0), (5, 0)).unwrap();
+
+        assert_eq!(unreachability.start_region.len(), 8);
+        assert_eq!(unreachability.goal_region.len(), 9);
+        assert_eq!(unreachability.walls_to_open, [(2, 2)]);
+        // One wall in every row
+        assert_eq!(
+            unreachability.separating_walls,
+            [(2, 0), (2, 1), (2, 2), (2, 3)]
+        );
+        assert_eq!(
+            unreachability.to_string(),
+            "The start is in a region of 8 blocks, the goal in one of 9 blocks, \
+             separated by the 4 walls at 2 0, 2 1, 2 2, 2 3. Opening the 1 walls at 2 2 would connect them"
+        );
+        assert!(map.explain_unreachable((0, 0), (1, 3)).is_none());
+        assert!(map.explain_unreachable((0, 0), (2, 0)).is_none());
+    }
+
+    #[test]
+    fn the_separating_walls_are_a_minimum_cut() {
+        // A room enclosed by a ring of walls
+        let map = Map::from_rows(&["......", ".####.", ".#..#.", ".####.", "......"]);
+
+        let unreachability = map.explain_unreachable((0, 0), (2, 2)).unwrap();
+
+        assert_eq!(unreachability.walls_to_open.len(), 1);
+        // The 6 walls next to the room close it in, the corners of the ring don't touch it.
+        // Opening every other wall keeps it closed in
+        let cut = &unreachability.separating_walls;
+        assert_eq!(cut.len(), 6);
+        let mut opened = map.clone();
+        for block in map
+            .iter_blocks()
+            .filter(|block| block.block_type == BlockType::Black)
+        {
+            if !cut.contains(&(block.x, block.y)) {
+                opened.blocks[block.y][block.x].block_type = BlockType::Green;
+            }
+        }
+        assert!(!opened.components().connected((0, 0), (2, 2)));
+    }
+}
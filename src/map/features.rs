@@ -0,0 +1,247 @@
+use anyhow::anyhow;
+use rand::{seq::index::sample, Rng};
+
+use crate::Axis;
+
+use super::{Block, BlockType, Map};
+
+/// Options of [Map::carve_river]
+#[derive(Debug, Clone)]
+pub struct RiverOptions {
+    /// How many blocks wide the river is
+    pub width: usize,
+    /// Whether the river can only be crossed on bridges. Otherwise it's yellow terrain that can be waded through slowly
+    pub impassable: bool,
+    /// How many bridges cross the river
+    pub bridges: usize,
+    /// The direction the river flows in, from edge to edge
+    pub axis: Axis,
+}
+
+impl Default for RiverOptions {
+    fn default() -> Self {
+        Self {
+            width: 2,
+            impassable: false,
+            bridges: 2,
+            axis: Axis::Horizontal,
+        }
+    }
+}
+
+/// Roads and rivers replace walls and terrain, but leave keys, doors, portals and the like in place
+fn is_carvable(block_type: BlockType) -> bool {
+    matches!(
+        block_type,
+        BlockType::Black
+            | BlockType::White
+            | BlockType::Green
+            | BlockType::Blue
+            | BlockType::Orange
+            | BlockType::Yellow
+    )
+}
+
+/// The blocks of a line between both points in steps along the axes, including both ends
+fn line((from_x, from_y): (usize, usize), (to_x, to_y): (usize, usize)) -> Vec<(usize, usize)> {
+    let (width, height) = (from_x.abs_diff(to_x), from_y.abs_diff(to_y));
+    let (mut x, mut y) = (from_x, from_y);
+    let mut points = vec![(x, y)];
+    while (x, y) != (to_x, to_y) {
+        // Step along the axis that lags behind, comparing the progress of both as fractions
+        let x_behind =
+            y == to_y || (x != to_x && from_x.abs_diff(x) * height <= from_y.abs_diff(y) * width);
+        if x_behind {
+            x = if to_x > x { x + 1 } else { x - 1 };
+        } else {
+            y = if to_y > y { y + 1 } else { y - 1 };
+        }
+        points.push((x, y));
+    }
+    points
+}
+
+impl Map {
+    /// Carves a road of green, the cheapest terrain, along straight lines between the waypoints
+    /// and returns its blocks from the first waypoint to the last. Walls on the way are broken through.
+    pub fn carve_road(&mut self, waypoints: &[(usize, usize)]) -> anyhow::Result<Vec<Block>> {
+        if let Some((x, y)) = waypoints
+            .iter()
+            .find(|(x, y)| *x >= self.width || *y >= self.height)
+        {
+            return Err(anyhow!("The waypoint {x} {y} is outside of the map"));
+        }
+        let mut points = waypoints.first().copied().into_iter().collect::<Vec<_>>();
+        for pair in waypoints.windows(2) {
+            points.extend(line(pair[0], pair[1]).into_iter().skip(1));
+        }
+        Ok(points
+            .into_iter()
+            .map(|(x, y)| {
+                let block = &mut self.blocks[y][x];
+                if is_carvable(block.block_type) {
+                    block.block_type = BlockType::Green;
+                }
+                *block
+            })
+            .collect())
+    }
+
+    /// Carves a road from the left to the right of the map through two random waypoints, see [carve_road](Self::carve_road).
+    /// The road stays off the outermost blocks, so that a maze keeps its outer wall.
+    pub fn carve_random_road(&mut self, rng: &mut impl Rng) -> anyhow::Result<Vec<Block>> {
+        if self.width < 3 || self.height < 3 {
+            return Err(anyhow!("The map is too small for a road"));
+        }
+        let waypoints = [1, self.width / 3, self.width * 2 / 3, self.width - 2]
+            .map(|x| (x, rng.gen_range(1..self.height - 1)));
+        self.carve_road(&waypoints)
+    }
+
+    /// Carves a meandering river from edge to edge and returns how many bridges cross it.
+    ///
+    /// Bridges are [weave crossings](Block::crossing) across the river, so that the water still flows below them.
+    /// They are only built where there is walkable ground on both banks, so there may be fewer than asked for.
+    pub fn carve_river(
+        &mut self,
+        options: &RiverOptions,
+        rng: &mut impl Rng,
+    ) -> anyhow::Result<usize> {
+        // Work on the columns of a horizontal river, the rows of a vertical one
+        let (length, breadth) = match options.axis {
+            Axis::Horizontal => (self.width, self.height),
+            Axis::Vertical => (self.height, self.width),
+        };
+        if options.width == 0 || options.width + 2 > breadth {
+            return Err(anyhow!(
+                "The river must be 1 to {} blocks wide to fit into the map with banks on both sides",
+                breadth.saturating_sub(2)
+            ));
+        }
+        let position = |along: usize, across: usize| match options.axis {
+            Axis::Horizontal => (along, across),
+            Axis::Vertical => (across, along),
+        };
+
+        // The first block of the river across its flow, wandering by at most one block per step
+        let highest = breadth - options.width - 1;
+        let mut bank = rng.gen_range(1..=highest);
+        let mut banks = vec![];
+        for _ in 0..length {
+            banks.push(bank);
+            bank = (bank + rng.gen_range(0..=2))
+                .saturating_sub(1)
+                .clamp(1, highest);
+        }
+
+        let water = if options.impassable {
+            BlockType::Black
+        } else {
+            BlockType::Yellow
+        };
+        for (along, bank) in banks.iter().enumerate() {
+            for across in *bank..bank + options.width {
+                let (x, y) = position(along, across);
+                if is_carvable(self.blocks[y][x].block_type) {
+                    self.blocks[y][x].block_type = water;
+                }
+            }
+        }
+
+        let walkable = |map: &Map, (x, y): (usize, usize)| map.blocks[y][x].is_walkable();
+        let candidates = banks
+            .iter()
+            .enumerate()
+            .filter(|(along, bank)| {
+                let water_blocks =
+                    (**bank..**bank + options.width).map(|across| position(*along, across));
+                walkable(self, position(*along, **bank - 1))
+                    && walkable(self, position(*along, **bank + options.width))
+                    && water_blocks
+                        .into_iter()
+                        .all(|(x, y)| is_carvable(self.blocks[y][x].block_type))
+            })
+            .map(|(along, bank)| (along, *bank))
+            .collect::<Vec<_>>();
+        let bridges = options.bridges.min(candidates.len());
+        // The bridge runs across the river, against the flow
+        let bridge = match options.axis {
+            Axis::Horizontal => BlockType::BridgeVertical,
+            Axis::Vertical => BlockType::BridgeHorizontal,
+        };
+        for index in sample(rng, candidates.len(), bridges) {
+            let (along, bank) = candidates[index];
+            for across in bank..bank + options.width {
+                let (x, y) = position(along, across);
+                self.blocks[y][x].block_type = bridge;
+            }
+        }
+        Ok(bridges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::a_star;
+
+    #[test]
+    fn roads_follow_the_waypoints() {
+        let mut map = Map::from_text("#####\n#ooo#\n#####\n#yyy#\n#####\n").unwrap();
+
+        let road = map.carve_road(&[(1, 1), (3, 3)]).unwrap();
+
+        assert_eq!(road.len(), 5);
+        assert!(road
+            .iter()
+            .all(|block| block.block_type() == BlockType::Green));
+        assert_eq!(
+            road.iter()
+                .map(|block| (block.x, block.y))
+                .collect::<Vec<_>>(),
+            [(1, 1), (2, 1), (2, 2), (3, 2), (3, 3)]
+        );
+        assert!(map.carve_road(&[(1, 1), (5, 1)]).is_err());
+        let mut rng = StdRng::seed_from_u64(1);
+        let road = map.carve_random_road(&mut rng).unwrap();
+        assert_eq!((road[0].x, road[road.len() - 1].x), (1, 3));
+    }
+
+    #[test]
+    fn impassable_rivers_are_crossed_on_bridges() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut map = Map::from_fn(30, 20, |_, _| BlockType::Green);
+        let options = RiverOptions {
+            width: 3,
+            impassable: true,
+            bridges: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(map.carve_river(&options, &mut rng).unwrap(), 1);
+
+        let bridges = map
+            .iter_blocks()
+            .filter(|block| block.crossing().is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(bridges.len(), 3);
+        // The only way from top to bottom leads over the bridge
+        let solution = a_star(
+            &map,
+            map.get_block(0, 0).unwrap(),
+            map.get_block(29, 19).unwrap(),
+        )
+        .unwrap();
+        assert!(solution
+            .path()
+            .iter()
+            .any(|block| block.crossing().is_some()));
+        let options = RiverOptions {
+            width: 19,
+            ..Default::default()
+        };
+        assert!(map.carve_river(&options, &mut rng).is_err());
+    }
+}
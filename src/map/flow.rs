@@ -0,0 +1,169 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use anyhow::anyhow;
+
+use super::{Block, Direction, Map, TextTheme};
+
+/// The cheapest direction towards one goal for every walkable block of a [Map], see [Map::flow_field].
+/// Any number of agents can follow it without searching on their own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowField {
+    goal: (usize, usize),
+    width: usize,
+    /// The direction of the next step and the remaining cost row by row,
+    /// `None` for blocks that can't reach the goal
+    cells: Vec<Option<(Option<Direction>, u32)>>,
+}
+
+impl FlowField {
+    pub fn goal(&self) -> (usize, usize) {
+        self.goal
+    }
+
+    fn cell(&self, x: usize, y: usize) -> Option<(Option<Direction>, u32)> {
+        if x >= self.width {
+            return None;
+        }
+        self.cells.get(y * self.width + x).copied().flatten()
+    }
+
+    /// The direction an agent on the block at `x`, `y` should step in.
+    /// `None` on the goal itself and on blocks that can't reach it.
+    pub fn direction(&self, x: usize, y: usize) -> Option<Direction> {
+        self.cell(x, y).and_then(|(direction, _)| direction)
+    }
+
+    /// The cost of following the field from the block at `x`, `y` to the goal
+    pub fn cost(&self, x: usize, y: usize) -> Option<u32> {
+        self.cell(x, y).map(|(_, cost)| cost)
+    }
+
+    /// The map in the ASCII theme with an arrow on every block that can reach the goal and `@` on the goal
+    pub fn to_text(&self, map: &Map) -> String {
+        map.iter_rows()
+            .map(|row| {
+                row.iter()
+                    .map(|block| match self.cell(block.x, block.y) {
+                        Some((Some(direction), _)) => arrow(direction).to_string(),
+                        Some((None, _)) => "@".to_string(),
+                        None => map
+                            .text_at(block.x, block.y, TextTheme::Ascii)
+                            .unwrap_or_default(),
+                    })
+                    .collect::<String>()
+                    + "\n"
+            })
+            .collect()
+    }
+}
+
+fn arrow(direction: Direction) -> char {
+    match direction {
+        Direction::Left => '←',
+        Direction::Up => '↑',
+        Direction::Right => '→',
+        Direction::Down => '↓',
+    }
+}
+
+impl Map {
+    /// Runs Dijkstra backwards from the goal, so that every block knows its cheapest next step.
+    ///
+    /// One-way blocks and portals are respected, stepping onto a portal moves the agent to its partner.
+    /// Agents don't carry keys, so doors are never passed. Weave crossings are only passed straight.
+    pub fn flow_field(&self, goal: Block) -> anyhow::Result<FlowField> {
+        if goal.x >= self.width || self.get_block(goal.x, goal.y).is_none() {
+            return Err(anyhow!("Please specify coordinates within the map"));
+        }
+        let index = |(x, y): (usize, usize)| y * self.width + x;
+
+        // Every step that ends on a block, with the block it starts from and its direction
+        let mut predecessors: HashMap<(usize, usize), Vec<(Block, Direction)>> = HashMap::new();
+        for from in self.walkable_blocks().filter(|block| block.x < self.width) {
+            for neighbor in self.get_adjacent(from.x, from.y) {
+                if !from.allows_step_to(neighbor) || neighbor.door().is_some() {
+                    continue;
+                }
+                let to = self
+                    .portal_partner(neighbor.x, neighbor.y)
+                    .unwrap_or(neighbor);
+                if let Some(direction) = Direction::between(*from, neighbor) {
+                    predecessors
+                        .entry((to.x, to.y))
+                        .or_default()
+                        .push((*from, direction));
+                }
+            }
+        }
+
+        let mut cells = vec![None; self.width * self.height];
+        let mut queue = BinaryHeap::from([Reverse((0, (goal.x, goal.y), None))]);
+        while let Some(Reverse((cost, position, direction))) = queue.pop() {
+            if cells[index(position)].is_some() {
+                continue;
+            }
+            cells[index(position)] = Some((direction, cost));
+            let block = self[position];
+            for (from, step) in predecessors.get(&position).into_iter().flatten() {
+                // Agents can't turn on a crossing, so they have to leave it on the axis they entered
+                let straight = block.crossing().is_none()
+                    || direction.is_none_or(|next| next == *step || next == step.opposite());
+                if straight && cells[index((from.x, from.y))].is_none() {
+                    let step_cost = block.speed() as u32;
+                    queue.push(Reverse((cost + step_cost, (from.x, from.y), Some(*step))));
+                }
+            }
+        }
+
+        Ok(FlowField {
+            goal: (goal.x, goal.y),
+            width: self.width,
+            cells,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star;
+
+    #[test]
+    fn following_the_field_costs_as_much_as_a_star() {
+        let map = Map::from_rows(&["....#", ".##.#", ".b...", "..o#."]);
+        let goal = map.get_block(4, 3).unwrap();
+
+        let field = map.flow_field(goal).unwrap();
+
+        for start in map.walkable_blocks() {
+            let solution = a_star(&map, *start, goal).unwrap();
+            assert_eq!(field.cost(start.x, start.y), Some(solution.cost()));
+        }
+        let (mut x, mut y) = (0, 0);
+        while let Some(direction) = field.direction(x, y) {
+            (x, y) = match direction {
+                Direction::Left => (x - 1, y),
+                Direction::Up => (x, y - 1),
+                Direction::Right => (x + 1, y),
+                Direction::Down => (x, y + 1),
+            };
+        }
+        assert_eq!((x, y), field.goal());
+    }
+
+    #[test]
+    fn blocks_that_cannot_reach_the_goal_have_no_direction() {
+        let map = Map::from_rows(&["..#.", ">.#."]);
+
+        let field = map.flow_field(map.get_block(0, 1).unwrap()).unwrap();
+
+        // The one-way block can't be entered from the right, so the agent has to go around
+        assert_eq!(field.direction(1, 1), Some(Direction::Up));
+        assert_eq!(field.direction(3, 0), None);
+        assert_eq!(field.cost(3, 0), None);
+        assert_eq!(field.to_text(&map), "↓←#.\n@↑#.\n");
+    }
+}
@@ -0,0 +1,80 @@
+use super::Map;
+
+/// A step the agent can take, see [Map::to_edge_list]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    /// The cost of entering `to`
+    pub cost: u32,
+}
+
+impl Map {
+    /// Every step the agent can take. One-way blocks and portals are respected.
+    /// Doors and weave crossings depend on the path that led there, so all of their steps are part of the list.
+    pub fn to_edge_list(&self) -> Vec<Edge> {
+        self.walkable_blocks()
+            .filter(|block| block.x < self.width)
+            .flat_map(|from| {
+                self.get_reachable(from.x, from.y)
+                    .into_iter()
+                    .map(move |to| Edge {
+                        from: (from.x, from.y),
+                        to: (to.x, to.y),
+                        cost: to.speed() as u32,
+                    })
+            })
+            .collect()
+    }
+
+    /// The steps of [to_edge_list](Self::to_edge_list) as directed petgraph graph,
+    /// with one node per walkable block that is weighted with its coordinates.
+    /// `into_edge_type` turns it into an undirected graph.
+    #[cfg(feature = "petgraph")]
+    pub fn to_graph(&self) -> petgraph::graph::DiGraph<(usize, usize), u32> {
+        let mut graph = petgraph::graph::DiGraph::new();
+        let nodes: std::collections::HashMap<_, _> = self
+            .walkable_blocks()
+            .filter(|block| block.x < self.width)
+            .map(|block| ((block.x, block.y), graph.add_node((block.x, block.y))))
+            .collect();
+        for edge in self.to_edge_list() {
+            graph.add_edge(nodes[&edge.from], nodes[&edge.to], edge.cost);
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edges_follow_one_way_blocks_and_portals() {
+        let map = Map::from_rows(&["P.>.", "##.P"]);
+
+        let edges = map
+            .to_edge_list()
+            .into_iter()
+            .map(|edge| (edge.from, edge.to))
+            .collect::<Vec<_>>();
+
+        assert!(edges.contains(&((1, 0), (2, 0))));
+        assert!(!edges.contains(&((3, 0), (2, 0))));
+        // Stepping onto a portal leads to its partner
+        assert!(edges.contains(&((2, 1), (0, 0))));
+        assert!(edges.contains(&((1, 0), (3, 1))));
+        assert!(!edges.contains(&((2, 1), (3, 1))));
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn petgraph_has_a_node_per_walkable_block() {
+        let map = Map::from_rows(&["..#", ".b."]);
+
+        let graph = map.to_graph();
+
+        assert_eq!(graph.node_count(), 5);
+        assert_eq!(graph.edge_count(), map.to_edge_list().len());
+    }
+}
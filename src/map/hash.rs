@@ -0,0 +1,83 @@
+use super::{BlockType, Map};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64 bit FNV-1a, which unlike the hashers of the standard library gives the same hash on every platform and release
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+impl Map {
+    /// A hash of the layout that stays the same across runs and platforms, e.g. to find duplicate mazes
+    /// or to pin the maze a seed generates in tests.
+    ///
+    /// Only the structure counts: all terrain is hashed alike regardless of its color and cost, and so is a drawn path.
+    /// Walls, keys, doors, portals, one-way blocks and crossings keep their type. Labels and metadata are ignored.
+    pub fn canonical_hash(&self) -> u64 {
+        let mut hash = FNV_OFFSET_BASIS;
+        for size in [self.width, self.height] {
+            hash = fnv1a(hash, &(size as u64).to_le_bytes());
+        }
+        for block in self.iter_blocks() {
+            let block_type = match block.block_type {
+                BlockType::Green
+                | BlockType::Blue
+                | BlockType::Orange
+                | BlockType::Yellow
+                | BlockType::Solution => BlockType::Green,
+                block_type => block_type,
+            };
+            hash = fnv1a(hash, &[block_type.to_byte()]);
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate, GenOptions, MazeAlgorithm};
+
+    #[test]
+    fn terrain_colors_do_not_change_the_hash() {
+        let map = Map::from_rows(&["..#", "ob#", "y.."]);
+
+        assert_eq!(
+            map.canonical_hash(),
+            Map::from_rows(&["..#", "..#", "..."]).canonical_hash()
+        );
+        assert_ne!(
+            map.canonical_hash(),
+            Map::from_rows(&["..#", "..#", "#.."]).canonical_hash()
+        );
+        assert_ne!(
+            map.canonical_hash(),
+            Map::from_rows(&["..#", "1.#", "..."]).canonical_hash()
+        );
+        // The same blocks in another shape
+        assert_ne!(
+            Map::from_rows(&["....", "##.."]).canonical_hash(),
+            Map::from_rows(&["..", "..", "##", ".."]).canonical_hash()
+        );
+    }
+
+    #[test]
+    fn seeds_generate_the_pinned_mazes() {
+        let maze = |algorithm| {
+            let options = GenOptions {
+                seed: Some(1),
+                ..Default::default()
+            };
+            Map::from(generate(8, 8, algorithm, &options).unwrap()).canonical_hash()
+        };
+
+        assert_eq!(
+            maze(MazeAlgorithm::RecursiveBacktracker),
+            0x9994_ce43_8ad8_e6d5
+        );
+        assert_eq!(maze(MazeAlgorithm::HuntAndKill), 0x5fc3_c7a2_2ad9_444d);
+    }
+}
@@ -0,0 +1,314 @@
+use std::ops::Range;
+
+use anyhow::anyhow;
+use image::{DynamicImage, Rgba, RgbaImage};
+use itertools::Itertools;
+
+use super::{Block, BlockType, Map, Palette};
+
+/// How the colors of an image are matched to block types
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportOptions {
+    /// How far (as euclidean distance in RGB) a pixel may be off the nearest block color, e.g. due to jpeg compression.
+    /// Zero only accepts exact colors. The one-way directions differ by a single step of blue,
+    /// so they are only told apart in losslessly compressed images.
+    pub tolerance: f64,
+    /// The colors of the block types in the image, e.g. the palette the image was rendered with
+    pub palette: Palette,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 48.0,
+            palette: Palette::default(),
+        }
+    }
+}
+
+impl ImportOptions {
+    /// The block type whose color is nearest to the pixel, if it is within the tolerance
+    fn block_type(&self, pixel: &Rgba<u8>) -> Option<BlockType> {
+        BlockType::all()
+            .map(|block_type| (block_type, self.distance(pixel, block_type)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .filter(|(_, distance)| *distance <= self.tolerance)
+            .map(|(block_type, _)| block_type)
+    }
+
+    fn is_border(&self, pixel: &Rgba<u8>) -> bool {
+        self.distance(pixel, BlockType::Border) <= self.tolerance
+    }
+
+    fn distance(&self, pixel: &Rgba<u8>, block_type: BlockType) -> f64 {
+        let color = self.palette.color(block_type);
+        color
+            .into_iter()
+            .zip(pixel.0)
+            .take(3)
+            .map(|(a, b)| (a as f64 - b as f64).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+impl Map {
+    /// Reads a map from an image, tolerating other block sizes and border widths than [to_image](Self::to_image) uses.
+    ///
+    /// The grid is found from the lines that are mostly border colored. Images without borders are split
+    /// into equally sized blocks, where the block size is detected from where the rows and columns change their colors.
+    /// Every block takes the color of its center pixel, so blurred or anti-aliased edges don't matter.
+    pub fn from_image(img: &DynamicImage) -> anyhow::Result<Map> {
+        Map::from_image_with(img, &ImportOptions::default())
+    }
+
+    /// Like [from_image](Self::from_image), but matches the colors as configured.
+    pub fn from_image_with(img: &DynamicImage, options: &ImportOptions) -> anyhow::Result<Map> {
+        let img = img.to_rgba8();
+        if img.width() == 0 || img.height() == 0 {
+            return Err(anyhow!("The image is empty"));
+        }
+
+        let rows = block_spans(
+            img.height() as usize,
+            |y| {
+                border_share(
+                    (0..img.width()).map(|x| img.get_pixel(x, y as u32)),
+                    options,
+                )
+            },
+            |y| {
+                (0..img.width())
+                    .all(|x| img.get_pixel(x, y as u32) == img.get_pixel(x, y as u32 - 1))
+            },
+        );
+        let columns = block_spans(
+            img.width() as usize,
+            |x| {
+                border_share(
+                    (0..img.height()).map(|y| img.get_pixel(x as u32, y)),
+                    options,
+                )
+            },
+            |x| {
+                (0..img.height())
+                    .all(|y| img.get_pixel(x as u32, y) == img.get_pixel(x as u32 - 1, y))
+            },
+        );
+        if rows.is_empty() || columns.is_empty() {
+            return Err(anyhow!("The image only consists of borders"));
+        }
+
+        let blocks = rows
+            .iter()
+            .enumerate()
+            .map(|(y, row)| {
+                columns
+                    .iter()
+                    .enumerate()
+                    .map(|(x, column)| {
+                        Ok(Block::new(x, y, block_type_at(&img, column, row, options)?))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Map::new(blocks))
+    }
+
+    /// Reads an ordinary black and white maze image, e.g. a scan or screenshot, without the grid of borders
+    /// [to_image](Self::to_image) draws. Every `block_size` x `block_size` pixels become one block,
+    /// which is a wall if most of its pixels are dark and green terrain otherwise.
+    pub fn from_monochrome_image(img: &DynamicImage, block_size: usize) -> anyhow::Result<Map> {
+        if block_size == 0 {
+            return Err(anyhow!("The block size must be at least one pixel"));
+        }
+        let luma = img.to_luma8();
+        let (width, height) = (luma.width() as usize, luma.height() as usize);
+        if width == 0 || height == 0 {
+            return Err(anyhow!("The image is empty"));
+        }
+
+        let blocks = (0..height.div_ceil(block_size))
+            .map(|y| {
+                (0..width.div_ceil(block_size))
+                    .map(|x| {
+                        let pixels = (y * block_size..((y + 1) * block_size).min(height))
+                            .cartesian_product(x * block_size..((x + 1) * block_size).min(width))
+                            .map(|(py, px)| luma.get_pixel(px as u32, py as u32).0[0])
+                            .collect_vec();
+                        let dark = pixels.iter().filter(|value| **value < 128).count();
+                        let block_type = if dark * 2 > pixels.len() {
+                            BlockType::Black
+                        } else {
+                            BlockType::Green
+                        };
+                        Block::new(x, y, block_type)
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+        Ok(Map::new(blocks))
+    }
+}
+
+fn border_share<'a>(
+    line: impl ExactSizeIterator<Item = &'a Rgba<u8>>,
+    options: &ImportOptions,
+) -> f64 {
+    let length = line.len();
+    line.filter(|pixel| options.is_border(pixel)).count() as f64 / length as f64
+}
+
+/// The pixel ranges of the blocks along one axis of the image with `length` pixel lines.
+/// Lines that are mostly border colored separate the blocks. Without such lines the blocks all have the same
+/// size, which is the greatest common divisor of the lengths of the runs of identical lines.
+fn block_spans(
+    length: usize,
+    border_share: impl Fn(usize) -> f64,
+    same_as_previous: impl Fn(usize) -> bool,
+) -> Vec<Range<usize>> {
+    let is_border = (0..length).map(|i| border_share(i) > 0.5).collect_vec();
+    if is_border.contains(&true) {
+        let mut spans = vec![];
+        let mut start = None;
+        for (i, border) in is_border.into_iter().chain([true]).enumerate() {
+            match (border, start) {
+                (false, None) => start = Some(i),
+                (true, Some(first)) => {
+                    spans.push(first..i);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        return spans;
+    }
+
+    let changes = (1..length).filter(|i| !same_as_previous(*i));
+    let run_lengths = [0]
+        .into_iter()
+        .chain(changes)
+        .chain([length])
+        .tuple_windows()
+        .map(|(start, end)| end - start);
+    let pitch = run_lengths.fold(0, gcd);
+    (0..length / pitch)
+        .map(|i| i * pitch..(i + 1) * pitch)
+        .collect_vec()
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn block_type_at(
+    img: &RgbaImage,
+    column: &Range<usize>,
+    row: &Range<usize>,
+    options: &ImportOptions,
+) -> anyhow::Result<BlockType> {
+    let (x, y) = ((column.start + column.end) / 2, (row.start + row.end) / 2);
+    let pixel = img.get_pixel(x as u32, y as u32);
+    match options.block_type(pixel) {
+        Some(BlockType::Border) | None => Err(anyhow!(
+            "The color {:?} of the block at pixel {x} {y} doesn't belong to any block type",
+            pixel.0
+        )),
+        Some(block_type) => Ok(block_type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RenderOptions;
+
+    #[test]
+    fn other_block_and_border_sizes_are_detected() {
+        let map = Map::from_rows(&["#.o", "b>1", "P.#"]);
+        let options = RenderOptions {
+            block_width: 7,
+            border_width: 1,
+            ..RenderOptions::default()
+        };
+        let image = DynamicImage::from(map.to_image_with(&options).unwrap());
+
+        let imported = Map::from_image(&image).unwrap();
+
+        assert_eq!(imported.to_string(), map.to_string());
+    }
+
+    #[test]
+    fn images_without_borders_are_split_into_equal_blocks() {
+        let map = Map::from_rows(&["##.", "..#", ".##"]);
+        let options = RenderOptions {
+            block_width: 4,
+            border_width: 0,
+            ..RenderOptions::default()
+        };
+        let image = DynamicImage::from(map.to_image_with(&options).unwrap());
+
+        let imported = Map::from_image(&image).unwrap();
+
+        assert_eq!(imported.to_string(), map.to_string());
+    }
+
+    #[test]
+    fn slightly_off_colors_match_the_nearest_block_type() {
+        let map = Map::from_rows(&["#.o", "b.y"]);
+        let mut image = map.to_image_with(&RenderOptions::default()).unwrap();
+        for pixel in image.pixels_mut() {
+            pixel.0[0] = pixel.0[0].saturating_add(9);
+            pixel.0[1] = pixel.0[1].saturating_sub(7);
+        }
+
+        let imported = Map::from_image(&DynamicImage::from(image)).unwrap();
+
+        assert_eq!(imported.to_string(), map.to_string());
+    }
+
+    #[test]
+    fn custom_palettes_are_matched() {
+        let map = Map::from_rows(&["#.", ".#"]);
+        let palette: Palette = "border=#000080,black=#202020".parse().unwrap();
+        let render_options = RenderOptions {
+            palette: palette.clone(),
+            ..RenderOptions::default()
+        };
+        let image = DynamicImage::from(map.to_image_with(&render_options).unwrap());
+        let import_options = ImportOptions {
+            palette,
+            ..ImportOptions::default()
+        };
+
+        let imported = Map::from_image_with(&image, &import_options).unwrap();
+
+        assert_eq!(imported.to_string(), map.to_string());
+    }
+
+    #[test]
+    fn monochrome_images_are_split_by_majority() {
+        let mut image = image::GrayImage::from_pixel(9, 6, image::Luma([255]));
+        // The top left block is mostly dark, the one next to it only has a single dark pixel
+        for (x, y) in [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (0, 2), (4, 1)] {
+            image.put_pixel(x, y, image::Luma([0]));
+        }
+
+        let map = Map::from_monochrome_image(&DynamicImage::from(image), 3).unwrap();
+
+        assert_eq!(map.to_string(), Map::from_rows(&["#..", "..."]).to_string());
+    }
+
+    #[test]
+    fn unknown_colors_are_reported() {
+        let image = DynamicImage::from(RgbaImage::from_pixel(4, 4, Rgba([90, 30, 160, 255])));
+
+        let error = Map::from_image(&image).unwrap_err();
+
+        assert!(error.to_string().contains("[90, 30, 160, 255]"));
+    }
+}
@@ -0,0 +1,229 @@
+use std::fmt::Write as _;
+
+#[cfg(feature = "image")]
+use image::RgbaImage;
+use itertools::Itertools;
+
+use super::{BlockType, Map, Palette, IMAGE_BLOCK_WIDTH};
+#[cfg(feature = "image")]
+use crate::layers::blend;
+
+/// How the isometric projection of a map is drawn, see [Map::to_isometric_svg]
+#[derive(Debug, Clone, PartialEq)]
+pub struct IsometricOptions {
+    /// The width of the diamond of a block in pixels, it is half as high
+    pub tile_width: u32,
+    /// How far walls rise above the floor in pixels, 0 for a flat map
+    pub wall_height: u32,
+    pub palette: Palette,
+}
+
+impl Default for IsometricOptions {
+    fn default() -> Self {
+        Self {
+            tile_width: 2 * IMAGE_BLOCK_WIDTH as u32,
+            wall_height: IMAGE_BLOCK_WIDTH as u32,
+            palette: Palette::default(),
+        }
+    }
+}
+
+/// A convex polygon in pixels with its color
+type Polygon = (Vec<(f64, f64)>, [u8; 4]);
+
+/// How much the sides of walls are darkened, the side facing left is lit more than the one facing right
+const LEFT_SHADE: f64 = 0.75;
+const RIGHT_SHADE: f64 = 0.55;
+
+impl IsometricOptions {
+    /// The pixel position of a corner of the grid, raised by `height` pixels
+    fn project(&self, map: &Map, (x, y): (usize, usize), height: u32) -> (f64, f64) {
+        let half_width = self.tile_width as f64 / 2.0;
+        let half_height = self.tile_width as f64 / 4.0;
+        (
+            (x as f64 - y as f64 + map.height as f64) * half_width,
+            (x + y) as f64 * half_height + (self.wall_height - height) as f64,
+        )
+    }
+}
+
+impl Map {
+    /// The width and height of the isometric projection in pixels
+    pub fn isometric_size(&self, options: &IsometricOptions) -> (u32, u32) {
+        let diagonal = (self.width + self.height) as u32;
+        (
+            diagonal * options.tile_width / 2,
+            diagonal * options.tile_width / 4 + options.wall_height,
+        )
+    }
+
+    /// Draws the map from above at an angle: every block is a diamond and walls are raised
+    /// by [wall_height](IsometricOptions::wall_height) with shaded sides. Solution maps show their path.
+    pub fn to_isometric_svg(&self, options: &IsometricOptions) -> String {
+        let (width, height) = self.isometric_size(options);
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        );
+        svg.push('\n');
+        for (corners, [r, g, b, a]) in self.isometric_polygons(options) {
+            let points = corners
+                .iter()
+                .map(|(x, y)| format!("{x:.2},{y:.2}"))
+                .join(" ");
+            let opacity = if a == 255 {
+                String::new()
+            } else {
+                format!(r#" fill-opacity="{:.3}""#, a as f64 / 255.0)
+            };
+            let _ = writeln!(
+                svg,
+                r#"<polygon points="{points}" fill="rgb({r},{g},{b})"{opacity}/>"#
+            );
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Like [to_isometric_svg](Self::to_isometric_svg), as image with a transparent background
+    #[cfg(feature = "image")]
+    pub fn to_isometric_image(&self, options: &IsometricOptions) -> Option<RgbaImage> {
+        let (width, height) = self.isometric_size(options);
+        if (width as u64) * (height as u64) * 4 > isize::MAX as u64 {
+            return None;
+        }
+        let mut image = RgbaImage::new(width, height);
+        for (corners, color) in self.isometric_polygons(options) {
+            let (min_x, max_x) = corners.iter().map(|(x, _)| *x).minmax().into_option()?;
+            let (min_y, max_y) = corners.iter().map(|(_, y)| *y).minmax().into_option()?;
+            let pixels = |min: f64, max: f64, size: u32| {
+                (min.floor().max(0.0) as u32)..(max.ceil().min(size as f64) as u32)
+            };
+            for py in pixels(min_y, max_y, height) {
+                for px in pixels(min_x, max_x, width) {
+                    if contains(&corners, (px as f64 + 0.5, py as f64 + 0.5)) {
+                        let pixel = image.get_pixel_mut(px, py);
+                        pixel.0 = blend(color, pixel.0);
+                    }
+                }
+            }
+        }
+        Some(image)
+    }
+
+    /// The faces of all blocks from back to front, so that nearer faces cover those behind them
+    fn isometric_polygons(&self, options: &IsometricOptions) -> Vec<Polygon> {
+        let mut polygons = vec![];
+        let blocks = self
+            .iter_blocks()
+            .sorted_by_key(|block| (block.x + block.y, block.x));
+        for block in blocks {
+            let color = options.palette.color(block.block_type);
+            if color[3] == 0 {
+                continue;
+            }
+            let (x, y) = (block.x, block.y);
+            let height = if block.block_type == BlockType::Black {
+                options.wall_height
+            } else {
+                0
+            };
+            let corners = |height| {
+                [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)]
+                    .map(|corner| options.project(self, corner, height))
+            };
+            let [top, right, bottom, left] = corners(height);
+            if height > 0 {
+                let [_, ground_right, ground_bottom, ground_left] = corners(0);
+                polygons.push((
+                    vec![left, bottom, ground_bottom, ground_left],
+                    shade(color, LEFT_SHADE),
+                ));
+                polygons.push((
+                    vec![bottom, right, ground_right, ground_bottom],
+                    shade(color, RIGHT_SHADE),
+                ));
+            }
+            polygons.push((vec![top, right, bottom, left], color));
+        }
+        polygons
+    }
+}
+
+/// Darkens the color, black walls are lightened first so that their sides can be told apart
+fn shade([r, g, b, a]: [u8; 4], factor: f64) -> [u8; 4] {
+    let lift = if [r, g, b] == [0, 0, 0] { 90.0 } else { 0.0 };
+    let channel = |c: u8| ((c as f64 + lift) * factor).round() as u8;
+    [channel(r), channel(g), channel(b), a]
+}
+
+/// Whether the point lies within the convex polygon, including its edges
+#[cfg(feature = "image")]
+fn contains(corners: &[(f64, f64)], (x, y): (f64, f64)) -> bool {
+    let sides = corners
+        .iter()
+        .zip(corners.iter().cycle().skip(1))
+        .map(|((x1, y1), (x2, y2))| (x2 - x1) * (y - y1) - (y2 - y1) * (x - x1));
+    let (mut positive, mut negative) = (false, false);
+    for side in sides {
+        positive |= side > 0.0;
+        negative |= side < 0.0;
+    }
+    !(positive && negative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> IsometricOptions {
+        IsometricOptions {
+            tile_width: 8,
+            wall_height: 4,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn walls_rise_above_the_floor() {
+        let map = Map::from_rows(&[".#", ".."]);
+
+        let svg = map.to_isometric_svg(&options());
+
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="12""#)
+        );
+        // Three floor diamonds and the top and two sides of the wall
+        assert_eq!(svg.matches("<polygon").count(), 6);
+        // The top of the wall at (1, 0) is raised by the wall height
+        assert!(svg.contains(
+            r#"<polygon points="12.00,2.00 16.00,4.00 12.00,6.00 8.00,4.00" fill="rgb(0,0,0)"/>"#
+        ));
+        assert_eq!(
+            map.to_isometric_svg(&IsometricOptions {
+                wall_height: 0,
+                ..options()
+            })
+            .matches("<polygon")
+            .count(),
+            4
+        );
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn the_image_draws_the_diamonds() {
+        let map = Map::from_rows(&[".#", ".."]);
+        let options = options();
+
+        let image = map.to_isometric_image(&options).unwrap();
+
+        assert_eq!(image.dimensions(), map.isometric_size(&options));
+        let green = options.palette.color(BlockType::Green);
+        // The left half of the floor at (0, 0), the right half is behind the side of the wall
+        assert_eq!(image.get_pixel(6, 6).0, green);
+        assert_eq!(image.get_pixel(8, 6).0, shade([0, 0, 0, 255], LEFT_SHADE));
+        assert_eq!(image.get_pixel(0, 0).0, [0; 4]);
+        assert_eq!(image.get_pixel(12, 4).0, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(13, 8).0, shade([0, 0, 0, 255], RIGHT_SHADE));
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::anyhow;
+use itertools::Itertools;
+use serde_json::{json, Value};
+
+use super::{Map, TextTheme};
+
+impl Map {
+    /// The map as `{"width": .., "height": .., "rows": [..], "labels": {"name": [x, y]}, "metadata": {"key": "value"}}`
+    /// with one string of [ASCII](TextTheme::Ascii) blocks per row, the format of the web API and the JavaScript bindings
+    pub fn to_json_value(&self) -> Value {
+        json!({
+            "width": self.width(),
+            "height": self.height(),
+            "rows": self
+                .to_text_themed(TextTheme::Ascii)
+                .lines()
+                .take(self.height())
+                .collect_vec(),
+            "labels": self
+                .labels()
+                .map(|(name, block)| (name.to_string(), json!([block.x, block.y])))
+                .collect::<serde_json::Map<_, _>>(),
+            "metadata": self
+                .metadata()
+                .entries()
+                .into_iter()
+                .map(|(key, value)| (key, Value::String(value)))
+                .collect::<serde_json::Map<_, _>>(),
+        })
+    }
+
+    /// Reads a map written by [to_json_value](Self::to_json_value). Only the rows are required,
+    /// the width and height are taken from them.
+    pub fn from_json_value(value: &Value) -> anyhow::Result<Map> {
+        let rows = value["rows"]
+            .as_array()
+            .and_then(|rows| rows.iter().map(Value::as_str).collect::<Option<Vec<_>>>())
+            .ok_or(anyhow!("The map must have a \"rows\" array of strings"))?;
+        let mut map = Map::from_text(&rows.join("\n"))?;
+        map.set_json_labels(&value["labels"])?;
+        map.set_json_metadata(&value["metadata"])?;
+        Ok(map)
+    }
+
+    /// Sets the metadata of a `{"key": "value"}` object
+    fn set_json_metadata(&mut self, metadata: &Value) -> anyhow::Result<()> {
+        let Some(metadata) = metadata.as_object() else {
+            return Ok(());
+        };
+        for (key, value) in metadata {
+            let value = value
+                .as_str()
+                .ok_or(anyhow!("The metadata \"{key}\" must be a string"))?;
+            self.metadata_mut().set(key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Names the blocks of a `{"name": [x, y]}` object
+    fn set_json_labels(&mut self, labels: &Value) -> anyhow::Result<()> {
+        let Some(labels) = labels.as_object() else {
+            return Ok(());
+        };
+        for (name, coordinates) in labels {
+            let block = coordinates
+                .as_array()
+                .and_then(|coordinates| match coordinates.as_slice() {
+                    [x, y] => self.get_block(x.as_u64()? as usize, y.as_u64()? as usize),
+                    _ => None,
+                })
+                .ok_or(anyhow!(
+                    "The label \"{name}\" must be [x, y] within the map"
+                ))?;
+            self.set_label(name, block)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_round_trip_through_json() {
+        let mut map = Map::from_rows(&["..#", "#.o"]);
+        map.set_label("start", map.get_block(0, 0).unwrap())
+            .unwrap();
+        map.metadata_mut().set("author", "someone").unwrap();
+
+        let value = map.to_json_value();
+
+        assert_eq!(value["width"], 3);
+        assert_eq!(value["labels"]["start"], json!([0, 0]));
+        let read = Map::from_json_value(&value).unwrap();
+        assert_eq!(read.to_json_value(), value);
+        assert!(Map::from_json_value(&json!({"rows": 3})).is_err());
+        assert!(Map::from_json_value(&json!({"rows": [".."], "labels": {"a": [5, 0]}})).is_err());
+    }
+}
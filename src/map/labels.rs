@@ -0,0 +1,222 @@
+use anyhow::anyhow;
+
+#[cfg(feature = "image")]
+use super::RenderOptions;
+use super::{Block, Map};
+
+/// Starts the lines after the rows of a text map that name a block, e.g. `@entrance 3 4`
+pub(super) const LABEL_PREFIX: char = '@';
+
+/// The glyphs of a 3x5 pixel font for digits and letters, row by row from the top with the highest bit on the left
+#[cfg(feature = "image")]
+const GLYPHS: [(char, [u8; 5]); 36] = [
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b011, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+];
+
+/// The colors of labels on light and on dark blocks
+#[cfg(feature = "image")]
+const LABEL_DARK: [u8; 4] = [0, 0, 0, 255];
+#[cfg(feature = "image")]
+const LABEL_LIGHT: [u8; 4] = [255, 255, 255, 255];
+
+/// The glyph of the first letter or digit of a label, drawn on its block in images
+#[cfg(feature = "image")]
+pub(super) fn glyph(name: &str) -> Option<[u8; 5]> {
+    let first = name.chars().next()?.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .find(|(c, _)| *c == first)
+        .map(|(_, glyph)| *glyph)
+}
+
+/// The color of a label on a block of the color, dark on light blocks and light on dark ones
+#[cfg(feature = "image")]
+pub(super) fn label_color([r, g, b, _]: [u8; 4]) -> [u8; 4] {
+    let luminance = 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64;
+    if luminance < 128.0 {
+        LABEL_LIGHT
+    } else {
+        LABEL_DARK
+    }
+}
+
+/// Draws the glyphs of labels into one pixel row through a row of blocks, `y` is the pixel row within the blocks.
+/// Every label is the index of its block in the row, its glyph and color. Glyphs are scaled by a tenth of the
+/// block width and sit in the top left corner, so that the center of the block keeps its color
+/// and images can still be read back. Blocks under 10 pixels have no room for labels.
+#[cfg(feature = "image")]
+pub(super) fn draw_labels(
+    pixels: &mut [u8],
+    labels: &[(usize, [u8; 5], [u8; 4])],
+    y: usize,
+    options: &RenderOptions,
+) {
+    let scale = options.block_width / 10;
+    let Some(bits_row) = (y / scale.max(1))
+        .checked_sub(1)
+        .filter(|row| scale > 0 && *row < 5)
+    else {
+        return;
+    };
+    let stride = options.block_width + options.border_width;
+    for (x, glyph, color) in labels {
+        for column in (0..3).filter(|column| glyph[bits_row] & (0b100 >> column) != 0) {
+            let left = x * stride + scale * (column + 1);
+            for pixel in pixels[4 * left..4 * (left + scale)].chunks_exact_mut(4) {
+                pixel.copy_from_slice(color);
+            }
+        }
+    }
+}
+
+impl Map {
+    /// Names the block, e.g. `entrance`, so that it can be found again with [label](Self::label).
+    /// A block can have several names, an existing name is moved to the block.
+    /// Names must not be empty or contain whitespace.
+    pub fn set_label(&mut self, name: &str, block: Block) -> anyhow::Result<()> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(anyhow!(
+                "The label '{name}' must not be empty or contain whitespace"
+            ));
+        }
+        if block.x >= self.width || block.y >= self.height {
+            return Err(anyhow!("Please specify coordinates within the map"));
+        }
+        self.labels.insert(name.to_string(), (block.x, block.y));
+        Ok(())
+    }
+
+    /// The block with the name, `None` if there is no such label
+    pub fn label(&self, name: &str) -> Option<Block> {
+        let (x, y) = *self.labels.get(name)?;
+        self.get_block(x, y)
+    }
+
+    /// Removes the name, returns the block it was given to
+    pub fn remove_label(&mut self, name: &str) -> Option<Block> {
+        let (x, y) = self.labels.remove(name)?;
+        self.get_block(x, y)
+    }
+
+    /// All names and their blocks, sorted by name
+    pub fn labels(&self) -> impl Iterator<Item = (&str, Block)> {
+        self.labels
+            .iter()
+            .filter_map(|(name, (x, y))| Some((name.as_str(), self.get_block(*x, *y)?)))
+    }
+
+    /// The lines that follow the rows of a text map, one `@name x y` per label
+    pub(super) fn label_lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.labels
+            .iter()
+            .map(|(name, (x, y))| format!("{LABEL_PREFIX}{name} {x} {y}\n"))
+    }
+
+    /// Reads a line written by [label_lines](Self::label_lines)
+    pub(super) fn parse_label_line(&mut self, line: &str) -> anyhow::Result<()> {
+        let invalid = || anyhow!("Invalid label '{line}', expected {LABEL_PREFIX}name x y");
+        let (name, x, y) = match line
+            .strip_prefix(LABEL_PREFIX)
+            .ok_or_else(invalid)?
+            .split_whitespace()
+            .collect::<Vec<_>>()[..]
+        {
+            [name, x, y] => (name, x.parse().ok(), y.parse().ok()),
+            _ => return Err(invalid()),
+        };
+        let block = x
+            .zip(y)
+            .and_then(|(x, y)| self.get_block(x, y))
+            .ok_or_else(|| anyhow!("The label '{name}' is outside of the map"))?;
+        self.set_label(name, block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextTheme;
+
+    #[test]
+    fn labels_survive_text_and_binary() {
+        let mut map = Map::from_rows(&["..#", "..."]);
+        map.set_label("entrance", map.get_block(0, 0).unwrap())
+            .unwrap();
+        map.set_label("treasury", map.get_block(2, 1).unwrap())
+            .unwrap();
+
+        let text = map.to_text_themed(TextTheme::Ascii);
+        assert_eq!(text, "..#\n...\n@entrance 0 0\n@treasury 2 1\n");
+        for read in [
+            Map::from_text(&text).unwrap(),
+            Map::from_bytes(&map.to_bytes()).unwrap(),
+        ] {
+            assert!(read.labels().eq(map.labels()));
+            assert_eq!(read.label("treasury"), map.get_block(2, 1));
+        }
+
+        assert!(map
+            .set_label("two words", map.get_block(0, 0).unwrap())
+            .is_err());
+        assert!(Map::from_text("..\n@far 5 0\n").is_err());
+        assert_eq!(map.remove_label("entrance"), map.get_block(0, 0));
+        assert_eq!(map.label("entrance"), None);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn labels_are_drawn_as_letters() {
+        let mut map = Map::from_rows(&[".."]);
+        map.set_label("exit", map.get_block(1, 0).unwrap()).unwrap();
+        let options = crate::RenderOptions {
+            block_width: 10,
+            border_width: 0,
+            ..Default::default()
+        };
+
+        let image = map.to_image_with(&options).unwrap();
+
+        let green = options.palette.color(crate::BlockType::Green);
+        // The E in the top left corner of the block, which keeps its color in the center
+        assert_eq!(image.get_pixel(11, 1).0, LABEL_DARK);
+        assert_eq!(image.get_pixel(12, 2).0, green);
+        assert_eq!(image.get_pixel(11, 5).0, LABEL_DARK);
+        assert_eq!(image.get_pixel(15, 5).0, green);
+        assert_eq!(image.get_pixel(1, 1).0, green);
+    }
+}
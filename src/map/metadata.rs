@@ -0,0 +1,227 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use anyhow::anyhow;
+
+use super::Map;
+
+/// Starts the lines after the rows of a text map that hold metadata, e.g. `%title The first maze`
+pub(super) const METADATA_PREFIX: char = '%';
+
+const TITLE: &str = "title";
+const AUTHOR: &str = "author";
+const SEED: &str = "seed";
+const ALGORITHM: &str = "algorithm";
+const CREATED: &str = "created";
+
+/// Where a map comes from, kept by all formats that can hold it: text, binary, run-length encoded, JSON
+/// and png files, where every entry is a text chunk.
+///
+/// Entries are [set](Self::set) by key, the well known keys `title`, `author`, `seed`, `algorithm` and `created`
+/// fill the fields, all other keys are custom.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// The seed the map was generated with
+    pub seed: Option<u64>,
+    /// The name of the algorithm that generated the map, e.g. `hunt-and-kill`
+    pub algorithm: Option<String>,
+    /// When the map was created, in seconds since the Unix epoch
+    pub created: Option<u64>,
+    custom: BTreeMap<String, String>,
+}
+
+impl Metadata {
+    /// The provenance of a generated maze
+    pub(crate) fn generated(algorithm: impl Display, seed: Option<u64>) -> Self {
+        Metadata {
+            seed,
+            algorithm: Some(algorithm.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the entry, parsing the values of `seed` and `created` as numbers.
+    /// Keys must be 1 to 79 printable ASCII characters without spaces, so that they fit into png text chunks.
+    /// Line breaks in values become spaces.
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        if key.is_empty() || key.len() > 79 || !key.chars().all(|c| c.is_ascii_graphic()) {
+            return Err(anyhow!(
+                "The metadata key '{key}' must be 1 to 79 printable ASCII characters without spaces"
+            ));
+        }
+        let value = value.replace(['\r', '\n'], " ");
+        let number = || {
+            value
+                .parse()
+                .map_err(|_| anyhow!("The metadata '{key}' must be a non-negative integer"))
+        };
+        match key {
+            TITLE => self.title = Some(value),
+            AUTHOR => self.author = Some(value),
+            SEED => self.seed = Some(number()?),
+            ALGORITHM => self.algorithm = Some(value),
+            CREATED => self.created = Some(number()?),
+            _ => {
+                self.custom.insert(key.to_string(), value);
+            }
+        }
+        Ok(())
+    }
+
+    /// The value of a custom entry
+    pub fn custom(&self, key: &str) -> Option<&str> {
+        self.custom.get(key).map(String::as_str)
+    }
+
+    /// All entries as text, the well known ones first and then the custom ones sorted by key
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let known = [
+            (TITLE, self.title.clone()),
+            (AUTHOR, self.author.clone()),
+            (SEED, self.seed.map(|seed| seed.to_string())),
+            (ALGORITHM, self.algorithm.clone()),
+            (CREATED, self.created.map(|created| created.to_string())),
+        ];
+        known
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value?)))
+            .chain(self.custom.clone())
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    /// Reads the text chunks of a png file, ignoring those that aren't valid metadata
+    #[cfg(feature = "image")]
+    pub fn from_png(bytes: &[u8]) -> anyhow::Result<Metadata> {
+        let reader = png::Decoder::new(bytes).read_info()?;
+        let info = reader.info();
+        let latin1 = info
+            .uncompressed_latin1_text
+            .iter()
+            .map(|chunk| (chunk.keyword.clone(), Some(chunk.text.clone())));
+        let utf8 = info
+            .utf8_text
+            .iter()
+            .map(|chunk| (chunk.keyword.clone(), chunk.get_text().ok()));
+        let mut metadata = Metadata::default();
+        for (key, value) in latin1.chain(utf8) {
+            if let Some(value) = value {
+                let _ = metadata.set(&key, &value);
+            }
+        }
+        Ok(metadata)
+    }
+
+    /// Adds the entries as text chunks, `tEXt` for Latin-1 values and `iTXt` for all others
+    #[cfg(feature = "image")]
+    pub(super) fn add_png_chunks<W: std::io::Write>(
+        &self,
+        encoder: &mut png::Encoder<W>,
+    ) -> anyhow::Result<()> {
+        for (key, value) in self.entries() {
+            if value.chars().all(|c| (c as u32) < 0x100) {
+                encoder.add_text_chunk(key, value)?;
+            } else {
+                encoder.add_itxt_chunk(key, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Map {
+    /// Where the map comes from, see [Metadata]
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    /// The lines that follow the rows of a text map, one `%key value` per entry
+    pub(super) fn metadata_lines(&self) -> impl Iterator<Item = String> {
+        self.metadata
+            .entries()
+            .into_iter()
+            .map(|(key, value)| format!("{METADATA_PREFIX}{key} {value}\n"))
+    }
+
+    /// Reads a line written by [metadata_lines](Self::metadata_lines)
+    pub(super) fn parse_metadata_line(&mut self, line: &str) -> anyhow::Result<()> {
+        let entry = line.strip_prefix(METADATA_PREFIX).ok_or(anyhow!(
+            "Invalid metadata '{line}', expected {METADATA_PREFIX}key value"
+        ))?;
+        let (key, value) = entry.split_once(' ').unwrap_or((entry, ""));
+        self.metadata.set(key, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextTheme;
+
+    fn metadata() -> Metadata {
+        let mut metadata = Metadata {
+            title: Some("The first maze".to_string()),
+            seed: Some(42),
+            ..Default::default()
+        };
+        metadata.set("level", "3").unwrap();
+        metadata.set("created", "1700000000").unwrap();
+        metadata
+    }
+
+    #[test]
+    fn metadata_survives_every_format() {
+        let mut map = Map::from_rows(&["..#", "..."]);
+        *map.metadata_mut() = metadata();
+
+        let text = map.to_text_themed(TextTheme::Ascii);
+        assert_eq!(
+            text,
+            "..#\n...\n%title The first maze\n%seed 42\n%created 1700000000\n%level 3\n"
+        );
+        let mut rle = vec![];
+        map.write_rle(&mut rle).unwrap();
+        for read in [
+            Map::from_text(&text).unwrap(),
+            Map::from_bytes(&map.to_bytes()).unwrap(),
+            Map::read_rle(rle.as_slice()).unwrap(),
+        ] {
+            assert_eq!(read.metadata(), &metadata());
+            assert_eq!(read.metadata().custom("level"), Some("3"));
+        }
+    }
+
+    #[test]
+    fn invalid_entries_are_rejected() {
+        let mut metadata = Metadata::default();
+
+        assert!(metadata.set("two words", "").is_err());
+        assert!(metadata.set("seed", "many").is_err());
+        assert!(metadata.set("", "").is_err());
+        assert!(metadata.is_empty());
+        metadata.set("note", "one\ntwo").unwrap();
+        assert_eq!(metadata.custom("note"), Some("one two"));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn png_files_carry_the_metadata_in_text_chunks() {
+        let mut map = Map::from_rows(&["..#", "..."]);
+        *map.metadata_mut() = metadata();
+        // Only Latin-1 fits into tEXt chunks
+        map.metadata_mut().author = Some("Łukasz".to_string());
+
+        let mut png = vec![];
+        map.write_png(&mut png).unwrap();
+
+        assert_eq!(&Metadata::from_png(&png).unwrap(), map.metadata());
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::anyhow;
+
+use super::{Block, BlockType, Map};
+
+impl Map {
+    /// Reads a map in the `.map` format of the MovingAI pathfinding benchmarks.
+    ///
+    /// Passable ground (`.`, `G`) and swamp (`S`) become green terrain, everything else
+    /// (out of bounds `@` `O`, trees `T` and water `W`) becomes walls, as water is only passable from water.
+    pub fn from_movingai(text: &str) -> anyhow::Result<Map> {
+        let mut lines = text.lines().map(str::trim_end);
+        let mut width = None;
+        let mut height = None;
+        loop {
+            let line = lines
+                .next()
+                .ok_or(anyhow!("The MovingAI map has no `map` line"))?;
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["type", _] => {}
+                ["height", value] => height = Some(value.parse::<usize>()?),
+                ["width", value] => width = Some(value.parse::<usize>()?),
+                ["map"] => break,
+                _ => return Err(anyhow!("Unexpected line '{line}' in the MovingAI header")),
+            }
+        }
+        let (width, height) = width
+            .zip(height)
+            .filter(|(width, height)| *width > 0 && *height > 0)
+            .ok_or(anyhow!(
+                "The MovingAI map needs a positive width and height"
+            ))?;
+
+        let blocks = (0..height)
+            .map(|y| {
+                let line = lines
+                    .next()
+                    .ok_or(anyhow!("The map ends after {y} of {height} rows"))?;
+                if line.chars().count() != width {
+                    return Err(anyhow!("Row {y} is not {width} blocks wide"));
+                }
+                line.chars()
+                    .enumerate()
+                    .map(|(x, c)| {
+                        let block_type = match c {
+                            '.' | 'G' | 'S' => BlockType::Green,
+                            '@' | 'O' | 'T' | 'W' => BlockType::Black,
+                            _ => return Err(anyhow!("Unknown terrain '{c}' in row {y}")),
+                        };
+                        Ok(Block::new(x, y, block_type))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Map::new(blocks))
+    }
+
+    /// Writes the map in the MovingAI `.map` format, walkable blocks as `.` and walls as `@`.
+    /// The format has no terrain costs, keys or the like, so they are lost.
+    pub fn to_movingai(&self) -> String {
+        let mut text = format!(
+            "type octile\nheight {}\nwidth {}\nmap\n",
+            self.height, self.width
+        );
+        for row in &self.blocks {
+            text.extend(
+                row.iter()
+                    .map(|block| if block.is_walkable() { '.' } else { '@' }),
+            );
+            text.push('\n');
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn movingai_maps_survive_a_round_trip() {
+        let text = "type octile\nheight 3\nwidth 4\nmap\n@@@@\n@.GT\n@S.W\n";
+
+        let map = Map::from_movingai(text).unwrap();
+
+        assert_eq!(
+            map.to_string(),
+            Map::from_rows(&["####", "#..#", "#..#"]).to_string()
+        );
+        assert_eq!(
+            map.to_movingai(),
+            "type octile\nheight 3\nwidth 4\nmap\n@@@@\n@..@\n@..@\n"
+        );
+    }
+
+    #[test]
+    fn malformed_movingai_maps_are_rejected() {
+        for text in [
+            "type octile\nheight 2\nwidth 2\nmap\n..\n",
+            "type octile\nheight 1\nwidth 2\nmap\n...\n",
+            "type octile\nheight 1\nwidth 2\nmap\n.x\n",
+            "type octile\nheight 1\nmap\n..\n",
+            "..\n",
+        ] {
+            assert!(Map::from_movingai(text).is_err(), "{text}");
+        }
+    }
+}
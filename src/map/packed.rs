@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use crate::MapProvider;
+
+use super::{Block, BlockType, Map};
+
+/// A [Map] that only stores the [BlockType] of every block, in a single row by row vector.
+/// The coordinates follow from the position, so a block takes two bytes instead of a whole [Block],
+/// and reading one needs no second indirection. It is read through [MapProvider],
+/// e.g. by [SolveAlgorithm::solve_provider](crate::SolveAlgorithm::solve_provider).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedMap {
+    width: usize,
+    height: usize,
+    block_types: Vec<BlockType>,
+    /// Every portal that has a partner with that partner, looked up once instead of on every step
+    portals: HashMap<(usize, usize), Block>,
+}
+
+impl From<&Map> for PackedMap {
+    fn from(map: &Map) -> Self {
+        Self {
+            width: map.width,
+            height: map.height,
+            block_types: map.iter_blocks().map(|block| block.block_type).collect(),
+            portals: map
+                .portal_pairs()
+                .into_iter()
+                .map(|(portal, partner)| ((portal.x, portal.y), partner))
+                .collect(),
+        }
+    }
+}
+
+impl From<&PackedMap> for Map {
+    fn from(packed: &PackedMap) -> Self {
+        Map::new(
+            packed
+                .block_types
+                .chunks(packed.width)
+                .enumerate()
+                .map(|(y, row)| {
+                    row.iter()
+                        .enumerate()
+                        .map(|(x, block_type)| Block::new(x, y, *block_type))
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+}
+
+impl MapProvider for PackedMap {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn block_type(&self, x: usize, y: usize) -> Option<BlockType> {
+        if x >= self.width {
+            return None;
+        }
+        self.block_types.get(y * self.width + x).copied()
+    }
+
+    fn portal_partner(&self, x: usize, y: usize) -> Option<Block> {
+        self.portals.get(&(x, y)).copied()
+    }
+
+    fn portal_pairs(&self) -> Vec<(Block, Block)> {
+        self.portals
+            .iter()
+            .filter_map(|(&(x, y), partner)| Some((self.get_block(x, y)?, *partner)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{a_star, SearchOptions, SolveAlgorithm};
+
+    #[test]
+    fn packed_maps_behave_like_the_map() {
+        let map = Map::from_rows(&["P.#..", "..>.P", "1A..#"]);
+
+        let packed = PackedMap::from(&map);
+
+        for block in map.iter_blocks() {
+            assert_eq!(packed.get_block(block.x, block.y), Some(*block));
+            assert_eq!(
+                packed.get_reachable(block.x, block.y),
+                map.get_reachable(block.x, block.y)
+            );
+        }
+        assert_eq!(packed.get_block(5, 0), None);
+        assert_eq!(Map::from(&packed).to_string(), map.to_string());
+        let start = map.get_block(0, 2).unwrap();
+        let goal = map.get_block(3, 0).unwrap();
+        let solution = SolveAlgorithm::AStar
+            .solve_provider(&packed, start, goal, &SearchOptions::default())
+            .unwrap();
+        assert_eq!(solution.cost(), a_star(&map, start, goal).unwrap().cost());
+    }
+}
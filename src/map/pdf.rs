@@ -0,0 +1,296 @@
+use std::{collections::HashSet, fmt::Write as _, str::FromStr};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+
+use super::{Block, BlockType, Map, Palette};
+use crate::Solution;
+
+/// Points (1/72 inch, the unit of PDF) per millimeter
+const POINTS_PER_MM: f64 = 72.0 / 25.4;
+
+/// The size of a PDF page in millimeters.
+/// Parses from `a4`, `a5`, `letter` or `<width>x<height>` in millimeters.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PageSize {
+    #[default]
+    A4,
+    A5,
+    Letter,
+    Custom {
+        width: f64,
+        height: f64,
+    },
+}
+
+impl PageSize {
+    /// The width and height in millimeters
+    pub fn dimensions(&self) -> (f64, f64) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::A5 => (148.0, 210.0),
+            PageSize::Letter => (215.9, 279.4),
+            PageSize::Custom { width, height } => (*width, *height),
+        }
+    }
+}
+
+impl FromStr for PageSize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "a4" => Ok(PageSize::A4),
+            "a5" => Ok(PageSize::A5),
+            "letter" => Ok(PageSize::Letter),
+            _ => {
+                let size = s.split_once('x').and_then(|(width, height)| {
+                    Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+                });
+                match size {
+                    Some((width, height)) if width > 0.0 && height > 0.0 => {
+                        Ok(PageSize::Custom { width, height })
+                    }
+                    _ => Err(anyhow!(
+                        "Unknown page size '{s}', expected a4, a5, letter or <width>x<height> in millimeters"
+                    )),
+                }
+            }
+        }
+    }
+}
+
+impl Map {
+    /// A PDF with the map as vector graphics on a single page, as large as fits within the margin (in millimeters)
+    /// and centered. Blocks take the colors of the palette, the borders between blocks are left out.
+    pub fn to_pdf(
+        &self,
+        page_size: PageSize,
+        margin: f64,
+        palette: &Palette,
+    ) -> anyhow::Result<Vec<u8>> {
+        let page = self.pdf_page(page_size, margin, palette, &[])?;
+        Ok(write_pdf(page_size, &[page]))
+    }
+
+    /// Like [to_pdf](Self::to_pdf), with a second page that shows the path of the solution, e.g. as answer key
+    pub fn to_pdf_with_solution(
+        &self,
+        page_size: PageSize,
+        margin: f64,
+        palette: &Palette,
+        solution: &Solution,
+    ) -> anyhow::Result<Vec<u8>> {
+        let pages = [
+            self.pdf_page(page_size, margin, palette, &[])?,
+            self.pdf_page(page_size, margin, palette, solution.path())?,
+        ];
+        Ok(write_pdf(page_size, &pages))
+    }
+
+    /// The content stream that fills a rectangle for every run of blocks with the same color in a row
+    fn pdf_page(
+        &self,
+        page_size: PageSize,
+        margin: f64,
+        palette: &Palette,
+        path: &[Block],
+    ) -> anyhow::Result<String> {
+        let (page_width, page_height) = page_size.dimensions();
+        let (page_width, page_height) = (page_width * POINTS_PER_MM, page_height * POINTS_PER_MM);
+        let margin = margin * POINTS_PER_MM;
+        let block = ((page_width - 2.0 * margin) / self.width as f64)
+            .min((page_height - 2.0 * margin) / self.height as f64);
+        if block <= 0.0 || margin < 0.0 {
+            return Err(anyhow!("The margin leaves no space for the map"));
+        }
+        let left = (page_width - block * self.width as f64) / 2.0;
+        let top = (page_height + block * self.height as f64) / 2.0;
+
+        let path = path
+            .iter()
+            .map(|step| (step.x, step.y))
+            .collect::<HashSet<_>>();
+        let mut content = String::new();
+        for (y, row) in self.blocks.iter().enumerate() {
+            let colors = row.iter().map(|block| {
+                if path.contains(&(block.x, block.y)) {
+                    palette.color(BlockType::Solution)
+                } else {
+                    palette.color(block.block_type)
+                }
+            });
+            let mut x = 0;
+            for (run, color) in colors.dedup_with_count() {
+                // Transparent blocks stay as blank as the paper
+                if color[3] > 0 {
+                    let [r, g, b, _] = color.map(|channel| channel as f64 / 255.0);
+                    let _ = writeln!(
+                        content,
+                        "{r:.3} {g:.3} {b:.3} rg {:.2} {:.2} {:.2} {block:.2} re f",
+                        left + x as f64 * block,
+                        top - (y + 1) as f64 * block,
+                        run as f64 * block,
+                    );
+                }
+                x += run;
+            }
+        }
+        Ok(content)
+    }
+}
+
+/// A PDF document with one page per content stream, all of the same size
+fn write_pdf(page_size: PageSize, pages: &[String]) -> Vec<u8> {
+    let (width, height) = page_size.dimensions();
+    let (width, height) = (width * POINTS_PER_MM, height * POINTS_PER_MM);
+    // The catalog and the page tree come first, then every page with its content
+    let page_ids = (0..pages.len()).map(|i| 3 + 2 * i).collect::<Vec<_>>();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{id} 0 R"))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        ),
+    ];
+    for (page, id) in pages.iter().zip(&page_ids) {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {width:.2} {height:.2}] /Contents {} 0 R >>",
+            id + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{page}endstream",
+            page.len()
+        ));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{object}\nendobj\n", i + 1).as_bytes());
+    }
+    let xref = pdf.len();
+    let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(trailer, "{offset:010} 00000 n ");
+    }
+    let _ = write!(
+        trailer,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        objects.len() + 1
+    );
+    pdf.extend_from_slice(trailer.as_bytes());
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star;
+
+    fn text(pdf: &[u8]) -> &str {
+        std::str::from_utf8(pdf).unwrap()
+    }
+
+    #[test]
+    fn the_cross_references_point_at_the_objects() {
+        let map = Map::from_rows(&["..#", "#.."]);
+
+        let pdf = map.to_pdf(PageSize::A4, 10.0, &Palette::default()).unwrap();
+        let pdf = text(&pdf);
+
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        let xref: usize = pdf.lines().rev().nth(1).unwrap().parse().unwrap();
+        assert!(pdf[xref..].starts_with("xref\n0 5\n"));
+        let offsets = pdf[xref..]
+            .lines()
+            .skip(3)
+            .take(4)
+            .map(|line| line[..10].parse::<usize>().unwrap());
+        for (i, offset) in offsets.enumerate() {
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+        assert!(pdf.contains("/Count 1"));
+        assert!(pdf.contains("/MediaBox [0 0 595.28 841.89]"));
+    }
+
+    #[test]
+    fn rows_are_drawn_as_runs_of_colors() {
+        let map = Map::from_rows(&["..#", "#.."]);
+
+        let pdf = map
+            .to_pdf(
+                PageSize::Custom {
+                    width: 30.0,
+                    height: 20.0,
+                },
+                0.0,
+                &Palette::default(),
+            )
+            .unwrap();
+
+        // Each block is 10mm wide and the map fills the whole page
+        let block = 10.0 * POINTS_PER_MM;
+        let fills: Vec<_> = text(&pdf)
+            .lines()
+            .filter(|line| line.ends_with("re f"))
+            .collect();
+        assert_eq!(fills.len(), 4);
+        assert_eq!(
+            fills[0],
+            format!(
+                "0.000 1.000 0.000 rg 0.00 {block:.2} {:.2} {block:.2} re f",
+                2.0 * block
+            )
+        );
+        assert!(map.to_pdf(PageSize::A5, 80.0, &Palette::default()).is_err());
+
+        // The palette colors the blocks
+        let palette: Palette = "default,green=#808080".parse().unwrap();
+        let pdf = map.to_pdf(PageSize::A5, 10.0, &palette).unwrap();
+        assert_eq!(text(&pdf).matches("0.502 0.502 0.502 rg").count(), 2);
+    }
+
+    #[test]
+    fn the_solution_is_on_the_second_page() {
+        let map = Map::from_rows(&["..#", "#.."]);
+        let solution = a_star(
+            &map,
+            map.get_block(0, 0).unwrap(),
+            map.get_block(2, 1).unwrap(),
+        )
+        .unwrap();
+
+        let pdf = map
+            .to_pdf_with_solution(PageSize::Letter, 20.0, &Palette::default(), &solution)
+            .unwrap();
+
+        let pdf = text(&pdf);
+        assert!(pdf.contains("/Count 2"));
+        let [r, g, b, _] = Palette::default()
+            .color(BlockType::Solution)
+            .map(|channel| channel as f64 / 255.0);
+        assert_eq!(pdf.matches(&format!("{r:.3} {g:.3} {b:.3} rg")).count(), 2);
+    }
+
+    #[test]
+    fn page_sizes_parse() {
+        assert_eq!("a5".parse::<PageSize>().unwrap(), PageSize::A5);
+        assert_eq!(
+            "100x50".parse::<PageSize>().unwrap(),
+            PageSize::Custom {
+                width: 100.0,
+                height: 50.0
+            }
+        );
+        assert!("0x50".parse::<PageSize>().is_err());
+        assert!("a3".parse::<PageSize>().is_err());
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+
+use crate::{
+    a_star,
+    search::{a_star_search, HeuristicWeight, Path, SearchSpace},
+    smooth::is_plain,
+    DistanceBound, MazeError, Solution, SolveReport, State,
+};
+
+use super::{Block, BlockType, Map};
+
+/// The cheapest corridor from one end to another, with the blocks between both ends in the order they are passed
+type Edges = HashMap<((usize, usize), (usize, usize)), (u32, Vec<(usize, usize)>)>;
+
+/// The ends every end of a corridor leads to, with the cost of getting there
+type Successors = HashMap<(usize, usize), Vec<((usize, usize), u32)>>;
+
+/// A corridor between two junctions of a [Map], see [Map::corridors]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Corridor {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    /// The blocks between both ends in the order they are passed from `from` to `to`
+    pub blocks: Vec<(usize, usize)>,
+    /// The cost of walking from `from` to `to`
+    pub cost: u32,
+    /// The cost of walking from `to` to `from`
+    pub reverse_cost: u32,
+}
+
+impl Map {
+    /// A copy of the map with every dead end filled with walls, repeatedly, until only blocks
+    /// that lie on a path between two others are left. In a perfect maze only the paths between the kept blocks remain,
+    /// so A* has far less to search while finding the same paths.
+    ///
+    /// Only plain terrain is filled, keys, doors, portals and the `keep` blocks (e.g. start and goal) are never removed.
+    pub fn pruned(&self, keep: &[Block]) -> Map {
+        let mut map = self.clone();
+        let keep: HashSet<(usize, usize)> = keep.iter().map(|block| (block.x, block.y)).collect();
+        let is_dead_end = |map: &Map, block: &Block| {
+            is_plain(block)
+                && !keep.contains(&(block.x, block.y))
+                && map.get_adjacent(block.x, block.y).len() <= 1
+        };
+
+        let mut queue: VecDeque<Block> = map
+            .walkable_blocks()
+            .filter(|block| is_dead_end(&map, block))
+            .copied()
+            .collect();
+        while let Some(block) = queue.pop_front() {
+            // Blocks can be queued several times, or stop being dead ends if they were queued too early
+            if !map[(block.x, block.y)].is_walkable() || !is_dead_end(&map, &block) {
+                continue;
+            }
+            map.blocks[block.y][block.x].block_type = BlockType::Black;
+            queue.extend(map.get_adjacent(block.x, block.y));
+        }
+        map
+    }
+
+    /// Collapses every corridor of plain terrain into a single edge between the blocks at its ends,
+    /// which are junctions, dead ends, special blocks or one of the `keep` blocks.
+    /// Searching the corridors instead of the blocks visits far fewer nodes.
+    /// Loops without any junction have no ends and are left out.
+    pub fn corridors(&self, keep: &[Block]) -> Vec<Corridor> {
+        let keep: HashSet<(usize, usize)> = keep.iter().map(|block| (block.x, block.y)).collect();
+        let is_inner = |block: &Block| {
+            is_plain(block)
+                && !keep.contains(&(block.x, block.y))
+                && self.get_adjacent(block.x, block.y).len() == 2
+        };
+
+        let mut corridors = vec![];
+        for end in self.walkable_blocks().filter(|block| !is_inner(block)) {
+            for first in self.get_adjacent(end.x, end.y) {
+                let mut previous = *end;
+                let mut current = first;
+                let mut blocks = vec![];
+                while is_inner(&current) {
+                    blocks.push(current);
+                    let next = self
+                        .get_adjacent(current.x, current.y)
+                        .into_iter()
+                        .find(|block| *block != previous)
+                        .expect("Corridor blocks have two neighbors");
+                    previous = current;
+                    current = next;
+                }
+                // Every corridor is found from both ends, only keep the one that starts at the smaller end
+                let last = blocks.last().copied().unwrap_or(*end);
+                if ((end.y, end.x), (first.y, first.x)) > ((current.y, current.x), (last.y, last.x))
+                {
+                    continue;
+                }
+                let inner_cost: u32 = blocks.iter().map(|block| block.speed() as u32).sum();
+                corridors.push(Corridor {
+                    from: (end.x, end.y),
+                    to: (current.x, current.y),
+                    blocks: blocks.iter().map(|block| (block.x, block.y)).collect(),
+                    cost: inner_cost + current.speed() as u32,
+                    reverse_cost: inner_cost + end.speed() as u32,
+                });
+            }
+        }
+        corridors
+    }
+
+    /// Solves with A* on the [corridors](Self::corridors) instead of the blocks: every corridor is a single
+    /// weighted edge between its ends, so only junctions, dead ends and both given blocks are expanded.
+    /// The path is as cheap as the one of [a_star](crate::a_star).
+    ///
+    /// Keys, doors, portals, one-way blocks and crossings change where a corridor leads,
+    /// so maps with anything but plain terrain are searched block by block with [a_star](crate::a_star).
+    pub fn solve_on_corridors(&self, start: Block, destination: Block) -> anyhow::Result<Solution> {
+        let (Some(start), Some(destination)) = (
+            self.get_block(start.x, start.y),
+            self.get_block(destination.x, destination.y),
+        ) else {
+            return Err(anyhow!("Please specify coordinates within the map"));
+        };
+        if self.walkable_blocks().any(|block| !is_plain(block)) {
+            return a_star(self, start, destination);
+        }
+
+        let mut edges = Edges::new();
+        for corridor in self.corridors(&[start, destination]) {
+            let reversed = corridor.blocks.iter().rev().copied().collect();
+            for (ends, cost, blocks) in [
+                ((corridor.from, corridor.to), corridor.cost, corridor.blocks),
+                (
+                    (corridor.to, corridor.from),
+                    corridor.reverse_cost,
+                    reversed,
+                ),
+            ] {
+                // Several corridors may connect the same ends, only the cheapest one is taken
+                if edges.get(&ends).is_none_or(|(known, _)| cost < *known) {
+                    edges.insert(ends, (cost, blocks));
+                }
+            }
+        }
+        let mut successors = Successors::new();
+        for ((from, to), (cost, _)) in &edges {
+            successors.entry(*from).or_default().push((*to, *cost));
+        }
+
+        let space = CorridorSpace {
+            map: self,
+            successors: &successors,
+            destination,
+            bound: DistanceBound::new(self, destination),
+        };
+        let route = a_star_search(&space, State::new(start)).ok_or(MazeError::NoPath)?;
+
+        // Walk every corridor the route takes block by block
+        let mut states = vec![State::new(start)];
+        for (from, to) in route.states.iter().tuple_windows() {
+            let ends = (
+                (from.location.x, from.location.y),
+                (to.location.x, to.location.y),
+            );
+            let (_, blocks) = &edges[&ends];
+            states.extend(blocks.iter().map(|&position| State::new(self[position])));
+            states.push(*to);
+        }
+        let path = Path {
+            states,
+            cost: route.cost,
+            expanded: route.expanded,
+        };
+        let report = SolveReport::new(&path, HeuristicWeight::Factor(1.0));
+        Ok(Solution::new(
+            path.states,
+            path.cost,
+            self.clone(),
+            report,
+            0,
+        ))
+    }
+}
+
+/// Moving from one end of a corridor to the other, see [Map::solve_on_corridors]
+struct CorridorSpace<'a> {
+    map: &'a Map,
+    successors: &'a Successors,
+    destination: Block,
+    bound: DistanceBound,
+}
+
+impl SearchSpace for CorridorSpace<'_> {
+    type State = State;
+
+    fn successors(&self, state: &State) -> Vec<(State, u32)> {
+        self.successors
+            .get(&(state.location.x, state.location.y))
+            .into_iter()
+            .flatten()
+            .map(|(position, cost)| (State::new(self.map[*position]), *cost))
+            .collect()
+    }
+
+    fn heuristic(&self, state: &State) -> u32 {
+        self.bound.estimate(state.location)
+    }
+
+    fn is_goal(&self, state: &State) -> bool {
+        state.location == self.destination
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{a_star, generate, GenOptions, MazeAlgorithm};
+
+    #[test]
+    fn pruning_a_perfect_maze_leaves_the_solution() {
+        let map =
+            Map::from(generate(9, 9, MazeAlgorithm::default(), &GenOptions::default()).unwrap());
+        let start = map.get_block(1, 1).unwrap();
+        let goal = map.get_block(17, 17).unwrap();
+
+        let pruned = map.pruned(&[start, goal]);
+        let solution = a_star(&pruned, start, goal).unwrap();
+
+        assert_eq!(solution.cost(), a_star(&map, start, goal).unwrap().cost());
+        assert_eq!(pruned.walkable_blocks().count(), solution.path().len());
+    }
+
+    #[test]
+    fn corridor_searches_expand_fewer_states_for_the_same_cost() {
+        let options = GenOptions {
+            loop_prob: Some(0.2),
+            seed: Some(4),
+            ..Default::default()
+        };
+        let map = Map::from(generate(12, 12, MazeAlgorithm::default(), &options).unwrap());
+        let start = map.get_block(1, 1).unwrap();
+
+        for goal in [(23, 23), (1, 23), (13, 7)] {
+            let goal = map.get_block(goal.0, goal.1).unwrap();
+            let on_corridors = map.solve_on_corridors(start, goal).unwrap();
+            let on_blocks = a_star(&map, start, goal).unwrap();
+
+            assert_eq!(on_corridors.cost(), on_blocks.cost());
+            assert_eq!(on_corridors.path().first(), Some(&start));
+            assert_eq!(on_corridors.path().last(), Some(&goal));
+            assert!(on_corridors
+                .path()
+                .iter()
+                .tuple_windows()
+                .all(|(a, b)| a.x.abs_diff(b.x) + a.y.abs_diff(b.y) == 1));
+            assert!(on_corridors.report().expanded < on_blocks.report().expanded);
+        }
+        let walled_in = map.get_block(0, 0).unwrap();
+        assert!(map.solve_on_corridors(start, walled_in).is_err());
+    }
+
+    #[test]
+    fn corridors_connect_junctions() {
+        let map = Map::from_rows(&["#.###", "#bbo.", "#.###"]);
+
+        let corridors = map.corridors(&[]);
+
+        assert_eq!(corridors.len(), 3);
+        let corridor = corridors
+            .iter()
+            .find(|corridor| corridor.to == (4, 1))
+            .unwrap();
+        assert_eq!(corridor.from, (1, 1));
+        assert_eq!(corridor.blocks, [(2, 1), (3, 1)]);
+        assert_eq!(corridor.cost, 2 + 5 + 1);
+        assert_eq!(corridor.reverse_cost, 2 + 5 + 2);
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::maze_generation::Axis;
+
+use super::{Block, Map};
+
+/// One bit per block of a grid, e.g. the blocks reachable from somewhere
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitGrid {
+    width: usize,
+    height: usize,
+    words: Vec<u64>,
+}
+
+impl BitGrid {
+    /// A grid with every bit cleared
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            words: vec![0; (width * height).div_ceil(64)],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height).then_some(y * self.width + x)
+    }
+
+    /// Whether the bit at `x`, `y` is set, `false` outside of the grid
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.index(x, y)
+            .is_some_and(|i| self.words[i / 64] & (1 << (i % 64)) != 0)
+    }
+
+    /// Sets the bit at `x`, `y` and returns whether it was cleared before.
+    /// Positions outside of the grid are ignored.
+    pub fn set(&mut self, x: usize, y: usize) -> bool {
+        let Some(i) = self.index(x, y) else {
+            return false;
+        };
+        let was_cleared = self.words[i / 64] & (1 << (i % 64)) == 0;
+        self.words[i / 64] |= 1 << (i % 64);
+        was_cleared
+    }
+
+    /// The number of set bits
+    pub fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// The positions of all set bits row by row
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.width * self.height)
+            .filter(|i| self.words[i / 64] & (1 << (i % 64)) != 0)
+            .map(|i| (i % self.width, i / self.width))
+    }
+}
+
+/// Where the agent is during the flood fill: its position, the keys it carries
+/// and the passage it moves along on a weave crossing
+type FillState = ((usize, usize), u8, Option<Axis>);
+
+impl Map {
+    /// Every block the agent can reach from `start`, including `start` itself.
+    /// Follows the same rules as [a_star](crate::a_star): one-way blocks, portals, doors that need keys
+    /// and crossings that can't be turned on.
+    pub fn reachable_from(&self, start: Block) -> BitGrid {
+        let mut reachable = BitGrid::new(self.width, self.height);
+        let Some(start) = self
+            .get_block(start.x, start.y)
+            .filter(|block| block.is_walkable())
+        else {
+            return reachable;
+        };
+        // Picking up a key or entering a crossing changes what can be reached next,
+        // so every combination of keys and passage is filled separately
+        let mut visited: HashMap<(u8, Option<Axis>), BitGrid> = HashMap::new();
+        let initial: FillState = ((start.x, start.y), with_key(0, start), None);
+        let mut queue = VecDeque::from([initial]);
+        while let Some(((x, y), keys, layer)) = queue.pop_front() {
+            let first_visit = visited
+                .entry((keys, layer))
+                .or_insert_with(|| BitGrid::new(self.width, self.height))
+                .set(x, y);
+            if !first_visit {
+                continue;
+            }
+            reachable.set(x, y);
+            let from = self[(x, y)];
+            for to in self.get_reachable(x, y) {
+                let axis = axis_between(from, to);
+                if layer.is_some_and(|layer| layer != axis)
+                    || to
+                        .door()
+                        .is_some_and(|color| keys & (1 << color as u8) == 0)
+                {
+                    continue;
+                }
+                queue.push_back((
+                    (to.x, to.y),
+                    with_key(keys, to),
+                    to.crossing().map(|_| axis),
+                ));
+            }
+        }
+        reachable
+    }
+
+    /// Whether the agent can get from `from` to `to`, without building a whole solution
+    pub fn is_reachable(&self, from: Block, to: Block) -> bool {
+        self.reachable_from(from).get(to.x, to.y)
+    }
+}
+
+fn with_key(keys: u8, block: Block) -> u8 {
+    match block.key() {
+        Some(color) => keys | 1 << color as u8,
+        None => keys,
+    }
+}
+
+fn axis_between(from: Block, to: Block) -> Axis {
+    if from.y == to.y {
+        Axis::Horizontal
+    } else {
+        Axis::Vertical
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star;
+
+    #[test]
+    fn reachability_agrees_with_a_star() {
+        let map = Map::from_rows(&["1.#..", "..A.>", "###.#", "B.>.."]);
+        let start = map.get_block(0, 0).unwrap();
+
+        let reachable = map.reachable_from(start);
+
+        for block in map.walkable_blocks() {
+            assert_eq!(
+                reachable.get(block.x, block.y),
+                a_star(&map, start, *block).is_ok(),
+                "{block:?}"
+            );
+        }
+        assert!(map.is_reachable(start, map.get_block(4, 1).unwrap()));
+        assert!(!map.is_reachable(map.get_block(4, 1).unwrap(), start));
+    }
+
+    #[test]
+    fn bit_grids_count_their_bits() {
+        let mut grid = BitGrid::new(9, 9);
+
+        assert!(grid.set(8, 8));
+        assert!(!grid.set(8, 8));
+        assert!(!grid.set(9, 0));
+        grid.set(0, 1);
+
+        assert!(grid.get(0, 1));
+        assert!(!grid.get(1, 0));
+        assert_eq!(grid.count(), 2);
+        assert_eq!(grid.iter().collect::<Vec<_>>(), [(0, 1), (8, 8)]);
+    }
+}
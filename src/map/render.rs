@@ -0,0 +1,481 @@
+#[cfg(feature = "image")]
+use std::io::{self, Write};
+use std::{collections::HashMap, str::FromStr};
+
+use anyhow::anyhow;
+#[cfg(feature = "image")]
+use image::RgbaImage;
+use itertools::Itertools;
+
+#[cfg(feature = "image")]
+use super::{
+    labels::{draw_labels, glyph, label_color},
+    Block, DownscalePolicy, Map,
+};
+use super::{BlockType, IMAGE_BLOCK_WIDTH, IMAGE_BORDER_WIDTH};
+
+/// How a [Map] is drawn as an image.
+/// Only images rendered with the default options can be read back with `Map::from`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    /// The width and height of a block in pixels
+    pub block_width: usize,
+    /// The width of the borders between blocks in pixels
+    pub border_width: usize,
+    pub palette: Palette,
+    /// The colors of gradients, e.g. from near to far in [Map::distance_image]
+    pub ramp: ColorRamp,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            block_width: IMAGE_BLOCK_WIDTH,
+            border_width: IMAGE_BORDER_WIDTH,
+            palette: Palette::default(),
+            ramp: ColorRamp::default(),
+        }
+    }
+}
+
+/// Colors that blend into each other, for drawing values between 0 and 1 such as in [Map::distance_image].
+///
+/// Parses from a comma separated list of at least two `#rrggbb[aa]` colors, from the color of 0 to the color of 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorRamp {
+    stops: Vec<[u8; 4]>,
+}
+
+impl ColorRamp {
+    /// Evenly spaced colors from 0 to 1
+    pub fn new(stops: Vec<[u8; 4]>) -> anyhow::Result<Self> {
+        if stops.len() < 2 {
+            return Err(anyhow!("A color ramp needs at least two colors"));
+        }
+        Ok(Self { stops })
+    }
+
+    /// The color of `t` between 0 and 1, blended from the two colors it lies between
+    pub fn at(&self, t: f64) -> [u8; 4] {
+        let position = t.clamp(0.0, 1.0) * (self.stops.len() - 1) as f64;
+        let i = (position as usize).min(self.stops.len() - 2);
+        let fraction = position - i as f64;
+        let (from, to) = (self.stops[i], self.stops[i + 1]);
+        std::array::from_fn(|channel| {
+            (from[channel] as f64 + (to[channel] as f64 - from[channel] as f64) * fraction).round()
+                as u8
+        })
+    }
+}
+
+impl Default for ColorRamp {
+    /// From dark purple over teal to yellow (viridis)
+    fn default() -> Self {
+        Self {
+            stops: vec![
+                [68, 1, 84, 255],
+                [59, 82, 139, 255],
+                [33, 145, 140, 255],
+                [94, 201, 98, 255],
+                [253, 231, 37, 255],
+            ],
+        }
+    }
+}
+
+impl FromStr for ColorRamp {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ColorRamp::new(
+            s.split(',')
+                .map(|color| parse_hex_color(color.trim()))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        )
+    }
+}
+
+/// The colors of the block types. Types without a color of their own keep their default color.
+///
+/// Parses from a comma separated list of `<block type>=#rrggbb[aa]`, e.g. `border=#000000,white=#ffffff`,
+/// which may start with the name of a predefined palette to change: `default`, `colorblind` or `grayscale`,
+/// e.g. `grayscale,border=#ffffff`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Palette {
+    colors: HashMap<BlockType, [u8; 4]>,
+}
+
+impl Palette {
+    /// Draws all blocks of the type in the RGBA color
+    pub fn with_color(mut self, block_type: BlockType, rgba: [u8; 4]) -> Self {
+        self.colors.insert(block_type, rgba);
+        self
+    }
+
+    pub fn color(&self, block_type: BlockType) -> [u8; 4] {
+        self.custom_color(block_type)
+            .unwrap_or_else(|| block_type.to_rgba())
+    }
+
+    /// The color of the block type if it differs from the default one
+    pub(crate) fn custom_color(&self, block_type: BlockType) -> Option<[u8; 4]> {
+        self.colors.get(&block_type).copied()
+    }
+
+    /// Terrain in the blue, reddish purple, orange and yellow of the Okabe-Ito palette,
+    /// which people with any kind of color blindness can tell apart
+    pub fn colorblind() -> Self {
+        Palette::default()
+            .with_color(BlockType::Green, [86, 180, 233, 255])
+            .with_color(BlockType::Blue, [204, 121, 167, 255])
+            .with_color(BlockType::Orange, [230, 159, 0, 255])
+            .with_color(BlockType::Yellow, [240, 228, 66, 255])
+            .with_color(BlockType::Border, [60, 60, 60, 255])
+            .with_color(BlockType::Solution, [213, 94, 0, 255])
+    }
+
+    /// Shades of gray for printing: the more expensive the terrain, the darker. Every other block type
+    /// takes the gray of its default color's brightness.
+    pub fn grayscale() -> Self {
+        let gray = |value: u8| [value, value, value, 255];
+        let palette = BlockType::all().fold(Palette::default(), |palette, block_type| {
+            let [r, g, b, a] = block_type.to_rgba();
+            let luma = (0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64).round() as u8;
+            palette.with_color(block_type, [luma, luma, luma, a])
+        });
+        palette
+            .with_color(BlockType::Black, gray(0))
+            .with_color(BlockType::Green, gray(235))
+            .with_color(BlockType::Blue, gray(190))
+            .with_color(BlockType::Orange, gray(150))
+            .with_color(BlockType::Yellow, gray(110))
+            .with_color(BlockType::Border, gray(170))
+            .with_color(BlockType::Solution, gray(60))
+    }
+}
+
+/// The names of the palettes that can start a parsed palette
+const PRESETS: [&str; 3] = ["default", "colorblind", "grayscale"];
+
+fn preset(name: &str) -> Option<Palette> {
+    match name {
+        "default" => Some(Palette::default()),
+        "colorblind" => Some(Palette::colorblind()),
+        "grayscale" => Some(Palette::grayscale()),
+        _ => None,
+    }
+}
+
+/// The block types that can be named in a palette
+const NAMED_BLOCK_TYPES: [(&str, BlockType); 12] = [
+    ("white", BlockType::White),
+    ("black", BlockType::Black),
+    ("orange", BlockType::Orange),
+    ("blue", BlockType::Blue),
+    ("green", BlockType::Green),
+    ("yellow", BlockType::Yellow),
+    ("border", BlockType::Border),
+    ("solution", BlockType::Solution),
+    ("stairs-up", BlockType::StairsUp),
+    ("stairs-down", BlockType::StairsDown),
+    ("bridge-horizontal", BlockType::BridgeHorizontal),
+    ("bridge-vertical", BlockType::BridgeVertical),
+];
+
+impl FromStr for Palette {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut entries = s
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .peekable();
+        let base = entries
+            .next_if(|entry| !entry.contains('='))
+            .map(|name| {
+                preset(name.trim()).ok_or(anyhow!(
+                    "Unknown palette '{name}', expected one of {}",
+                    PRESETS.join(", ")
+                ))
+            })
+            .transpose()?
+            .unwrap_or_default();
+        entries.try_fold(base, |palette, entry| {
+            let (name, color) = entry
+                .split_once('=')
+                .ok_or(anyhow!("'{entry}' is not of the form <block type>=#rrggbb"))?;
+            let block_type = NAMED_BLOCK_TYPES
+                .iter()
+                .find(|(known, _)| *known == name.trim())
+                .map(|(_, block_type)| *block_type)
+                .ok_or(anyhow!(
+                    "Unknown block type '{name}', expected one of {}",
+                    NAMED_BLOCK_TYPES.iter().map(|(known, _)| known).join(", ")
+                ))?;
+            Ok(palette.with_color(block_type, parse_hex_color(color.trim())?))
+        })
+    }
+}
+
+fn parse_hex_color(color: &str) -> anyhow::Result<[u8; 4]> {
+    let digits = color
+        .strip_prefix('#')
+        .filter(|digits| matches!(digits.len(), 6 | 8) && digits.is_ascii())
+        .ok_or(anyhow!(
+            "'{color}' is not a color of the form #rrggbb or #rrggbbaa"
+        ))?;
+    let mut rgba = [255; 4];
+    for (channel, i) in rgba.iter_mut().zip((0..digits.len()).step_by(2)) {
+        *channel = u8::from_str_radix(&digits[i..i + 2], 16)
+            .map_err(|_| anyhow!("'{color}' is not a valid hex color"))?;
+    }
+    Ok(rgba)
+}
+
+#[cfg(feature = "image")]
+impl Map {
+    pub fn to_image(self) -> Option<RgbaImage> {
+        self.to_image_with(&RenderOptions::default())
+    }
+
+    pub fn to_image_with(&self, options: &RenderOptions) -> Option<RgbaImage> {
+        self.to_image_colored(options, |block| options.palette.color(block.block_type))
+    }
+
+    /// A preview that fits within `max_dimension` pixels in both directions, without borders.
+    /// Maps larger than that merge blocks to the most common type of each square, see [DownscalePolicy::Majority],
+    /// so huge maps are never drawn at full size. `None` for a `max_dimension` of 0.
+    pub fn thumbnail(&self, max_dimension: u32) -> Option<RgbaImage> {
+        self.thumbnail_with(max_dimension, &Palette::default())
+    }
+
+    /// Like [thumbnail](Self::thumbnail), but in the colors of the palette
+    pub fn thumbnail_with(&self, max_dimension: u32, palette: &Palette) -> Option<RgbaImage> {
+        let longest = self.width.max(self.height);
+        let factor = longest.div_ceil(max_dimension.try_into().ok().filter(|max| *max > 0)?);
+        let options = |map: &Map| RenderOptions {
+            block_width: (max_dimension as usize / map.width.max(map.height)).max(1),
+            border_width: 0,
+            palette: palette.clone(),
+            ..Default::default()
+        };
+        if factor > 1 {
+            let pooled = self.downscale(factor, DownscalePolicy::Majority).ok()?;
+            pooled.to_image_with(&options(&pooled))
+        } else {
+            self.to_image_with(&options(self))
+        }
+    }
+
+    /// Draws the map like [to_image_with](Self::to_image_with), but with the RGBA color returned for each block.
+    pub(crate) fn to_image_colored(
+        &self,
+        options: &RenderOptions,
+        color: impl Fn(&Block) -> [u8; 4],
+    ) -> Option<RgbaImage> {
+        let (image_width, image_height) = self.image_size(options);
+        let mut buffer_vec = Vec::with_capacity(image_width as usize * image_height as usize * 4);
+        self.write_pixel_rows(options, &color, |row| {
+            buffer_vec.extend_from_slice(row);
+            Ok(())
+        })
+        .ok()?;
+
+        RgbaImage::from_vec(image_width, image_height, buffer_vec)
+    }
+
+    /// Encodes the same image as [to_image](Self::to_image) as png one pixel row at a time,
+    /// so that only a single row of pixels is in memory at once.
+    pub fn write_png(&self, writer: impl Write) -> anyhow::Result<()> {
+        self.write_png_with(writer, &RenderOptions::default())
+    }
+
+    pub fn write_png_with(
+        &self,
+        writer: impl Write,
+        options: &RenderOptions,
+    ) -> anyhow::Result<()> {
+        let (image_width, image_height) = self.image_size(options);
+        let mut encoder = png::Encoder::new(writer, image_width, image_height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        self.metadata.add_png_chunks(&mut encoder)?;
+        let mut png_writer = encoder.write_header()?;
+        let mut stream = png_writer.stream_writer()?;
+        let color = |block: &Block| options.palette.color(block.block_type);
+        self.write_pixel_rows(options, &color, |row| stream.write_all(row))?;
+        stream.finish()?;
+        Ok(())
+    }
+
+    pub(crate) fn image_size(&self, options: &RenderOptions) -> (u32, u32) {
+        let length = |blocks: usize| {
+            (blocks * options.block_width + blocks.saturating_sub(1) * options.border_width) as u32
+        };
+        (length(self.width), length(self.height))
+    }
+
+    /// Passes the RGBA pixels of the image row by row from top to bottom.
+    fn write_pixel_rows(
+        &self,
+        options: &RenderOptions,
+        color: &impl Fn(&Block) -> [u8; 4],
+        mut write_row: impl FnMut(&[u8]) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let (image_width, _) = self.image_size(options);
+        let border_row = options
+            .palette
+            .color(BlockType::Border)
+            .repeat(image_width as usize);
+        for (i, block_row) in self.blocks.iter().enumerate() {
+            if i > 0 {
+                for _ in 0..options.border_width {
+                    write_row(&border_row)?;
+                }
+            }
+            let pixel_row = block_row_pixels(block_row, options, color);
+            let labels = self
+                .labels
+                .iter()
+                .filter(|(_, (_, y))| *y == i)
+                .filter_map(|(name, (x, _))| {
+                    let block = block_row.get(*x)?;
+                    Some((*x, glyph(name)?, label_color(color(block))))
+                })
+                .collect_vec();
+            for y in 0..options.block_width {
+                if labels.is_empty() {
+                    write_row(&pixel_row)?;
+                } else {
+                    let mut labeled = pixel_row.clone();
+                    draw_labels(&mut labeled, &labels, y, options);
+                    write_row(&labeled)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A single row of RGBA pixels through a row of blocks
+#[cfg(feature = "image")]
+fn block_row_pixels(
+    block_row: &[Block],
+    options: &RenderOptions,
+    color: &impl Fn(&Block) -> [u8; 4],
+) -> Vec<u8> {
+    let border = options
+        .palette
+        .color(BlockType::Border)
+        .repeat(options.border_width);
+    let mut pixels = vec![];
+    for (i, block) in block_row.iter().enumerate() {
+        if i > 0 {
+            pixels.extend_from_slice(&border);
+        }
+        pixels.extend(color(block).repeat(options.block_width));
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn custom_sizes_and_colors_are_used() {
+        let map = Map::from_rows(&[".#", "o."]);
+        let options = RenderOptions {
+            block_width: 4,
+            border_width: 1,
+            palette: "border=#000000,black=#102030".parse().unwrap(),
+            ..Default::default()
+        };
+
+        let image = map.to_image_with(&options).unwrap();
+
+        assert_eq!(image.dimensions(), (9, 9));
+        assert_eq!(image.get_pixel(4, 0).0, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(5, 0).0, [16, 32, 48, 255]);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn thumbnails_fit_within_the_maximum() {
+        let map = Map::from_fn(300, 120, |x, _| {
+            if x % 3 == 0 {
+                BlockType::Black
+            } else {
+                BlockType::Blue
+            }
+        });
+
+        let thumbnail = map.thumbnail(100).unwrap();
+
+        // Every 3x3 square has two blue columns and one of walls
+        assert_eq!(thumbnail.dimensions(), (100, 40));
+        assert!(thumbnail
+            .pixels()
+            .all(|pixel| pixel.0 == Palette::default().color(BlockType::Blue)));
+        let small = Map::from_rows(&[".#", "o."]).thumbnail(9).unwrap();
+        assert_eq!(small.dimensions(), (8, 8));
+        assert!(map.thumbnail(0).is_none());
+    }
+
+    #[test]
+    fn color_ramps_blend_between_their_colors() {
+        let ramp: ColorRamp = "#000000, #ff8000,#ffffff80".parse().unwrap();
+
+        assert_eq!(ramp.at(0.0), [0, 0, 0, 255]);
+        assert_eq!(ramp.at(0.25), [128, 64, 0, 255]);
+        assert_eq!(ramp.at(0.5), [255, 128, 0, 255]);
+        assert_eq!(ramp.at(1.0), [255, 255, 255, 128]);
+        assert_eq!(ramp.at(2.0), ramp.at(1.0));
+        assert!("#000000".parse::<ColorRamp>().is_err());
+        assert!("#000000,black".parse::<ColorRamp>().is_err());
+    }
+
+    #[test]
+    fn invalid_palettes_are_rejected() {
+        assert!("lava=#ff0000".parse::<Palette>().is_err());
+        assert!("border=red".parse::<Palette>().is_err());
+        assert!("sepia".parse::<Palette>().is_err());
+        assert!("border=#000000,grayscale".parse::<Palette>().is_err());
+    }
+
+    #[test]
+    fn predefined_palettes_can_be_changed() {
+        let palette: Palette = "grayscale, border=#ffffff".parse().unwrap();
+
+        assert_eq!(palette.color(BlockType::Border), [255, 255, 255, 255]);
+        assert_eq!(
+            palette.color(BlockType::Green),
+            Palette::grayscale().color(BlockType::Green)
+        );
+        assert_eq!("default".parse::<Palette>().unwrap(), Palette::default());
+        assert_eq!(
+            "colorblind".parse::<Palette>().unwrap(),
+            Palette::colorblind()
+        );
+    }
+
+    #[test]
+    fn grayscale_terrain_gets_darker_with_its_cost() {
+        let palette = Palette::grayscale();
+        let terrain = [
+            BlockType::Green,
+            BlockType::Blue,
+            BlockType::Orange,
+            BlockType::Yellow,
+        ];
+
+        for block_type in BlockType::all() {
+            let [r, g, b, _] = palette.color(block_type);
+            assert!(r == g && g == b, "{block_type:?}");
+        }
+        assert!(terrain
+            .windows(2)
+            .all(|pair| palette.color(pair[0])[0] > palette.color(pair[1])[0]));
+    }
+}
@@ -0,0 +1,210 @@
+use std::io::{self, BufRead, BufWriter, Write};
+
+use anyhow::anyhow;
+#[cfg(feature = "gzip")]
+use flate2::{bufread::MultiGzDecoder, write::GzEncoder, Compression};
+use itertools::Itertools;
+
+use super::{
+    text::{ascii_char, from_ascii_char, is_annotation},
+    Block, Map,
+};
+
+/// The first word of the header line, followed by the version, the width and the height
+const HEADER: &str = "mazes-rle";
+const VERSION: u32 = 1;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+impl Map {
+    /// Writes the map as run-length encoded text, one line per row, which keeps huge maps with long
+    /// corridors small. The rows are written one after another, so the whole text is never in memory.
+    ///
+    /// The first line is `mazes-rle 1 <width> <height>`. Every row consists of the characters
+    /// of [TextTheme::Ascii](super::TextTheme::Ascii), each preceded by how often it repeats if that's more than once,
+    /// e.g. `#3.o#`. Keys are digits themselves, so they are escaped with a backslash: `2\1` are two purple keys.
+    /// Metadata and labels follow the rows like in [Map::from_text].
+    pub fn write_rle(&self, writer: impl Write) -> io::Result<()> {
+        let mut writer = BufWriter::new(writer);
+        writeln!(writer, "{HEADER} {VERSION} {} {}", self.width, self.height)?;
+        for row in &self.blocks {
+            let mut line = String::new();
+            for (block_type, run) in &row.iter().chunk_by(|block| block.block_type) {
+                let run = run.count();
+                if run > 1 {
+                    line += &run.to_string();
+                }
+                let c = ascii_char(block_type);
+                if c.is_ascii_digit() {
+                    line.push('\\');
+                }
+                line.push(c);
+            }
+            line.push('\n');
+            writer.write_all(line.as_bytes())?;
+        }
+        for line in self.annotation_lines() {
+            writer.write_all(line.as_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// Like [write_rle](Self::write_rle), but compresses the text with gzip
+    #[cfg(feature = "gzip")]
+    pub fn write_rle_gzip(&self, writer: impl Write) -> io::Result<()> {
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+        self.write_rle(&mut encoder)?;
+        encoder.finish()?.flush()
+    }
+
+    /// Reads a map written by [write_rle](Self::write_rle) line by line.
+    /// Gzip compressed text is decompressed on the fly with the `gzip` feature.
+    pub fn read_rle(mut reader: impl BufRead) -> anyhow::Result<Map> {
+        if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+            #[cfg(feature = "gzip")]
+            return read_rle_lines(io::BufReader::new(MultiGzDecoder::new(reader)));
+            #[cfg(not(feature = "gzip"))]
+            return Err(anyhow!(
+                "Reading gzip compressed maps needs the gzip feature"
+            ));
+        }
+        read_rle_lines(reader)
+    }
+}
+
+fn read_rle_lines(reader: impl BufRead) -> anyhow::Result<Map> {
+    let mut lines = reader.lines();
+    let header = lines
+        .next()
+        .transpose()?
+        .ok_or(anyhow!("The data is not a run-length encoded map"))?;
+    let (width, height) = match header.split_whitespace().collect_vec()[..] {
+        [HEADER, version, width, height] => {
+            if version != VERSION.to_string() {
+                return Err(anyhow!(
+                    "Unsupported run-length encoded map version {version}"
+                ));
+            }
+            let size = |value: &str| {
+                value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|size| *size > 0)
+                    .ok_or(anyhow!("'{header}' does not contain a valid size"))
+            };
+            (size(width)?, size(height)?)
+        }
+        _ => return Err(anyhow!("The data is not a run-length encoded map")),
+    };
+
+    let rows = (0..height)
+        .map(|y| {
+            let line = lines
+                .next()
+                .transpose()?
+                .ok_or(anyhow!("The map ends after {y} of {height} rows"))?;
+            decode_row(&line, y, width)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut map = Map::new(rows);
+    for line in lines {
+        let line = line?;
+        if is_annotation(&line) {
+            map.parse_annotation(&line)?;
+        } else if !line.is_empty() {
+            return Err(anyhow!("The map has more than {height} rows"));
+        }
+    }
+    Ok(map)
+}
+
+fn decode_row(line: &str, y: usize, width: usize) -> anyhow::Result<Vec<Block>> {
+    let mut row = Vec::with_capacity(width);
+    let mut count: Option<usize> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        let block_char = match c {
+            '0'..='9' => {
+                count = count
+                    .unwrap_or(0)
+                    .checked_mul(10)
+                    .and_then(|count| count.checked_add(c as usize - '0' as usize));
+                if count.is_none() {
+                    return Err(anyhow!("Row {y} repeats a block too often"));
+                }
+                continue;
+            }
+            '\\' => chars
+                .next()
+                .ok_or(anyhow!("Row {y} ends with a backslash"))?,
+            c => c,
+        };
+        let block_type = from_ascii_char(block_char)
+            .ok_or(anyhow!("Unknown block '{block_char}' in row {y}"))?;
+        let run = count.take().unwrap_or(1);
+        if run == 0 || row.len() + run > width {
+            return Err(anyhow!("Row {y} is not {width} blocks wide"));
+        }
+        let start = row.len();
+        row.extend((start..start + run).map(|x| Block::new(x, y, block_type)));
+    }
+    if count.is_some() {
+        return Err(anyhow!("Row {y} ends with a count"));
+    }
+    if row.len() != width {
+        return Err(anyhow!("Row {y} is not {width} blocks wide"));
+    }
+    Ok(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_are_counted_and_keys_escaped() {
+        let map = Map::from_rows(&["#####", "#..11", "#2o.<"]);
+        let mut text = vec![];
+
+        map.write_rle(&mut text).unwrap();
+
+        assert_eq!(
+            String::from_utf8(text.clone()).unwrap(),
+            "mazes-rle 1 5 3\n5#\n#2.2\\1\n#\\2o.<\n"
+        );
+        assert_eq!(
+            Map::read_rle(text.as_slice()).unwrap().to_string(),
+            map.to_string()
+        );
+    }
+
+    #[test]
+    fn malformed_text_is_rejected() {
+        for text in [
+            "mazes-rle 2 2 1\n2.\n",
+            "mazes-rle 1 2 2\n2.\n",
+            "mazes-rle 1 2 1\n3.\n",
+            "mazes-rle 1 2 1\n.2\n",
+            "mazes-rle 1 2 1\n.~\n",
+            "mazes-rle 1 2 1\n2.\n#\n",
+            "#.\n",
+        ] {
+            assert!(Map::read_rle(text.as_bytes()).is_err(), "{text}");
+        }
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip_compressed_maps_are_detected() {
+        let map = Map::from(crate::generate_maze(300, 300, None).unwrap());
+        let (mut plain, mut compressed) = (vec![], vec![]);
+
+        map.write_rle(&mut plain).unwrap();
+        map.write_rle_gzip(&mut compressed).unwrap();
+
+        assert!(compressed.len() < plain.len() / 2);
+        assert_eq!(
+            Map::read_rle(compressed.as_slice()).unwrap().to_string(),
+            map.to_string()
+        );
+    }
+}
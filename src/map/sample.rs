@@ -0,0 +1,103 @@
+use rand::{seq::IteratorRandom, Rng};
+
+use super::{Block, Map};
+
+/// How often a random block is drawn before falling back to choosing among all walkable blocks
+const SAMPLE_ATTEMPTS: usize = 64;
+
+impl Map {
+    /// A walkable block chosen uniformly at random, `None` if there is none.
+    /// Draws random positions first, which is fast on open maps, and only lists the walkable blocks
+    /// if that keeps hitting walls.
+    pub fn random_walkable(&self, rng: &mut impl Rng) -> Option<Block> {
+        for _ in 0..SAMPLE_ATTEMPTS {
+            let (x, y) = (rng.gen_range(0..self.width), rng.gen_range(0..self.height));
+            if let Some(block) = self.get_block(x, y).filter(Block::is_walkable) {
+                return Some(block);
+            }
+        }
+        self.walkable_blocks().choose(rng).copied()
+    }
+
+    /// A random start and goal that are connected and at least `min_distance` blocks apart
+    /// (in steps along the axes, ignoring walls), e.g. for benchmarks. `None` if there is no such pair.
+    ///
+    /// The start is chosen uniformly among the blocks that have a goal far enough away, then the goal among those.
+    pub fn random_pair_at_least(
+        &self,
+        rng: &mut impl Rng,
+        min_distance: usize,
+    ) -> Option<(Block, Block)> {
+        let components = self.components();
+        // The extremes of x + y and x - y of every region bound how far apart two of its blocks can be
+        let mut extremes = vec![[isize::MAX, isize::MIN, isize::MAX, isize::MIN]; components.len()];
+        for ((x, y), label) in components.iter_labels() {
+            let (sum, difference) = ((x + y) as isize, x as isize - y as isize);
+            let [min_sum, max_sum, min_difference, max_difference] = &mut extremes[label];
+            *min_sum = (*min_sum).min(sum);
+            *max_sum = (*max_sum).max(sum);
+            *min_difference = (*min_difference).min(difference);
+            *max_difference = (*max_difference).max(difference);
+        }
+        let farthest = |(x, y): (usize, usize), label: usize| {
+            let (sum, difference) = ((x + y) as isize, x as isize - y as isize);
+            let [min_sum, max_sum, min_difference, max_difference] = extremes[label];
+            [
+                sum - min_sum,
+                max_sum - sum,
+                difference - min_difference,
+                max_difference - difference,
+            ]
+            .into_iter()
+            .max()
+            .unwrap_or(0) as usize
+        };
+
+        let ((start_x, start_y), label) = components
+            .iter_labels()
+            .filter(|(position, label)| farthest(*position, *label) >= min_distance)
+            .choose(rng)?;
+        let ((goal_x, goal_y), _) = components
+            .iter_labels()
+            .filter(|((x, y), other)| {
+                *other == label && x.abs_diff(start_x) + y.abs_diff(start_y) >= min_distance
+            })
+            .choose(rng)?;
+        Some((
+            self.get_block(start_x, start_y)?,
+            self.get_block(goal_x, goal_y)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn sampled_blocks_are_walkable_and_far_enough_apart() {
+        let map = Map::from(crate::generate_maze(10, 10, Some(0.1)).unwrap());
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..20 {
+            assert!(map.random_walkable(&mut rng).unwrap().is_walkable());
+            let (start, goal) = map.random_pair_at_least(&mut rng, 25).unwrap();
+            assert!(start.x.abs_diff(goal.x) + start.y.abs_diff(goal.y) >= 25);
+            assert!(map
+                .components()
+                .connected((start.x, start.y), (goal.x, goal.y)));
+        }
+    }
+
+    #[test]
+    fn impossible_pairs_are_none() {
+        let map = Map::from_rows(&["..#..", "#####"]);
+        let mut rng = StdRng::seed_from_u64(7);
+
+        assert_eq!(map.random_pair_at_least(&mut rng, 2), None);
+        assert!(map.random_pair_at_least(&mut rng, 1).is_some());
+        assert_eq!(Map::from_rows(&["##"]).random_walkable(&mut rng), None);
+    }
+}
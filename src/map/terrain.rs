@@ -0,0 +1,256 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::anyhow;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::Color;
+
+use super::{BlockType, Map};
+
+/// The directions of the gradients of the noise, the diagonals and the axes
+const GRADIENTS: [(f64, f64); 8] = [
+    (1.0, 1.0),
+    (-1.0, 1.0),
+    (1.0, -1.0),
+    (-1.0, -1.0),
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+];
+
+/// Perlin noise rarely gets close to ±1, so it's stretched before it's mapped onto 0 to 1
+const NOISE_STRETCH: f64 = 2.0;
+
+/// The terrain of the noise values up to a bound, see [TerrainNoise].
+/// Parses from `<color>:<bound>`, e.g. `green:0.35`, or just `<color>` for all values up to 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainBand {
+    pub color: Color,
+    /// The highest noise value between 0 and 1 that gets the color
+    pub up_to: f64,
+}
+
+impl Display for TerrainBand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.color, self.up_to)
+    }
+}
+
+impl FromStr for TerrainBand {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (color, up_to) = match s.split_once(':') {
+            Some((color, up_to)) => (
+                color,
+                up_to
+                    .parse()
+                    .map_err(|_| anyhow!("'{up_to}' of the terrain band '{s}' is not a number"))?,
+            ),
+            None => (s, 1.0),
+        };
+        Ok(TerrainBand {
+            color: color.trim().parse()?,
+            up_to,
+        })
+    }
+}
+
+/// Paints the terrain of a map with smooth noise, so that patches of cheap and expensive terrain alternate
+/// like roads, grass, mud and swamps. See [Map::paint_terrain]
+#[derive(Debug, Clone)]
+pub struct TerrainNoise {
+    /// The size in blocks of the patches
+    pub scale: f64,
+    /// How many layers of noise are added up, each with half the size and weight of the one before.
+    /// More layers give rougher borders between the patches
+    pub octaves: usize,
+    /// The terrain by the noise value, sorted by their bounds. Values above the last bound get the last color.
+    /// The values cluster around 0.5, about 40% of the blocks lie between 0.4 and 0.6
+    pub bands: Vec<TerrainBand>,
+    /// Paints the same terrain every time for the same seed and options. Random terrain if `None`
+    pub seed: Option<u64>,
+}
+
+impl Default for TerrainNoise {
+    fn default() -> Self {
+        let band = |color, up_to| TerrainBand { color, up_to };
+        Self {
+            scale: 12.0,
+            octaves: 3,
+            // Cheap roads at the bottom of the noise, grass in between and swamps at the top
+            bands: vec![
+                band(Color::Green, 0.35),
+                band(Color::Blue, 0.6),
+                band(Color::Orange, 0.7),
+                band(Color::Yellow, 1.0),
+            ],
+            seed: None,
+        }
+    }
+}
+
+/// Perlin's gradient noise: random gradients on a grid, blended smoothly in between
+struct Perlin {
+    permutation: Vec<usize>,
+}
+
+impl Perlin {
+    fn new(rng: &mut StdRng) -> Self {
+        let mut permutation = (0..256).collect::<Vec<_>>();
+        permutation.shuffle(rng);
+        permutation.extend_from_within(..);
+        Self { permutation }
+    }
+
+    /// Between -1 and 1, 0 on the grid points
+    fn at(&self, x: f64, y: f64) -> f64 {
+        let (cell_x, cell_y) = (x.floor(), y.floor());
+        let (dx, dy) = (x - cell_x, y - cell_y);
+        let (cell_x, cell_y) = (cell_x as i64 & 255, cell_y as i64 & 255);
+        let influence = |corner_x: i64, corner_y: i64| {
+            let hash = self.permutation[self.permutation[((cell_x + corner_x) & 255) as usize]
+                + ((cell_y + corner_y) & 255) as usize];
+            let (gradient_x, gradient_y) = GRADIENTS[hash % GRADIENTS.len()];
+            gradient_x * (dx - corner_x as f64) + gradient_y * (dy - corner_y as f64)
+        };
+        let fade = |t: f64| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        let (u, v) = (fade(dx), fade(dy));
+        lerp(
+            lerp(influence(0, 0), influence(1, 0), u),
+            lerp(influence(0, 1), influence(1, 1), u),
+            v,
+        )
+    }
+}
+
+impl Map {
+    /// Repaints the green, blue, orange and yellow terrain by the noise value of each block, between 0 and 1.
+    /// Walls and special blocks like keys, doors and portals keep their type.
+    pub fn paint_terrain(&mut self, noise: &TerrainNoise) -> anyhow::Result<()> {
+        if noise.scale <= 0.0 || !noise.scale.is_finite() {
+            return Err(anyhow!("The scale of the terrain noise must be positive"));
+        }
+        if noise.bands.is_empty() {
+            return Err(anyhow!("The terrain noise needs at least one band"));
+        }
+        if noise
+            .bands
+            .windows(2)
+            .any(|bands| bands[0].up_to > bands[1].up_to)
+        {
+            return Err(anyhow!(
+                "The bands of the terrain noise must be sorted by their bounds"
+            ));
+        }
+        let mut rng = match noise.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let perlin = Perlin::new(&mut rng);
+        let octaves = noise.octaves.max(1);
+        // Dividing by the sum of the weights keeps the layers together within ±1
+        let total_weight: f64 = (0..octaves).map(|octave| 0.5f64.powi(octave as i32)).sum();
+
+        for block in self.blocks.iter_mut().flatten() {
+            if !matches!(
+                block.block_type,
+                BlockType::Green | BlockType::Blue | BlockType::Orange | BlockType::Yellow
+            ) {
+                continue;
+            }
+            let value = (0..octaves)
+                .map(|octave| {
+                    let frequency = 2f64.powi(octave as i32) / noise.scale;
+                    perlin.at(block.x as f64 * frequency, block.y as f64 * frequency)
+                        / 2f64.powi(octave as i32)
+                })
+                .sum::<f64>()
+                / total_weight;
+            let value = ((value * NOISE_STRETCH + 1.0) / 2.0).clamp(0.0, 1.0);
+            let band = noise
+                .bands
+                .iter()
+                .find(|band| value <= band.up_to)
+                .or(noise.bands.last())
+                .expect("There is at least one band");
+            block.block_type = band.color.into();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_paints_patches_of_every_terrain() {
+        let mut map = Map::from_fn(80, 60, |x, y| {
+            if x == 40 {
+                BlockType::Black
+            } else if (x, y) == (10, 10) {
+                BlockType::Portal(crate::PortalColor::Orange)
+            } else {
+                BlockType::Green
+            }
+        });
+        let noise = TerrainNoise {
+            seed: Some(1),
+            ..Default::default()
+        };
+
+        map.paint_terrain(&noise).unwrap();
+
+        for terrain in [
+            BlockType::Green,
+            BlockType::Blue,
+            BlockType::Orange,
+            BlockType::Yellow,
+        ] {
+            let count = map
+                .iter_blocks()
+                .filter(|block| block.block_type() == terrain)
+                .count();
+            assert!(count > 80 * 60 / 50, "{terrain:?}: {count}");
+        }
+        assert!(map
+            .get_block(40, 5)
+            .is_some_and(|block| !block.is_walkable()));
+        assert_eq!(
+            map.get_block(10, 10).unwrap().block_type(),
+            BlockType::Portal(crate::PortalColor::Orange)
+        );
+        // Smooth noise: most neighbors share their terrain
+        let same = (0..79)
+            .flat_map(|x| (0..60).map(move |y| (x, y)))
+            .filter(|&(x, y)| {
+                map.get_block(x, y).unwrap().block_type()
+                    == map.get_block(x + 1, y).unwrap().block_type()
+            })
+            .count();
+        assert!(same > 79 * 60 * 3 / 4);
+    }
+
+    #[test]
+    fn bands_parse_and_must_be_sorted() {
+        assert_eq!(
+            "blue:0.3".parse::<TerrainBand>().unwrap(),
+            TerrainBand {
+                color: Color::Blue,
+                up_to: 0.3
+            }
+        );
+        assert_eq!("yellow".parse::<TerrainBand>().unwrap().up_to, 1.0);
+        assert!("blue:much".parse::<TerrainBand>().is_err());
+
+        let mut map = Map::from_rows(&["..."]);
+        let noise = TerrainNoise {
+            bands: vec!["blue:0.6".parse().unwrap(), "green:0.3".parse().unwrap()],
+            ..Default::default()
+        };
+        assert!(map.paint_terrain(&noise).is_err());
+    }
+}
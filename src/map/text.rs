@@ -0,0 +1,528 @@
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    io::{self, BufWriter, IsTerminal, Write},
+    str::FromStr,
+};
+
+use anyhow::anyhow;
+
+use super::{
+    labels::LABEL_PREFIX, metadata::METADATA_PREFIX, Block, BlockType, Direction, KeyColor, Map,
+    Palette, PortalColor,
+};
+
+/// How blocks are written as text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextTheme {
+    /// Colored emoji squares, which take two columns in most terminals but not in all of them
+    #[default]
+    Emoji,
+    /// One ASCII character per block: `#` for walls, `.` for green terrain and letters for everything else
+    Ascii,
+    /// Like ASCII, but walls are drawn as connected lines and green terrain is empty
+    Box,
+    /// Two spaces with the color of the block as ANSI background color from the 256 color palette
+    Ansi,
+    /// Two spaces with the exact image color of the block as 24 bit ANSI background color.
+    /// The solution is drawn in a color that stands out from all terrain.
+    TrueColor,
+}
+
+impl TextTheme {
+    pub const ALL: [TextTheme; 5] = [
+        TextTheme::Emoji,
+        TextTheme::Ascii,
+        TextTheme::Box,
+        TextTheme::Ansi,
+        TextTheme::TrueColor,
+    ];
+
+    /// The most colorful theme the terminal of stdout supports according to `COLORTERM` and `TERM`.
+    /// Without a terminal or with `NO_COLOR` set no escape codes are used.
+    pub fn detect() -> Self {
+        let variable = |name| std::env::var(name).unwrap_or_default();
+        if !std::io::stdout().is_terminal() || !variable("NO_COLOR").is_empty() {
+            TextTheme::Emoji
+        } else if matches!(variable("COLORTERM").as_str(), "truecolor" | "24bit") {
+            TextTheme::TrueColor
+        } else if variable("TERM").contains("256color") {
+            TextTheme::Ansi
+        } else {
+            TextTheme::Emoji
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            TextTheme::Emoji => "emoji",
+            TextTheme::Ascii => "ascii",
+            TextTheme::Box => "box",
+            TextTheme::Ansi => "ansi",
+            TextTheme::TrueColor => "truecolor",
+        }
+    }
+
+    /// How many terminal columns a block takes
+    pub fn block_width(&self) -> usize {
+        match self {
+            TextTheme::Emoji | TextTheme::Ansi | TextTheme::TrueColor => 2,
+            TextTheme::Ascii | TextTheme::Box => 1,
+        }
+    }
+}
+
+impl Display for TextTheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TextTheme {
+    type Err = anyhow::Error;
+
+    /// Besides the names of the themes `auto` [detects](TextTheme::detect) the theme.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "auto" {
+            return Ok(TextTheme::detect());
+        }
+        TextTheme::ALL
+            .into_iter()
+            .find(|theme| theme.name() == s)
+            .ok_or(anyhow!(
+                "Unknown theme '{s}', expected one of auto, emoji, ascii, box, ansi, truecolor"
+            ))
+    }
+}
+
+/// The character of a block type in the ASCII theme, matching what `Map::from_rows` reads in tests
+pub(super) fn ascii_char(block_type: BlockType) -> char {
+    match block_type {
+        BlockType::White => ' ',
+        BlockType::Black => '#',
+        BlockType::Orange => 'o',
+        BlockType::Blue => 'b',
+        BlockType::Green => '.',
+        BlockType::Yellow => 'y',
+        BlockType::Border => '?',
+        BlockType::Solution => '*',
+        BlockType::StairsUp => 'u',
+        BlockType::StairsDown => 'd',
+        BlockType::BridgeHorizontal => '-',
+        BlockType::BridgeVertical => '|',
+        BlockType::Key(color) => (b'1' + color as u8) as char,
+        BlockType::Door(color) => (b'A' + color as u8) as char,
+        BlockType::Portal(color) => (b'P' + color as u8) as char,
+        BlockType::OneWay(Direction::Left) => '<',
+        BlockType::OneWay(Direction::Up) => '^',
+        BlockType::OneWay(Direction::Right) => '>',
+        BlockType::OneWay(Direction::Down) => 'v',
+    }
+}
+
+/// The block type of a character in the ASCII theme
+pub(super) fn from_ascii_char(c: char) -> Option<BlockType> {
+    BlockType::all().find(|block_type| ascii_char(*block_type) == c)
+}
+
+/// The 256 color palette index closest to the image color of a block type
+fn ansi_color(block_type: BlockType) -> u8 {
+    match block_type {
+        BlockType::White => 231,
+        BlockType::Black => 16,
+        BlockType::Orange => 173,
+        BlockType::Blue => 21,
+        BlockType::Green => 46,
+        BlockType::Yellow => 226,
+        BlockType::Border => 196,
+        BlockType::Solution => 99,
+        BlockType::StairsUp => 51,
+        BlockType::StairsDown => 201,
+        BlockType::BridgeHorizontal => 130,
+        BlockType::BridgeVertical => 94,
+        BlockType::Key(KeyColor::Purple) => 90,
+        BlockType::Key(KeyColor::Green) => 28,
+        BlockType::Key(KeyColor::Blue) => 18,
+        BlockType::Door(KeyColor::Purple) => 53,
+        BlockType::Door(KeyColor::Green) => 22,
+        BlockType::Door(KeyColor::Blue) => 17,
+        BlockType::Portal(PortalColor::Orange) => 208,
+        BlockType::Portal(PortalColor::Yellow) => 184,
+        BlockType::Portal(PortalColor::Brown) => 58,
+        BlockType::OneWay(_) => 218,
+    }
+}
+
+/// The 256 color palette index closest to an RGB color, either from the 6x6x6 color cube or the gray ramp
+fn nearest_ansi_color([r, g, b, _]: [u8; 4]) -> u8 {
+    const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest_level = |channel: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|i| CUBE_LEVELS[*i].abs_diff(channel))
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (
+        16 + 36 * ri + 6 * gi + bi,
+        [CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]],
+    );
+    // The gray ramp runs from 8 to 238 in steps of 10
+    let gray_step =
+        ((((r as usize + g as usize + b as usize) / 3).saturating_sub(8) + 5) / 10).min(23);
+    let level = (8 + 10 * gray_step) as u8;
+    let gray = (232 + gray_step, [level; 3]);
+    [cube, gray]
+        .into_iter()
+        .min_by_key(|(_, rgb)| color_distance(*rgb, [r, g, b]))
+        .map_or(16, |(index, _)| index as u8)
+}
+
+/// The squared euclidean distance between two RGB colors
+fn color_distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (*a as u32).abs_diff(b as u32).pow(2))
+        .sum()
+}
+
+/// The colored square emoji with their approximate colors
+const EMOJI_SQUARES: [(&str, [u8; 3]); 9] = [
+    ("⬜", [230, 231, 232]),
+    ("⬛", [49, 55, 61]),
+    ("🟥", [221, 46, 68]),
+    ("🟧", [244, 144, 12]),
+    ("🟨", [253, 203, 88]),
+    ("🟩", [120, 177, 89]),
+    ("🟦", [85, 172, 238]),
+    ("🟪", [170, 142, 214]),
+    ("🟫", [193, 105, 79]),
+];
+
+/// The square emoji closest to an RGB color
+fn nearest_emoji_square([r, g, b, _]: [u8; 4]) -> &'static str {
+    EMOJI_SQUARES
+        .iter()
+        .min_by_key(|(_, rgb)| color_distance(*rgb, [r, g, b]))
+        .map_or("⬜", |(emoji, _)| emoji)
+}
+
+/// The solution color of the true color theme, a hot pink that no terrain uses
+const SOLUTION_TRUE_COLOR: [u8; 4] = [255, 20, 147, 255];
+
+/// The character drawn on top of the background color. Plain terrain is only colored,
+/// everything else keeps its ASCII character.
+fn colored_char(block_type: BlockType) -> char {
+    match block_type {
+        BlockType::White
+        | BlockType::Black
+        | BlockType::Orange
+        | BlockType::Blue
+        | BlockType::Green
+        | BlockType::Yellow
+        | BlockType::Border => ' ',
+        BlockType::Solution => '•',
+        block_type => ascii_char(block_type),
+    }
+}
+
+/// Box drawing characters for walls, indexed by which neighbors are walls as bits: up, right, down, left
+const WALL_CHARS: [char; 16] = [
+    '■', '│', '─', '└', '│', '│', '┌', '├', '─', '┘', '─', '┴', '┐', '┤', '┬', '┼',
+];
+
+/// Whether the line after the rows of a text map holds metadata or a label
+pub(super) fn is_annotation(line: &str) -> bool {
+    line.starts_with([LABEL_PREFIX, METADATA_PREFIX])
+}
+
+impl Map {
+    /// Reads a map written with [TextTheme::Ascii], one character per block. Rows must have the same width.
+    /// Lines like `@entrance 3 4` after the rows [label](Map::set_label) blocks,
+    /// lines like `%title The first maze` are [metadata](Map::metadata).
+    pub fn from_text(text: &str) -> anyhow::Result<Map> {
+        let lines = text
+            .trim_end_matches(['\n', '\r'])
+            .lines()
+            .collect::<Vec<_>>();
+        let (rows, annotations) = lines.split_at(
+            lines
+                .iter()
+                .position(|line| is_annotation(line))
+                .unwrap_or(lines.len()),
+        );
+        let rows = rows
+            .iter()
+            .enumerate()
+            .map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .map(|(x, c)| {
+                        from_ascii_char(c)
+                            .map(|block_type| Block::new(x, y, block_type))
+                            .ok_or(anyhow!("Unknown block '{c}' at {x} {y}"))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let width = rows.first().map_or(0, Vec::len);
+        if width == 0 {
+            return Err(anyhow!("The map must at least have one block"));
+        }
+        if rows.iter().any(|row| row.len() != width) {
+            return Err(anyhow!("Every row of the map must have the same width"));
+        }
+        let mut map = Map::new(rows);
+        for line in annotations {
+            map.parse_annotation(line)?;
+        }
+        Ok(map)
+    }
+
+    /// The metadata and labels of the map as lines that follow its rows in text formats
+    pub(super) fn annotation_lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.metadata_lines().chain(self.label_lines())
+    }
+
+    /// Reads a line written by [annotation_lines](Self::annotation_lines)
+    pub(super) fn parse_annotation(&mut self, line: &str) -> anyhow::Result<()> {
+        if line.starts_with(METADATA_PREFIX) {
+            self.parse_metadata_line(line)
+        } else {
+            self.parse_label_line(line)
+        }
+    }
+
+    pub fn to_string_with_locations(&self, locations: &[Block], with_numbers: bool) -> String {
+        self.text_lines(
+            locations,
+            with_numbers,
+            TextTheme::Emoji,
+            &Palette::default(),
+        )
+        .collect()
+    }
+
+    /// The map as text in the given theme, without row and column numbers
+    pub fn to_text_themed(&self, theme: TextTheme) -> String {
+        self.to_text_with(theme, &Palette::default())
+    }
+
+    /// Like [to_text_themed](Self::to_text_themed), but the colored themes use the colors of the palette.
+    /// The emoji theme draws terrain with a color of its own as the square emoji closest to that color.
+    pub fn to_text_with(&self, theme: TextTheme, palette: &Palette) -> String {
+        self.text_lines(&[], false, theme, palette).collect()
+    }
+
+    /// Writes the same text as [to_text_themed](Self::to_text_themed) line by line,
+    /// so the whole text never has to be in memory.
+    pub fn write_text(
+        &self,
+        writer: impl Write,
+        with_numbers: bool,
+        theme: TextTheme,
+    ) -> io::Result<()> {
+        self.write_text_with(writer, with_numbers, theme, &Palette::default())
+    }
+
+    /// Writes the same text as [to_text_with](Self::to_text_with) line by line
+    pub fn write_text_with(
+        &self,
+        writer: impl Write,
+        with_numbers: bool,
+        theme: TextTheme,
+        palette: &Palette,
+    ) -> io::Result<()> {
+        let mut writer = BufWriter::new(writer);
+        for line in self.text_lines(&[], with_numbers, theme, palette) {
+            writer.write_all(line.as_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// The lines of the text representation including their line breaks
+    pub(crate) fn text_lines<'a>(
+        &'a self,
+        locations: &'a [Block],
+        with_numbers: bool,
+        theme: TextTheme,
+        palette: &'a Palette,
+    ) -> impl Iterator<Item = String> + 'a {
+        let header = with_numbers.then(|| {
+            let numbers: String = (0..self.width)
+                .map(|i| match theme.block_width() {
+                    1 => (i % 10).to_string(),
+                    _ => format!("{:>2}", i),
+                })
+                .collect();
+            format!("  {numbers}\n")
+        });
+        let rows = self.blocks.iter().enumerate().map(move |(i, row)| {
+            let mut line = if with_numbers {
+                format!("{:>2}", i)
+            } else {
+                String::new()
+            };
+            for block in row {
+                let mut block = *block;
+                if locations.contains(&block) {
+                    block.block_type = BlockType::Solution;
+                }
+                line += &self.block_text(&block, theme, palette);
+            }
+            line + "\n"
+        });
+        // Only plain ASCII text can be read back with its metadata and labels
+        let annotations = (theme == TextTheme::Ascii && !with_numbers)
+            .then(|| self.annotation_lines())
+            .into_iter()
+            .flatten();
+        header.into_iter().chain(rows).chain(annotations)
+    }
+
+    /// The text of the block at `x`, `y` in the given theme
+    pub fn text_at(&self, x: usize, y: usize, theme: TextTheme) -> Option<String> {
+        self.get_block(x, y).map(|block| {
+            self.block_text(&block, theme, &Palette::default())
+                .into_owned()
+        })
+    }
+
+    fn block_text(&self, block: &Block, theme: TextTheme, palette: &Palette) -> Cow<'static, str> {
+        let custom_color = palette.custom_color(block.block_type);
+        match theme {
+            // Only plain terrain is drawn as colored square
+            TextTheme::Emoji => match custom_color {
+                Some(color) if colored_char(block.block_type) == ' ' => {
+                    nearest_emoji_square(color).into()
+                }
+                _ => block.to_string().into(),
+            },
+            TextTheme::Ascii => ascii_char(block.block_type).to_string().into(),
+            TextTheme::Box => match block.block_type {
+                BlockType::Black => self.wall_char(block.x, block.y).to_string().into(),
+                BlockType::Green => " ".into(),
+                block_type => ascii_char(block_type).to_string().into(),
+            },
+            TextTheme::Ansi => format!(
+                "\x1b[48;5;{}m{} \x1b[0m",
+                custom_color.map_or_else(|| ansi_color(block.block_type), nearest_ansi_color),
+                colored_char(block.block_type)
+            )
+            .into(),
+            TextTheme::TrueColor => {
+                let [r, g, b, _] = match (block.block_type, custom_color) {
+                    (_, Some(color)) => color,
+                    (BlockType::Solution, None) => SOLUTION_TRUE_COLOR,
+                    (block_type, None) => block_type.to_rgba(),
+                };
+                // White text on the solution, black text on everything else
+                let foreground = if block.block_type == BlockType::Solution {
+                    97
+                } else {
+                    30
+                };
+                format!(
+                    "\x1b[48;2;{r};{g};{b};{foreground}m{} \x1b[0m",
+                    colored_char(block.block_type)
+                )
+                .into()
+            }
+        }
+    }
+
+    /// The box drawing character for a wall, connected to the walls next to it
+    fn wall_char(&self, x: usize, y: usize) -> char {
+        let is_wall = |x: Option<usize>, y: Option<usize>| {
+            x.zip(y)
+                .and_then(|(x, y)| self.get_block(x, y))
+                .is_some_and(|block| block.block_type == BlockType::Black)
+        };
+        let neighbors = [
+            is_wall(Some(x), y.checked_sub(1)),
+            is_wall(x.checked_add(1), Some(y)),
+            is_wall(Some(x), y.checked_add(1)),
+            is_wall(x.checked_sub(1), Some(y)),
+        ];
+        let index = neighbors
+            .iter()
+            .enumerate()
+            .fold(0, |index, (bit, is_wall)| {
+                index | (usize::from(*is_wall) << bit)
+            });
+        WALL_CHARS[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn ascii_theme_uses_one_character_per_block() {
+        let map = Map::from_rows(&["#.o", "b1P"]);
+
+        assert_eq!(map.to_text_themed(TextTheme::Ascii), "#.o\nb1P\n");
+    }
+
+    #[test]
+    fn ascii_text_can_be_read_back() {
+        let map = Map::from_rows(&["#.o<", "b1PA"]);
+
+        let text = map.to_text_themed(TextTheme::Ascii);
+
+        assert_eq!(Map::from_text(&text).unwrap().to_string(), map.to_string());
+        assert!(Map::from_text("#.\n#").is_err());
+        assert!(Map::from_text("#~").is_err());
+    }
+
+    #[test]
+    fn true_color_theme_uses_the_image_colors() {
+        let map = Map::from_rows(&["o"]);
+
+        assert_eq!(
+            map.to_text_themed(TextTheme::TrueColor),
+            "\x1b[48;2;200;113;55;30m  \x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn colored_themes_use_the_palette() {
+        let map = Map::from_rows(&[".boy"]);
+        let palette = Palette::colorblind();
+
+        assert_eq!(
+            map.to_text_with(TextTheme::TrueColor, &palette),
+            map.to_text_themed(TextTheme::TrueColor)
+                .replace("0;255;0", "86;180;233")
+                .replace("0;0;255", "204;121;167")
+                .replace("200;113;55", "230;159;0")
+                .replace("255;255;0", "240;228;66")
+        );
+        // Each terrain keeps its own emoji and ANSI color
+        assert_eq!(map.to_text_with(TextTheme::Emoji, &palette), "🟦🟪🟧🟨\n");
+        let ansi = map.to_text_with(TextTheme::Ansi, &palette);
+        let colors: HashSet<_> = ansi.split("\x1b[48;5;").skip(1).collect();
+        assert_eq!(colors.len(), 4);
+        assert_eq!(map.to_text_with(TextTheme::Ascii, &palette), ".boy\n");
+    }
+
+    #[test]
+    fn nearest_ansi_colors() {
+        assert_eq!(nearest_ansi_color([0, 0, 0, 255]), 16);
+        assert_eq!(nearest_ansi_color([255, 255, 255, 255]), 231);
+        assert_eq!(
+            nearest_ansi_color([0, 255, 0, 255]),
+            ansi_color(BlockType::Green)
+        );
+        assert_eq!(nearest_ansi_color([128, 128, 128, 255]), 244);
+    }
+
+    #[test]
+    fn box_theme_connects_walls() {
+        let map = Map::from_rows(&["###", "#..", "#.#"]);
+
+        assert_eq!(map.to_text_themed(TextTheme::Box), "┌──\n│  \n│ ■\n");
+    }
+}
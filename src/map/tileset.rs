@@ -0,0 +1,249 @@
+use anyhow::anyhow;
+use image::{imageops, GenericImageView, RgbaImage};
+
+use super::{Block, BlockType, Map, Palette};
+use crate::{layers::blend, Solution};
+
+/// The tiles of a [Tileset] in the order they are cut from a sheet, left to right and top to bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tile {
+    Wall,
+    /// Walkable blocks whose terrain has no tile of its own
+    Floor,
+    Green,
+    Blue,
+    Orange,
+    Yellow,
+    /// Drawn over the terrain of the blocks the solution passes
+    Path,
+    /// Drawn over the first block of the solution
+    Start,
+    /// Drawn over the last block of the solution
+    Goal,
+}
+
+impl Tile {
+    pub const ALL: [Tile; 9] = [
+        Tile::Wall,
+        Tile::Floor,
+        Tile::Green,
+        Tile::Blue,
+        Tile::Orange,
+        Tile::Yellow,
+        Tile::Path,
+        Tile::Start,
+        Tile::Goal,
+    ];
+}
+
+/// Square images that replace the flat colors of the blocks, see [Map::to_image_with_tileset].
+/// Blocks without a tile, such as keys, doors and portals, are filled with their color of the default palette.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tileset {
+    tile_size: u32,
+    /// Indexed like [Tile::ALL]
+    tiles: [Option<RgbaImage>; 9],
+}
+
+impl Tileset {
+    /// Cuts the tiles of size `tile_size` from the sheet in the order of [Tile::ALL], left to right and top to bottom.
+    /// Sheets may end early or leave tiles fully transparent for tiles they don't have.
+    pub fn from_sheet(sheet: &RgbaImage, tile_size: u32) -> anyhow::Result<Self> {
+        if tile_size == 0 || sheet.width() < tile_size || sheet.height() < tile_size {
+            return Err(anyhow!(
+                "The tileset of {}x{} pixels has no room for tiles of {tile_size} pixels",
+                sheet.width(),
+                sheet.height()
+            ));
+        }
+        let columns = sheet.width() / tile_size;
+        let rows = sheet.height() / tile_size;
+        let tiles = std::array::from_fn(|i| {
+            let (column, row) = (i as u32 % columns, i as u32 / columns);
+            (row < rows)
+                .then(|| {
+                    sheet
+                        .view(column * tile_size, row * tile_size, tile_size, tile_size)
+                        .to_image()
+                })
+                .filter(|tile| tile.pixels().any(|pixel| pixel[3] > 0))
+        });
+        Ok(Self { tile_size, tiles })
+    }
+
+    /// Replaces a tile, which must be as large as the others
+    pub fn with_tile(mut self, tile: Tile, image: RgbaImage) -> anyhow::Result<Self> {
+        if image.dimensions() != (self.tile_size, self.tile_size) {
+            return Err(anyhow!(
+                "The tile has {}x{} pixels, but the tileset uses tiles of {} pixels",
+                image.width(),
+                image.height(),
+                self.tile_size
+            ));
+        }
+        self.tiles[Self::index(tile)] = Some(image);
+        Ok(self)
+    }
+
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    pub fn tile(&self, tile: Tile) -> Option<&RgbaImage> {
+        self.tiles[Self::index(tile)].as_ref()
+    }
+
+    fn index(tile: Tile) -> usize {
+        tile as usize
+    }
+
+    /// The tile for the terrain of the block, the floor for terrain without one
+    fn terrain(&self, block_type: BlockType) -> Option<&RgbaImage> {
+        let tile = match block_type {
+            BlockType::Black => Tile::Wall,
+            BlockType::Green => Tile::Green,
+            BlockType::Blue => Tile::Blue,
+            BlockType::Orange => Tile::Orange,
+            BlockType::Yellow => Tile::Yellow,
+            _ => return None,
+        };
+        self.tile(tile).or_else(|| {
+            (tile != Tile::Wall)
+                .then(|| self.tile(Tile::Floor))
+                .flatten()
+        })
+    }
+}
+
+impl Map {
+    /// Draws every block as a tile of the tileset instead of a flat color, without borders.
+    /// Blocks of a solution map are drawn as floor with the path on top.
+    pub fn to_image_with_tileset(&self, tileset: &Tileset) -> Option<RgbaImage> {
+        self.tiled_image(tileset, &[])
+    }
+
+    /// Like [to_image_with_tileset](Self::to_image_with_tileset), with the path of the solution over the terrain
+    /// and the start and goal tiles on its ends
+    pub fn to_image_with_tileset_and_solution(
+        &self,
+        tileset: &Tileset,
+        solution: &Solution,
+    ) -> Option<RgbaImage> {
+        self.tiled_image(tileset, solution.path())
+    }
+
+    fn tiled_image(&self, tileset: &Tileset, path: &[Block]) -> Option<RgbaImage> {
+        let size = tileset.tile_size;
+        let mut image = RgbaImage::new(
+            (self.width as u32).checked_mul(size)?,
+            (self.height as u32).checked_mul(size)?,
+        );
+        let palette = Palette::default();
+        let position = |block: &Block| (block.x, block.y);
+        let on_path = |block: &Block| path.iter().any(|step| position(step) == position(block));
+        for block in self.iter_blocks() {
+            let (x, y) = (block.x as u32 * size, block.y as u32 * size);
+            let overlays = [
+                (on_path(block) || block.block_type == BlockType::Solution)
+                    .then(|| tileset.tile(Tile::Path))
+                    .flatten(),
+                (path.first().map(position) == Some(position(block)))
+                    .then(|| tileset.tile(Tile::Start))
+                    .flatten(),
+                (path.last().map(position) == Some(position(block)) && path.len() > 1)
+                    .then(|| tileset.tile(Tile::Goal))
+                    .flatten(),
+            ];
+            let terrain = match block.block_type {
+                BlockType::Solution => tileset.tile(Tile::Floor),
+                block_type => tileset.terrain(block_type),
+            };
+            match terrain {
+                Some(tile) => imageops::replace(&mut image, tile, x as i64, y as i64),
+                None => {
+                    let color = palette.color(block.block_type);
+                    for pixel_y in y..y + size {
+                        for pixel_x in x..x + size {
+                            image.get_pixel_mut(pixel_x, pixel_y).0 = color;
+                        }
+                    }
+                }
+            }
+            for overlay in overlays.into_iter().flatten() {
+                for (pixel_x, pixel_y, pixel) in overlay.enumerate_pixels() {
+                    let under = image.get_pixel_mut(x + pixel_x, y + pixel_y);
+                    under.0 = blend(pixel.0, under.0);
+                }
+            }
+        }
+        Some(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+    use crate::a_star;
+
+    /// A sheet of 2x2 tiles in a row, each in a flat color
+    fn sheet(colors: &[[u8; 4]]) -> RgbaImage {
+        RgbaImage::from_fn(colors.len() as u32 * 2, 2, |x, _| {
+            Rgba(colors[x as usize / 2])
+        })
+    }
+
+    const WALL: [u8; 4] = [10, 10, 10, 255];
+    const FLOOR: [u8; 4] = [200, 200, 200, 255];
+    const GREEN: [u8; 4] = [0, 180, 0, 255];
+    const PATH: [u8; 4] = [255, 0, 0, 128];
+
+    #[test]
+    fn tiles_are_cut_from_the_sheet() {
+        let tileset = Tileset::from_sheet(&sheet(&[WALL, FLOOR, [0; 4], GREEN]), 2).unwrap();
+
+        assert_eq!(tileset.tile(Tile::Wall).unwrap().get_pixel(1, 1).0, WALL);
+        // Transparent tiles and those beyond the end of the sheet are missing
+        assert!(tileset.tile(Tile::Green).is_none());
+        assert_eq!(tileset.tile(Tile::Blue).unwrap().get_pixel(0, 0).0, GREEN);
+        assert!(tileset.tile(Tile::Goal).is_none());
+        assert!(Tileset::from_sheet(&sheet(&[WALL]), 3).is_err());
+        assert!(tileset
+            .clone()
+            .with_tile(Tile::Goal, RgbaImage::new(3, 3))
+            .is_err());
+        let tileset = tileset.with_tile(Tile::Goal, sheet(&[GREEN])).unwrap();
+        assert!(tileset.tile(Tile::Goal).is_some());
+    }
+
+    #[test]
+    fn blocks_are_drawn_as_tiles() {
+        let tileset = Tileset::from_sheet(&sheet(&[WALL, FLOOR, GREEN]), 2)
+            .unwrap()
+            .with_tile(Tile::Path, sheet(&[PATH]))
+            .unwrap();
+        let map = Map::from_rows(&["..#", "#b."]);
+
+        let image = map.to_image_with_tileset(&tileset).unwrap();
+
+        assert_eq!(image.dimensions(), (6, 4));
+        assert_eq!(image.get_pixel(0, 0).0, GREEN);
+        assert_eq!(image.get_pixel(5, 1).0, WALL);
+        // Blue has no tile of its own
+        assert_eq!(image.get_pixel(3, 3).0, FLOOR);
+
+        let solution = a_star(
+            &map,
+            map.get_block(0, 0).unwrap(),
+            map.get_block(2, 1).unwrap(),
+        )
+        .unwrap();
+        let image = map
+            .to_image_with_tileset_and_solution(&tileset, &solution)
+            .unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, blend(PATH, GREEN));
+        assert_eq!(image.get_pixel(3, 3).0, blend(PATH, FLOOR));
+        assert_eq!(image.get_pixel(0, 3).0, WALL);
+    }
+}
@@ -0,0 +1,210 @@
+use std::fmt::Display;
+
+use itertools::Itertools;
+
+use super::{BlockType, Map};
+
+/// A problem found by [Map::validate]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationFinding {
+    /// The row at `y` has a different number of blocks than the first row
+    RaggedRow { y: usize, width: usize },
+    /// A border colored block within the map, usually a color that wasn't recognized when importing
+    UnknownColor { x: usize, y: usize },
+    /// The map has no walkable block at all
+    NoWalkableBlocks,
+    /// Walkable blocks that can't be reached from the largest walkable region. `block` is one of them.
+    Island { block: (usize, usize), size: usize },
+    /// The start or goal is outside the map or not walkable
+    MissingEndpoint {
+        name: &'static str,
+        x: usize,
+        y: usize,
+    },
+    /// The goal can't be reached from the start, regardless of keys and one-way blocks
+    Unreachable,
+}
+
+impl Display for ValidationFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationFinding::RaggedRow { y, width } => {
+                write!(f, "Row {y} has {width} blocks, unlike the rows before")
+            }
+            ValidationFinding::UnknownColor { x, y } => {
+                write!(f, "The block at {x} {y} has an unknown color")
+            }
+            ValidationFinding::NoWalkableBlocks => write!(f, "The map has no walkable block"),
+            ValidationFinding::Island {
+                block: (x, y),
+                size,
+            } => write!(
+                f,
+                "{size} walkable blocks around {x} {y} are cut off from the rest of the map"
+            ),
+            ValidationFinding::MissingEndpoint { name, x, y } => {
+                write!(
+                    f,
+                    "The {name} at {x} {y} is not a walkable block of the map"
+                )
+            }
+            ValidationFinding::Unreachable => write!(f, "The goal can't be reached from the start"),
+        }
+    }
+}
+
+/// Everything [Map::validate] found. A map without findings can be solved between any two walkable blocks,
+/// unless keys, doors or one-way blocks get in the way.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    findings: Vec<ValidationFinding>,
+}
+
+impl ValidationReport {
+    pub fn findings(&self) -> &[ValidationFinding] {
+        &self.findings
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for finding in &self.findings {
+            writeln!(f, "{finding}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Map {
+    /// Checks the map for problems that make it unusable or hard to solve.
+    pub fn validate(&self) -> ValidationReport {
+        let mut findings = self
+            .blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.len() != self.width)
+            .map(|(y, row)| ValidationFinding::RaggedRow {
+                y,
+                width: row.len(),
+            })
+            .collect_vec();
+        findings.extend(
+            self.iter_blocks()
+                .filter(|block| block.block_type == BlockType::Border)
+                .map(|block| ValidationFinding::UnknownColor {
+                    x: block.x,
+                    y: block.y,
+                }),
+        );
+
+        let components = self.components();
+        match components.largest() {
+            None => findings.push(ValidationFinding::NoWalkableBlocks),
+            Some(largest) => {
+                let mut first_blocks = vec![None; components.len()];
+                for (position, label) in components.iter_labels() {
+                    first_blocks[label].get_or_insert(position);
+                }
+                findings.extend(
+                    first_blocks
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(label, _)| *label != largest)
+                        .map(|(label, block)| ValidationFinding::Island {
+                            block: block.expect("Every region has a block"),
+                            size: components.sizes()[label],
+                        }),
+                );
+            }
+        }
+        ValidationReport { findings }
+    }
+
+    /// Like [validate](Self::validate), but also checks that the goal can be reached from the start.
+    /// Islands are only reported when they contain the start or goal.
+    pub fn validate_route(&self, start: (usize, usize), goal: (usize, usize)) -> ValidationReport {
+        let mut report = self.validate();
+        let components = self.components();
+
+        let mut endpoint_regions = vec![];
+        for (name, (x, y)) in [("start", start), ("goal", goal)] {
+            let region = self
+                .get_block(x, y)
+                .filter(|block| block.is_walkable())
+                .and(components.label(x, y));
+            match region {
+                Some(region) => endpoint_regions.push(region),
+                None => report
+                    .findings
+                    .push(ValidationFinding::MissingEndpoint { name, x, y }),
+            }
+        }
+        if let [start_region, goal_region] = endpoint_regions[..] {
+            if start_region != goal_region {
+                report.findings.push(ValidationFinding::Unreachable);
+            }
+            report.findings.retain(|finding| match finding {
+                ValidationFinding::Island { block, .. } => [Some(start_region), Some(goal_region)]
+                    .contains(&components.label(block.0, block.1)),
+                _ => true,
+            });
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Block;
+
+    #[test]
+    fn a_connected_map_is_valid() {
+        let map = Map::from_rows(&["..#", "#..", "P#P"]);
+
+        assert!(map.validate().is_valid());
+        assert!(map.validate_route((0, 0), (2, 2)).is_valid());
+    }
+
+    #[test]
+    fn islands_and_unreachable_goals_are_reported() {
+        let map = Map::from_rows(&["...#.", "####."]);
+
+        assert_eq!(
+            map.validate().findings(),
+            [ValidationFinding::Island {
+                block: (4, 0),
+                size: 2
+            }]
+        );
+        let findings = map.validate_route((0, 0), (4, 1)).findings().to_vec();
+        assert!(findings.contains(&ValidationFinding::Unreachable));
+        assert!(map.validate_route((3, 0), (0, 0)).findings().contains(
+            &ValidationFinding::MissingEndpoint {
+                name: "start",
+                x: 3,
+                y: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn ragged_rows_and_unknown_colors_are_reported() {
+        let map = Map::new(vec![
+            vec![
+                Block::new(0, 0, BlockType::Green),
+                Block::new(1, 0, BlockType::Border),
+            ],
+            vec![Block::new(0, 1, BlockType::Green)],
+        ]);
+
+        let findings = map.validate().findings().to_vec();
+
+        assert!(findings.contains(&ValidationFinding::RaggedRow { y: 1, width: 1 }));
+        assert!(findings.contains(&ValidationFinding::UnknownColor { x: 1, y: 0 }));
+    }
+}
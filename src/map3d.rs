@@ -0,0 +1,352 @@
+use std::fmt::Display;
+
+use anyhow::anyhow;
+#[cfg(feature = "image")]
+use image::{imageops, Rgba, RgbaImage};
+use itertools::Itertools;
+use rand::seq::SliceRandom;
+
+#[cfg(feature = "image")]
+use crate::map::IMAGE_BLOCK_WIDTH;
+use crate::{
+    euclidean_distance,
+    map::BlockType,
+    maze_generation::{derive_seed, generate},
+    search::{a_star_search, SearchSpace},
+    Block, GenOptions, Map, MazeAlgorithm, MazeError,
+};
+
+/// A block on a specific level of a [Map3D]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Location3D {
+    pub level: usize,
+    pub block: Block,
+}
+
+/// A stack of equally sized [Map]s. Level 0 is the bottom level.
+/// A `StairsUp` block leads to the `StairsDown` block at the same position one level higher and vice versa.
+#[derive(Debug, Clone)]
+pub struct Map3D {
+    levels: Vec<Map>,
+}
+
+impl Map3D {
+    pub fn new(levels: Vec<Map>) -> anyhow::Result<Self> {
+        let first = levels
+            .first()
+            .ok_or(anyhow!("A 3D map must at least have one level"))?;
+        if levels
+            .iter()
+            .any(|level| level.width() != first.width() || level.height() != first.height())
+        {
+            return Err(anyhow!(
+                "All levels of a 3D map must have the same dimensions"
+            ));
+        }
+        Ok(Self { levels })
+    }
+
+    pub fn levels(&self) -> &[Map] {
+        &self.levels
+    }
+
+    pub fn get_location(&self, level: usize, x: usize, y: usize) -> Option<Location3D> {
+        self.levels
+            .get(level)
+            .and_then(|map| map.get_block(x, y))
+            .map(|block| Location3D { level, block })
+    }
+
+    /// The walkable neighbors on the same level plus the other end of a staircase
+    pub fn get_reachable(&self, location: Location3D) -> Vec<Location3D> {
+        let Location3D { level, block } = location;
+        let mut reachable = self.levels[level]
+            .get_reachable(block.x, block.y)
+            .into_iter()
+            .map(|block| Location3D { level, block })
+            .collect_vec();
+
+        let other_end = match block.block_type() {
+            BlockType::StairsUp => self.get_location(level + 1, block.x, block.y),
+            BlockType::StairsDown if level > 0 => self.get_location(level - 1, block.x, block.y),
+            _ => None,
+        };
+        reachable.extend(other_end.filter(|location| location.block.is_walkable()));
+
+        reachable
+    }
+
+    /// Renders each level as its own image.
+    #[cfg(feature = "image")]
+    pub fn to_images(self) -> Option<Vec<RgbaImage>> {
+        self.levels.into_iter().map(Map::to_image).collect()
+    }
+
+    /// Renders all levels side by side (bottom level on the left) into a single image.
+    #[cfg(feature = "image")]
+    pub fn to_sprite_sheet(self) -> Option<RgbaImage> {
+        let images = self.to_images()?;
+        let level_width = images.first()?.width();
+        let level_height = images.first()?.height();
+        let gap = IMAGE_BLOCK_WIDTH as u32;
+        let sheet_width = level_width * images.len() as u32 + gap * (images.len() as u32 - 1);
+
+        let mut sheet =
+            RgbaImage::from_pixel(sheet_width, level_height, Rgba(BlockType::Border.to_rgba()));
+        for (i, image) in images.iter().enumerate() {
+            let x = i as u32 * (level_width + gap);
+            imageops::replace(&mut sheet, image, x as i64, 0);
+        }
+        Some(sheet)
+    }
+}
+
+impl Display for Map3D {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, level) in self.levels.iter().enumerate() {
+            writeln!(f, "Level {i}")?;
+            write!(f, "{level}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Generates a maze on every level and connects neighboring levels with `stairs_per_level` staircases.
+pub fn generate_3d(
+    width: usize,
+    height: usize,
+    levels: usize,
+    algorithm: MazeAlgorithm,
+    options: &GenOptions,
+    stairs_per_level: usize,
+) -> anyhow::Result<Map3D> {
+    if levels > 1 && stairs_per_level == 0 {
+        return Err(anyhow!(
+            "Levels can only be connected with at least one staircase"
+        ));
+    }
+    if stairs_per_level > width * height {
+        return Err(anyhow!("There are more staircases than cells on a level"));
+    }
+    // A middle level holds the stairs down from the level below and the stairs up to the one above
+    if levels > 2 && 2 * stairs_per_level > width * height {
+        return Err(anyhow!(
+            "The middle levels can't hold the staircases up and down, there are more than cells on a level"
+        ));
+    }
+    // Every level gets its own seed, or all levels would look alike, and the stairs one more
+    let level_options = |level: usize| GenOptions {
+        seed: options.seed.map(|seed| derive_seed(seed, level as u64)),
+        ..options.clone()
+    };
+    let mut maps = (0..levels)
+        .map(|level| generate(width, height, algorithm, &level_options(level)).map(Map::from))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut rng = level_options(levels).rng();
+
+    for level in 1..levels {
+        let is_free = |map: &Map, x: usize, y: usize| {
+            map.get_block(x, y)
+                .is_some_and(|block| !is_stairs(block.block_type()))
+        };
+        // Cell centers are always walkable
+        let mut free = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x * 2 + 1, y * 2 + 1)))
+            .filter(|&(x, y)| is_free(&maps[level - 1], x, y) && is_free(&maps[level], x, y))
+            .collect_vec();
+        if free.len() < stairs_per_level {
+            return Err(anyhow!(
+                "Level {level} only has room for {} staircases",
+                free.len()
+            ));
+        }
+        free.shuffle(&mut rng);
+        for &(x, y) in &free[..stairs_per_level] {
+            maps[level - 1].set_block_type(x, y, BlockType::StairsUp)?;
+            maps[level].set_block_type(x, y, BlockType::StairsDown)?;
+        }
+    }
+
+    Map3D::new(maps)
+}
+
+fn is_stairs(block_type: BlockType) -> bool {
+    block_type == BlockType::StairsUp || block_type == BlockType::StairsDown
+}
+
+pub struct Solution3D {
+    steps: Vec<Location3D>,
+    map: Map3D,
+    cost: u32,
+}
+
+impl Solution3D {
+    pub fn steps(&self) -> &[Location3D] {
+        &self.steps
+    }
+
+    pub fn cost(&self) -> u32 {
+        self.cost
+    }
+
+    pub fn to_solution_map(self) -> Map3D {
+        self.map
+    }
+}
+
+impl Display for Solution3D {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, level) in self.map.levels.iter().enumerate() {
+            writeln!(f, "Level {i}")?;
+            f.write_str(&level.to_string_with_locations(&[], false))?;
+        }
+        writeln!(
+            f,
+            "This solution cost {} and involves {} steps",
+            self.cost,
+            self.steps.len()
+        )
+    }
+}
+
+struct Space3D<'a> {
+    map: &'a Map3D,
+    destination: Location3D,
+    cheapest_stairs: u32,
+    /// Portals make the straight line distance on a level overestimate the cost
+    has_portals: bool,
+}
+
+impl SearchSpace for Space3D<'_> {
+    type State = Location3D;
+
+    fn successors(&self, state: &Location3D) -> Vec<(Location3D, u32)> {
+        self.map
+            .get_reachable(*state)
+            .into_iter()
+            .map(|location| (location, location.block.speed() as u32))
+            .collect_vec()
+    }
+
+    fn heuristic(&self, state: &Location3D) -> u32 {
+        // Every level change costs at least one step onto a staircase
+        let level_changes =
+            state.level.abs_diff(self.destination.level) as u32 * self.cheapest_stairs;
+        if self.has_portals {
+            level_changes
+        } else {
+            euclidean_distance(state.block, self.destination.block) + level_changes
+        }
+    }
+
+    fn is_goal(&self, state: &Location3D) -> bool {
+        *state == self.destination
+    }
+}
+
+/// Like [a_star](crate::a_star), but may use stairs to change between levels.
+pub fn a_star_3d(
+    map: &Map3D,
+    start: Location3D,
+    destination: Location3D,
+) -> anyhow::Result<Solution3D> {
+    let space = Space3D {
+        map,
+        destination,
+        cheapest_stairs: Block::new(0, 0, BlockType::StairsUp)
+            .speed()
+            .min(Block::new(0, 0, BlockType::StairsDown).speed()) as u32,
+        has_portals: map
+            .levels
+            .iter()
+            .any(|level| !level.portal_pairs().is_empty()),
+    };
+    let path = a_star_search(&space, start).ok_or(MazeError::NoPath)?;
+
+    let mut solution_map = map.clone();
+    for (level, locations) in &path.states.iter().chunk_by(|location| location.level) {
+        let blocks = locations.map(|location| location.block).collect_vec();
+        solution_map.levels[level].enter_solution(&blocks);
+    }
+
+    Ok(Solution3D {
+        steps: path.states,
+        map: solution_map,
+        cost: path.cost,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_star_3d_takes_the_stairs() {
+        let map = Map3D::new(vec![
+            Map::from_rows(&["u#u", ".#."]),
+            Map::from_rows(&["d.d", "..."]),
+        ])
+        .unwrap();
+        let start = map.get_location(0, 0, 1).unwrap();
+        let destination = map.get_location(0, 2, 1).unwrap();
+
+        let solution = a_star_3d(&map, start, destination).unwrap();
+
+        assert!(solution.steps().iter().any(|location| location.level == 1));
+        assert_eq!(solution.steps().last(), Some(&destination));
+        // Four stair blocks and two green blocks
+        assert_eq!(solution.cost(), 4 * 3 + 2);
+    }
+
+    #[test]
+    fn generated_3d_maze_connects_all_levels() {
+        let map =
+            generate_3d(4, 4, 3, MazeAlgorithm::default(), &GenOptions::default(), 2).unwrap();
+        let start = map.get_location(0, 1, 1).unwrap();
+        let destination = map.get_location(2, 7, 7).unwrap();
+
+        assert!(a_star_3d(&map, start, destination).is_ok());
+    }
+
+    #[test]
+    fn middle_levels_need_room_for_stairs_up_and_down() {
+        let options = GenOptions::default();
+
+        assert!(generate_3d(2, 2, 3, MazeAlgorithm::default(), &options, 3).is_err());
+        let map = generate_3d(2, 2, 3, MazeAlgorithm::default(), &options, 2).unwrap();
+        let stairs = map.levels()[1]
+            .iter_blocks()
+            .filter(|block| is_stairs(block.block_type()))
+            .count();
+        assert_eq!(stairs, 4);
+        // Two levels have no middle level
+        assert!(generate_3d(2, 2, 2, MazeAlgorithm::default(), &options, 4).is_ok());
+    }
+
+    #[test]
+    fn the_same_seed_generates_the_same_levels_and_stairs() {
+        let options = GenOptions {
+            seed: Some(8),
+            ..Default::default()
+        };
+        let generate = || {
+            generate_3d(5, 5, 3, MazeAlgorithm::default(), &options, 2)
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(generate(), generate());
+        let map = generate_3d(5, 5, 3, MazeAlgorithm::default(), &options, 2).unwrap();
+        // Without the stairs, the levels are different mazes
+        let without_stairs = |level: &Map| {
+            level
+                .iter_blocks()
+                .map(|block| block.is_walkable())
+                .collect_vec()
+        };
+        assert_ne!(
+            without_stairs(&map.levels()[0]),
+            without_stairs(&map.levels()[1])
+        );
+    }
+}
@@ -1,13 +1,55 @@
+mod aldous_broder;
+mod binary_tree;
+mod cave;
+mod coloring;
+mod difficulty;
+mod graph;
+mod growing_tree;
+mod hunt_and_kill;
+mod mask;
+mod recursive_backtracker;
+mod sidewinder;
+mod symmetry;
+mod unicursal;
+
+use std::{fmt::Display, str::FromStr};
+
+use itertools::Itertools;
 use rand::{
     distributions::{Distribution, Standard},
-    seq::SliceRandom,
-    Rng,
+    rngs::StdRng,
+    seq::{IteratorRandom, SliceRandom},
+    Rng, SeedableRng,
 };
 
 use anyhow::{anyhow, Ok};
 
-/// The entered loop_prop is divided by this factor, to create a behavior in which 1 is almost total connection and 0 is no loops.
-const LOOP_PROB_FACTOR: f64 = 6.20;
+use crate::Metadata;
+
+pub use cave::{generate_cave, CaveOptions};
+pub use coloring::ColoringStrategy;
+pub use difficulty::{generate_with_difficulty, DifficultyRange, MazeAnalysis};
+pub use mask::Mask;
+pub use symmetry::Symmetry;
+pub use unicursal::{generate_unicursal, Labyrinth};
+
+/// How many of the `closed` walls a perfect maze left between its cells to open for the loop probability:
+/// that fraction of them, rounded. Opening one of them creates exactly one loop.
+pub(crate) fn loop_count(loop_prob: Option<f64>, closed: usize) -> usize {
+    (loop_prob.unwrap_or(0.0).clamp(0.0, 1.0) * closed as f64).round() as usize
+}
+
+/// The seed of one of several independent random streams derived from a single seed, e.g. one per band
+/// of [generate_parallel]. Mixes both with SplitMix64, so that neighboring seeds and streams don't share one.
+pub(crate) fn derive_seed(seed: u64, stream: u64) -> u64 {
+    let mix = |mut z: u64| {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+    mix(mix(seed.wrapping_add(GOLDEN_GAMMA)) ^ stream.wrapping_add(1).wrapping_mul(GOLDEN_GAMMA))
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq)]
 pub enum Wall {
@@ -23,6 +65,39 @@ pub enum Color {
     Green,
 }
 
+impl Color {
+    pub const ALL: [Color; 4] = [Color::Blue, Color::Orange, Color::Yellow, Color::Green];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Color::Blue => "blue",
+            Color::Orange => "orange",
+            Color::Yellow => "yellow",
+            Color::Green => "green",
+        }
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Color {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Color::ALL
+            .into_iter()
+            .find(|color| color.name() == s)
+            .ok_or(anyhow!(
+                "Unknown color '{s}'. Possible values: {}",
+                Color::ALL.iter().join(", ")
+            ))
+    }
+}
+
 impl Distribution<Color> for Standard {
     fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Color {
         match rng.gen_range(0..4) {
@@ -34,6 +109,22 @@ impl Distribution<Color> for Standard {
     }
 }
 
+/// The orientation of a passage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+impl Axis {
+    pub fn perpendicular(&self) -> Axis {
+        match self {
+            Axis::Horizontal => Axis::Vertical,
+            Axis::Vertical => Axis::Horizontal,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Cell {
     pub top: Wall,
@@ -43,6 +134,10 @@ pub struct Cell {
     pub x: usize,
     pub y: usize,
     pub color: Color,
+    /// In weave mazes: the axis of the passage that crosses over the other one
+    pub crossing: Option<Axis>,
+    /// Masked out cells are never carved and stay solid walls
+    pub masked: bool,
 }
 
 impl Cell {
@@ -55,6 +150,8 @@ impl Cell {
             x,
             y,
             color: rand::random(),
+            crossing: None,
+            masked: false,
         }
     }
 
@@ -79,6 +176,16 @@ impl Cell {
         Err(anyhow!("The other cell is not a neighbor to self"))
     }
 
+    /// The wall between self and the neighboring other cell
+    fn wall_to(&self, other: &Cell) -> anyhow::Result<Wall> {
+        Ok(match self.relation(other)? {
+            Relation::Top => self.top,
+            Relation::Right => self.right,
+            Relation::Bottom => self.bottom,
+            Relation::Left => self.left,
+        })
+    }
+
     fn open_wall_to(&mut self, other: &Cell) -> anyhow::Result<()> {
         match self.relation(other)? {
             Relation::Top => self.top = Wall::Open,
@@ -109,6 +216,13 @@ pub struct MazeMap {
     pub width: usize,
     pub height: usize,
     pub cells: Vec<Vec<Cell>>,
+    /// Every connection in the order it was made, only recorded for [generate_maze_iter]
+    carved: Option<Vec<CarveEvent>>,
+    /// The colors the generator picks from for each branch, see [ColoringStrategy]
+    branch_colors: Vec<Color>,
+    /// Which cells' passages are copies of each other, see [Symmetry]
+    symmetry: Symmetry,
+    metadata: Metadata,
 }
 
 impl MazeMap {
@@ -127,17 +241,91 @@ impl MazeMap {
             cells,
             width,
             height,
+            carved: None,
+            branch_colors: Color::ALL.to_vec(),
+            symmetry: Symmetry::None,
+            metadata: Metadata::default(),
         }
     }
 
-    fn get_cell(&self, x: usize, y: usize) -> Option<&Cell> {
+    /// The algorithm and seed the maze was generated with, kept by the [Map](crate::Map) made from it
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    pub fn get_cell(&self, x: usize, y: usize) -> Option<&Cell> {
         self.cells.get(y).and_then(|row| row.get(x))
     }
 
+    /// Every cell row by row, including masked ones
+    pub fn cells(&self) -> impl Iterator<Item = &Cell> {
+        self.cells.iter().flatten()
+    }
+
+    /// The wall between two neighboring cells, `None` if they aren't neighbors or outside of the maze
+    pub fn wall_between(&self, a: (usize, usize), b: (usize, usize)) -> Option<Wall> {
+        let a = self.get_cell(a.0, a.1)?;
+        let b = self.get_cell(b.0, b.1)?;
+        a.wall_to(b).ok()
+    }
+
+    /// The neighbors the cell at `x`, `y` has a passage to.
+    /// Both passages of a weave crossing lead through the same cell, so the graph allows turning there.
+    pub fn open_neighbors(&self, (x, y): (usize, usize)) -> Vec<(usize, usize)> {
+        let Some(cell) = self.get_cell(x, y) else {
+            return vec![];
+        };
+        self.get_neighbors(cell)
+            .into_iter()
+            .filter(|neighbor| cell.wall_to(neighbor).ok() == Some(Wall::Open))
+            .map(|neighbor| (neighbor.x, neighbor.y))
+            .collect()
+    }
+
+    /// The maze as graph: the open neighbors of every cell as indices `y * width + x`, in the same order.
+    /// Masked cells have no neighbors.
+    pub fn adjacency_list(&self) -> Vec<Vec<usize>> {
+        self.cells()
+            .map(|cell| {
+                self.open_neighbors((cell.x, cell.y))
+                    .into_iter()
+                    .map(|(x, y)| y * self.width + x)
+                    .collect()
+            })
+            .collect()
+    }
+
     fn get_cell_mut(&mut self, x: usize, y: usize) -> Option<&mut Cell> {
         self.cells.get_mut(y).and_then(|row| row.get_mut(x))
     }
 
+    fn apply_mask(&mut self, mask: &Mask) {
+        for cell in self.cells.iter_mut().flatten() {
+            cell.masked = !mask.is_available(cell.x, cell.y);
+        }
+    }
+
+    fn available_cells(&self) -> impl Iterator<Item = &Cell> {
+        self.cells.iter().flatten().filter(|cell| !cell.masked)
+    }
+
+    fn random_cell<R: Rng>(&self, rng: &mut R) -> anyhow::Result<Cell> {
+        self.available_cells()
+            .copied()
+            .choose(rng)
+            .ok_or(anyhow!("The maze must at least have one available cell"))
+    }
+
+    fn set_cell_color(&mut self, cell: &Cell, color: Color) {
+        if let Some(cell) = self.get_cell_mut(cell.x, cell.y) {
+            cell.set_color(color);
+        }
+    }
+
     fn get_neighbors(&self, cell: &Cell) -> Vec<Cell> {
         let mut neighbors = vec![];
 
@@ -161,12 +349,45 @@ impl MazeMap {
         neighbors
             .into_iter()
             .filter(|cell| cell.is_some())
-            .map(|c_opt| c_opt.map(|cell| cell.clone()))
+            .map(|c_opt| c_opt.copied())
             .collect::<Option<Vec<_>>>()
             .expect("Each cell should have at least 2 neighbors")
+            .into_iter()
+            .filter(|cell| !cell.masked)
+            .collect()
     }
 
     fn connect_cells(&mut self, cell_a: &Cell, cell_b: &Cell) -> anyhow::Result<()> {
+        self.open_walls(cell_a, cell_b)?;
+        self.record(CarveEvent {
+            from: (cell_a.x, cell_a.y),
+            to: (cell_b.x, cell_b.y),
+            under: None,
+        });
+        Ok(())
+    }
+
+    /// Connects `from` with `to` through a tunnel below `under`, whose straight corridor becomes a weave crossing
+    fn tunnel_under(&mut self, from: &Cell, under: &Cell, to: &Cell) -> anyhow::Result<()> {
+        let existing_axis = if under.left == Wall::Open {
+            Axis::Horizontal
+        } else {
+            Axis::Vertical
+        };
+        self.open_walls(from, under)?;
+        self.open_walls(under, to)?;
+        if let Some(cell) = self.get_cell_mut(under.x, under.y) {
+            cell.crossing = Some(existing_axis);
+        }
+        self.record(CarveEvent {
+            from: (from.x, from.y),
+            to: (to.x, to.y),
+            under: Some((under.x, under.y)),
+        });
+        Ok(())
+    }
+
+    fn open_walls(&mut self, cell_a: &Cell, cell_b: &Cell) -> anyhow::Result<()> {
         self.get_cell_mut(cell_a.x, cell_a.y)
             .ok_or(anyhow!("Cell_A is not a part of the map"))?
             .open_wall_to(cell_b)?;
@@ -174,9 +395,419 @@ impl MazeMap {
         self.get_cell_mut(cell_b.x, cell_b.y)
             .ok_or(anyhow!("Cell_B is not a part of the map"))?
             .open_wall_to(cell_a)?;
-
         Ok(())
     }
+
+    fn record(&mut self, event: CarveEvent) {
+        if let Some(carved) = self.carved.as_mut() {
+            carved.push(event);
+        }
+    }
+}
+
+/// The algorithm used to carve the passages of a maze.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MazeAlgorithm {
+    /// Depth-first search with an explicit stack. Long, winding corridors with few dead ends.
+    #[default]
+    RecursiveBacktracker,
+    /// Like the backtracker, but instead of backtracking it hunts for the next unvisited cell.
+    HuntAndKill,
+    /// Unbiased random walk. Slow, but every perfect maze is equally likely.
+    AldousBroder,
+    /// Grows the maze from a list of active cells, see [`SelectionPolicy`].
+    GrowingTree,
+    /// Row by row runs that are connected upwards. Very fast, biased towards vertical passages.
+    Sidewinder,
+    /// Every cell connects either up or right. The fastest algorithm, strongly biased diagonally.
+    BinaryTree,
+}
+
+impl MazeAlgorithm {
+    pub const ALL: [MazeAlgorithm; 6] = [
+        MazeAlgorithm::RecursiveBacktracker,
+        MazeAlgorithm::HuntAndKill,
+        MazeAlgorithm::AldousBroder,
+        MazeAlgorithm::GrowingTree,
+        MazeAlgorithm::Sidewinder,
+        MazeAlgorithm::BinaryTree,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            MazeAlgorithm::RecursiveBacktracker => "backtracker",
+            MazeAlgorithm::HuntAndKill => "hunt-and-kill",
+            MazeAlgorithm::AldousBroder => "aldous-broder",
+            MazeAlgorithm::GrowingTree => "growing-tree",
+            MazeAlgorithm::Sidewinder => "sidewinder",
+            MazeAlgorithm::BinaryTree => "binary-tree",
+        }
+    }
+}
+
+impl Display for MazeAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for MazeAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MazeAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == s)
+            .ok_or(anyhow!(
+                "Unknown maze algorithm '{s}'. Possible values: {}",
+                MazeAlgorithm::ALL.iter().join(", ")
+            ))
+    }
+}
+
+/// Decides which active cell the growing tree algorithm carves from next.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SelectionPolicy {
+    /// The most recently added cell (behaves like the recursive backtracker)
+    #[default]
+    Newest,
+    /// The least recently added cell (long straight corridors)
+    Oldest,
+    /// A random cell (behaves like Prim's algorithm)
+    Random,
+    /// The newest cell with the given probability between 0 and 1, otherwise a random cell
+    Mixed(f64),
+}
+
+impl SelectionPolicy {
+    fn select<R: Rng>(&self, len: usize, rng: &mut R) -> usize {
+        match self {
+            SelectionPolicy::Newest => len - 1,
+            SelectionPolicy::Oldest => 0,
+            SelectionPolicy::Random => rng.gen_range(0..len),
+            SelectionPolicy::Mixed(newest_prob) => {
+                if rng.gen_bool(newest_prob.clamp(0.0, 1.0)) {
+                    len - 1
+                } else {
+                    rng.gen_range(0..len)
+                }
+            }
+        }
+    }
+}
+
+impl Display for SelectionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelectionPolicy::Newest => f.write_str("newest"),
+            SelectionPolicy::Oldest => f.write_str("oldest"),
+            SelectionPolicy::Random => f.write_str("random"),
+            SelectionPolicy::Mixed(newest_prob) => write!(f, "mixed:{newest_prob}"),
+        }
+    }
+}
+
+impl FromStr for SelectionPolicy {
+    type Err = anyhow::Error;
+
+    /// Parses `newest`, `oldest`, `random` or `mixed:<probability of newest>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "newest" => Ok(SelectionPolicy::Newest),
+            "oldest" => Ok(SelectionPolicy::Oldest),
+            "random" => Ok(SelectionPolicy::Random),
+            _ => {
+                let newest_prob: f64 = s
+                    .strip_prefix("mixed:")
+                    .ok_or(anyhow!(
+                        "Unknown selection policy '{s}'. Possible values: newest, oldest, random, mixed:<0..1>"
+                    ))?
+                    .parse()
+                    .map_err(|_| anyhow!("'{s}' does not contain a valid probability"))?;
+                if !(0.0..=1.0).contains(&newest_prob) {
+                    return Err(anyhow!("The probability of '{s}' must be between 0 and 1"));
+                }
+                Ok(SelectionPolicy::Mixed(newest_prob))
+            }
+        }
+    }
+}
+
+/// Options shared by all maze generation algorithms.
+#[derive(Debug, Clone, Default)]
+pub struct GenOptions {
+    /// The fraction between 0 and 1 of the walls between cells that a perfect maze keeps closed,
+    /// which are opened afterwards to create loops. Every opened wall is one more loop, see [MazeMap::count_loops].
+    pub loop_prob: Option<f64>,
+    /// Only used by [`MazeAlgorithm::GrowingTree`]
+    pub selection_policy: SelectionPolicy,
+    /// The probability between 0 and 1 that a passage tunnels under a corridor where possible, creating a weave maze.
+    /// Only used by [`MazeAlgorithm::RecursiveBacktracker`]
+    pub weave: Option<f64>,
+    /// Restricts the maze to the available cells of the mask. Must have the same dimensions as the maze.
+    /// Not supported by [`MazeAlgorithm::Sidewinder`] and [`MazeAlgorithm::BinaryTree`], which rely on complete rows.
+    pub mask: Option<Mask>,
+    /// Generates the same maze every time for the same seed and options. A random maze if `None`
+    pub seed: Option<u64>,
+    /// The colors of the cells, which decide the terrain. Not used by hexagonal and circular mazes
+    pub coloring: ColoringStrategy,
+    /// Carves one part of the maze and copies its passages to the others, see [Symmetry].
+    /// Not supported together with masks and by [`MazeAlgorithm::Sidewinder`] and [`MazeAlgorithm::BinaryTree`].
+    pub symmetry: Symmetry,
+}
+
+impl GenOptions {
+    pub(crate) fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+}
+
+/// Tracks which cells a generator has already carved into.
+struct Visited {
+    width: usize,
+    cells: Vec<bool>,
+    count: usize,
+}
+
+impl Visited {
+    fn new(map: &MazeMap) -> Self {
+        Self {
+            width: map.width,
+            cells: vec![false; map.width * map.height],
+            count: 0,
+        }
+    }
+
+    fn contains(&self, cell: &Cell) -> bool {
+        self.cells[cell.y * self.width + cell.x]
+    }
+
+    /// Returns whether the cell wasn't visited before
+    fn insert(&mut self, cell: &Cell) -> bool {
+        let newly_visited = !std::mem::replace(&mut self.cells[cell.y * self.width + cell.x], true);
+        self.count += usize::from(newly_visited);
+        newly_visited
+    }
+
+    fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// Calls back with the percentage of carved cells whenever it changes.
+pub(crate) struct Progress<'a> {
+    total: usize,
+    last_percent: Option<u8>,
+    callback: Option<&'a mut dyn FnMut(u8)>,
+}
+
+impl<'a> Progress<'a> {
+    fn new(total: usize, callback: Option<&'a mut dyn FnMut(u8)>) -> Self {
+        Self {
+            total,
+            last_percent: None,
+            callback,
+        }
+    }
+
+    fn update(&mut self, carved: usize) {
+        let Some(callback) = self.callback.as_mut() else {
+            return;
+        };
+        let percent = (carved * 100 / self.total.max(1)).min(100) as u8;
+        if self.last_percent != Some(percent) {
+            self.last_percent = Some(percent);
+            callback(percent);
+        }
+    }
+}
+
+/// Generates a maze of `width` x `height` cells with the given algorithm.
+pub fn generate(
+    width: usize,
+    height: usize,
+    algorithm: MazeAlgorithm,
+    options: &GenOptions,
+) -> anyhow::Result<MazeMap> {
+    generate_reporting(width, height, algorithm, options, None, false)
+}
+
+/// Like [generate], but calls `on_progress` with the percentage of carved cells whenever it changes.
+/// The sidewinder and binary tree algorithms only report once they are done.
+pub fn generate_with_progress(
+    width: usize,
+    height: usize,
+    algorithm: MazeAlgorithm,
+    options: &GenOptions,
+    on_progress: &mut dyn FnMut(u8),
+) -> anyhow::Result<MazeMap> {
+    generate_reporting(width, height, algorithm, options, Some(on_progress), false)
+}
+
+/// Two cells the generator connected, neighbors or the ends of a tunnel in a weave maze
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CarveEvent {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    /// The cell a tunnel passes below, between `from` and `to`. Its corridor becomes a [crossing](Cell::crossing)
+    pub under: Option<(usize, usize)>,
+}
+
+impl CarveEvent {
+    /// The blocks of the [Map](crate::Map) made from the maze that the passage opens:
+    /// the cell it starts in, the wall between both cells and the cell it leads to.
+    /// A tunnel also opens the crossing it passes below and the walls on both sides of it.
+    pub fn blocks(&self) -> Vec<(usize, usize)> {
+        let cells = match self.under {
+            Some(under) => vec![self.from, under, self.to],
+            None => vec![self.from, self.to],
+        };
+        let mut blocks = vec![(cells[0].0 * 2 + 1, cells[0].1 * 2 + 1)];
+        for pair in cells.windows(2) {
+            let ((from_x, from_y), (to_x, to_y)) = (pair[0], pair[1]);
+            blocks.push((from_x + to_x + 1, from_y + to_y + 1));
+            blocks.push((to_x * 2 + 1, to_y * 2 + 1));
+        }
+        blocks
+    }
+}
+
+/// The passages of a generated maze in the order they were carved, see [generate_maze_iter].
+#[derive(Debug)]
+pub struct CarveEvents {
+    maze: MazeMap,
+    events: std::vec::IntoIter<CarveEvent>,
+}
+
+impl CarveEvents {
+    /// The finished maze
+    pub fn maze(&self) -> &MazeMap {
+        &self.maze
+    }
+
+    pub fn into_maze(self) -> MazeMap {
+        self.maze
+    }
+}
+
+impl Iterator for CarveEvents {
+    type Item = CarveEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.events.size_hint()
+    }
+}
+
+/// Like [generate], but also yields every passage in the order it was carved, including loops,
+/// e.g. to animate the generation. The maze is generated up front.
+pub fn generate_maze_iter(
+    width: usize,
+    height: usize,
+    algorithm: MazeAlgorithm,
+    options: &GenOptions,
+) -> anyhow::Result<CarveEvents> {
+    let mut maze = generate_reporting(width, height, algorithm, options, None, true)?;
+    let events = maze.carved.take().unwrap_or_default().into_iter();
+    Ok(CarveEvents { maze, events })
+}
+
+fn generate_reporting(
+    width: usize,
+    height: usize,
+    algorithm: MazeAlgorithm,
+    options: &GenOptions,
+    on_progress: Option<&mut dyn FnMut(u8)>,
+    record: bool,
+) -> anyhow::Result<MazeMap> {
+    if width == 0 || height == 0 {
+        return Err(anyhow!("The maze must at least have the dimensions 1x1"));
+    }
+    let mut map = MazeMap::new(width, height);
+    if record {
+        map.carved = Some(vec![]);
+    }
+    map.branch_colors = options.coloring.branch_colors()?;
+    let mut rng = options.rng();
+    // The initial colors are part of the maze as well
+    for y in 0..height {
+        for x in 0..width {
+            map.cells[y][x].color = map.branch_color(&mut rng);
+        }
+    }
+
+    if let Some(mask) = &options.mask {
+        if mask.width() != width || mask.height() != height {
+            return Err(anyhow!(
+                "The mask has the dimensions {}x{}, but the maze {width}x{height}",
+                mask.width(),
+                mask.height()
+            ));
+        }
+        if !mask.is_connected() {
+            return Err(anyhow!(
+                "The available cells of the mask must be connected to each other"
+            ));
+        }
+        if matches!(
+            algorithm,
+            MazeAlgorithm::Sidewinder | MazeAlgorithm::BinaryTree
+        ) {
+            return Err(anyhow!("The {algorithm} algorithm does not support masks"));
+        }
+        map.apply_mask(mask);
+    }
+    if options.symmetry != Symmetry::None {
+        options.symmetry.check_dimensions(width, height)?;
+        if options.mask.is_some() {
+            return Err(anyhow!("Symmetric mazes do not support masks"));
+        }
+        // Both rely on complete rows
+        if matches!(
+            algorithm,
+            MazeAlgorithm::Sidewinder | MazeAlgorithm::BinaryTree
+        ) {
+            return Err(anyhow!(
+                "The {algorithm} algorithm does not support symmetry"
+            ));
+        }
+        map.symmetry = options.symmetry;
+        map.mask_symmetric_copies();
+    }
+
+    let total = map.available_cells().count();
+    let mut progress = Progress::new(total, on_progress);
+    match algorithm {
+        MazeAlgorithm::RecursiveBacktracker => {
+            recursive_backtracker::carve(&mut map, options, &mut rng, &mut progress)?
+        }
+        MazeAlgorithm::HuntAndKill => hunt_and_kill::carve(&mut map, &mut rng, &mut progress)?,
+        MazeAlgorithm::AldousBroder => aldous_broder::carve(&mut map, &mut rng, &mut progress)?,
+        MazeAlgorithm::GrowingTree => {
+            growing_tree::carve(&mut map, options.selection_policy, &mut rng, &mut progress)?
+        }
+        MazeAlgorithm::Sidewinder => sidewinder::carve(&mut map, &mut rng)?,
+        MazeAlgorithm::BinaryTree => binary_tree::carve(&mut map, &mut rng)?,
+    }
+
+    if map.symmetry != Symmetry::None {
+        map.mirror_passages(&mut rng)?;
+    }
+    add_loops(&mut map, options.loop_prob, &mut rng)?;
+    options.coloring.paint(&mut map);
+    if map.symmetry != Symmetry::None {
+        map.mirror_colors();
+    }
+    progress.update(total);
+    map.metadata = Metadata::generated(algorithm, options.seed);
+
+    Ok(map)
 }
 
 /// https://en.wikipedia.org/wiki/Maze_generation_algorithm#Iterative_implementation_(with_stack)
@@ -185,48 +816,127 @@ pub fn generate_maze(
     height: usize,
     loop_prob: Option<f64>,
 ) -> anyhow::Result<MazeMap> {
-    let mut map = MazeMap::new(width, height);
-    let first_cell = map
-        .get_cell(0, 0)
-        .ok_or(anyhow!("The maze must at least have the dimensions 1x1"))?;
-    let mut stack = vec![first_cell.clone()];
-    let mut visited = vec![first_cell.clone()];
-    let mut color: Color = rand::random();
-    let mut rng = rand::thread_rng();
-
-    while let Some(current_cell) = stack.pop() {
-        let unvisited_neighbors: Vec<Cell> = map
-            .get_neighbors(&current_cell)
-            .into_iter()
-            .filter(|cell| {
-                !visited.contains(cell)
-                    || rng.gen_bool(
-                        loop_prob
-                            .filter(|f| *f != 0.0)
-                            .map(|f| f / LOOP_PROB_FACTOR)
-                            .unwrap_or(0.0),
-                    )
+    generate(
+        width,
+        height,
+        MazeAlgorithm::RecursiveBacktracker,
+        &GenOptions {
+            loop_prob,
+            ..Default::default()
+        },
+    )
+}
+
+/// Like [generate], but splits the maze into `threads` horizontal bands that are generated at the same time.
+/// Neighboring bands are connected by a single passage, so perfect bands still make a perfect maze.
+/// Masks are not supported.
+pub fn generate_parallel(
+    width: usize,
+    height: usize,
+    algorithm: MazeAlgorithm,
+    options: &GenOptions,
+    threads: usize,
+) -> anyhow::Result<MazeMap> {
+    if options.mask.is_some() {
+        return Err(anyhow!("Parallel generation does not support masks"));
+    }
+    if options.symmetry != Symmetry::None {
+        return Err(anyhow!("Parallel generation does not support symmetry"));
+    }
+    let bands = threads.clamp(1, height.max(1));
+    if bands == 1 {
+        return generate(width, height, algorithm, options);
+    }
+
+    // The first bands get one extra row if the height can't be divided evenly
+    let band_heights = (0..bands)
+        .map(|band| height / bands + usize::from(band < height % bands))
+        .collect_vec();
+    let band_maps = std::thread::scope(|scope| {
+        let handles = band_heights
+            .iter()
+            .enumerate()
+            .map(|(band, band_height)| {
+                // Every band needs its own seed, or all bands would look alike.
+                // The loops are added to the whole maze, so that the seams get some as well.
+                let band_options = GenOptions {
+                    seed: options.seed.map(|seed| derive_seed(seed, band as u64)),
+                    loop_prob: None,
+                    ..options.clone()
+                };
+                scope.spawn(move || generate(width, *band_height, algorithm, &band_options))
             })
-            .collect();
-
-        if !unvisited_neighbors.is_empty() {
-            stack.push(current_cell);
-            let chosen_cell = unvisited_neighbors
-                .choose(&mut rand::thread_rng())
-                .expect("The get_neighbors can't be empty");
-            map.connect_cells(&current_cell, &chosen_cell)?;
-            map.get_cell_mut(current_cell.x, current_cell.y)
-                .map(|cell| cell.set_color(color));
-            visited.push(chosen_cell.clone());
-            stack.push(chosen_cell.clone());
-        } else {
-            color = rand::random();
-        }
+            .collect_vec();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("A generator thread panicked"))
+            .collect::<anyhow::Result<Vec<_>>>()
+    })?;
+
+    let mut cells = Vec::with_capacity(height);
+    for band_map in band_maps {
+        let offset = cells.len();
+        cells.extend(band_map.cells.into_iter().map(|mut row| {
+            for cell in row.iter_mut() {
+                cell.y += offset;
+            }
+            row
+        }));
+    }
+    let mut map = MazeMap {
+        width,
+        height,
+        cells,
+        carved: None,
+        branch_colors: options.coloring.branch_colors()?,
+        symmetry: Symmetry::None,
+        metadata: Metadata::generated(algorithm, options.seed),
+    };
+
+    let mut rng = options.rng();
+    let mut seam = 0;
+    for band_height in &band_heights[..bands - 1] {
+        seam += band_height;
+        let x = rng.gen_range(0..width);
+        let (above, below) = (map.cells[seam - 1][x], map.cells[seam][x]);
+        map.connect_cells(&above, &below)?;
     }
+    add_loops(&mut map, options.loop_prob, &mut rng)?;
+    // The gradient runs through the whole maze, not each band
+    options.coloring.paint(&mut map);
 
     Ok(map)
 }
 
+/// Opens randomly chosen closed walls between available cells of the perfect maze, as many as
+/// [loop_count] asks for. Symmetric mazes open the counterparts of each wall as well,
+/// which count towards the loops. [MazeMap::count_loops] tells how many were opened.
+fn add_loops<R: Rng>(map: &mut MazeMap, loop_prob: Option<f64>, rng: &mut R) -> anyhow::Result<()> {
+    let mut closed = vec![];
+    for cell in map.available_cells() {
+        if let Some(right) = map.get_cell(cell.x + 1, cell.y) {
+            if cell.right == Wall::Closed && !right.masked {
+                closed.push((*cell, *right));
+            }
+        }
+        if let Some(bottom) = map.get_cell(cell.x, cell.y + 1) {
+            if cell.bottom == Wall::Closed && !bottom.masked {
+                closed.push((*cell, *bottom));
+            }
+        }
+    }
+    let count = loop_count(loop_prob, closed.len());
+    closed.shuffle(rng);
+    let mut opened = 0;
+    for (a, b) in closed {
+        if opened >= count {
+            break;
+        }
+        opened += map.connect_symmetric(&a, &b)?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -333,4 +1043,356 @@ mod tests {
         assert_eq!(map.get_cell(1, 1).unwrap().left, Wall::Open);
         assert_eq!(map.get_cell(0, 1).unwrap().right, Wall::Open);
     }
+
+    fn open_wall_count(map: &MazeMap) -> usize {
+        map.cells
+            .iter()
+            .flatten()
+            .flat_map(|cell| [cell.right, cell.bottom])
+            .filter(|wall| *wall == Wall::Open)
+            .count()
+    }
+
+    fn reachable_cell_count(map: &MazeMap) -> usize {
+        let mut visited = Visited::new(map);
+        let mut stack = vec![map.cells[0][0]];
+        let mut count = 0;
+        while let Some(cell) = stack.pop() {
+            if visited.contains(&cell) {
+                continue;
+            }
+            visited.insert(&cell);
+            count += 1;
+            for neighbor in map.get_neighbors(&cell) {
+                let wall = match cell.relation(&neighbor).unwrap() {
+                    Relation::Top => cell.top,
+                    Relation::Right => cell.right,
+                    Relation::Bottom => cell.bottom,
+                    Relation::Left => cell.left,
+                };
+                if wall == Wall::Open {
+                    stack.push(neighbor);
+                }
+            }
+        }
+        count
+    }
+
+    fn assert_perfect(algorithm: MazeAlgorithm) {
+        let map = generate(8, 5, algorithm, &GenOptions::default()).unwrap();
+        assert_eq!(reachable_cell_count(&map), 8 * 5);
+        assert_eq!(open_wall_count(&map), 8 * 5 - 1);
+    }
+
+    #[test]
+    fn backtracker_generates_perfect_maze() {
+        assert_perfect(MazeAlgorithm::RecursiveBacktracker);
+    }
+
+    #[test]
+    fn progress_is_reported_up_to_completion() {
+        for algorithm in MazeAlgorithm::ALL {
+            let mut reports = vec![];
+            generate_with_progress(10, 10, algorithm, &GenOptions::default(), &mut |percent| {
+                reports.push(percent)
+            })
+            .unwrap();
+
+            assert!(reports.is_sorted(), "{algorithm}: {reports:?}");
+            assert_eq!(reports.last(), Some(&100), "{algorithm}");
+        }
+    }
+
+    #[test]
+    fn carve_events_replay_the_maze() {
+        for algorithm in MazeAlgorithm::ALL {
+            let events = generate_maze_iter(6, 4, algorithm, &GenOptions::default()).unwrap();
+            let mut replayed = MazeMap::new(6, 4);
+            let carved = events.collect_vec();
+            for event in &carved {
+                let from = replayed.cells[event.from.1][event.from.0];
+                let to = replayed.cells[event.to.1][event.to.0];
+                replayed.connect_cells(&from, &to).unwrap();
+            }
+
+            assert_eq!(carved.len(), 6 * 4 - 1, "{algorithm}");
+            assert_eq!(reachable_cell_count(&replayed), 6 * 4, "{algorithm}");
+        }
+    }
+
+    #[test]
+    fn carve_events_replay_weave_mazes() {
+        let options = GenOptions {
+            weave: Some(1.0),
+            seed: Some(3),
+            ..Default::default()
+        };
+        let mut events =
+            generate_maze_iter(8, 8, MazeAlgorithm::RecursiveBacktracker, &options).unwrap();
+        let carved = events.by_ref().collect_vec();
+        let maze = events.into_maze();
+
+        let mut replayed = MazeMap::new(8, 8);
+        for event in &carved {
+            let cell = |(x, y): (usize, usize)| replayed.cells[y][x];
+            let (from, to) = (cell(event.from), cell(event.to));
+            match event.under {
+                Some(under) => replayed.tunnel_under(&from, &cell(under), &to).unwrap(),
+                None => replayed.connect_cells(&from, &to).unwrap(),
+            }
+        }
+        let passages = |maze: &MazeMap| {
+            maze.cells()
+                .map(|cell| (cell.top, cell.right, cell.bottom, cell.left, cell.crossing))
+                .collect_vec()
+        };
+        assert!(carved.iter().any(|event| event.under.is_some()));
+        assert_eq!(passages(&replayed), passages(&maze));
+
+        // Uncovering the blocks of every event in a solid map leads to the generated map
+        let map = crate::Map::from(maze);
+        let mut uncovered = vec![vec![false; map.width()]; map.height()];
+        for (x, y) in carved.iter().flat_map(CarveEvent::blocks) {
+            uncovered[y][x] = true;
+        }
+        for block in map.iter_blocks() {
+            assert_eq!(
+                uncovered[block.y][block.x],
+                block.is_walkable(),
+                "{block:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn the_same_seed_generates_the_same_maze() {
+        let options = GenOptions {
+            seed: Some(42),
+            loop_prob: Some(0.3),
+            ..Default::default()
+        };
+        for algorithm in MazeAlgorithm::ALL {
+            let first = crate::Map::from(generate(9, 7, algorithm, &options).unwrap());
+            let second = crate::Map::from(generate(9, 7, algorithm, &options).unwrap());
+            assert_eq!(first.to_string(), second.to_string(), "{algorithm}");
+        }
+        let parallel = |seed| {
+            let options = GenOptions {
+                seed: Some(seed),
+                ..Default::default()
+            };
+            crate::Map::from(
+                generate_parallel(9, 8, MazeAlgorithm::default(), &options, 2).unwrap(),
+            )
+            .to_string()
+        };
+        assert_eq!(parallel(1), parallel(1));
+        assert_ne!(parallel(1), parallel(2));
+        // The second band of one seed must not repeat the first band of the next seed
+        assert_ne!(derive_seed(1, 1), derive_seed(2, 0));
+        let bands = (0..4)
+            .flat_map(|seed| (0..4).map(move |band| derive_seed(seed, band)))
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(bands.len(), 16);
+    }
+
+    #[test]
+    fn loop_probability_opens_that_fraction_of_the_closed_walls() {
+        // 10 x 10 cells have 180 walls between them, of which a perfect maze opens 99
+        for algorithm in MazeAlgorithm::ALL {
+            for (loop_prob, loops) in [(0.0, 0), (0.25, 20), (1.0, 81)] {
+                let options = GenOptions {
+                    loop_prob: Some(loop_prob),
+                    ..Default::default()
+                };
+                let maze = generate(10, 10, algorithm, &options).unwrap();
+                assert_eq!(maze.count_loops(), loops, "{algorithm} {loop_prob}");
+                assert_eq!(
+                    open_wall_count(&maze),
+                    99 + loops,
+                    "{algorithm} {loop_prob}"
+                );
+            }
+        }
+        let options = GenOptions {
+            loop_prob: Some(0.5),
+            ..Default::default()
+        };
+        let parallel = generate_parallel(10, 10, MazeAlgorithm::default(), &options, 3).unwrap();
+        assert_eq!(parallel.count_loops(), 41);
+    }
+
+    #[test]
+    fn parallel_generation_creates_a_perfect_maze() {
+        let map =
+            generate_parallel(9, 23, MazeAlgorithm::default(), &GenOptions::default(), 4).unwrap();
+
+        assert_eq!((map.width, map.height, map.cells.len()), (9, 23, 23));
+        assert!(map
+            .cells
+            .iter()
+            .enumerate()
+            .all(|(y, row)| row.iter().all(|cell| cell.y == y)));
+        assert_eq!(reachable_cell_count(&map), 9 * 23);
+        assert_eq!(open_wall_count(&map), 9 * 23 - 1);
+    }
+
+    #[test]
+    fn weave_maze_is_connected_and_crosses_straight_corridors() {
+        let options = GenOptions {
+            weave: Some(1.0),
+            ..Default::default()
+        };
+        let map = generate(12, 12, MazeAlgorithm::RecursiveBacktracker, &options).unwrap();
+        let crossings = map
+            .cells
+            .iter()
+            .flatten()
+            .filter(|cell| cell.crossing.is_some())
+            .collect::<Vec<_>>();
+        for cell in &crossings {
+            assert_eq!(
+                [cell.top, cell.right, cell.bottom, cell.left],
+                [Wall::Open; 4]
+            );
+        }
+        assert_eq!(reachable_cell_count(&map), 12 * 12);
+        // Each tunnel adds one more passage than a plain step, without visiting an additional cell
+        assert_eq!(open_wall_count(&map), 12 * 12 - 1 + crossings.len());
+    }
+
+    #[test]
+    fn the_graph_of_a_perfect_maze_is_a_tree() {
+        let map = generate(7, 5, MazeAlgorithm::GrowingTree, &GenOptions::default()).unwrap();
+
+        let adjacency = map.adjacency_list();
+
+        assert_eq!(adjacency.len(), 7 * 5);
+        let edges = adjacency.iter().map(Vec::len).sum::<usize>();
+        assert_eq!(edges, 2 * (7 * 5 - 1));
+        for (index, neighbors) in adjacency.iter().enumerate() {
+            let cell = (index % 7, index / 7);
+            for neighbor in neighbors {
+                let neighbor = (neighbor % 7, neighbor / 7);
+                assert_eq!(map.wall_between(cell, neighbor), Some(Wall::Open));
+                assert!(map.open_neighbors(neighbor).contains(&cell));
+            }
+        }
+        assert_eq!(map.wall_between((0, 0), (1, 1)), None);
+        assert_eq!(map.wall_between((6, 0), (7, 0)), None);
+    }
+
+    #[test]
+    fn masked_cells_are_never_carved() {
+        let mask = Mask::from_text("..X..\n.....\nX...X\n").unwrap();
+        for algorithm in [
+            MazeAlgorithm::RecursiveBacktracker,
+            MazeAlgorithm::HuntAndKill,
+            MazeAlgorithm::AldousBroder,
+            MazeAlgorithm::GrowingTree,
+        ] {
+            let options = GenOptions {
+                mask: Some(mask.clone()),
+                ..Default::default()
+            };
+            let map = generate(5, 3, algorithm, &options).unwrap();
+            for cell in map.cells.iter().flatten().filter(|cell| cell.masked) {
+                assert_eq!(
+                    [cell.top, cell.right, cell.bottom, cell.left],
+                    [Wall::Closed; 4]
+                );
+            }
+            assert_eq!(open_wall_count(&map), mask.available_count() - 1);
+        }
+    }
+
+    #[test]
+    fn weave_tunnels_never_lead_into_masked_cells() {
+        let mask =
+            Mask::from_text(".......\n.......\n..X.X..\n.......\n..X.X..\n.......\n").unwrap();
+        for seed in 0..20 {
+            let options = GenOptions {
+                mask: Some(mask.clone()),
+                weave: Some(1.0),
+                seed: Some(seed),
+                ..Default::default()
+            };
+            let map = generate(7, 6, MazeAlgorithm::RecursiveBacktracker, &options).unwrap();
+            for cell in map.cells.iter().flatten().filter(|cell| cell.masked) {
+                assert_eq!(
+                    [cell.top, cell.right, cell.bottom, cell.left],
+                    [Wall::Closed; 4],
+                    "seed {seed}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn disconnected_mask_is_rejected() {
+        let options = GenOptions {
+            mask: Some(Mask::from_text("..X..").unwrap()),
+            ..Default::default()
+        };
+        assert!(generate(5, 1, MazeAlgorithm::default(), &options).is_err());
+    }
+
+    #[test]
+    fn hunt_and_kill_generates_perfect_maze() {
+        assert_perfect(MazeAlgorithm::HuntAndKill);
+    }
+
+    #[test]
+    fn aldous_broder_generates_perfect_maze() {
+        assert_perfect(MazeAlgorithm::AldousBroder);
+    }
+
+    #[test]
+    fn growing_tree_generates_perfect_maze() {
+        assert_perfect(MazeAlgorithm::GrowingTree);
+        for selection_policy in [
+            SelectionPolicy::Oldest,
+            SelectionPolicy::Random,
+            SelectionPolicy::Mixed(0.5),
+        ] {
+            let options = GenOptions {
+                selection_policy,
+                ..Default::default()
+            };
+            let map = generate(8, 5, MazeAlgorithm::GrowingTree, &options).unwrap();
+            assert_eq!(reachable_cell_count(&map), 8 * 5);
+            assert_eq!(open_wall_count(&map), 8 * 5 - 1);
+        }
+    }
+
+    #[test]
+    fn sidewinder_generates_perfect_maze() {
+        assert_perfect(MazeAlgorithm::Sidewinder);
+    }
+
+    #[test]
+    fn binary_tree_generates_perfect_maze() {
+        assert_perfect(MazeAlgorithm::BinaryTree);
+    }
+
+    #[test]
+    fn selection_policy_parses_mixed_probability() {
+        assert_eq!(
+            "mixed:0.25".parse::<SelectionPolicy>().unwrap(),
+            SelectionPolicy::Mixed(0.25)
+        );
+        assert!("mixed:2".parse::<SelectionPolicy>().is_err());
+        assert!("newer".parse::<SelectionPolicy>().is_err());
+    }
+
+    #[test]
+    fn maze_algorithm_round_trips_through_str() {
+        for algorithm in MazeAlgorithm::ALL {
+            assert_eq!(
+                algorithm.to_string().parse::<MazeAlgorithm>().unwrap(),
+                algorithm
+            );
+        }
+        assert!("prim".parse::<MazeAlgorithm>().is_err());
+    }
 }
@@ -0,0 +1,49 @@
+use rand::{seq::SliceRandom, Rng};
+
+use super::{MazeMap, Progress, Visited};
+
+/// Random walk over the whole map, carving a passage whenever an unvisited cell is entered.
+/// Produces a uniform spanning tree, but may take a long time to hit the last unvisited cells.
+///
+/// https://en.wikipedia.org/wiki/Maze_generation_algorithm#Aldous-Broder_algorithm
+pub(super) fn carve<R: Rng>(
+    map: &mut MazeMap,
+    rng: &mut R,
+    progress: &mut Progress,
+) -> anyhow::Result<()> {
+    let mut visited = Visited::new(map);
+    let mut color = map.branch_color(rng);
+    let mut current = map.random_cell(rng)?;
+    let mut remaining = map.available_cells().count() - 1;
+    // Colors change whenever the walk has to cross already carved cells, so that each carved branch gets its own color.
+    let mut wandering = false;
+
+    visited.insert(&current);
+    map.set_cell_color(&current, color);
+
+    while remaining > 0 {
+        let next = *map
+            .get_neighbors(&current)
+            .choose(rng)
+            .ok_or(anyhow::anyhow!(
+                "The maze must at least have the dimensions 1x2"
+            ))?;
+
+        if !visited.contains(&next) {
+            if wandering {
+                color = map.branch_color(rng);
+                wandering = false;
+            }
+            map.connect_cells(&current, &next)?;
+            map.set_cell_color(&next, color);
+            visited.insert(&next);
+            progress.update(visited.count());
+            remaining -= 1;
+        } else {
+            wandering = true;
+        }
+        current = next;
+    }
+
+    Ok(())
+}
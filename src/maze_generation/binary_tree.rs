@@ -0,0 +1,37 @@
+use rand::Rng;
+
+use super::MazeMap;
+
+/// Every cell opens either its top or its right wall. Needs no bookkeeping at all,
+/// but leaves a straight corridor along the top row and the right column.
+///
+/// https://weblog.jamisbuck.org/2011/2/1/maze-generation-binary-tree-algorithm
+pub(super) fn carve<R: Rng>(map: &mut MazeMap, rng: &mut R) -> anyhow::Result<()> {
+    for y in 0..map.height {
+        let mut color = map.branch_color(rng);
+        for x in 0..map.width {
+            let cell = map.cells[y][x];
+            map.set_cell_color(&cell, color);
+
+            let can_go_top = y > 0;
+            let can_go_right = x + 1 < map.width;
+            let go_top = match (can_go_top, can_go_right) {
+                (true, true) => rng.gen_bool(0.5),
+                (true, false) => true,
+                (false, true) => false,
+                (false, false) => continue,
+            };
+
+            if go_top {
+                let top = map.cells[y - 1][x];
+                map.connect_cells(&cell, &top)?;
+                color = map.branch_color(rng);
+            } else {
+                let right = map.cells[y][x + 1];
+                map.connect_cells(&cell, &right)?;
+            }
+        }
+    }
+
+    Ok(())
+}
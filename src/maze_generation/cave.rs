@@ -0,0 +1,223 @@
+use std::collections::VecDeque;
+
+use anyhow::anyhow;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{BlockType, Map, Metadata};
+
+/// The terrain of floor blocks by their distance to the nearest wall, from right next to it inwards.
+/// Farther blocks are green.
+const TERRAIN_BY_WALL_DISTANCE: [BlockType; 3] =
+    [BlockType::Yellow, BlockType::Orange, BlockType::Blue];
+
+/// Options of [generate_cave]
+#[derive(Debug, Clone)]
+pub struct CaveOptions {
+    /// The fraction between 0 and 1 of blocks that start as walls
+    pub fill: f64,
+    /// How often the automaton smooths the random noise. More iterations give rounder caves
+    pub iterations: usize,
+    /// Generates the same cave every time for the same seed and options. A random cave if `None`
+    pub seed: Option<u64>,
+}
+
+impl Default for CaveOptions {
+    fn default() -> Self {
+        Self {
+            fill: 0.45,
+            iterations: 4,
+            seed: None,
+        }
+    }
+}
+
+/// Generates a cave of `width` x `height` blocks with a cellular automaton: every block starts as a wall
+/// with the fill probability, then each iteration turns blocks with at least five walls among their eight neighbors
+/// into walls, keeps walls with four and turns all others into floor. The border is always wall.
+///
+/// Separate caves are connected through the shortest tunnels afterwards, so that every floor block
+/// can be reached. The floor gets more expensive towards the walls: yellow right next to them,
+/// then orange and blue, and green farther inside.
+pub fn generate_cave(width: usize, height: usize, options: &CaveOptions) -> anyhow::Result<Map> {
+    if width < 3 || height < 3 {
+        return Err(anyhow!(
+            "The cave must at least have the dimensions 3x3, not {width}x{height}"
+        ));
+    }
+    if !(0.0..=1.0).contains(&options.fill) {
+        return Err(anyhow!("The fill must be between 0 and 1"));
+    }
+    let mut rng = match options.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let is_border = |x: usize, y: usize| x == 0 || y == 0 || x == width - 1 || y == height - 1;
+
+    let mut walls = (0..width * height)
+        .map(|index| is_border(index % width, index / width) || rng.gen_bool(options.fill))
+        .collect::<Vec<_>>();
+    for _ in 0..options.iterations {
+        walls = (0..width * height)
+            .map(|index| {
+                let (x, y) = (index % width, index / width);
+                if is_border(x, y) {
+                    return true;
+                }
+                let neighbors = wall_neighbors(&walls, width, x, y);
+                neighbors >= 5 || (walls[index] && neighbors >= 4)
+            })
+            .collect();
+    }
+    connect_caves(&mut walls, width, height);
+
+    let distances = wall_distances(&walls, width, height);
+    let mut map = Map::from_fn(width, height, |x, y| {
+        let index = y * width + x;
+        if walls[index] {
+            BlockType::Black
+        } else {
+            TERRAIN_BY_WALL_DISTANCE
+                .get(distances[index] - 1)
+                .copied()
+                .unwrap_or(BlockType::Green)
+        }
+    });
+    *map.metadata_mut() = Metadata::generated("cave", options.seed);
+    Ok(map)
+}
+
+/// The walls among the eight neighbors of a block that isn't on the border
+fn wall_neighbors(walls: &[bool], width: usize, x: usize, y: usize) -> usize {
+    (y - 1..=y + 1)
+        .flat_map(|ny| (x - 1..=x + 1).map(move |nx| (nx, ny)))
+        .filter(|&(nx, ny)| (nx, ny) != (x, y) && walls[ny * width + nx])
+        .count()
+}
+
+/// The indices of the blocks next to a block along the axes
+fn neighbors(width: usize, height: usize, index: usize) -> impl Iterator<Item = usize> {
+    let (x, y) = (index % width, index / width);
+    [
+        (x > 0).then(|| index - 1),
+        (x + 1 < width).then_some(index + 1),
+        (y > 0).then(|| index - width),
+        (y + 1 < height).then_some(index + width),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+/// Connects every cave to the first one by carving the shortest tunnel from the connected caves
+/// to the next one, until all are connected. Tunnels stay off the border. Without floor there is nothing to connect.
+fn connect_caves(walls: &mut [bool], width: usize, height: usize) {
+    let is_border = |index: usize| {
+        let (x, y) = (index % width, index / width);
+        x == 0 || y == 0 || x == width - 1 || y == height - 1
+    };
+    let Some(first) = walls.iter().position(|wall| !wall) else {
+        return;
+    };
+    let mut connected = vec![false; walls.len()];
+    let mut frontier = vec![first];
+    connected[first] = true;
+    loop {
+        // Everything the connected caves reach without tunneling
+        while let Some(index) = frontier.pop() {
+            for neighbor in neighbors(width, height, index) {
+                if !walls[neighbor] && !connected[neighbor] {
+                    connected[neighbor] = true;
+                    frontier.push(neighbor);
+                }
+            }
+        }
+
+        // The nearest floor block of another cave through the walls, and the tunnel there
+        let mut previous = vec![None; walls.len()];
+        let mut queue = (0..walls.len())
+            .filter(|index| connected[*index])
+            .collect::<VecDeque<_>>();
+        let mut reached = None;
+        'search: while let Some(index) = queue.pop_front() {
+            for neighbor in neighbors(width, height, index) {
+                if connected[neighbor] || previous[neighbor].is_some() || is_border(neighbor) {
+                    continue;
+                }
+                previous[neighbor] = Some(index);
+                if !walls[neighbor] {
+                    reached = Some(neighbor);
+                    break 'search;
+                }
+                queue.push_back(neighbor);
+            }
+        }
+        let Some(mut index) = reached else {
+            return;
+        };
+        while !connected[index] {
+            walls[index] = false;
+            connected[index] = true;
+            frontier.push(index);
+            index = previous[index].expect("The tunnel leads back to the connected caves");
+        }
+    }
+}
+
+/// The distance of every floor block to the nearest wall in steps along the axes, 0 for walls
+fn wall_distances(walls: &[bool], width: usize, height: usize) -> Vec<usize> {
+    let mut distances = vec![usize::MAX; walls.len()];
+    let mut queue = VecDeque::new();
+    for (index, _) in walls.iter().enumerate().filter(|(_, wall)| **wall) {
+        distances[index] = 0;
+        queue.push_back(index);
+    }
+    while let Some(index) = queue.pop_front() {
+        for neighbor in neighbors(width, height, index) {
+            if distances[neighbor] == usize::MAX {
+                distances[neighbor] = distances[index] + 1;
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caves_are_connected_and_walled_in() {
+        for seed in 0..5 {
+            let options = CaveOptions {
+                seed: Some(seed),
+                ..Default::default()
+            };
+            let map = generate_cave(60, 40, &options).unwrap();
+
+            assert_eq!(map.components().len(), 1);
+            assert!(map.walkable_blocks().count() > 60 * 40 / 4);
+            assert!(map
+                .walkable_blocks()
+                .all(|block| block.x > 0 && block.y > 0 && block.x < 59 && block.y < 39));
+            // Floor right next to a wall is the most expensive
+            assert!(map
+                .walkable_blocks()
+                .filter(|block| map.get_adjacent(block.x, block.y).len() < 4)
+                .all(|block| block.block_type() == BlockType::Yellow));
+        }
+    }
+
+    #[test]
+    fn the_seed_decides_the_cave() {
+        let options = CaveOptions {
+            seed: Some(9),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            generate_cave(30, 20, &options).unwrap().canonical_hash(),
+            generate_cave(30, 20, &options).unwrap().canonical_hash()
+        );
+        assert!(generate_cave(2, 20, &options).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+use std::{collections::VecDeque, fmt::Display, str::FromStr};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+use rand::{seq::SliceRandom, Rng};
+
+use super::{Color, MazeMap};
+
+/// The colors of the gradient, from the start outwards: the cheapest terrain first
+const GRADIENT: [Color; 4] = [Color::Green, Color::Blue, Color::Orange, Color::Yellow];
+
+/// Decides the colors of the cells, which become the terrain of the [Map](crate::Map) made from the maze.
+/// Parses from `per-branch`, `single:<color>`, `gradient`, `checkerboard[:<color>,<color>]` or `palette:<color>,..`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ColoringStrategy {
+    /// Every branch the generator carves gets a random color
+    #[default]
+    PerBranch,
+    /// Every cell gets the same color
+    Single(Color),
+    /// Bands of green, blue, orange and yellow by the distance from the first cell along the passages,
+    /// so the terrain gets more expensive the deeper the maze goes
+    Gradient,
+    /// Alternates between both colors like a checkerboard
+    Checkerboard(Color, Color),
+    /// Like [ColoringStrategy::PerBranch], but only with these colors
+    Palette(Vec<Color>),
+}
+
+impl ColoringStrategy {
+    /// The colors branches are colored with while carving
+    pub(super) fn branch_colors(&self) -> anyhow::Result<Vec<Color>> {
+        match self {
+            ColoringStrategy::Single(color) => Ok(vec![*color]),
+            ColoringStrategy::Palette(colors) if colors.is_empty() => {
+                Err(anyhow!("The palette must at least have one color"))
+            }
+            ColoringStrategy::Palette(colors) => Ok(colors.clone()),
+            _ => Ok(Color::ALL.to_vec()),
+        }
+    }
+
+    /// Recolors the carved maze for the strategies that don't depend on the branches
+    pub(super) fn paint(&self, map: &mut MazeMap) {
+        match self {
+            ColoringStrategy::Gradient => paint_gradient(map),
+            ColoringStrategy::Checkerboard(even, odd) => {
+                for cell in map.cells.iter_mut().flatten() {
+                    cell.color = if (cell.x + cell.y) % 2 == 0 {
+                        *even
+                    } else {
+                        *odd
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn paint_gradient(map: &mut MazeMap) {
+    let Some(first) = map.available_cells().next().map(|cell| (cell.x, cell.y)) else {
+        return;
+    };
+    let mut distances = vec![vec![None; map.width]; map.height];
+    distances[first.1][first.0] = Some(0);
+    let mut queue = VecDeque::from([first]);
+    while let Some((x, y)) = queue.pop_front() {
+        let distance = distances[y][x].unwrap_or(0);
+        for (nx, ny) in map.open_neighbors((x, y)) {
+            if distances[ny][nx].is_none() {
+                distances[ny][nx] = Some(distance + 1);
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+
+    let farthest = distances
+        .iter()
+        .flatten()
+        .flatten()
+        .max()
+        .copied()
+        .unwrap_or(0);
+    for cell in map.cells.iter_mut().flatten() {
+        if let Some(distance) = distances[cell.y][cell.x] {
+            cell.color = GRADIENT[distance * GRADIENT.len() / (farthest + 1)];
+        }
+    }
+}
+
+impl MazeMap {
+    /// A random color for a new branch
+    pub(super) fn branch_color<R: Rng>(&self, rng: &mut R) -> Color {
+        *self
+            .branch_colors
+            .choose(rng)
+            .expect("The strategy always gives at least one color")
+    }
+}
+
+impl Display for ColoringStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColoringStrategy::PerBranch => f.write_str("per-branch"),
+            ColoringStrategy::Single(color) => write!(f, "single:{color}"),
+            ColoringStrategy::Gradient => f.write_str("gradient"),
+            ColoringStrategy::Checkerboard(even, odd) => write!(f, "checkerboard:{even},{odd}"),
+            ColoringStrategy::Palette(colors) => write!(f, "palette:{}", colors.iter().join(",")),
+        }
+    }
+}
+
+impl FromStr for ColoringStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, colors) = match s.split_once(':') {
+            Some((name, colors)) => (
+                name,
+                colors
+                    .split(',')
+                    .map(|color| color.trim().parse::<Color>())
+                    .collect::<anyhow::Result<Vec<_>>>()?,
+            ),
+            None => (s, vec![]),
+        };
+        match (name, &colors[..]) {
+            ("per-branch", []) => Ok(ColoringStrategy::PerBranch),
+            ("single", [color]) => Ok(ColoringStrategy::Single(*color)),
+            ("gradient", []) => Ok(ColoringStrategy::Gradient),
+            ("checkerboard", []) => Ok(ColoringStrategy::Checkerboard(Color::Green, Color::Yellow)),
+            ("checkerboard", [even, odd]) => Ok(ColoringStrategy::Checkerboard(*even, *odd)),
+            ("palette", [_, ..]) => Ok(ColoringStrategy::Palette(colors)),
+            _ => Err(anyhow!(
+                "Unknown coloring '{s}'. Possible values: per-branch, single:<color>, gradient, checkerboard[:<color>,<color>], palette:<color>,.."
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate, GenOptions, MazeAlgorithm};
+
+    fn colors(coloring: ColoringStrategy, algorithm: MazeAlgorithm) -> Vec<Color> {
+        let options = GenOptions {
+            coloring,
+            ..Default::default()
+        };
+        let maze = generate(8, 6, algorithm, &options).unwrap();
+        maze.cells().map(|cell| cell.color).collect()
+    }
+
+    #[test]
+    fn colorings_only_use_their_colors() {
+        for algorithm in MazeAlgorithm::ALL {
+            let single = colors(ColoringStrategy::Single(Color::Blue), algorithm);
+            assert!(
+                single.iter().all(|color| *color == Color::Blue),
+                "{algorithm}"
+            );
+
+            let palette = colors(
+                ColoringStrategy::Palette(vec![Color::Green, Color::Orange]),
+                algorithm,
+            );
+            assert!(
+                palette
+                    .iter()
+                    .all(|color| [Color::Green, Color::Orange].contains(color)),
+                "{algorithm}"
+            );
+        }
+        let checkerboard = colors(
+            ColoringStrategy::Checkerboard(Color::Green, Color::Yellow),
+            MazeAlgorithm::default(),
+        );
+        assert_eq!(
+            checkerboard[..3],
+            [Color::Green, Color::Yellow, Color::Green]
+        );
+        // The second row starts with the other color
+        assert_eq!(checkerboard[8], Color::Yellow);
+    }
+
+    #[test]
+    fn gradient_gets_more_expensive_away_from_the_start() {
+        let gradient = colors(ColoringStrategy::Gradient, MazeAlgorithm::default());
+
+        assert_eq!(gradient[0], Color::Green);
+        for color in GRADIENT {
+            assert!(gradient.contains(&color));
+        }
+    }
+
+    #[test]
+    fn coloring_round_trips_through_str() {
+        for coloring in [
+            ColoringStrategy::PerBranch,
+            ColoringStrategy::Single(Color::Orange),
+            ColoringStrategy::Gradient,
+            ColoringStrategy::Checkerboard(Color::Blue, Color::Green),
+            ColoringStrategy::Palette(vec![Color::Yellow, Color::Blue, Color::Green]),
+        ] {
+            assert_eq!(
+                coloring.to_string().parse::<ColoringStrategy>().unwrap(),
+                coloring
+            );
+        }
+        assert_eq!(
+            "checkerboard".parse::<ColoringStrategy>().unwrap(),
+            ColoringStrategy::Checkerboard(Color::Green, Color::Yellow)
+        );
+        assert!("palette:".parse::<ColoringStrategy>().is_err());
+        assert!("single:red".parse::<ColoringStrategy>().is_err());
+        assert!("single:blue,green".parse::<ColoringStrategy>().is_err());
+        assert!("rainbow".parse::<ColoringStrategy>().is_err());
+    }
+}
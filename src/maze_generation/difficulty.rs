@@ -0,0 +1,166 @@
+use std::ops::RangeInclusive;
+
+use anyhow::anyhow;
+use rand::Rng;
+
+use crate::a_star_maze;
+
+use super::{generate, GenOptions, MazeAlgorithm, MazeMap};
+
+/// How hard a maze is to solve from its first to its last available cell, usually the top left and bottom right.
+/// See [MazeMap::analyze]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MazeAnalysis {
+    /// The cells on the cheapest path, both ends inclusive
+    pub solution_length: usize,
+    /// The cells with a single passage
+    pub dead_ends: usize,
+    /// The cells on the cheapest path where more than one passage leads on, so that a solver has to choose
+    pub decision_points: usize,
+}
+
+/// The bounds of the metrics of a [MazeAnalysis] a maze should have, see [generate_with_difficulty].
+/// Metrics without bounds can take any value
+#[derive(Debug, Clone, Default)]
+pub struct DifficultyRange {
+    pub solution_length: Option<RangeInclusive<usize>>,
+    pub dead_ends: Option<RangeInclusive<usize>>,
+    pub decision_points: Option<RangeInclusive<usize>>,
+}
+
+impl DifficultyRange {
+    pub fn contains(&self, analysis: &MazeAnalysis) -> bool {
+        let within = |range: &Option<RangeInclusive<usize>>, value: usize| {
+            range.as_ref().is_none_or(|range| range.contains(&value))
+        };
+        within(&self.solution_length, analysis.solution_length)
+            && within(&self.dead_ends, analysis.dead_ends)
+            && within(&self.decision_points, analysis.decision_points)
+    }
+}
+
+impl MazeMap {
+    /// Measures the solution length, dead ends and decision points of the maze.
+    /// Fails if the last available cell can't be reached from the first one.
+    pub fn analyze(&self) -> anyhow::Result<MazeAnalysis> {
+        let mut available = self.available_cells();
+        let start = available
+            .next()
+            .ok_or(anyhow!("The maze has no available cells"))?;
+        let destination = available.last().unwrap_or(start);
+        let solution = a_star_maze(self, (start.x, start.y), (destination.x, destination.y))?;
+        let path = solution.path();
+
+        let dead_ends = self
+            .available_cells()
+            .filter(|cell| self.open_neighbors((cell.x, cell.y)).len() == 1)
+            .count();
+        // Agents can't turn on a crossing, so it never offers a choice
+        let decision_points = path
+            .iter()
+            .enumerate()
+            .take(path.len() - 1)
+            .filter(|(index, (x, y))| {
+                let ways_back = usize::from(*index > 0);
+                self.cells[*y][*x].crossing.is_none()
+                    && self.open_neighbors((*x, *y)).len() > 1 + ways_back
+            })
+            .count();
+
+        Ok(MazeAnalysis {
+            solution_length: path.len(),
+            dead_ends,
+            decision_points,
+        })
+    }
+}
+
+/// Generates mazes until one lies within the target difficulty and returns it together with its analysis.
+/// Every attempt gets its own seed, drawn from the seed of the options, which the metadata of the maze records.
+/// Fails once `max_attempts` mazes missed the target.
+pub fn generate_with_difficulty(
+    width: usize,
+    height: usize,
+    algorithm: MazeAlgorithm,
+    options: &GenOptions,
+    target: &DifficultyRange,
+    max_attempts: usize,
+) -> anyhow::Result<(MazeMap, MazeAnalysis)> {
+    let mut rng = options.rng();
+    for _ in 0..max_attempts {
+        let attempt = GenOptions {
+            seed: Some(rng.gen()),
+            ..options.clone()
+        };
+        let maze = generate(width, height, algorithm, &attempt)?;
+        let analysis = maze.analyze()?;
+        if target.contains(&analysis) {
+            return Ok((maze, analysis));
+        }
+    }
+    Err(anyhow!(
+        "None of the {max_attempts} mazes matched the difficulty"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn analysis_counts_along_the_solution() {
+        // A comb: the solution runs along the top row and down to the last tooth, the other teeth are dead ends
+        let mut maze = MazeMap::new(4, 2);
+        for x in 0..4 {
+            let (top, bottom) = (maze.cells[0][x], maze.cells[1][x]);
+            if x < 3 {
+                let right = maze.cells[0][x + 1];
+                maze.connect_cells(&top, &right).unwrap();
+            }
+            maze.connect_cells(&top, &bottom).unwrap();
+        }
+
+        let analysis = maze.analyze().unwrap();
+
+        assert_eq!(
+            analysis,
+            MazeAnalysis {
+                solution_length: 5,
+                dead_ends: 4,
+                decision_points: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn generation_retries_until_the_difficulty_matches() {
+        let options = GenOptions {
+            seed: Some(2),
+            ..Default::default()
+        };
+        let target = DifficultyRange {
+            solution_length: Some(40..=100),
+            ..Default::default()
+        };
+
+        let (maze, analysis) =
+            generate_with_difficulty(10, 10, MazeAlgorithm::default(), &options, &target, 50)
+                .unwrap();
+
+        assert!(target.contains(&analysis));
+        assert_eq!(maze.analyze().unwrap(), analysis);
+        let impossible = DifficultyRange {
+            dead_ends: Some(0..=0),
+            ..Default::default()
+        };
+        assert!(generate_with_difficulty(
+            10,
+            10,
+            MazeAlgorithm::default(),
+            &options,
+            &impossible,
+            3
+        )
+        .is_err());
+    }
+}
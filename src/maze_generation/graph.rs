@@ -0,0 +1,176 @@
+use std::fmt::Write;
+
+use super::{Axis, MazeMap};
+
+impl MazeMap {
+    /// Every passage between two cells once, the cell with the smaller index `y * width + x` first
+    fn passages(&self) -> impl Iterator<Item = ((usize, usize), (usize, usize))> + '_ {
+        self.cells().flat_map(|cell| {
+            self.open_neighbors((cell.x, cell.y))
+                .into_iter()
+                .filter(move |(x, y)| (*y, *x) > (cell.y, cell.x))
+                .map(move |neighbor| ((cell.x, cell.y), neighbor))
+        })
+    }
+
+    /// Whether the maze is a spanning tree of its available cells: every cell is reachable
+    /// and there is exactly one way between any two of them, so no loops.
+    /// The two passages of a weave crossing count as separate, like in [count_loops](Self::count_loops).
+    pub fn is_perfect(&self) -> bool {
+        let (nodes, passages, components) = self.graph_size();
+        components == 1 && passages + 1 == nodes
+    }
+
+    /// The number of independent loops, i.e. how many passages could be closed again
+    /// without disconnecting any cells. 0 for a perfect maze.
+    /// The passage crossing over a weave crossing doesn't connect to the one below it, so tunnels don't form loops.
+    pub fn count_loops(&self) -> usize {
+        let (nodes, passages, components) = self.graph_size();
+        passages + components - nodes
+    }
+
+    /// The number of nodes, passages and connected components of the maze as graph.
+    /// Available cells are nodes, weave crossings are two of them: one per axis.
+    fn graph_size(&self) -> (usize, usize, usize) {
+        let cell_count = self.width * self.height;
+        // Index of the node of a cell that a passage along the axis enters.
+        // The vertical passage of a crossing gets its own node after all cells.
+        let node = |(x, y): (usize, usize), axis: Axis| {
+            let index = y * self.width + x;
+            match self.cells[y][x].crossing {
+                Some(_) if axis == Axis::Vertical => cell_count + index,
+                _ => index,
+            }
+        };
+        let mut parents = (0..2 * cell_count).collect::<Vec<_>>();
+        fn root(parents: &mut [usize], mut node: usize) -> usize {
+            while parents[node] != node {
+                parents[node] = parents[parents[node]];
+                node = parents[node];
+            }
+            node
+        }
+
+        let mut nodes = 0;
+        for cell in self.cells().filter(|cell| !cell.masked) {
+            nodes += 1 + usize::from(cell.crossing.is_some());
+        }
+        let (mut passages, mut components) = (0, nodes);
+        for (from, to) in self.passages() {
+            let axis = if from.1 == to.1 {
+                Axis::Horizontal
+            } else {
+                Axis::Vertical
+            };
+            passages += 1;
+            let (a, b) = (
+                root(&mut parents, node(from, axis)),
+                root(&mut parents, node(to, axis)),
+            );
+            if a != b {
+                parents[a] = b;
+                components -= 1;
+            }
+        }
+        (nodes, passages, components)
+    }
+
+    /// The maze as undirected Graphviz graph. Nodes are named `c<x>_<y>` and pinned to their position,
+    /// so `neato -n` draws the maze as grid. Masked cells are left out.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph maze {\n    node [shape=square, label=\"\"];\n");
+        for cell in self.cells().filter(|cell| !cell.masked) {
+            // Graphviz' y axis points upwards
+            let _ = writeln!(
+                dot,
+                "    c{x}_{y} [pos=\"{},{}\"];",
+                cell.x * 36,
+                (self.height - 1 - cell.y) * 36,
+                x = cell.x,
+                y = cell.y
+            );
+        }
+        for ((from_x, from_y), (to_x, to_y)) in self.passages() {
+            let _ = writeln!(dot, "    c{from_x}_{from_y} -- c{to_x}_{to_y};");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The cell graph as petgraph graph with one node per cell, weighted with its coordinates.
+    /// The node index of a cell is `y * width + x`, masked cells have no edges.
+    #[cfg(feature = "petgraph")]
+    pub fn to_graph(&self) -> petgraph::graph::UnGraph<(usize, usize), ()> {
+        let mut graph = petgraph::graph::UnGraph::new_undirected();
+        for cell in self.cells() {
+            graph.add_node((cell.x, cell.y));
+        }
+        let index = |(x, y): (usize, usize)| petgraph::graph::NodeIndex::new(y * self.width + x);
+        for (from, to) in self.passages() {
+            graph.add_edge(index(from), index(to), ());
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{generate, GenOptions, MazeAlgorithm, MazeMap};
+
+    #[test]
+    fn dot_contains_every_passage_once() {
+        let maze = generate(4, 3, MazeAlgorithm::HuntAndKill, &GenOptions::default()).unwrap();
+
+        let dot = maze.to_dot();
+
+        assert!(dot.starts_with("graph maze {"));
+        assert_eq!(dot.matches(" -- ").count(), 4 * 3 - 1);
+        assert_eq!(dot.matches("[pos=").count(), 4 * 3);
+    }
+
+    #[test]
+    fn generated_mazes_are_perfect_until_loops_are_added() {
+        for algorithm in MazeAlgorithm::ALL {
+            let maze = generate(6, 5, algorithm, &GenOptions::default()).unwrap();
+            assert!(maze.is_perfect(), "{algorithm}");
+            assert_eq!(maze.count_loops(), 0, "{algorithm}");
+        }
+        let weave = GenOptions {
+            weave: Some(1.0),
+            ..Default::default()
+        };
+        assert!(generate(8, 8, MazeAlgorithm::default(), &weave)
+            .unwrap()
+            .is_perfect());
+
+        let loops = GenOptions {
+            loop_prob: Some(1.0),
+            seed: Some(3),
+            ..Default::default()
+        };
+        let maze = generate(6, 5, MazeAlgorithm::default(), &loops).unwrap();
+        let passages = maze.passages().count();
+        assert!(!maze.is_perfect());
+        assert_eq!(maze.count_loops(), passages - (6 * 5 - 1));
+    }
+
+    #[test]
+    fn unconnected_cells_are_not_perfect() {
+        let maze = MazeMap::new(2, 1);
+
+        assert!(!maze.is_perfect());
+        assert_eq!(maze.count_loops(), 0);
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn petgraph_of_a_perfect_maze_is_a_tree() {
+        let maze = generate(5, 5, MazeAlgorithm::default(), &GenOptions::default()).unwrap();
+
+        let graph = maze.to_graph();
+
+        assert_eq!(graph.node_count(), 25);
+        assert_eq!(graph.edge_count(), 24);
+        assert_eq!(petgraph::algo::connected_components(&graph), 1);
+    }
+}
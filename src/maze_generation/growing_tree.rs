@@ -0,0 +1,46 @@
+use rand::{seq::SliceRandom, Rng};
+
+use super::{Cell, MazeMap, Progress, SelectionPolicy, Visited};
+
+/// Keeps a list of active cells and repeatedly carves from one of them, picked by the `policy`.
+/// Always picking the newest cell behaves like the backtracker, always picking a random one like Prim's algorithm.
+///
+/// https://weblog.jamisbuck.org/2011/1/27/maze-generation-growing-tree-algorithm
+pub(super) fn carve<R: Rng>(
+    map: &mut MazeMap,
+    policy: SelectionPolicy,
+    rng: &mut R,
+    progress: &mut Progress,
+) -> anyhow::Result<()> {
+    let mut visited = Visited::new(map);
+    let mut color = map.branch_color(rng);
+    let first_cell = map.random_cell(rng)?;
+    let mut active = vec![first_cell];
+
+    visited.insert(&first_cell);
+    map.set_cell_color(&first_cell, color);
+
+    while !active.is_empty() {
+        let index = policy.select(active.len(), rng);
+        let cell = active[index];
+
+        let unvisited_neighbors: Vec<Cell> = map
+            .get_neighbors(&cell)
+            .into_iter()
+            .filter(|neighbor| !visited.contains(neighbor))
+            .collect();
+
+        if let Some(next) = unvisited_neighbors.choose(rng) {
+            map.connect_cells(&cell, next)?;
+            map.set_cell_color(next, color);
+            visited.insert(next);
+            progress.update(visited.count());
+            active.push(*next);
+        } else {
+            active.remove(index);
+            color = map.branch_color(rng);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use rand::{seq::SliceRandom, Rng};
+
+use super::{Cell, MazeMap, Progress, Visited};
+
+/// Random walk until stuck, then scan the map row by row for an unvisited cell next to the carved area ("hunt").
+///
+/// https://weblog.jamisbuck.org/2011/1/24/maze-generation-hunt-and-kill-algorithm
+pub(super) fn carve<R: Rng>(
+    map: &mut MazeMap,
+    rng: &mut R,
+    progress: &mut Progress,
+) -> anyhow::Result<()> {
+    let mut visited = Visited::new(map);
+    let mut color = map.branch_color(rng);
+    let mut current = Some(map.random_cell(rng)?);
+
+    while let Some(cell) = current {
+        visited.insert(&cell);
+        progress.update(visited.count());
+        map.set_cell_color(&cell, color);
+
+        let unvisited_neighbors: Vec<Cell> = map
+            .get_neighbors(&cell)
+            .into_iter()
+            .filter(|neighbor| !visited.contains(neighbor))
+            .collect();
+
+        current = if let Some(next) = unvisited_neighbors.choose(rng) {
+            map.connect_cells(&cell, next)?;
+            Some(*next)
+        } else {
+            color = map.branch_color(rng);
+            hunt(map, &visited, rng)?
+        };
+    }
+
+    Ok(())
+}
+
+/// Finds the first unvisited cell bordering a visited one and connects it to the carved area.
+fn hunt<R: Rng>(map: &mut MazeMap, visited: &Visited, rng: &mut R) -> anyhow::Result<Option<Cell>> {
+    let candidate = map.cells.iter().flatten().find_map(|cell| {
+        if cell.masked || visited.contains(cell) {
+            return None;
+        }
+        let visited_neighbors: Vec<Cell> = map
+            .get_neighbors(cell)
+            .into_iter()
+            .filter(|neighbor| visited.contains(neighbor))
+            .collect();
+        visited_neighbors
+            .choose(rng)
+            .map(|neighbor| (*cell, *neighbor))
+    });
+
+    if let Some((cell, neighbor)) = candidate {
+        map.connect_cells(&cell, &neighbor)?;
+        return Ok(Some(cell));
+    }
+    Ok(None)
+}
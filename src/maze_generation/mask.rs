@@ -0,0 +1,110 @@
+use anyhow::anyhow;
+#[cfg(feature = "image")]
+use image::DynamicImage;
+use itertools::Itertools;
+
+/// Marks which cells of a maze may be carved. Masked out cells stay solid walls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mask {
+    width: usize,
+    height: usize,
+    available: Vec<bool>,
+}
+
+impl Mask {
+    /// A mask where every cell is available
+    pub fn full(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            available: vec![true; width * height],
+        }
+    }
+
+    /// Every pixel is one cell. Dark pixels are masked out, light pixels are available.
+    #[cfg(feature = "image")]
+    pub fn from_image(img: &DynamicImage) -> Self {
+        let luma = img.to_luma8();
+        Self {
+            width: luma.width() as usize,
+            height: luma.height() as usize,
+            available: luma.pixels().map(|pixel| pixel.0[0] >= 128).collect_vec(),
+        }
+    }
+
+    /// Every character is one cell. `X` and `#` are masked out, every other character is available.
+    pub fn from_text(text: &str) -> anyhow::Result<Self> {
+        let rows = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.trim_end().chars().collect_vec())
+            .collect_vec();
+        let width = rows
+            .first()
+            .ok_or(anyhow!("The mask must at least have a height of 1"))?
+            .len();
+        if rows.iter().any(|row| row.len() != width) {
+            return Err(anyhow!("All rows of the mask must have the same length"));
+        }
+        Ok(Self {
+            width,
+            height: rows.len(),
+            available: rows
+                .iter()
+                .flatten()
+                .map(|c| *c != 'X' && *c != '#')
+                .collect_vec(),
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_available(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.available[y * self.width + x]
+    }
+
+    pub fn set_available(&mut self, x: usize, y: usize, available: bool) {
+        if x < self.width && y < self.height {
+            self.available[y * self.width + x] = available;
+        }
+    }
+
+    pub fn available_count(&self) -> usize {
+        self.available
+            .iter()
+            .filter(|available| **available)
+            .count()
+    }
+
+    /// Whether all available cells can reach each other. Otherwise no single maze can cover them.
+    pub fn is_connected(&self) -> bool {
+        let Some(first) = self.available.iter().position(|available| *available) else {
+            return false;
+        };
+        let mut seen = vec![false; self.available.len()];
+        let mut stack = vec![(first % self.width, first / self.width)];
+        let mut count = 0;
+        while let Some((x, y)) = stack.pop() {
+            if !self.is_available(x, y) || seen[y * self.width + x] {
+                continue;
+            }
+            seen[y * self.width + x] = true;
+            count += 1;
+            stack.push((x + 1, y));
+            stack.push((x, y + 1));
+            if x > 0 {
+                stack.push((x - 1, y));
+            }
+            if y > 0 {
+                stack.push((x, y - 1));
+            }
+        }
+        count == self.available_count()
+    }
+}
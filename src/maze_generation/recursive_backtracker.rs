@@ -0,0 +1,105 @@
+use rand::{seq::SliceRandom, Rng};
+
+use super::{Cell, GenOptions, MazeMap, Progress, Visited, Wall};
+
+enum Move {
+    /// Carve into a neighboring cell
+    Step(Cell),
+    /// Carve under the first cell into the second one
+    Tunnel(Cell, Cell),
+}
+
+/// https://en.wikipedia.org/wiki/Maze_generation_algorithm#Iterative_implementation_(with_stack)
+pub(super) fn carve<R: Rng>(
+    map: &mut MazeMap,
+    options: &GenOptions,
+    rng: &mut R,
+    progress: &mut Progress,
+) -> anyhow::Result<()> {
+    let first_cell = *map.available_cells().next().ok_or(anyhow::anyhow!(
+        "The maze must at least have one available cell"
+    ))?;
+    let mut stack = vec![first_cell];
+    let mut visited = Visited::new(map);
+    visited.insert(&first_cell);
+    let mut color = map.branch_color(rng);
+    let weave_prob = options.weave.unwrap_or(0.0);
+
+    while let Some(current_cell) = stack.pop() {
+        let mut moves: Vec<Move> = map
+            .get_neighbors(&current_cell)
+            .into_iter()
+            .filter(|cell| !visited.contains(cell))
+            .map(Move::Step)
+            .collect();
+        if weave_prob > 0.0 {
+            moves.extend(
+                tunnel_candidates(map, &current_cell, &visited)
+                    .into_iter()
+                    .filter(|_| rng.gen_bool(weave_prob))
+                    .map(|(under, beyond)| Move::Tunnel(under, beyond)),
+            );
+        }
+
+        if let Some(chosen_move) = moves.choose(rng) {
+            stack.push(current_cell);
+            let chosen_cell = match chosen_move {
+                Move::Step(cell) => {
+                    map.connect_cells(&current_cell, cell)?;
+                    *cell
+                }
+                Move::Tunnel(under, beyond) => {
+                    map.tunnel_under(&current_cell, under, beyond)?;
+                    *beyond
+                }
+            };
+            if let Some(cell) = map.get_cell_mut(current_cell.x, current_cell.y) {
+                cell.set_color(color);
+            }
+            if visited.insert(&chosen_cell) {
+                progress.update(visited.count());
+            }
+            stack.push(chosen_cell);
+        } else {
+            color = map.branch_color(rng);
+        }
+    }
+
+    Ok(())
+}
+
+/// Unvisited cells two steps away that can be reached by tunneling under a straight corridor, together with the tunneled cell.
+/// Cells outside of the mask are never tunneled into, just like they are never stepped into.
+fn tunnel_candidates(map: &MazeMap, cell: &Cell, visited: &Visited) -> Vec<(Cell, Cell)> {
+    [(1, 0), (-1, 0), (0, 1), (0, -1)]
+        .into_iter()
+        .filter_map(|(dx, dy)| {
+            let under = map.get_cell(
+                cell.x.checked_add_signed(dx)?,
+                cell.y.checked_add_signed(dy)?,
+            )?;
+            let beyond = map.get_cell(
+                cell.x.checked_add_signed(2 * dx)?,
+                cell.y.checked_add_signed(2 * dy)?,
+            )?;
+            // The corridor must run perpendicular to the tunnel
+            let is_perpendicular_corridor = if dx != 0 {
+                under.top == Wall::Open
+                    && under.bottom == Wall::Open
+                    && under.left == Wall::Closed
+                    && under.right == Wall::Closed
+            } else {
+                under.left == Wall::Open
+                    && under.right == Wall::Open
+                    && under.top == Wall::Closed
+                    && under.bottom == Wall::Closed
+            };
+            (under.crossing.is_none()
+                && is_perpendicular_corridor
+                && visited.contains(under)
+                && !beyond.masked
+                && !visited.contains(beyond))
+            .then_some((*under, *beyond))
+        })
+        .collect()
+}
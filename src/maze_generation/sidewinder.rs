@@ -0,0 +1,36 @@
+use rand::Rng;
+
+use super::MazeMap;
+
+/// Works row by row: carves runs of cells to the right and connects each run to the row above
+/// through one randomly chosen cell of the run. The top row is a single corridor.
+///
+/// https://weblog.jamisbuck.org/2011/2/3/maze-generation-sidewinder-algorithm
+pub(super) fn carve<R: Rng>(map: &mut MazeMap, rng: &mut R) -> anyhow::Result<()> {
+    for y in 0..map.height {
+        let mut run_start = 0;
+        let mut color = map.branch_color(rng);
+        for x in 0..map.width {
+            let cell = map.cells[y][x];
+            map.set_cell_color(&cell, color);
+
+            let at_right_edge = x + 1 == map.width;
+            let close_run = at_right_edge || (y > 0 && rng.gen_bool(0.5));
+
+            if close_run {
+                if y > 0 {
+                    let chosen = map.cells[y][rng.gen_range(run_start..=x)];
+                    let top = map.cells[y - 1][chosen.x];
+                    map.connect_cells(&chosen, &top)?;
+                }
+                run_start = x + 1;
+                color = map.branch_color(rng);
+            } else {
+                let right = map.cells[y][x + 1];
+                map.connect_cells(&cell, &right)?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,300 @@
+use std::{collections::VecDeque, fmt::Display, str::FromStr};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+use rand::{seq::IteratorRandom, Rng};
+
+use super::{Cell, MazeMap, Wall};
+
+/// Makes the passages of a maze reflected or rotated copies of each other, e.g. for fair arenas in competitive games.
+/// Parses from `none`, `mirror`, `mirror-both`, `rotate` or `rotate-quarter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Symmetry {
+    #[default]
+    None,
+    /// The right half mirrors the left half
+    Mirror,
+    /// The left and right as well as the top and bottom halves mirror each other
+    MirrorBoth,
+    /// The maze looks the same after half a turn
+    Rotate,
+    /// The maze looks the same after every quarter turn. Only for square mazes
+    RotateQuarter,
+}
+
+/// Maps a cell of the maze to its symmetric counterpart
+#[derive(Debug, Clone, Copy)]
+enum Transform {
+    FlipX,
+    FlipY,
+    HalfTurn,
+    QuarterTurn,
+    ThreeQuarterTurn,
+}
+
+impl Transform {
+    fn apply(&self, (x, y): (usize, usize), width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Transform::FlipX => (width - 1 - x, y),
+            Transform::FlipY => (x, height - 1 - y),
+            Transform::HalfTurn => (width - 1 - x, height - 1 - y),
+            Transform::QuarterTurn => (width - 1 - y, x),
+            Transform::ThreeQuarterTurn => (y, height - 1 - x),
+        }
+    }
+
+    /// Whether horizontal passages become vertical ones
+    fn swaps_axes(&self) -> bool {
+        matches!(self, Transform::QuarterTurn | Transform::ThreeQuarterTurn)
+    }
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 5] = [
+        Symmetry::None,
+        Symmetry::Mirror,
+        Symmetry::MirrorBoth,
+        Symmetry::Rotate,
+        Symmetry::RotateQuarter,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Symmetry::None => "none",
+            Symmetry::Mirror => "mirror",
+            Symmetry::MirrorBoth => "mirror-both",
+            Symmetry::Rotate => "rotate",
+            Symmetry::RotateQuarter => "rotate-quarter",
+        }
+    }
+
+    /// The transforms besides the identity that map the maze onto itself
+    fn transforms(&self) -> &'static [Transform] {
+        match self {
+            Symmetry::None => &[],
+            Symmetry::Mirror => &[Transform::FlipX],
+            Symmetry::MirrorBoth => &[Transform::FlipX, Transform::FlipY, Transform::HalfTurn],
+            Symmetry::Rotate => &[Transform::HalfTurn],
+            Symmetry::RotateQuarter => &[
+                Transform::QuarterTurn,
+                Transform::HalfTurn,
+                Transform::ThreeQuarterTurn,
+            ],
+        }
+    }
+
+    pub(super) fn check_dimensions(&self, width: usize, height: usize) -> anyhow::Result<()> {
+        if *self == Symmetry::RotateQuarter && width != height {
+            return Err(anyhow!(
+                "The {self} symmetry needs a square maze, not {width}x{height}"
+            ));
+        }
+        Ok(())
+    }
+
+    /// The cell and its symmetric counterparts, which may repeat for cells on an axis
+    fn images(&self, cell: (usize, usize), width: usize, height: usize) -> Vec<(usize, usize)> {
+        std::iter::once(cell)
+            .chain(
+                self.transforms()
+                    .iter()
+                    .map(|transform| transform.apply(cell, width, height)),
+            )
+            .collect()
+    }
+
+    /// The cell that stands for all symmetric counterparts of a cell: the first of them row by row
+    fn representative(&self, cell: (usize, usize), width: usize, height: usize) -> (usize, usize) {
+        self.images(cell, width, height)
+            .into_iter()
+            .min_by_key(|(x, y)| (*y, *x))
+            .expect("A cell is its own image")
+    }
+}
+
+impl Display for Symmetry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Symmetry::ALL
+            .into_iter()
+            .find(|symmetry| symmetry.name() == s)
+            .ok_or(anyhow!(
+                "Unknown symmetry '{s}'. Possible values: {}",
+                Symmetry::ALL.iter().join(", ")
+            ))
+    }
+}
+
+impl MazeMap {
+    /// Masks out every cell but the representatives, so that the generator only carves one copy
+    pub(super) fn mask_symmetric_copies(&mut self) {
+        let (width, height, symmetry) = (self.width, self.height, self.symmetry);
+        for cell in self.cells.iter_mut().flatten() {
+            cell.masked =
+                symmetry.representative((cell.x, cell.y), width, height) != (cell.x, cell.y);
+        }
+    }
+
+    /// Connects both cells and their symmetric counterparts, returns how many walls were opened
+    pub(super) fn connect_symmetric(&mut self, a: &Cell, b: &Cell) -> anyhow::Result<usize> {
+        let images = self
+            .symmetry
+            .images((a.x, a.y), self.width, self.height)
+            .into_iter()
+            .zip(self.symmetry.images((b.x, b.y), self.width, self.height));
+        let mut opened = 0;
+        for ((ax, ay), (bx, by)) in images {
+            let (a, b) = (self.cells[ay][ax], self.cells[by][bx]);
+            if a.wall_to(&b)? == Wall::Closed {
+                self.connect_cells(&a, &b)?;
+                opened += 1;
+            }
+        }
+        Ok(opened)
+    }
+
+    /// Copies the passages the generator carved into the representatives to their counterparts
+    /// and connects the copies through random symmetric passages
+    pub(super) fn mirror_passages<R: Rng>(&mut self, rng: &mut R) -> anyhow::Result<()> {
+        let representatives = self.available_cells().copied().collect_vec();
+        for cell in self.cells.iter_mut().flatten() {
+            cell.masked = false;
+        }
+        for cell in &representatives {
+            for neighbor in self.get_neighbors(cell) {
+                if cell.wall_to(&neighbor)? == Wall::Open {
+                    self.connect_symmetric(cell, &neighbor)?;
+                }
+            }
+            for transform in self.symmetry.transforms() {
+                let (x, y) = transform.apply((cell.x, cell.y), self.width, self.height);
+                self.cells[y][x].crossing = cell.crossing.map(|axis| {
+                    if transform.swaps_axes() {
+                        axis.perpendicular()
+                    } else {
+                        axis
+                    }
+                });
+            }
+        }
+
+        loop {
+            let components = self.components();
+            let between_components = self
+                .cells()
+                .flat_map(|cell| {
+                    [(cell.x + 1, cell.y), (cell.x, cell.y + 1)]
+                        .into_iter()
+                        .filter_map(|(x, y)| self.get_cell(x, y))
+                        .filter(|neighbor| {
+                            components[cell.y][cell.x] != components[neighbor.y][neighbor.x]
+                        })
+                        .map(|neighbor| (*cell, *neighbor))
+                })
+                .choose(rng);
+            let Some((a, b)) = between_components else {
+                return Ok(());
+            };
+            self.connect_symmetric(&a, &b)?;
+        }
+    }
+
+    /// Gives the counterparts of the representatives their colors
+    pub(super) fn mirror_colors(&mut self) {
+        let (width, height, symmetry) = (self.width, self.height, self.symmetry);
+        for y in 0..height {
+            for x in 0..width {
+                let (rx, ry) = symmetry.representative((x, y), width, height);
+                self.cells[y][x].color = self.cells[ry][rx].color;
+            }
+        }
+    }
+
+    /// The number of the connected part of the maze every cell belongs to
+    fn components(&self) -> Vec<Vec<usize>> {
+        let mut components = vec![vec![usize::MAX; self.width]; self.height];
+        let mut count = 0;
+        for cell in self.cells() {
+            if components[cell.y][cell.x] != usize::MAX {
+                continue;
+            }
+            components[cell.y][cell.x] = count;
+            let mut queue = VecDeque::from([(cell.x, cell.y)]);
+            while let Some(current) = queue.pop_front() {
+                for (x, y) in self.open_neighbors(current) {
+                    if components[y][x] == usize::MAX {
+                        components[y][x] = count;
+                        queue.push_back((x, y));
+                    }
+                }
+            }
+            count += 1;
+        }
+        components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate, GenOptions, Map, MazeAlgorithm};
+
+    fn symmetric_map(width: usize, height: usize, symmetry: Symmetry) -> Map {
+        let options = GenOptions {
+            symmetry,
+            seed: Some(7),
+            ..Default::default()
+        };
+        Map::from(generate(width, height, MazeAlgorithm::HuntAndKill, &options).unwrap())
+    }
+
+    fn is_walkable(map: &Map, (x, y): (usize, usize)) -> bool {
+        map.get_block(x, y).unwrap().is_walkable()
+    }
+
+    #[test]
+    fn copies_match_and_stay_connected() {
+        for (width, height, symmetry) in [
+            (6, 5, Symmetry::Mirror),
+            (7, 4, Symmetry::Mirror),
+            (5, 6, Symmetry::MirrorBoth),
+            (6, 7, Symmetry::Rotate),
+            (5, 5, Symmetry::RotateQuarter),
+        ] {
+            let map = symmetric_map(width, height, symmetry);
+            let (width, height) = (map.width(), map.height());
+            let blocks = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)));
+            for block in blocks {
+                for image in symmetry.images(block, width, height) {
+                    assert_eq!(
+                        is_walkable(&map, block),
+                        is_walkable(&map, image),
+                        "{symmetry} at {block:?}"
+                    );
+                }
+            }
+            assert_eq!(map.components().len(), 1, "{symmetry}");
+        }
+    }
+
+    #[test]
+    fn unsupported_combinations_are_rejected() {
+        let options = GenOptions {
+            symmetry: Symmetry::RotateQuarter,
+            ..Default::default()
+        };
+        assert!(generate(4, 5, MazeAlgorithm::RecursiveBacktracker, &options).is_err());
+        assert!(generate(4, 4, MazeAlgorithm::Sidewinder, &options).is_err());
+        assert_eq!(
+            "mirror-both".parse::<Symmetry>().unwrap(),
+            Symmetry::MirrorBoth
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::anyhow;
+use itertools::Itertools;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::Map;
+
+use super::{generate, GenOptions, MazeAlgorithm, MazeMap, Wall};
+
+/// The labels of the ends of the path in [Map]s made from a [Labyrinth]
+const ENTRANCE: &str = "entrance";
+const CENTER: &str = "center";
+
+/// A unicursal labyrinth: a single winding path without branches through every cell,
+/// from an entrance on the border to the center. See [generate_unicursal]
+#[derive(Debug)]
+pub struct Labyrinth {
+    maze: MazeMap,
+    path: Vec<(usize, usize)>,
+}
+
+impl Labyrinth {
+    pub fn maze(&self) -> &MazeMap {
+        &self.maze
+    }
+
+    pub fn into_maze(self) -> MazeMap {
+        self.maze
+    }
+
+    /// Every cell in the order the path visits them, from the entrance to the center
+    pub fn path(&self) -> &[(usize, usize)] {
+        &self.path
+    }
+
+    /// The cell on the border where the path starts
+    pub fn entrance(&self) -> (usize, usize) {
+        self.path[0]
+    }
+
+    /// The cell in the middle of the maze where the path ends
+    pub fn center(&self) -> (usize, usize) {
+        self.path[self.path.len() - 1]
+    }
+}
+
+/// The map of the maze with the labels `entrance` and `center` on the ends of the path
+impl From<Labyrinth> for Map {
+    fn from(value: Labyrinth) -> Self {
+        let ends = [(ENTRANCE, value.entrance()), (CENTER, value.center())];
+        let mut map = Map::from(value.maze);
+        for (name, (x, y)) in ends {
+            let block = map
+                .get_block(x * 2 + 1, y * 2 + 1)
+                .expect("The cells of the maze are part of the map");
+            map.set_label(name, block)
+                .expect("The labels are valid names");
+        }
+        map
+    }
+}
+
+/// Generates a unicursal labyrinth of `width` x `height` cells, which must both be even.
+///
+/// A perfect maze of half the size is generated with the algorithm first. Splitting each of its cells
+/// into four and its passages into two lanes turns it into a single loop along its walls,
+/// which is cut in the center. The other end of the path is then moved to the border by reversing
+/// parts of the path. Loops and weaving are ignored, masks are not supported.
+pub fn generate_unicursal(
+    width: usize,
+    height: usize,
+    algorithm: MazeAlgorithm,
+    options: &GenOptions,
+) -> anyhow::Result<Labyrinth> {
+    if width < 2 || height < 2 || !width.is_multiple_of(2) || !height.is_multiple_of(2) {
+        return Err(anyhow!(
+            "A unicursal labyrinth needs an even width and height of at least 2 cells, not {width}x{height}"
+        ));
+    }
+    if options.mask.is_some() {
+        return Err(anyhow!("Unicursal labyrinths do not support masks"));
+    }
+    let perfect_options = GenOptions {
+        loop_prob: None,
+        weave: None,
+        ..options.clone()
+    };
+    let perfect = generate(width / 2, height / 2, algorithm, &perfect_options)?;
+    let mut rng = options.rng();
+
+    let lanes = lanes(&perfect);
+    let center = (width / 2, height / 2);
+    let mut path = vec![center];
+    let mut previous = None;
+    loop {
+        let current = path[path.len() - 1];
+        let next = lanes[current.1 * width + current.0]
+            .into_iter()
+            .find(|cell| Some(*cell) != previous)
+            .expect("Every cell has two lanes");
+        if next == center {
+            break;
+        }
+        previous = Some(current);
+        path.push(next);
+    }
+    if path.len() != width * height {
+        return Err(anyhow!(
+            "The lanes of the maze don't form a single loop, it must be perfect"
+        ));
+    }
+
+    move_end_to_border(&mut path, width, height, &mut rng)?;
+    path.reverse();
+
+    let mut maze = MazeMap::new(width, height);
+    for cell in maze.cells.iter_mut().flatten() {
+        cell.color = perfect.cells[cell.y / 2][cell.x / 2].color;
+    }
+    for (&(ax, ay), &(bx, by)) in path.iter().tuple_windows() {
+        let (a, b) = (maze.cells[ay][ax], maze.cells[by][bx]);
+        maze.connect_cells(&a, &b)?;
+    }
+    options.coloring.paint(&mut maze);
+    maze.metadata = perfect.metadata;
+
+    Ok(Labyrinth { maze, path })
+}
+
+/// The two neighbors of every cell of the doubled maze along the loop, indexed by `y * width + x`
+fn lanes(perfect: &MazeMap) -> Vec<[(usize, usize); 2]> {
+    let width = perfect.width * 2;
+    let mut neighbors = vec![vec![]; width * perfect.height * 2];
+    let mut link = |(ax, ay): (usize, usize), (bx, by): (usize, usize)| {
+        neighbors[ay * width + ax].push((bx, by));
+        neighbors[by * width + bx].push((ax, ay));
+    };
+    for cell in perfect.cells() {
+        let (left, top) = (cell.x * 2, cell.y * 2);
+        let (right, bottom) = (left + 1, top + 1);
+        // A closed wall turns the lanes, an open one lets them pass into the neighbor
+        if cell.top == Wall::Closed {
+            link((left, top), (right, top));
+        }
+        if cell.left == Wall::Closed {
+            link((left, top), (left, bottom));
+        }
+        if cell.right == Wall::Closed {
+            link((right, top), (right, bottom));
+        } else {
+            link((right, top), (right + 1, top));
+            link((right, bottom), (right + 1, bottom));
+        }
+        if cell.bottom == Wall::Closed {
+            link((left, bottom), (right, bottom));
+        } else {
+            link((left, bottom), (left, bottom + 1));
+            link((right, bottom), (right, bottom + 1));
+        }
+    }
+    neighbors
+        .into_iter()
+        .map(|lanes| {
+            lanes
+                .try_into()
+                .expect("The lanes of a perfect maze pass every cell once")
+        })
+        .collect()
+}
+
+/// Moves the last cell of the Hamiltonian path to the border of the maze by backbiting: the end connects
+/// to a neighbor on the path, and the part of the path after that neighbor is reversed.
+/// The first cell stays where it is.
+fn move_end_to_border<R: Rng>(
+    path: &mut [(usize, usize)],
+    width: usize,
+    height: usize,
+    rng: &mut R,
+) -> anyhow::Result<()> {
+    let border_distance = |(x, y): (usize, usize)| x.min(y).min(width - 1 - x).min(height - 1 - y);
+    let mut position = vec![0; width * height];
+    for (index, (x, y)) in path.iter().enumerate() {
+        position[y * width + x] = index;
+    }
+    let last = path.len() - 1;
+
+    for _ in 0..width * height * 4 {
+        let (x, y) = path[last];
+        if border_distance((x, y)) == 0 {
+            return Ok(());
+        }
+        // The end is inside the maze and has four neighbors, one of them before it on the path
+        let new_ends = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+            .into_iter()
+            .map(|(x, y)| position[y * width + x] + 1)
+            .filter(|new_end| *new_end < last)
+            .collect_vec();
+        // Half of the moves are random, so that the end can't get stuck going back and forth
+        let new_end = if rng.gen_bool(0.5) {
+            *new_ends.choose(rng).expect("The end has neighbors")
+        } else {
+            *new_ends
+                .iter()
+                .min_by_key(|new_end| border_distance(path[**new_end]))
+                .expect("The end has neighbors")
+        };
+        path[new_end..].reverse();
+        for (index, (x, y)) in path.iter().enumerate().skip(new_end) {
+            position[y * width + x] = index;
+        }
+    }
+    Err(anyhow!("The path could not be led to the border"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star;
+
+    #[test]
+    fn the_path_visits_every_cell_without_branches() {
+        for algorithm in [
+            MazeAlgorithm::RecursiveBacktracker,
+            MazeAlgorithm::AldousBroder,
+        ] {
+            let options = GenOptions {
+                seed: Some(3),
+                ..Default::default()
+            };
+            let labyrinth = generate_unicursal(12, 8, algorithm, &options).unwrap();
+
+            let path = labyrinth.path();
+            assert_eq!(path.iter().unique().count(), 12 * 8);
+            let (x, y) = labyrinth.entrance();
+            assert!(x == 0 || y == 0 || x == 11 || y == 7);
+            assert_eq!(labyrinth.center(), (6, 4));
+            let maze = labyrinth.maze();
+            for (index, cell) in path.iter().enumerate() {
+                let expected = usize::from(index > 0) + usize::from(index + 1 < path.len());
+                assert_eq!(maze.open_neighbors(*cell).len(), expected);
+            }
+
+            let map = Map::from(labyrinth);
+            let (entrance, center) = (map.label("entrance").unwrap(), map.label("center").unwrap());
+            let solution = a_star(&map, entrance, center).unwrap();
+            assert_eq!(solution.path().len(), 2 * 12 * 8 - 1);
+        }
+    }
+
+    #[test]
+    fn odd_sizes_are_rejected() {
+        let options = GenOptions::default();
+
+        assert!(generate_unicursal(5, 4, MazeAlgorithm::default(), &options).is_err());
+        assert!(generate_unicursal(0, 4, MazeAlgorithm::default(), &options).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::anyhow;
+use itertools::Itertools;
+
+use crate::{
+    search::{a_star_search, SearchSpace},
+    Axis, Block, BlockType, CarveEvent, MazeError, MazeMap,
+};
+
+/// A path through the cells of a [MazeMap], see [a_star_maze]
+#[derive(Debug, Clone)]
+pub struct MazeSolution {
+    path: Vec<(usize, usize)>,
+    cost: u32,
+}
+
+impl MazeSolution {
+    /// The cells from start to destination, both inclusive
+    pub fn path(&self) -> &[(usize, usize)] {
+        &self.path
+    }
+
+    /// The same cost as the path through the blocks of the [Map](crate::Map) made from the maze
+    pub fn cost(&self) -> u32 {
+        self.cost
+    }
+
+    /// The blocks of the [Map](crate::Map) made from the maze that the path leads through,
+    /// including the passages between the cells
+    pub fn to_blocks(&self) -> Vec<(usize, usize)> {
+        let first = self.path.first().map(|(x, y)| (x * 2 + 1, y * 2 + 1));
+        let passages = self.path.iter().tuple_windows().flat_map(|(from, to)| {
+            CarveEvent {
+                from: *from,
+                to: *to,
+                under: None,
+            }
+            .blocks()
+            .into_iter()
+            .skip(1)
+        });
+        first.into_iter().chain(passages).collect()
+    }
+}
+
+/// A cell and, on a weave crossing, the axis of the passage the agent moves along
+type CellState = ((usize, usize), Option<Axis>);
+
+struct MazeSpace<'a> {
+    maze: &'a MazeMap,
+    destination: (usize, usize),
+}
+
+impl MazeSpace<'_> {
+    /// The cost of entering the block of a cell, the same as in the [Map](crate::Map)
+    fn cell_speed(&self, (x, y): (usize, usize)) -> u32 {
+        let cell = self.maze.cells[y][x];
+        let block_type = match cell.crossing {
+            Some(Axis::Horizontal) => BlockType::BridgeHorizontal,
+            Some(Axis::Vertical) => BlockType::BridgeVertical,
+            None => cell.color.into(),
+        };
+        speed(block_type)
+    }
+}
+
+impl SearchSpace for MazeSpace<'_> {
+    type State = CellState;
+
+    fn successors(&self, &(position, layer): &CellState) -> Vec<(CellState, u32)> {
+        self.maze
+            .open_neighbors(position)
+            .into_iter()
+            .filter_map(|next| {
+                let axis = if next.1 == position.1 {
+                    Axis::Horizontal
+                } else {
+                    Axis::Vertical
+                };
+                // Agents can't turn on a crossing
+                if layer.is_some_and(|layer| layer != axis) {
+                    return None;
+                }
+                // The passage between two cells has the color of the right or the lower one
+                let owner = position.max(next);
+                let passage = speed(self.maze.cells[owner.1][owner.0].color.into());
+                let next_layer = self.maze.cells[next.1][next.0].crossing.map(|_| axis);
+                Some(((next, next_layer), passage + self.cell_speed(next)))
+            })
+            .collect_vec()
+    }
+
+    fn heuristic(&self, &((x, y), _): &CellState) -> u32 {
+        // Every step leads through a passage and a cell, which cost at least 1 each
+        2 * (x.abs_diff(self.destination.0) + y.abs_diff(self.destination.1)) as u32
+    }
+
+    fn is_goal(&self, &(position, _): &CellState) -> bool {
+        position == self.destination
+    }
+}
+
+fn speed(block_type: BlockType) -> u32 {
+    Block::new(0, 0, block_type).speed() as u32
+}
+
+/// Like [a_star](crate::a_star) on the [Map](crate::Map) made from the maze, but searches the cells directly,
+/// which are a quarter of the blocks. Costs are the same as on the map.
+pub fn a_star_maze(
+    maze: &MazeMap,
+    start: (usize, usize),
+    destination: (usize, usize),
+) -> anyhow::Result<MazeSolution> {
+    let is_open = |(x, y): (usize, usize)| maze.get_cell(x, y).is_some_and(|cell| !cell.masked);
+    if !is_open(start) || !is_open(destination) {
+        return Err(anyhow!("Please specify coordinates within the map"));
+    }
+    let space = MazeSpace { maze, destination };
+    let path = a_star_search(&space, (start, None)).ok_or(MazeError::NoPath)?;
+    Ok(MazeSolution {
+        path: path
+            .states
+            .into_iter()
+            .map(|(position, _)| position)
+            .collect(),
+        cost: path.cost,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{a_star, generate, GenOptions, Map, MazeAlgorithm};
+
+    #[test]
+    fn costs_match_the_block_map() {
+        for weave in [None, Some(1.0)] {
+            let options = GenOptions {
+                loop_prob: Some(0.3),
+                weave,
+                seed: Some(7),
+                ..Default::default()
+            };
+            let maze = generate(9, 7, MazeAlgorithm::RecursiveBacktracker, &options).unwrap();
+            let map =
+                Map::from(generate(9, 7, MazeAlgorithm::RecursiveBacktracker, &options).unwrap());
+
+            let solution = a_star_maze(&maze, (0, 0), (8, 6)).unwrap();
+            let expected = a_star(
+                &map,
+                map.get_block(1, 1).unwrap(),
+                map.get_block(17, 13).unwrap(),
+            )
+            .unwrap();
+
+            assert_eq!(solution.cost(), expected.cost());
+            let blocks = solution.to_blocks();
+            assert_eq!(blocks.len(), 2 * solution.path().len() - 1);
+            for (from, to) in blocks.iter().tuple_windows() {
+                assert_eq!(from.0.abs_diff(to.0) + from.1.abs_diff(to.1), 1);
+                assert!(map.get_block(to.0, to.1).unwrap().is_walkable());
+            }
+        }
+    }
+}
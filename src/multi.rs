@@ -0,0 +1,367 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+    fmt::Display,
+};
+
+use anyhow::anyhow;
+#[cfg(feature = "image")]
+use image::{Rgba, RgbaImage};
+use itertools::Itertools;
+
+#[cfg(feature = "image")]
+use crate::map::{IMAGE_BLOCK_WIDTH, IMAGE_BORDER_WIDTH};
+use crate::{
+    search::{a_star_search, SearchSpace},
+    Block, DistanceBound, Map, MazeError, State,
+};
+
+/// The colors used to draw the routes of the agents, repeated if there are more agents
+#[cfg(feature = "image")]
+const AGENT_COLORS: [[u8; 4]; 6] = [
+    [230, 25, 75, 255],
+    [60, 180, 75, 255],
+    [0, 130, 200, 255],
+    [245, 130, 48, 255],
+    [145, 30, 180, 255],
+    [70, 240, 240, 255],
+];
+
+/// The routes of several agents that never occupy the same block at the same time
+/// and never swap places with each other.
+pub struct MultiSolution {
+    /// The block of every agent at each time step. Waiting repeats the block.
+    routes: Vec<Vec<Block>>,
+    costs: Vec<u32>,
+    map: Map,
+}
+
+impl MultiSolution {
+    pub fn routes(&self) -> &[Vec<Block>] {
+        &self.routes
+    }
+
+    pub fn costs(&self) -> &[u32] {
+        &self.costs
+    }
+
+    /// The sum of the costs of all agents
+    pub fn cost(&self) -> u32 {
+        self.costs.iter().sum()
+    }
+
+    /// The number of time steps until the last agent arrived
+    pub fn makespan(&self) -> usize {
+        self.routes
+            .iter()
+            .map(|route| route.len() - 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Renders the map with the route of each agent in its own color.
+    #[cfg(feature = "image")]
+    pub fn to_image(&self) -> Option<RgbaImage> {
+        let mut image = self.map.clone().to_image()?;
+        let stride = (IMAGE_BLOCK_WIDTH + IMAGE_BORDER_WIDTH) as u32;
+        for (route, color) in self.routes.iter().zip(AGENT_COLORS.iter().cycle()) {
+            for block in route {
+                let (left, top) = (block.x as u32 * stride, block.y as u32 * stride);
+                for y in top..top + IMAGE_BLOCK_WIDTH as u32 {
+                    for x in left..left + IMAGE_BLOCK_WIDTH as u32 {
+                        image.put_pixel(x, y, Rgba(*color));
+                    }
+                }
+            }
+        }
+        Some(image)
+    }
+}
+
+impl Display for MultiSolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut map = self.map.clone();
+        for route in &self.routes {
+            map.enter_solution(route);
+        }
+        f.write_str(&map.to_string_with_locations(&[], false))?;
+        for (i, (route, cost)) in self.routes.iter().zip(&self.costs).enumerate() {
+            writeln!(
+                f,
+                "Agent {i} arrives after {} steps at a cost of {cost}",
+                route.len() - 1
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The blocks and moves a single agent must avoid
+#[derive(Debug, Clone, Default)]
+struct Constraints {
+    blocks: HashSet<(Block, usize)>,
+    /// A move from the first to the second block starting at the time step
+    moves: HashSet<(Block, Block, usize)>,
+}
+
+impl Constraints {
+    fn allows(&self, from: Block, to: Block, time: usize) -> bool {
+        !self.blocks.contains(&(to, time + 1)) && !self.moves.contains(&(from, to, time))
+    }
+
+    /// The agent may only stay at its goal after the last time it is forbidden there
+    fn earliest_arrival(&self, goal: Block) -> usize {
+        self.blocks
+            .iter()
+            .filter(|(block, _)| *block == goal)
+            .map(|(_, time)| time + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn latest_time(&self) -> usize {
+        let block_times = self.blocks.iter().map(|(_, time)| *time);
+        let move_times = self.moves.iter().map(|(_, _, time)| *time);
+        block_times.chain(move_times).max().unwrap_or(0)
+    }
+}
+
+/// Moving and waiting on a [Map] while respecting the [Constraints] of one agent
+struct SpaceTime<'a> {
+    map: &'a Map,
+    constraints: &'a Constraints,
+    destination: Block,
+    bound: DistanceBound,
+    earliest_arrival: usize,
+    horizon: usize,
+}
+
+impl SearchSpace for SpaceTime<'_> {
+    type State = (State, usize);
+
+    fn successors(&self, (state, time): &(State, usize)) -> Vec<((State, usize), u32)> {
+        if *time >= self.horizon {
+            return vec![];
+        }
+        let location = state.location;
+        let moves = self
+            .map
+            .get_reachable(location.x, location.y)
+            .into_iter()
+            .filter(|block| state.can_move_to(*block))
+            .map(|block| ((state.moved_to(block), time + 1), block.speed() as u32));
+        // Waiting costs as much as a step on the cheapest terrain
+        let wait = std::iter::once(((*state, time + 1), 1));
+
+        moves
+            .chain(wait)
+            .filter(|((next, _), _)| self.constraints.allows(location, next.location, *time))
+            .collect_vec()
+    }
+
+    fn heuristic(&self, (state, _): &(State, usize)) -> u32 {
+        self.bound.estimate(state.location)
+    }
+
+    fn is_goal(&self, (state, time): &(State, usize)) -> bool {
+        state.location == self.destination && *time >= self.earliest_arrival
+    }
+}
+
+/// Plans the cheapest route of a single agent that respects its constraints
+fn plan_route(
+    map: &Map,
+    (start, goal): (Block, Block),
+    constraints: &Constraints,
+) -> Option<(Vec<Block>, u32)> {
+    let space = SpaceTime {
+        map,
+        constraints,
+        destination: goal,
+        bound: DistanceBound::new(map, goal),
+        earliest_arrival: constraints.earliest_arrival(goal),
+        horizon: map.width() * map.height() + constraints.latest_time() + 1,
+    };
+    let path = a_star_search(&space, (State::new(start), 0))?;
+    let route = path
+        .states
+        .into_iter()
+        .map(|(state, _)| state.location)
+        .collect_vec();
+    Some((route, path.cost))
+}
+
+enum Conflict {
+    /// Both agents are on the block at the time step
+    Block(usize, usize, Block, usize),
+    /// The agents swap their blocks starting at the time step
+    Swap(usize, usize, Block, Block, usize),
+}
+
+/// The block of the agent at the time step. Agents stay at their goal once they arrived.
+fn position(route: &[Block], time: usize) -> Block {
+    route[time.min(route.len() - 1)]
+}
+
+fn first_conflict(routes: &[Vec<Block>]) -> Option<Conflict> {
+    let makespan = routes.iter().map(|route| route.len()).max().unwrap_or(0);
+    for time in 0..makespan {
+        for ((a, route_a), (b, route_b)) in routes.iter().enumerate().tuple_combinations() {
+            let (here_a, here_b) = (position(route_a, time), position(route_b, time));
+            if here_a == here_b {
+                return Some(Conflict::Block(a, b, here_a, time));
+            }
+            if position(route_a, time + 1) == here_b && position(route_b, time + 1) == here_a {
+                return Some(Conflict::Swap(a, b, here_a, here_b, time));
+            }
+        }
+    }
+    None
+}
+
+/// A node of the constraint tree searched by [solve_multi]
+#[derive(Clone)]
+struct ConstraintNode {
+    constraints: Vec<Constraints>,
+    routes: Vec<Vec<Block>>,
+    costs: Vec<u32>,
+}
+
+/// The maximum number of constraint tree nodes explored before [solve_multi] gives up
+const MAX_CONSTRAINT_NODES: usize = 10_000;
+
+/// Finds routes for several agents given as `(start, goal)` pairs that don't collide with each other.
+/// Uses conflict-based search: every agent is planned on its own, and whenever two routes collide
+/// the search branches into forbidding the conflict for one agent or the other.
+/// The sum of the costs of all routes is minimal.
+pub fn solve_multi(map: &Map, agents: &[(Block, Block)]) -> anyhow::Result<MultiSolution> {
+    if !agents.iter().map(|(start, _)| start).all_unique() {
+        return Err(anyhow!("Two agents can't start on the same block"));
+    }
+    if !agents.iter().map(|(_, goal)| goal).all_unique() {
+        return Err(anyhow!("Two agents can't share a goal"));
+    }
+
+    let constraints = vec![Constraints::default(); agents.len()];
+    let (routes, costs) = agents
+        .iter()
+        .zip(&constraints)
+        .map(|(agent, constraints)| plan_route(map, *agent, constraints))
+        .collect::<Option<(Vec<_>, Vec<_>)>>()
+        .ok_or(MazeError::NoPath)?;
+
+    let mut nodes = vec![ConstraintNode {
+        constraints,
+        routes,
+        costs,
+    }];
+    let mut open = BinaryHeap::from([Reverse((nodes[0].costs.iter().sum::<u32>(), 0))]);
+
+    while let Some(Reverse((_, index))) = open.pop() {
+        if nodes.len() > MAX_CONSTRAINT_NODES {
+            break;
+        }
+        let node = nodes[index].clone();
+        let branches = match first_conflict(&node.routes) {
+            None => {
+                return Ok(MultiSolution {
+                    routes: node.routes,
+                    costs: node.costs,
+                    map: map.clone(),
+                })
+            }
+            Some(Conflict::Block(a, b, block, time)) => {
+                vec![
+                    (a, Some((block, time)), None),
+                    (b, Some((block, time)), None),
+                ]
+            }
+            Some(Conflict::Swap(a, b, here_a, here_b, time)) => vec![
+                (a, None, Some((here_a, here_b, time))),
+                (b, None, Some((here_b, here_a, time))),
+            ],
+        };
+
+        for (agent, block, movement) in branches {
+            let mut child = node.clone();
+            child.constraints[agent].blocks.extend(block);
+            child.constraints[agent].moves.extend(movement);
+            // A block conflict at the start can't be resolved by this agent
+            if block.is_some_and(|(block, time)| time == 0 && block == agents[agent].0) {
+                continue;
+            }
+            if let Some((route, cost)) = plan_route(map, agents[agent], &child.constraints[agent]) {
+                child.routes[agent] = route;
+                child.costs[agent] = cost;
+                open.push(Reverse((child.costs.iter().sum(), nodes.len())));
+                nodes.push(child);
+            }
+        }
+    }
+
+    Err(anyhow!("There is no collision free path"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_collision_free(solution: &MultiSolution) {
+        let routes = solution.routes();
+        for time in 0..=solution.makespan() {
+            for (a, b) in routes.iter().tuple_combinations() {
+                assert_ne!(position(a, time), position(b, time));
+                assert!(
+                    position(a, time) != position(b, time + 1)
+                        || position(b, time) != position(a, time + 1)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn agents_in_a_corridor_with_a_niche_let_each_other_pass() {
+        let map = Map::from_rows(&["#.###", ".....", "#####"]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+
+        let solution = solve_multi(
+            &map,
+            &[(block(0, 1), block(4, 1)), (block(4, 1), block(0, 1))],
+        )
+        .unwrap();
+
+        assert_collision_free(&solution);
+        assert_eq!(solution.routes()[0].last(), Some(&block(4, 1)));
+        assert_eq!(solution.routes()[1].last(), Some(&block(0, 1)));
+    }
+
+    #[test]
+    fn agents_crossing_paths_do_not_collide() {
+        let map = Map::from_rows(&[".....", ".....", ".....", ".....", "....."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+
+        let solution = solve_multi(
+            &map,
+            &[
+                (block(0, 2), block(4, 2)),
+                (block(2, 0), block(2, 4)),
+                (block(4, 0), block(0, 4)),
+            ],
+        )
+        .unwrap();
+
+        assert_collision_free(&solution);
+    }
+
+    #[test]
+    fn a_corridor_without_room_to_pass_has_no_solution() {
+        let map = Map::from_rows(&["...."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+
+        assert!(solve_multi(
+            &map,
+            &[(block(0, 0), block(3, 0)), (block(3, 0), block(0, 0))]
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use itertools::Itertools;
+
+use crate::{Block, BlockType, Map};
+
+/// A wall that would shorten the path or connect the start and the destination if it were opened,
+/// see [suggest_openings]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Opening {
+    pub x: usize,
+    pub y: usize,
+    /// The estimated cost of the cheapest path with the wall turned into a green block
+    pub cost: u32,
+    /// How much cheaper the path gets, `None` if there is no path without the opening
+    pub saving: Option<u32>,
+}
+
+/// Suggests up to `k` single walls to open, e.g. where to put a door to make the destination reachable.
+/// The openings that create a path come first, then those that save the most.
+///
+/// Needs only two distance fields, one from the start and one from the destination:
+/// the cost through a wall is the cost to a block next to it, one for the opened wall
+/// and the cost from a block on its other side. Walking back from the destination ignores that
+/// one-way blocks and keys only work in one direction, so the costs are estimates on such maps.
+pub fn suggest_openings(
+    map: &Map,
+    start_block: Block,
+    destination_block: Block,
+    k: usize,
+) -> anyhow::Result<Vec<Opening>> {
+    let from_start = map.distance_field(start_block)?;
+    let from_destination = map.distance_field(destination_block)?;
+    let current = from_start.cost(destination_block.x, destination_block.y);
+    let arrival = destination_block.speed() as u32;
+
+    let openings = map
+        .iter_blocks()
+        .filter(|block| block.block_type() == BlockType::Black && block.x < map.width())
+        .filter_map(|wall| {
+            let opened = Block::new(wall.x, wall.y, BlockType::Green);
+            let neighbors = map.get_adjacent(wall.x, wall.y);
+            let to_wall = neighbors
+                .iter()
+                .filter(|block| block.allows_step_to(opened))
+                .filter_map(|block| from_start.cost(block.x, block.y))
+                .min()?;
+            // The destination field counts entering the block next to the wall, which the path does too,
+            // but not entering the destination itself
+            let from_wall = neighbors
+                .iter()
+                .filter(|block| opened.allows_step_to(**block))
+                .filter_map(|block| from_destination.cost(block.x, block.y))
+                .min()?;
+            let cost = to_wall + opened.speed() as u32 + from_wall + arrival;
+            match current {
+                Some(current) if cost >= current => None,
+                _ => Some(Opening {
+                    x: wall.x,
+                    y: wall.y,
+                    cost,
+                    saving: current.map(|current| current - cost),
+                }),
+            }
+        })
+        .sorted_by_key(|opening| (opening.cost, opening.y, opening.x))
+        .take(k)
+        .collect();
+    Ok(openings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star;
+
+    #[test]
+    fn openings_connect_and_shorten() {
+        let map = Map::from_rows(&["..#..", "..#..", "..#..", "....."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+
+        let openings = suggest_openings(&map, block(0, 0), block(4, 0), 2).unwrap();
+
+        assert_eq!(a_star(&map, block(0, 0), block(4, 0)).unwrap().cost(), 10);
+        assert_eq!(
+            openings,
+            [
+                Opening {
+                    x: 2,
+                    y: 0,
+                    cost: 4,
+                    saving: Some(6)
+                },
+                Opening {
+                    x: 2,
+                    y: 1,
+                    cost: 6,
+                    saving: Some(4)
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn openings_create_paths() {
+        let map = Map::from_rows(&["..#..", "..#..", "..##."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+
+        let openings = suggest_openings(&map, block(0, 0), block(4, 0), 5).unwrap();
+
+        assert_eq!(openings.len(), 2);
+        assert_eq!((openings[0].x, openings[0].y), (2, 0));
+        assert_eq!(openings[0].cost, 4);
+        assert!(openings.iter().all(|opening| opening.saving.is_none()));
+        // Opening the walls at 2 2 or 3 2 alone doesn't connect both sides
+        assert_eq!((openings[1].x, openings[1].y), (2, 1));
+    }
+}
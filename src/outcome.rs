@@ -0,0 +1,126 @@
+use itertools::Itertools;
+
+use crate::{Block, Map, MazeError, SearchOptions, Solution, SolveAlgorithm};
+
+/// The result of [SolveAlgorithm::solve_or_approach]
+pub enum SolveOutcome {
+    Solved(Solution),
+    /// The destination can't be reached. The path leads to the reachable block nearest to it instead.
+    Unreachable {
+        best_effort: Solution,
+        /// The number of steps up, down, left or right from the end of the path to the destination,
+        /// ignoring walls
+        remaining_distance: u32,
+    },
+}
+
+impl SolveOutcome {
+    /// The solution or the path that gets closest to the destination
+    pub fn solution(&self) -> &Solution {
+        match self {
+            SolveOutcome::Solved(solution) => solution,
+            SolveOutcome::Unreachable { best_effort, .. } => best_effort,
+        }
+    }
+
+    pub fn into_solution(self) -> Solution {
+        match self {
+            SolveOutcome::Solved(solution) => solution,
+            SolveOutcome::Unreachable { best_effort, .. } => best_effort,
+        }
+    }
+
+    pub fn is_solved(&self) -> bool {
+        matches!(self, SolveOutcome::Solved(_))
+    }
+}
+
+impl SolveAlgorithm {
+    /// Like [solve](Self::solve), but if there is no path, returns the path to the reachable block
+    /// nearest to the destination, so that an agent can still move towards it.
+    /// Of several blocks equally near to the destination the cheapest to get to is picked.
+    pub fn solve_or_approach(
+        self,
+        map: &Map,
+        start_block: Block,
+        destination_block: Block,
+        options: &SearchOptions,
+    ) -> anyhow::Result<SolveOutcome> {
+        let error = match self.solve(map, start_block, destination_block, options) {
+            Ok(solution) => return Ok(SolveOutcome::Solved(solution)),
+            Err(error) if error.downcast_ref() == Some(&MazeError::NoPath) => error,
+            Err(error) => return Err(error),
+        };
+        let distance = |x: usize, y: usize| {
+            (x.abs_diff(destination_block.x) + y.abs_diff(destination_block.y)) as u32
+        };
+        let field = map.distance_field_with(start_block, options)?;
+        let candidates = map
+            .walkable_blocks()
+            .filter_map(|block| Some((*block, field.cost(block.x, block.y)?)))
+            .sorted_by_key(|(block, cost)| (distance(block.x, block.y), *cost));
+        // The nearest block may be beyond the budget, then the next nearest one is tried
+        for (nearest, _) in candidates {
+            match self.solve(map, start_block, nearest, options) {
+                Ok(best_effort) => {
+                    return Ok(SolveOutcome::Unreachable {
+                        best_effort,
+                        remaining_distance: distance(nearest.x, nearest.y),
+                    })
+                }
+                Err(error)
+                    if matches!(
+                        error.downcast_ref(),
+                        Some(MazeError::PathNotFoundWithinBudget { .. })
+                    ) => {}
+                Err(error) => return Err(error),
+            }
+        }
+        Err(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walled_off_destinations_are_approached() {
+        let map = Map::from_rows(&["...#.", "...#.", ".b.#."]);
+        let block = |x, y| map.get_block(x, y).unwrap();
+
+        let outcome = SolveAlgorithm::AStar
+            .solve_or_approach(&map, block(0, 0), block(4, 2), &SearchOptions::default())
+            .unwrap();
+
+        let SolveOutcome::Unreachable {
+            best_effort,
+            remaining_distance,
+        } = &outcome
+        else {
+            panic!("The destination is walled off");
+        };
+        assert_eq!(*remaining_distance, 2);
+        assert_eq!(best_effort.path().last(), Some(&block(2, 2)));
+        assert!(!outcome.is_solved());
+
+        let outcome = SolveAlgorithm::AStar
+            .solve_or_approach(&map, block(0, 0), block(2, 2), &SearchOptions::default())
+            .unwrap();
+        assert!(outcome.is_solved());
+        assert_eq!(outcome.solution().cost(), 4);
+
+        // The avoided blocks are neither on the way nor a place to approach
+        let options = SearchOptions::default().avoid("2,0,2,1".parse().unwrap());
+        let outcome = SolveAlgorithm::AStar
+            .solve_or_approach(&map, block(0, 0), block(4, 0), &options)
+            .unwrap();
+        assert!(!outcome.is_solved());
+        assert!(outcome
+            .solution()
+            .path()
+            .iter()
+            .all(|block| block.x != 2 || block.y == 2));
+        assert_eq!(outcome.solution().path().last(), Some(&block(1, 0)));
+    }
+}
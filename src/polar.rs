@@ -0,0 +1,448 @@
+use std::{f64::consts::TAU, fmt::Write as _};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    map::{BlockType, IMAGE_BLOCK_WIDTH, IMAGE_BORDER_WIDTH},
+    maze_generation::{loop_count, Color},
+    search::{a_star_search, SearchSpace},
+    Block, GenOptions, MazeError, Palette,
+};
+
+/// A cell of a [PolarMap]: the `index`-th cell (clockwise, starting at 12 o'clock) of ring number `ring`.
+/// Ring 0 is the single cell in the center.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PolarCoord {
+    pub ring: usize,
+    pub index: usize,
+}
+
+impl PolarCoord {
+    pub fn new(ring: usize, index: usize) -> Self {
+        Self { ring, index }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PolarCell {
+    pub coord: PolarCoord,
+    pub color: Color,
+    /// The neighbors this cell has an open passage to
+    links: Vec<PolarCoord>,
+}
+
+impl PolarCell {
+    fn new(coord: PolarCoord) -> Self {
+        Self {
+            coord,
+            color: Color::Green,
+            links: vec![],
+        }
+    }
+
+    pub fn links(&self) -> &[PolarCoord] {
+        &self.links
+    }
+
+    pub fn is_linked(&self, other: PolarCoord) -> bool {
+        self.links.contains(&other)
+    }
+
+    fn speed(&self) -> u32 {
+        Block::new(0, 0, BlockType::from(self.color)).speed() as u32
+    }
+}
+
+/// A circular (theta) maze of concentric rings. Outer rings are subdivided into more cells so that all cells have roughly the same size.
+#[derive(Debug, Clone)]
+pub struct PolarMap {
+    rings: Vec<Vec<PolarCell>>,
+}
+
+impl PolarMap {
+    pub fn new(ring_count: usize) -> Self {
+        let mut rings: Vec<Vec<PolarCell>> = vec![];
+        for ring in 0..ring_count {
+            let cell_count = if ring == 0 {
+                1
+            } else {
+                let previous_count = rings[ring - 1].len();
+                let circumference = TAU * ring as f64;
+                // Rings have a height of 1, so the ratio tells how often a cell of the previous ring fits along this one
+                let ratio = (circumference / previous_count as f64).round().max(1.0) as usize;
+                previous_count * ratio
+            };
+            rings.push(
+                (0..cell_count)
+                    .map(|index| PolarCell::new(PolarCoord::new(ring, index)))
+                    .collect_vec(),
+            );
+        }
+        Self { rings }
+    }
+
+    pub fn ring_count(&self) -> usize {
+        self.rings.len()
+    }
+
+    pub fn cells_in_ring(&self, ring: usize) -> usize {
+        self.rings.get(ring).map_or(0, Vec::len)
+    }
+
+    pub fn get_cell(&self, coord: PolarCoord) -> Option<&PolarCell> {
+        self.rings.get(coord.ring)?.get(coord.index)
+    }
+
+    fn get_cell_mut(&mut self, coord: PolarCoord) -> Option<&mut PolarCell> {
+        self.rings.get_mut(coord.ring)?.get_mut(coord.index)
+    }
+
+    pub fn iter_cells(&self) -> impl Iterator<Item = &PolarCell> {
+        self.rings.iter().flatten()
+    }
+
+    /// The clockwise neighbor in the same ring
+    pub fn clockwise(&self, coord: PolarCoord) -> Option<PolarCoord> {
+        let count = self.cells_in_ring(coord.ring);
+        (count > 1).then(|| PolarCoord::new(coord.ring, (coord.index + 1) % count))
+    }
+
+    /// The counterclockwise neighbor in the same ring
+    pub fn counterclockwise(&self, coord: PolarCoord) -> Option<PolarCoord> {
+        let count = self.cells_in_ring(coord.ring);
+        (count > 1).then(|| PolarCoord::new(coord.ring, (coord.index + count - 1) % count))
+    }
+
+    /// The neighbor in the next ring towards the center
+    pub fn inward(&self, coord: PolarCoord) -> Option<PolarCoord> {
+        if coord.ring == 0 {
+            return None;
+        }
+        let ratio = self.cells_in_ring(coord.ring) / self.cells_in_ring(coord.ring - 1);
+        Some(PolarCoord::new(coord.ring - 1, coord.index / ratio))
+    }
+
+    /// The neighbors in the next ring away from the center
+    pub fn outward(&self, coord: PolarCoord) -> Vec<PolarCoord> {
+        let outer_count = self.cells_in_ring(coord.ring + 1);
+        if outer_count == 0 {
+            return vec![];
+        }
+        let ratio = outer_count / self.cells_in_ring(coord.ring);
+        (coord.index * ratio..(coord.index + 1) * ratio)
+            .map(|index| PolarCoord::new(coord.ring + 1, index))
+            .collect_vec()
+    }
+
+    /// All neighbors, regardless of walls
+    pub fn get_neighbors(&self, coord: PolarCoord) -> Vec<PolarCoord> {
+        let mut neighbors = vec![];
+        neighbors.extend(self.clockwise(coord));
+        neighbors.extend(self.counterclockwise(coord));
+        neighbors.extend(self.inward(coord));
+        neighbors.extend(self.outward(coord));
+        // A ring of two cells has the same neighbor in both directions
+        neighbors.into_iter().unique().collect_vec()
+    }
+
+    fn link(&mut self, a: PolarCoord, b: PolarCoord) -> anyhow::Result<()> {
+        self.get_cell_mut(a)
+            .ok_or(anyhow!("Cell_A is not a part of the map"))?
+            .links
+            .push(b);
+        self.get_cell_mut(b)
+            .ok_or(anyhow!("Cell_B is not a part of the map"))?
+            .links
+            .push(a);
+        Ok(())
+    }
+
+    /// Renders the maze as an SVG document. The cells of `highlighted` are drawn in the solution color.
+    pub fn to_svg(&self, highlighted: &[PolarCoord]) -> String {
+        self.to_svg_with(highlighted, &Palette::default())
+    }
+
+    /// Like [to_svg](Self::to_svg), but in the colors of the palette
+    pub fn to_svg_with(&self, highlighted: &[PolarCoord], palette: &Palette) -> String {
+        let [wall_r, wall_g, wall_b, _] = palette.color(BlockType::Black);
+        let ring_width = IMAGE_BLOCK_WIDTH as f64;
+        let size = 2.0 * ring_width * self.ring_count() as f64 + 2.0 * IMAGE_BORDER_WIDTH as f64;
+        let center = size / 2.0;
+        let point = |radius: f64, theta: f64| {
+            // theta 0 is 12 o'clock, growing clockwise
+            (center + radius * theta.sin(), center - radius * theta.cos())
+        };
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size:.0}" height="{size:.0}" viewBox="0 0 {size:.0} {size:.0}">"#
+        );
+        svg.push('\n');
+
+        for cell in self.iter_cells() {
+            let [r, g, b, _] = if highlighted.contains(&cell.coord) {
+                palette.color(BlockType::Solution)
+            } else {
+                palette.color(BlockType::from(cell.color))
+            };
+            let fill = format!("rgb({r},{g},{b})");
+            if cell.coord.ring == 0 {
+                let _ = writeln!(
+                    svg,
+                    r#"<circle cx="{center:.2}" cy="{center:.2}" r="{ring_width:.2}" fill="{fill}"/>"#
+                );
+                continue;
+            }
+            let (inner, outer) = (
+                cell.coord.ring as f64 * ring_width,
+                (cell.coord.ring + 1) as f64 * ring_width,
+            );
+            let (theta_start, theta_end) = self.theta_range(cell.coord);
+            let (ax, ay) = point(inner, theta_start);
+            let (bx, by) = point(outer, theta_start);
+            let (cx, cy) = point(outer, theta_end);
+            let (dx, dy) = point(inner, theta_end);
+            let _ = writeln!(
+                svg,
+                r#"<path d="M {ax:.2} {ay:.2} L {bx:.2} {by:.2} A {outer:.2} {outer:.2} 0 0 1 {cx:.2} {cy:.2} L {dx:.2} {dy:.2} A {inner:.2} {inner:.2} 0 0 0 {ax:.2} {ay:.2} Z" fill="{fill}"/>"#
+            );
+        }
+
+        for cell in self.iter_cells().filter(|cell| cell.coord.ring > 0) {
+            let (inner, outer) = (
+                cell.coord.ring as f64 * ring_width,
+                (cell.coord.ring + 1) as f64 * ring_width,
+            );
+            let (theta_start, theta_end) = self.theta_range(cell.coord);
+            if self
+                .inward(cell.coord)
+                .is_some_and(|inward| !cell.is_linked(inward))
+            {
+                let (ax, ay) = point(inner, theta_start);
+                let (dx, dy) = point(inner, theta_end);
+                let _ = writeln!(
+                    svg,
+                    r#"<path d="M {ax:.2} {ay:.2} A {inner:.2} {inner:.2} 0 0 1 {dx:.2} {dy:.2}" fill="none" stroke="rgb({wall_r},{wall_g},{wall_b})" stroke-width="{IMAGE_BORDER_WIDTH}" stroke-linecap="round"/>"#
+                );
+            }
+            if self
+                .clockwise(cell.coord)
+                .is_some_and(|clockwise| !cell.is_linked(clockwise))
+            {
+                let (cx, cy) = point(outer, theta_end);
+                let (dx, dy) = point(inner, theta_end);
+                let _ = writeln!(
+                    svg,
+                    r#"<line x1="{dx:.2}" y1="{dy:.2}" x2="{cx:.2}" y2="{cy:.2}" stroke="rgb({wall_r},{wall_g},{wall_b})" stroke-width="{IMAGE_BORDER_WIDTH}" stroke-linecap="round"/>"#
+                );
+            }
+        }
+
+        let outer_radius = ring_width * self.ring_count() as f64;
+        let _ = writeln!(
+            svg,
+            r#"<circle cx="{center:.2}" cy="{center:.2}" r="{outer_radius:.2}" fill="none" stroke="rgb({wall_r},{wall_g},{wall_b})" stroke-width="{IMAGE_BORDER_WIDTH}"/>"#
+        );
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn theta_range(&self, coord: PolarCoord) -> (f64, f64) {
+        let theta = TAU / self.cells_in_ring(coord.ring) as f64;
+        (coord.index as f64 * theta, (coord.index + 1) as f64 * theta)
+    }
+}
+
+/// Generates a circular maze of `rings` rings with the recursive backtracker, starting in the center.
+pub fn generate_polar(rings: usize, options: &GenOptions) -> anyhow::Result<PolarMap> {
+    if rings == 0 {
+        return Err(anyhow!("The maze must at least have one ring"));
+    }
+    let mut map = PolarMap::new(rings);
+    let mut rng = options.rng();
+    let first = PolarCoord::new(0, 0);
+    // One flag per cell, laid out like the rings
+    let mut visited = map
+        .rings
+        .iter()
+        .map(|ring| vec![false; ring.len()])
+        .collect_vec();
+    visited[0][0] = true;
+    let mut stack = vec![first];
+    let mut color: Color = rng.gen();
+
+    while let Some(current) = stack.pop() {
+        let candidates = map
+            .get_neighbors(current)
+            .into_iter()
+            .filter(|neighbor| !visited[neighbor.ring][neighbor.index])
+            .collect_vec();
+
+        if let Some(next) = candidates.choose(&mut rng).copied() {
+            stack.push(current);
+            map.link(current, next)?;
+            if let Some(cell) = map.get_cell_mut(current) {
+                cell.color = color;
+            }
+            if !visited[next.ring][next.index] {
+                visited[next.ring][next.index] = true;
+                stack.push(next);
+            }
+        } else {
+            // Dead ends have no passage of their own, they take the color of the branch that led there
+            if let Some(cell) = map.get_cell_mut(current) {
+                cell.color = color;
+            }
+            color = rng.gen();
+        }
+    }
+
+    // Every wall once, from the side of the cell that comes first
+    let closed = map
+        .rings
+        .iter()
+        .flatten()
+        .flat_map(|cell| {
+            map.get_neighbors(cell.coord)
+                .into_iter()
+                .filter(|neighbor| {
+                    (cell.coord.ring, cell.coord.index) < (neighbor.ring, neighbor.index)
+                        && !cell.is_linked(*neighbor)
+                })
+                .map(|neighbor| (cell.coord, neighbor))
+        })
+        .collect_vec();
+    let count = loop_count(options.loop_prob, closed.len());
+    for (a, b) in closed.choose_multiple(&mut rng, count) {
+        map.link(*a, *b)?;
+    }
+
+    Ok(map)
+}
+
+pub struct PolarSolution {
+    path: Vec<PolarCoord>,
+    cost: u32,
+}
+
+impl PolarSolution {
+    pub fn path(&self) -> &[PolarCoord] {
+        &self.path
+    }
+
+    pub fn cost(&self) -> u32 {
+        self.cost
+    }
+}
+
+struct PolarSpace<'a> {
+    map: &'a PolarMap,
+    destination: PolarCoord,
+}
+
+impl SearchSpace for PolarSpace<'_> {
+    type State = PolarCoord;
+
+    fn successors(&self, state: &PolarCoord) -> Vec<(PolarCoord, u32)> {
+        self.map
+            .get_cell(*state)
+            .map(|cell| cell.links().to_vec())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|coord| self.map.get_cell(coord))
+            .map(|cell| (cell.coord, cell.speed()))
+            .collect_vec()
+    }
+
+    fn heuristic(&self, state: &PolarCoord) -> u32 {
+        // Every step changes the ring by at most one
+        state.ring.abs_diff(self.destination.ring) as u32
+    }
+
+    fn is_goal(&self, state: &PolarCoord) -> bool {
+        *state == self.destination
+    }
+}
+
+/// Like [a_star](crate::a_star), but on a [PolarMap]. Entering a cell costs the speed of its terrain color.
+pub fn a_star_polar(
+    map: &PolarMap,
+    start: PolarCoord,
+    destination: PolarCoord,
+) -> anyhow::Result<PolarSolution> {
+    if map.get_cell(start).is_none() || map.get_cell(destination).is_none() {
+        return Err(anyhow!("Please specify coordinates within the map"));
+    }
+    let space = PolarSpace { map, destination };
+    let path = a_star_search(&space, start).ok_or(MazeError::NoPath)?;
+    Ok(PolarSolution {
+        path: path.states,
+        cost: path.cost,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outer_rings_are_subdivided() {
+        let map = PolarMap::new(5);
+        assert_eq!(map.cells_in_ring(0), 1);
+        assert_eq!(map.cells_in_ring(1), 6);
+        for ring in 2..5 {
+            assert!(map.cells_in_ring(ring) >= map.cells_in_ring(ring - 1));
+            assert_eq!(map.cells_in_ring(ring) % map.cells_in_ring(ring - 1), 0);
+        }
+    }
+
+    #[test]
+    fn inward_and_outward_are_inverse() {
+        let map = PolarMap::new(6);
+        for cell in map.iter_cells() {
+            for outward in map.outward(cell.coord) {
+                assert_eq!(map.inward(outward), Some(cell.coord));
+            }
+        }
+    }
+
+    #[test]
+    fn generated_polar_maze_is_perfect() {
+        let map = generate_polar(5, &GenOptions::default()).unwrap();
+        let cell_count = map.iter_cells().count();
+        let link_count: usize = map.iter_cells().map(|cell| cell.links().len()).sum();
+        assert_eq!(link_count / 2, cell_count - 1);
+
+        let center = PolarCoord::new(0, 0);
+        for cell in map.iter_cells() {
+            assert!(a_star_polar(&map, center, cell.coord).is_ok());
+        }
+    }
+
+    #[test]
+    fn svgs_use_the_palette() {
+        let map = generate_polar(3, &GenOptions::default()).unwrap();
+        let palette = Palette::default()
+            .with_color(BlockType::Black, [1, 2, 3, 255])
+            .with_color(BlockType::Solution, [4, 5, 6, 255]);
+
+        let svg = map.to_svg_with(&[PolarCoord::new(0, 0)], &palette);
+
+        assert!(svg.contains(r#"stroke="rgb(1,2,3)""#));
+        assert!(!svg.contains("black"));
+        assert!(svg.contains(r#"fill="rgb(4,5,6)""#));
+    }
+
+    #[test]
+    fn the_same_seed_generates_the_same_maze() {
+        let options = |seed| GenOptions {
+            seed: Some(seed),
+            loop_prob: Some(0.2),
+            ..Default::default()
+        };
+        let svg = |seed| generate_polar(5, &options(seed)).unwrap().to_svg(&[]);
+
+        assert_eq!(svg(3), svg(3));
+        assert_ne!(svg(3), svg(4));
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{search_grid, Block, BlockType, Map, SearchOptions, SolveAlgorithm, SolveReport};
+
+/// Read access to the blocks of a map, however they are stored.
+/// Solvers like [a_star_provider] only need this, so that maps don't have to fit into memory as [Map].
+pub trait MapProvider {
+    /// The number of blocks per row
+    fn width(&self) -> usize;
+
+    /// The number of rows
+    fn height(&self) -> usize;
+
+    /// The terrain of the block at `x`, `y`, `None` outside of the map
+    fn block_type(&self, x: usize, y: usize) -> Option<BlockType>;
+
+    fn get_block(&self, x: usize, y: usize) -> Option<Block> {
+        Some(Block::new(x, y, self.block_type(x, y)?))
+    }
+
+    /// The other portal of the same color, like [Map::portal_partner].
+    /// Providers that don't know where their portals are treat them as ordinary blocks.
+    fn portal_partner(&self, _x: usize, _y: usize) -> Option<Block> {
+        None
+    }
+
+    /// Every portal that has a partner together with that partner
+    fn portal_pairs(&self) -> Vec<(Block, Block)> {
+        vec![]
+    }
+
+    /// The walkable blocks the agent can reach in one step, like [Map::get_reachable]
+    fn get_reachable(&self, x: usize, y: usize) -> Vec<Block> {
+        let Some(from) = self.get_block(x, y) else {
+            return vec![];
+        };
+        let left = x.checked_sub(1).map(|left| (left, y));
+        let up = y.checked_sub(1).map(|up| (x, up));
+        [left, up, Some((x + 1, y)), Some((x, y + 1))]
+            .into_iter()
+            .flatten()
+            .filter_map(|(x, y)| self.get_block(x, y))
+            .filter(|block| block.is_walkable() && from.allows_step_to(*block))
+            .map(|block| {
+                if block.portal().is_some() {
+                    self.portal_partner(block.x, block.y).unwrap_or(block)
+                } else {
+                    block
+                }
+            })
+            .collect()
+    }
+}
+
+impl MapProvider for Map {
+    fn width(&self) -> usize {
+        Map::width(self)
+    }
+
+    fn height(&self) -> usize {
+        Map::height(self)
+    }
+
+    fn block_type(&self, x: usize, y: usize) -> Option<BlockType> {
+        Some(Map::get_block(self, x, y)?.block_type())
+    }
+
+    fn get_block(&self, x: usize, y: usize) -> Option<Block> {
+        Map::get_block(self, x, y)
+    }
+
+    fn portal_partner(&self, x: usize, y: usize) -> Option<Block> {
+        Map::portal_partner(self, x, y)
+    }
+
+    fn portal_pairs(&self) -> Vec<(Block, Block)> {
+        Map::portal_pairs(self)
+    }
+
+    fn get_reachable(&self, x: usize, y: usize) -> Vec<Block> {
+        Map::get_reachable(self, x, y)
+    }
+}
+
+/// A path found on a [MapProvider]. Unlike [Solution](crate::Solution) it holds no copy of the map.
+#[derive(Debug, Clone)]
+pub struct ProviderSolution {
+    path: Vec<Block>,
+    cost: u32,
+    report: SolveReport,
+}
+
+impl ProviderSolution {
+    /// The blocks from start to destination, both inclusive
+    pub fn path(&self) -> &[Block] {
+        &self.path
+    }
+
+    pub fn cost(&self) -> u32 {
+        self.cost
+    }
+
+    pub fn report(&self) -> &SolveReport {
+        &self.report
+    }
+}
+
+/// Like [a_star_with](crate::a_star_with), but reads the map through a [MapProvider],
+/// e.g. a [ChunkedMap](crate::ChunkedMap) that only keeps some of its blocks in memory.
+pub fn a_star_provider<M: MapProvider>(
+    map: &M,
+    start_block: Block,
+    destination_block: Block,
+    options: &SearchOptions,
+) -> anyhow::Result<ProviderSolution> {
+    SolveAlgorithm::AStar.solve_provider(map, start_block, destination_block, options)
+}
+
+impl SolveAlgorithm {
+    /// Like [solve](Self::solve), but reads the map through a [MapProvider]
+    pub fn solve_provider<M: MapProvider>(
+        self,
+        map: &M,
+        start_block: Block,
+        destination_block: Block,
+        options: &SearchOptions,
+    ) -> anyhow::Result<ProviderSolution> {
+        let (path, report) =
+            search_grid(map, self, start_block, destination_block, options, &mut ())?;
+        Ok(ProviderSolution {
+            path: path.states.iter().map(|state| state.location).collect(),
+            cost: path.cost,
+            report,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star;
+
+    /// Only implements the required methods
+    struct Terrain<'a>(&'a Map);
+
+    impl MapProvider for Terrain<'_> {
+        fn width(&self) -> usize {
+            self.0.width()
+        }
+
+        fn height(&self) -> usize {
+            self.0.height()
+        }
+
+        fn block_type(&self, x: usize, y: usize) -> Option<BlockType> {
+            self.0.block_type(x, y)
+        }
+    }
+
+    #[test]
+    fn the_default_methods_match_the_map() {
+        let map = Map::from_rows(&[".>.#", "...#", "#.<."]);
+
+        for block in map.iter_blocks() {
+            assert_eq!(
+                Terrain(&map).get_reachable(block.x, block.y),
+                map.get_reachable(block.x, block.y)
+            );
+        }
+    }
+
+    #[test]
+    fn a_star_provider_follows_portals_of_maps() {
+        let map = Map::from_rows(&["P.#..", "..#.P"]);
+        let start = map.get_block(1, 1).unwrap();
+        let goal = map.get_block(3, 0).unwrap();
+
+        let solution = a_star_provider(&map, start, goal, &SearchOptions::default()).unwrap();
+
+        assert_eq!(solution.cost(), a_star(&map, start, goal).unwrap().cost());
+        assert_eq!(solution.path().first(), Some(&start));
+        assert_eq!(solution.path().last(), Some(&goal));
+    }
+}
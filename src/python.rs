@@ -0,0 +1,174 @@
+use anyhow::anyhow;
+use numpy::{ndarray::Array2, PyArray2, PyReadonlyArray2};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{BlockType, GenOptions, Map, MazeAlgorithm, MazeError};
+
+/// The cost and the `(x, y)` coordinates of a path
+type PyPath = (u32, Vec<(usize, usize)>);
+
+/// A map of blocks. As numpy array every block is a `uint8` with its index in `BLOCK_TYPES`.
+#[pyclass(name = "Map")]
+#[derive(Clone)]
+struct PyMap {
+    map: Map,
+}
+
+#[pymethods]
+impl PyMap {
+    /// Reads a map from a two dimensional array of block type indices, indexed by row and column
+    #[staticmethod]
+    fn from_numpy(cells: PyReadonlyArray2<u8>) -> PyResult<Self> {
+        let cells = cells.as_array();
+        let (height, width) = cells.dim();
+        let map = map_from_cells(&cells.iter().copied().collect::<Vec<_>>(), width, height)
+            .map_err(to_py_err)?;
+        Ok(Self { map })
+    }
+
+    /// The block type index of every block as array of shape `(height, width)`
+    fn to_numpy<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<u8>>> {
+        let shape = (self.map.height(), self.map.width());
+        let cells = Array2::from_shape_vec(shape, map_to_cells(&self.map))
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(PyArray2::from_owned_array(py, cells))
+    }
+
+    /// Reads a map in the ASCII text format of the command line tool
+    #[staticmethod]
+    fn from_text(text: &str) -> PyResult<Self> {
+        let map = Map::from_text(text).map_err(to_py_err)?;
+        Ok(Self { map })
+    }
+
+    fn to_text(&self) -> String {
+        self.map.to_text_themed(crate::TextTheme::Ascii)
+    }
+
+    #[getter]
+    fn width(&self) -> usize {
+        self.map.width()
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.map.height()
+    }
+
+    fn __str__(&self) -> String {
+        self.map.to_string()
+    }
+}
+
+/// Generates a maze of `width` × `height` cells as map of `2 * width + 1` × `2 * height + 1` blocks.
+/// `algorithm` is named like on the command line.
+#[pyfunction]
+#[pyo3(signature = (width, height, algorithm = "backtracker", seed = None, loop_prob = None))]
+fn generate_maze(
+    width: usize,
+    height: usize,
+    algorithm: &str,
+    seed: Option<u64>,
+    loop_prob: Option<f64>,
+) -> PyResult<PyMap> {
+    let algorithm = algorithm.parse::<MazeAlgorithm>().map_err(to_py_err)?;
+    let options = GenOptions {
+        seed,
+        loop_prob,
+        ..Default::default()
+    };
+    let maze = crate::generate(width, height, algorithm, &options).map_err(to_py_err)?;
+    Ok(PyMap {
+        map: Map::from(maze),
+    })
+}
+
+/// The cheapest path from `start` to `goal` as `(cost, [(x, y), ..])`, `None` if there is no path
+#[pyfunction]
+fn a_star(map: &PyMap, start: (usize, usize), goal: (usize, usize)) -> PyResult<Option<PyPath>> {
+    let (Some(start), Some(goal)) = (
+        map.map.get_block(start.0, start.1),
+        map.map.get_block(goal.0, goal.1),
+    ) else {
+        return Err(PyValueError::new_err(
+            "Please specify coordinates within the map",
+        ));
+    };
+    match crate::a_star(&map.map, start, goal) {
+        Ok(solution) => Ok(Some((
+            solution.cost(),
+            solution
+                .path()
+                .iter()
+                .map(|block| (block.x, block.y))
+                .collect(),
+        ))),
+        Err(e) if e.downcast_ref() == Some(&MazeError::NoPath) => Ok(None),
+        Err(e) => Err(to_py_err(e)),
+    }
+}
+
+#[pymodule]
+fn mazes(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyMap>()?;
+    module.add_function(wrap_pyfunction!(generate_maze, module)?)?;
+    module.add_function(wrap_pyfunction!(a_star, module)?)?;
+    module.add("BLOCK_TYPES", block_type_names())?;
+    Ok(())
+}
+
+fn to_py_err(error: anyhow::Error) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// The names of the block types, in the order of their indices in numpy arrays
+fn block_type_names() -> Vec<String> {
+    BlockType::all()
+        .map(|block_type| format!("{block_type:?}"))
+        .collect()
+}
+
+/// The block type index of every block, row by row
+fn map_to_cells(map: &Map) -> Vec<u8> {
+    map.iter_blocks()
+        .map(|block| block.block_type().to_byte())
+        .collect()
+}
+
+fn map_from_cells(cells: &[u8], width: usize, height: usize) -> anyhow::Result<Map> {
+    if width == 0 || height == 0 {
+        return Err(anyhow!("The map must at least have one block"));
+    }
+    let rows = cells
+        .chunks(width)
+        .enumerate()
+        .map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(x, cell)| {
+                    BlockType::from_byte(*cell)
+                        .map(|block_type| crate::Block::new(x, y, block_type))
+                        .ok_or(anyhow!("Unknown block type {cell} at {x} {y}"))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(Map::new(rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_round_trip() {
+        let map = Map::from_rows(&["#.1A", "P>.P"]);
+
+        let cells = map_to_cells(&map);
+        let read = map_from_cells(&cells, 4, 2).unwrap();
+
+        assert_eq!(read.to_string(), map.to_string());
+        assert_eq!(block_type_names()[cells[0] as usize], "Black");
+        assert!(map_from_cells(&[0, 200], 2, 1).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+/// A rectangle of blocks, both corners inclusive. Parses from `x1,y1,x2,y2` or `x,y` for a single block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Region {
+    min: (usize, usize),
+    max: (usize, usize),
+}
+
+impl Region {
+    /// The rectangle between two opposite corners in any order
+    pub fn new(x1: usize, y1: usize, x2: usize, y2: usize) -> Self {
+        Self {
+            min: (x1.min(x2), y1.min(y2)),
+            max: (x1.max(x2), y1.max(y2)),
+        }
+    }
+
+    /// A region of only the block at `x`, `y`
+    pub fn block(x: usize, y: usize) -> Self {
+        Self::new(x, y, x, y)
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y)
+    }
+}
+
+impl FromStr for Region {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let coordinates = s
+            .split(',')
+            .map(|coordinate| coordinate.trim().parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| anyhow!("'{s}' is not of the form x1,y1,x2,y2 or x,y"))?;
+        match coordinates[..] {
+            [x, y] => Ok(Region::block(x, y)),
+            [x1, y1, x2, y2] => Ok(Region::new(x1, y1, x2, y2)),
+            _ => Err(anyhow!("'{s}' is not of the form x1,y1,x2,y2 or x,y")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions_parse_rectangles_and_blocks() {
+        let region: Region = "4, 3,1,1".parse().unwrap();
+
+        assert!(region.contains(1, 1) && region.contains(4, 3) && region.contains(2, 2));
+        assert!(!region.contains(0, 2) && !region.contains(2, 4));
+        assert_eq!("2,5".parse::<Region>().unwrap(), Region::block(2, 5));
+        assert!("1,2,3".parse::<Region>().is_err());
+        assert!("a,b".parse::<Region>().is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use std::f64::consts::SQRT_2;
+
+use anyhow::anyhow;
+use itertools::Itertools;
+
+use crate::{
+    search::{a_star_search, SearchSpace},
+    Block, Map, Solution,
+};
+
+/// How far a cost may be off the optimal length of a scenario, which is rounded to eight decimals
+const TOLERANCE: f64 = 1e-4;
+
+/// One problem of a MovingAI `.scen` file: a start and goal on a map and the length of the optimal path.
+///
+/// The benchmark moves in eight directions, where diagonal steps cost `√2` and may not cut corners.
+/// The solvers of this crate only move in four directions, so their costs are checked against the optimal cost
+/// of moving in four directions, see [check_cost](Self::check_cost).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    /// Groups scenarios of similar length
+    pub bucket: usize,
+    /// The file of the map, relative to the benchmark
+    pub map: String,
+    pub width: usize,
+    pub height: usize,
+    pub start: (usize, usize),
+    pub goal: (usize, usize),
+    pub optimal_length: f64,
+}
+
+impl Scenario {
+    /// Reads the scenarios of a `.scen` file, which starts with a `version` line.
+    pub fn parse_all(text: &str) -> anyhow::Result<Vec<Scenario>> {
+        text.lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with("version"))
+            .enumerate()
+            .map(|(index, line)| {
+                Scenario::parse(line).map_err(|error| anyhow!("Scenario {index}: {error}"))
+            })
+            .collect()
+    }
+
+    fn parse(line: &str) -> anyhow::Result<Scenario> {
+        let fields = line.split('\t').map(str::trim).collect::<Vec<_>>();
+        let [bucket, map, width, height, start_x, start_y, goal_x, goal_y, optimal_length] =
+            fields[..]
+        else {
+            return Err(anyhow!("'{line}' does not have 9 tab separated fields"));
+        };
+        Ok(Scenario {
+            bucket: bucket.parse()?,
+            map: map.to_string(),
+            width: width.parse()?,
+            height: height.parse()?,
+            start: (start_x.parse()?, start_y.parse()?),
+            goal: (goal_x.parse()?, goal_y.parse()?),
+            optimal_length: optimal_length.parse()?,
+        })
+    }
+
+    /// Writes scenarios as a `.scen` file that [parse_all](Self::parse_all) reads
+    pub fn format_all(scenarios: &[Scenario]) -> String {
+        let mut text = "version 1\n".to_string();
+        for scenario in scenarios {
+            text += &format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{:.8}\n",
+                scenario.bucket,
+                scenario.map,
+                scenario.width,
+                scenario.height,
+                scenario.start.0,
+                scenario.start.1,
+                scenario.goal.0,
+                scenario.goal.1,
+                scenario.optimal_length
+            );
+        }
+        text
+    }
+
+    /// The optimal cost of a path on `map` that only moves in four directions, like the solvers of this crate do.
+    ///
+    /// The optimal length is recomputed on the map in eight directions first, which fails if there is no path
+    /// or the length differs from the one of the scenario, e.g. because the map is another one.
+    /// Only holds on maps where every block costs one, like [imported](Map::from_movingai) ones.
+    pub fn expected_cost(&self, map: &Map) -> anyhow::Result<u32> {
+        let length = |diagonals| {
+            let space = BenchmarkSpace {
+                map,
+                goal: self.goal,
+                diagonals,
+            };
+            a_star_search(&space, self.start)
+                .map(|path| path.cost)
+                .ok_or(anyhow!(
+                    "There is no path from {} {} to {} {}",
+                    self.start.0,
+                    self.start.1,
+                    self.goal.0,
+                    self.goal.1
+                ))
+        };
+        let octile_length = length(true)?;
+        if (octile_length - self.optimal_length).abs() > TOLERANCE {
+            return Err(anyhow!(
+                "The optimal length on the map is {octile_length:.8}, but the scenario expects {}",
+                self.optimal_length
+            ));
+        }
+        Ok(length(false)?.round() as u32)
+    }
+
+    /// Checks that `cost` is the [expected cost](Self::expected_cost) of the scenario on `map`
+    pub fn check_cost(&self, map: &Map, cost: u32) -> anyhow::Result<()> {
+        let expected = self.expected_cost(map)?;
+        if cost != expected {
+            return Err(anyhow!(
+                "The cost {cost} is not the optimal cost {expected} of moving in four directions"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks that the solution solves this scenario on `map`: the map has the size of the scenario,
+    /// the path [is valid](Solution::verify), connects start and goal and [costs](Self::check_cost) what it should.
+    pub fn verify(&self, map: &Map, solution: &Solution) -> anyhow::Result<()> {
+        if (map.width(), map.height()) != (self.width, self.height) {
+            return Err(anyhow!(
+                "The map is {}x{}, but the scenario expects {}x{}",
+                map.width(),
+                map.height(),
+                self.width,
+                self.height
+            ));
+        }
+        solution.verify(map)?;
+        let (first, last) = (solution.path[0], solution.path[solution.path.len() - 1]);
+        if (first.x, first.y) != self.start || (last.x, last.y) != self.goal {
+            return Err(anyhow!(
+                "The path leads from {} {} to {} {} instead of from {} {} to {} {}",
+                first.x,
+                first.y,
+                last.x,
+                last.y,
+                self.start.0,
+                self.start.1,
+                self.goal.0,
+                self.goal.1
+            ));
+        }
+        self.check_cost(map, solution.cost)
+    }
+}
+
+/// Moving between the walkable blocks like the benchmark does: straight steps cost one and, if allowed,
+/// diagonal ones `√2`, but only if both blocks beside the diagonal are walkable as well
+struct BenchmarkSpace<'a> {
+    map: &'a Map,
+    goal: (usize, usize),
+    diagonals: bool,
+}
+
+impl SearchSpace<f64> for BenchmarkSpace<'_> {
+    type State = (usize, usize);
+
+    fn successors(&self, &(x, y): &(usize, usize)) -> Vec<((usize, usize), f64)> {
+        let walkable = |dx: isize, dy: isize| {
+            let (x, y) = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+            (x < self.map.width())
+                .then(|| self.map.get_block(x, y))
+                .flatten()
+                .filter(Block::is_walkable)
+                .map(|_| (x, y))
+        };
+        let mut successors = [(0, -1), (1, 0), (0, 1), (-1, 0)]
+            .into_iter()
+            .filter_map(|(dx, dy)| walkable(dx, dy))
+            .map(|position| (position, 1.0))
+            .collect_vec();
+        if self.diagonals {
+            for (dx, dy) in [(-1, -1), (1, -1), (1, 1), (-1, 1)] {
+                if walkable(dx, 0).is_some() && walkable(0, dy).is_some() {
+                    successors.extend(walkable(dx, dy).map(|position| (position, SQRT_2)));
+                }
+            }
+        }
+        successors
+    }
+
+    fn heuristic(&self, &(x, y): &(usize, usize)) -> f64 {
+        let (dx, dy) = (
+            x.abs_diff(self.goal.0) as f64,
+            y.abs_diff(self.goal.1) as f64,
+        );
+        if self.diagonals {
+            dx.max(dy) + (SQRT_2 - 1.0) * dx.min(dy)
+        } else {
+            dx + dy
+        }
+    }
+
+    fn is_goal(&self, state: &(usize, usize)) -> bool {
+        *state == self.goal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star;
+
+    const MAP: &str = "type octile\nheight 4\nwidth 4\nmap\n....\n.@@.\n....\n@@@.\n";
+
+    #[test]
+    fn scenarios_survive_a_round_trip() {
+        let text = "version 1\n0\tarena.map\t4\t4\t0\t0\t3\t3\t4.24264069\n1\tarena.map\t4\t4\t0\t2\t3\t0\t3.82842712\n";
+
+        let scenarios = Scenario::parse_all(text).unwrap();
+
+        assert_eq!(scenarios.len(), 2);
+        assert_eq!(scenarios[1].start, (0, 2));
+        assert_eq!(scenarios[1].goal, (3, 0));
+        assert_eq!(Scenario::format_all(&scenarios), text);
+        assert!(Scenario::parse_all("version 1\n0 arena.map 4 4\n").is_err());
+    }
+
+    #[test]
+    fn optimal_solutions_are_verified() {
+        let map = Map::from_movingai(MAP).unwrap();
+        let scenarios = Scenario::parse_all(
+            "version 1\n0\tarena.map\t4\t4\t0\t0\t3\t3\t6\n0\tarena.map\t4\t4\t0\t0\t3\t3\t4\n",
+        )
+        .unwrap();
+        let start = map.get_block(0, 0).unwrap();
+        let solution = a_star(&map, start, map.get_block(3, 3).unwrap()).unwrap();
+        let elsewhere = a_star(&map, start, map.get_block(3, 2).unwrap()).unwrap();
+
+        assert!(scenarios[0].verify(&map, &solution).is_ok());
+        assert!(scenarios[1].verify(&map, &solution).is_err());
+        assert!(scenarios[0].verify(&map, &elsewhere).is_err());
+    }
+
+    #[test]
+    fn costs_are_checked_against_the_recomputed_lengths() {
+        let map =
+            Map::from_movingai("type octile\nheight 3\nwidth 4\nmap\n....\n..@.\n....\n").unwrap();
+        // Diagonally to (1, 1), straight on below the wall, since diagonals don't cut its corners
+        let scenario =
+            Scenario::parse_all("version 1\n0\tarena.map\t4\t3\t0\t0\t3\t2\t4.41421356\n")
+                .unwrap()
+                .remove(0);
+
+        assert_eq!(scenario.expected_cost(&map).unwrap(), 5);
+        assert!(scenario.check_cost(&map, 5).is_ok());
+        // Both lie between the length and √2 times of it, but neither is optimal
+        assert!(scenario.check_cost(&map, 4).is_err());
+        assert!(scenario.check_cost(&map, 6).is_err());
+        let elsewhere = Scenario {
+            optimal_length: 4.0,
+            ..scenario
+        };
+        assert!(elsewhere.expected_cost(&map).is_err());
+    }
+}
@@ -0,0 +1,540 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    hash::Hash,
+};
+
+use priority_queue::PriorityQueue;
+
+/// The arithmetic of path costs. Integer costs are exact for whole terrain costs,
+/// floating point costs for euclidean distances and fractional terrain costs.
+pub(crate) trait Cost: Copy + PartialOrd + Debug {
+    const ZERO: Self;
+    /// Larger than every cost of a path
+    const INFINITY: Self;
+
+    /// Adds without overflowing, the result stays at most [INFINITY](Self::INFINITY)
+    fn saturating_add(self, other: Self) -> Self;
+
+    fn as_f64(self) -> f64;
+}
+
+impl Cost for u32 {
+    const ZERO: Self = 0;
+    const INFINITY: Self = u32::MAX;
+
+    fn saturating_add(self, other: Self) -> Self {
+        u32::saturating_add(self, other)
+    }
+
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Cost for f64 {
+    const ZERO: Self = 0.0;
+    const INFINITY: Self = f64::INFINITY;
+
+    fn saturating_add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn as_f64(self) -> f64 {
+        self
+    }
+}
+
+/// A graph that can be searched with [`a_star_search`], with integer costs unless stated otherwise.
+pub(crate) trait SearchSpace<C: Cost = u32> {
+    type State: Clone + Eq + Hash;
+
+    /// All states reachable in one step together with the cost of that step
+    fn successors(&self, state: &Self::State) -> Vec<(Self::State, C)>;
+
+    /// The estimated remaining cost to a goal. Must never overestimate for the result to be optimal.
+    fn heuristic(&self, state: &Self::State) -> C;
+
+    fn is_goal(&self, state: &Self::State) -> bool;
+
+    /// Limits the paths the search may find, unlimited by default
+    fn budget(&self) -> Budget<C> {
+        Budget::default()
+    }
+}
+
+/// The most a path may cost and the most steps it may take, `None` for no limit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Budget<C> {
+    pub max_cost: Option<C>,
+    pub max_steps: Option<usize>,
+}
+
+impl<C> Default for Budget<C> {
+    fn default() -> Self {
+        Self {
+            max_cost: None,
+            max_steps: None,
+        }
+    }
+}
+
+impl<C: Cost> Budget<C> {
+    /// Whether a path that reaches a state after `steps` steps, with `estimate` as lower bound of its total cost,
+    /// exceeds the budget
+    fn is_exceeded(&self, estimate: C, steps: usize) -> bool {
+        self.max_cost.is_some_and(|max_cost| estimate > max_cost)
+            || self.max_steps.is_some_and(|max_steps| steps > max_steps)
+    }
+}
+
+/// The states from start to goal (both inclusive) and the cost of the whole path.
+#[derive(Debug, Clone)]
+pub(crate) struct Path<S, C = u32> {
+    pub states: Vec<S>,
+    pub cost: C,
+    /// The number of states taken from the frontier
+    pub expanded: usize,
+}
+
+/// How the frontier is ordered: by `g + weight * h` or, when greedy, only by `h`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum HeuristicWeight {
+    Factor(f64),
+    Greedy,
+}
+
+impl HeuristicWeight {
+    /// The factor by which a found path may at most be more expensive than the cheapest one
+    fn suboptimality_bound(self) -> Option<f64> {
+        match self {
+            HeuristicWeight::Factor(weight) => Some(weight.max(1.0)),
+            HeuristicWeight::Greedy => None,
+        }
+    }
+
+    fn priority<C: Cost>(self, cost: C, heuristic: C) -> Priority {
+        match self {
+            HeuristicWeight::Factor(weight) => {
+                Priority(cost.as_f64() + weight * heuristic.as_f64())
+            }
+            HeuristicWeight::Greedy => Priority(heuristic.as_f64()),
+        }
+    }
+}
+
+/// The order of the frontier. Floats are totally ordered here, so that they can be used in the queue.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Priority(pub f64);
+
+impl Eq for Priority {}
+
+impl PartialOrd for Priority {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Priority {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Statistics about a finished search
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolveReport {
+    /// The number of states the search expanded
+    pub expanded: usize,
+    /// The cost of the path is at most this factor times the cheapest cost, `None` if there is no guarantee
+    pub suboptimality_bound: Option<f64>,
+}
+
+impl SolveReport {
+    /// Whether the path is guaranteed to be the cheapest one
+    pub fn is_optimal(&self) -> bool {
+        self.suboptimality_bound == Some(1.0)
+    }
+
+    pub(crate) fn new<S, C>(path: &Path<S, C>, weight: HeuristicWeight) -> Self {
+        Self {
+            expanded: path.expanded,
+            suboptimality_bound: weight.suboptimality_bound(),
+        }
+    }
+}
+
+pub(crate) fn a_star_search<C: Cost, S: SearchSpace<C>>(
+    space: &S,
+    start: S::State,
+) -> Option<Path<S::State, C>> {
+    best_first_search(space, start, HeuristicWeight::Factor(1.0))
+}
+
+/// A* with a weighted heuristic. A weight of one is plain A*, larger weights trade optimality for speed.
+pub(crate) fn best_first_search<C: Cost, S: SearchSpace<C>>(
+    space: &S,
+    start: S::State,
+    weight: HeuristicWeight,
+) -> Option<Path<S::State, C>> {
+    interruptible_search(space, start, weight, || false, &mut ()).unwrap_or(None)
+}
+
+/// Gets told what a search does, e.g. to record a [SearchTrace](crate::SearchTrace). Does nothing by default.
+pub(crate) trait Observer<S> {
+    /// The state was taken from the frontier to look at its successors
+    fn expanded(&mut self, _state: &S) {}
+
+    /// The state was added to the frontier, or got cheaper, when `from` was expanded
+    fn reached(&mut self, _state: &S, _from: &S) {}
+}
+
+impl<S> Observer<S> for () {}
+
+/// How many states are expanded between two checks whether the search should stop
+const STOP_CHECK_INTERVAL: usize = 256;
+
+/// Like [best_first_search], but gives up with `Err(Interrupted::Stopped)` as soon as `should_stop` returns true.
+/// States beyond the [budget](SearchSpace::budget) are never expanded, if there is no path within it
+/// the search fails with `Err(Interrupted::OverBudget)`. The observer sees every expanded and reached state.
+pub(crate) fn interruptible_search<C: Cost, S: SearchSpace<C>>(
+    space: &S,
+    start: S::State,
+    weight: HeuristicWeight,
+    should_stop: impl Fn() -> bool,
+    observer: &mut impl Observer<S::State>,
+) -> Result<Option<Path<S::State, C>>, Interrupted<C>> {
+    let budget = space.budget();
+    if budget.max_steps.is_some() {
+        return step_limited_search(space, start, weight, should_stop, observer);
+    }
+    let mut frontier: PriorityQueue<S::State, Reverse<Priority>> = PriorityQueue::new();
+    // The cheapest known cost of each state and the state it was reached from
+    let mut reached: HashMap<S::State, (C, Option<S::State>)> = HashMap::new();
+    // The lowest estimate of a path's cost that was cut off by the budget
+    let mut frontier_cost: Option<C> = None;
+
+    reached.insert(start.clone(), (C::ZERO, None));
+    frontier.push(
+        start.clone(),
+        Reverse(weight.priority(C::ZERO, space.heuristic(&start))),
+    );
+    let mut expanded = 0;
+
+    while let Some((state, _)) = frontier.pop() {
+        expanded += 1;
+        if (expanded - 1) % STOP_CHECK_INTERVAL == 0 && should_stop() {
+            return Err(Interrupted::Stopped);
+        }
+        observer.expanded(&state);
+        let cost = reached[&state].0;
+        if space.is_goal(&state) {
+            return Ok(Some(Path {
+                states: reconstruct_states(&reached, state),
+                cost,
+                expanded,
+            }));
+        }
+        for (next, step_cost) in space.successors(&state) {
+            let next_cost = cost.saturating_add(step_cost);
+            if reached
+                .get(&next)
+                .is_none_or(|(known_cost, _)| next_cost < *known_cost)
+            {
+                let estimate = next_cost.saturating_add(space.heuristic(&next));
+                if budget.is_exceeded(estimate, 0) {
+                    if frontier_cost.is_none_or(|lowest| estimate < lowest) {
+                        frontier_cost = Some(estimate);
+                    }
+                    continue;
+                }
+                reached.insert(next.clone(), (next_cost, Some(state.clone())));
+                observer.reached(&next, &state);
+                // Replaces the priority if the state is already part of the frontier
+                let f = weight.priority(next_cost, space.heuristic(&next));
+                frontier.push(next, Reverse(f));
+            }
+        }
+    }
+
+    match frontier_cost {
+        Some(frontier_cost) => Err(Interrupted::OverBudget { frontier_cost }),
+        None => Ok(None),
+    }
+}
+
+/// [interruptible_search] with a limit on the steps. The cheapest path to a state may take too many steps
+/// while a more expensive one still fits, so every state is searched once per number of steps,
+/// unless another path to it is at most as expensive and takes at most as many steps.
+fn step_limited_search<C: Cost, S: SearchSpace<C>>(
+    space: &S,
+    start: S::State,
+    weight: HeuristicWeight,
+    should_stop: impl Fn() -> bool,
+    observer: &mut impl Observer<S::State>,
+) -> Result<Option<Path<S::State, C>>, Interrupted<C>> {
+    let budget = space.budget();
+    let mut frontier: PriorityQueue<(S::State, usize), Reverse<Priority>> = PriorityQueue::new();
+    // The cost of each state after a number of steps and where it was reached from
+    let mut reached: HashMap<_, (C, Option<_>)> = HashMap::new();
+    // The cost and steps of the paths to each state that no other path beats in both
+    let mut pareto: HashMap<S::State, Vec<(C, usize)>> = HashMap::new();
+    let mut frontier_cost: Option<C> = None;
+
+    reached.insert((start.clone(), 0), (C::ZERO, None));
+    pareto.insert(start.clone(), vec![(C::ZERO, 0)]);
+    frontier.push(
+        (start.clone(), 0),
+        Reverse(weight.priority(C::ZERO, space.heuristic(&start))),
+    );
+    let mut expanded = 0;
+
+    while let Some(((state, steps), _)) = frontier.pop() {
+        expanded += 1;
+        if (expanded - 1) % STOP_CHECK_INTERVAL == 0 && should_stop() {
+            return Err(Interrupted::Stopped);
+        }
+        let cost = reached[&(state.clone(), steps)].0;
+        // A cheaper and shorter path found since makes this one pointless
+        if !pareto[&state].contains(&(cost, steps)) {
+            continue;
+        }
+        observer.expanded(&state);
+        if space.is_goal(&state) {
+            return Ok(Some(Path {
+                states: reconstruct_states(&reached, (state, steps))
+                    .into_iter()
+                    .map(|(state, _)| state)
+                    .collect(),
+                cost,
+                expanded,
+            }));
+        }
+        let next_steps = steps + 1;
+        for (next, step_cost) in space.successors(&state) {
+            let next_cost = cost.saturating_add(step_cost);
+            let known = pareto.entry(next.clone()).or_default();
+            if known.iter().any(|(known_cost, known_steps)| {
+                *known_cost <= next_cost && *known_steps <= next_steps
+            }) {
+                continue;
+            }
+            let estimate = next_cost.saturating_add(space.heuristic(&next));
+            if budget.is_exceeded(estimate, next_steps) {
+                if frontier_cost.is_none_or(|lowest| estimate < lowest) {
+                    frontier_cost = Some(estimate);
+                }
+                continue;
+            }
+            known.retain(|(known_cost, known_steps)| {
+                !(next_cost <= *known_cost && next_steps <= *known_steps)
+            });
+            known.push((next_cost, next_steps));
+            reached.insert(
+                (next.clone(), next_steps),
+                (next_cost, Some((state.clone(), steps))),
+            );
+            observer.reached(&next, &state);
+            let f = weight.priority(next_cost, space.heuristic(&next));
+            frontier.push((next, next_steps), Reverse(f));
+        }
+    }
+
+    match frontier_cost {
+        Some(frontier_cost) => Err(Interrupted::OverBudget { frontier_cost }),
+        None => Ok(None),
+    }
+}
+
+/// Dijkstra from the start to every reachable state: the cost of the cheapest path to each of them.
+/// There is no goal, so the heuristic and the budget are ignored.
+pub(crate) fn explore<C: Cost, S: SearchSpace<C>>(
+    space: &S,
+    start: S::State,
+) -> HashMap<S::State, C> {
+    let mut frontier: PriorityQueue<S::State, Reverse<Priority>> = PriorityQueue::new();
+    let mut settled: HashMap<S::State, C> = HashMap::new();
+    let mut known: HashMap<S::State, C> = HashMap::from([(start.clone(), C::ZERO)]);
+    frontier.push(start, Reverse(Priority(0.0)));
+
+    while let Some((state, _)) = frontier.pop() {
+        let cost = known[&state];
+        for (next, step_cost) in space.successors(&state) {
+            let next_cost = cost.saturating_add(step_cost);
+            if !settled.contains_key(&next)
+                && known
+                    .get(&next)
+                    .is_none_or(|known_cost| next_cost < *known_cost)
+            {
+                known.insert(next.clone(), next_cost);
+                frontier.push(next, Reverse(Priority(next_cost.as_f64())));
+            }
+        }
+        settled.insert(state, cost);
+    }
+    settled
+}
+
+/// Why the search ended before it found a path or made sure there is none
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Interrupted<C = u32> {
+    /// The caller stopped it
+    Stopped,
+    /// Every path within the budget was searched, the cheapest one beyond it costs at least `frontier_cost`
+    OverBudget { frontier_cost: C },
+}
+
+fn reconstruct_states<S: Clone + Eq + Hash, C>(
+    reached: &HashMap<S, (C, Option<S>)>,
+    goal: S,
+) -> Vec<S> {
+    let mut states = vec![goal];
+    while let Some((_, Some(parent))) = states.last().and_then(|state| reached.get(state)) {
+        states.push(parent.clone());
+    }
+    states.reverse();
+    states
+}
+
+/// Iterative deepening A*: repeated depth first searches with a growing bound on `cost + heuristic`.
+/// Only keeps the current path in memory, at the price of expanding states several times.
+/// Gives up with `Err(Interrupted::Stopped)` as soon as `should_stop` returns true
+/// and with `Err(Interrupted::OverBudget)` if there is no path within the [budget](SearchSpace::budget).
+pub(crate) fn ida_star_search<C: Cost, S: SearchSpace<C>>(
+    space: &S,
+    start: S::State,
+    should_stop: impl Fn() -> bool,
+) -> Result<Option<Path<S::State, C>>, Interrupted<C>> {
+    let budget = space.budget();
+    let mut threshold = space.heuristic(&start);
+    let mut expanded = 0;
+
+    loop {
+        if should_stop() {
+            return Err(Interrupted::Stopped);
+        }
+        if budget.is_exceeded(threshold, 0) {
+            return Err(Interrupted::OverBudget {
+                frontier_cost: threshold,
+            });
+        }
+        // The lowest estimate of a path's cost that was cut off by the step limit
+        let mut frontier_cost: Option<C> = None;
+        // The cheapest f value that exceeded the threshold becomes the next threshold
+        let mut next_threshold = C::INFINITY;
+        let mut path = vec![start.clone()];
+        let mut on_path = HashSet::from([start.clone()]);
+        // The cost of each state on the path and the successors that are left to try
+        let mut stack = vec![(C::ZERO, space.successors(&start).into_iter())];
+        expanded += 1;
+        if space.is_goal(&start) {
+            return Ok(Some(Path {
+                states: path,
+                cost: C::ZERO,
+                expanded,
+            }));
+        }
+
+        while let Some((cost, successors)) = stack.last_mut() {
+            let cost = *cost;
+            let Some((next, step_cost)) = successors.next() else {
+                stack.pop();
+                on_path.remove(
+                    &path
+                        .pop()
+                        .expect("Every stack entry has a state on the path"),
+                );
+                continue;
+            };
+            if on_path.contains(&next) {
+                continue;
+            }
+            let next_cost = cost.saturating_add(step_cost);
+            let f = next_cost.saturating_add(space.heuristic(&next));
+            if budget.is_exceeded(C::ZERO, path.len()) {
+                if frontier_cost.is_none_or(|lowest| f < lowest) {
+                    frontier_cost = Some(f);
+                }
+                continue;
+            }
+            if f > threshold {
+                if f < next_threshold {
+                    next_threshold = f;
+                }
+                continue;
+            }
+
+            expanded += 1;
+            if expanded % STOP_CHECK_INTERVAL == 0 && should_stop() {
+                return Err(Interrupted::Stopped);
+            }
+            if space.is_goal(&next) {
+                path.push(next);
+                return Ok(Some(Path {
+                    states: path,
+                    cost: next_cost,
+                    expanded,
+                }));
+            }
+            stack.push((next_cost, space.successors(&next).into_iter()));
+            on_path.insert(next.clone());
+            path.push(next);
+        }
+
+        if next_threshold >= C::INFINITY {
+            return match frontier_cost {
+                Some(frontier_cost) => Err(Interrupted::OverBudget { frontier_cost }),
+                None => Ok(None),
+            };
+        }
+        threshold = next_threshold;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An open grid where agents also move diagonally, so that steps have irrational costs
+    struct DiagonalGrid {
+        size: i32,
+        goal: (i32, i32),
+    }
+
+    impl SearchSpace<f64> for DiagonalGrid {
+        type State = (i32, i32);
+
+        fn successors(&self, &(x, y): &(i32, i32)) -> Vec<((i32, i32), f64)> {
+            (-1..=1)
+                .flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)))
+                .filter(|&(dx, dy)| (dx, dy) != (0, 0))
+                .map(|(dx, dy)| ((x + dx, y + dy), f64::from(dx).hypot(f64::from(dy))))
+                .filter(|((x, y), _)| (0..self.size).contains(x) && (0..self.size).contains(y))
+                .collect()
+        }
+
+        fn heuristic(&self, &(x, y): &(i32, i32)) -> f64 {
+            f64::from(self.goal.0 - x).hypot(f64::from(self.goal.1 - y))
+        }
+
+        fn is_goal(&self, state: &(i32, i32)) -> bool {
+            *state == self.goal
+        }
+    }
+
+    #[test]
+    fn floating_point_costs_are_not_rounded() {
+        let space = DiagonalGrid {
+            size: 5,
+            goal: (3, 3),
+        };
+
+        let path = a_star_search(&space, (0, 0)).unwrap();
+        let deepened = ida_star_search(&space, (0, 0), || false).unwrap().unwrap();
+
+        assert_eq!(path.states.len(), 4);
+        assert!((path.cost - 3.0 * 2.0_f64.sqrt()).abs() < 1e-9);
+        assert!((deepened.cost - path.cost).abs() < 1e-9);
+    }
+}
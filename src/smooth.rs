@@ -0,0 +1,196 @@
+use std::fmt::Display;
+
+use itertools::Itertools;
+
+use crate::{Block, BlockType, Direction, Map, Solution};
+
+/// A [Solution] with unnecessary zig-zags removed, see [Solution::smoothed].
+/// The agent moves in straight lines from waypoint to waypoint.
+#[derive(Debug, Clone)]
+pub struct SmoothedPath {
+    waypoints: Vec<Block>,
+    cost: f64,
+    original_cost: f64,
+}
+
+impl SmoothedPath {
+    /// The start, every corner and the goal
+    pub fn waypoints(&self) -> &[Block] {
+        &self.waypoints
+    }
+
+    /// The length of every straight line times the average terrain cost of the blocks it crosses
+    pub fn cost(&self) -> f64 {
+        self.cost
+    }
+
+    /// The terrain cost of the path before smoothing, in the same unit as [cost](Self::cost).
+    /// Unlike [Solution::cost] it doesn't contain turn penalties.
+    pub fn original_cost(&self) -> f64 {
+        self.original_cost
+    }
+}
+
+impl Display for SmoothedPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "The smoothed path costs {:.2} instead of {} and has {} waypoints:",
+            self.cost,
+            self.original_cost,
+            self.waypoints.len()
+        )?;
+        write!(
+            f,
+            "{}",
+            self.waypoints
+                .iter()
+                .map(|block| format!("{} {}", block.x, block.y))
+                .join(" -> ")
+        )
+    }
+}
+
+impl Solution {
+    /// Pulls the path straight wherever a line between two of its blocks only crosses plain terrain
+    /// and doesn't cost more than the steps it replaces.
+    /// Keys, doors, portals, one-way blocks, stairs and crossings always stay waypoints.
+    pub fn smoothed(&self, map: &Map) -> SmoothedPath {
+        let path = &self.path;
+        let original_cost = path.iter().skip(1).map(|block| block.speed() as f64).sum();
+        let mut waypoints = path.iter().take(1).copied().collect_vec();
+        let mut cost = 0.0;
+
+        let mut anchor = 0;
+        while anchor + 1 < path.len() {
+            // The next block of the path can always be reached
+            let mut reached = anchor + 1;
+            let mut reached_cost = path[reached].speed() as f64;
+            let mut steps_cost = reached_cost;
+            for end in anchor + 2..path.len() {
+                let previous = path[end - 1];
+                if !is_plain(&previous) || Direction::between(previous, path[end]).is_none() {
+                    break;
+                }
+                steps_cost += path[end].speed() as f64;
+                match line_cost(map, path[anchor], path[end]) {
+                    Some(line_cost) if line_cost <= steps_cost => {
+                        reached = end;
+                        reached_cost = line_cost;
+                    }
+                    _ => break,
+                }
+            }
+            waypoints.push(path[reached]);
+            cost += reached_cost;
+            anchor = reached;
+        }
+
+        SmoothedPath {
+            waypoints,
+            cost,
+            original_cost,
+        }
+    }
+}
+
+/// Terrain without any special rules, which a straight line may cross
+pub(crate) fn is_plain(block: &Block) -> bool {
+    matches!(
+        block.block_type(),
+        BlockType::Green | BlockType::Blue | BlockType::Orange | BlockType::Yellow
+    )
+}
+
+/// The cost of a straight line between the centers of both blocks,
+/// `None` if it touches a block that isn't plain terrain (except for `to` itself)
+fn line_cost(map: &Map, from: Block, to: Block) -> Option<f64> {
+    let crossed = crossed_blocks((from.x, from.y), (to.x, to.y));
+    let mut speeds = 0.0;
+    for &(x, y) in &crossed {
+        let block = map.get_block(x, y)?;
+        if !is_plain(&block) && (x, y) != (to.x, to.y) {
+            return None;
+        }
+        speeds += block.speed() as f64;
+    }
+    let length =
+        ((to.x as f64 - from.x as f64).powi(2) + (to.y as f64 - from.y as f64).powi(2)).sqrt();
+    Some(length * speeds / crossed.len() as f64)
+}
+
+/// Every block a straight line between the centers of two blocks touches, without `from`.
+/// A line through the corner of four blocks touches both blocks beside it, so it can't slip between two walls.
+///
+/// https://www.redblobgames.com/grids/line-drawing/#supercover
+pub(crate) fn crossed_blocks(from: (usize, usize), to: (usize, usize)) -> Vec<(usize, usize)> {
+    let (dx, dy) = (to.0 as i64 - from.0 as i64, to.1 as i64 - from.1 as i64);
+    let (nx, ny) = (dx.abs(), dy.abs());
+    let (sx, sy) = (dx.signum(), dy.signum());
+    let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+    let (mut ix, mut iy) = (0, 0);
+    let mut blocks = vec![];
+    while ix < nx || iy < ny {
+        // Which of the next vertical or horizontal block edges the line crosses first
+        let decision = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+        if decision == 0 {
+            blocks.push(((x + sx) as usize, y as usize));
+            blocks.push((x as usize, (y + sy) as usize));
+            x += sx;
+            y += sy;
+            ix += 1;
+            iy += 1;
+        } else if decision < 0 {
+            x += sx;
+            ix += 1;
+        } else {
+            y += sy;
+            iy += 1;
+        }
+        blocks.push((x as usize, y as usize));
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a_star;
+
+    #[test]
+    fn open_rooms_are_crossed_in_a_straight_line() {
+        let map = Map::from_rows(&[".....", ".....", ".....", "....."]);
+        let solution = a_star(
+            &map,
+            map.get_block(0, 0).unwrap(),
+            map.get_block(4, 3).unwrap(),
+        )
+        .unwrap();
+
+        let smoothed = solution.smoothed(&map);
+
+        assert_eq!(smoothed.waypoints().len(), 2);
+        assert_eq!(smoothed.original_cost(), 7.0);
+        assert!((smoothed.cost() - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn lines_do_not_cut_through_walls() {
+        let map = Map::from_rows(&["...#.", ".#...", "....."]);
+        let solution = a_star(
+            &map,
+            map.get_block(0, 0).unwrap(),
+            map.get_block(4, 0).unwrap(),
+        )
+        .unwrap();
+
+        let smoothed = solution.smoothed(&map);
+
+        for (from, to) in smoothed.waypoints().iter().tuple_windows() {
+            assert!(crossed_blocks((from.x, from.y), (to.x, to.y))
+                .into_iter()
+                .all(|(x, y)| map.get_block(x, y).unwrap().is_walkable()));
+        }
+        assert!(smoothed.cost() <= smoothed.original_cost());
+    }
+}
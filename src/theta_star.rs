@@ -0,0 +1,220 @@
+use std::{cmp::Reverse, collections::HashMap, fmt::Display};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+use priority_queue::PriorityQueue;
+
+use crate::{
+    search::Priority,
+    smooth::{crossed_blocks, is_plain},
+    Block, BlockType, Map, MazeError,
+};
+
+type Position = (usize, usize);
+
+/// A path of straight lines in any direction, found by [theta_star]
+#[derive(Debug, Clone)]
+pub struct AnyAnglePath {
+    waypoints: Vec<Block>,
+    length: f64,
+    expanded: usize,
+}
+
+impl AnyAnglePath {
+    /// The start, every corner and the goal
+    pub fn waypoints(&self) -> &[Block] {
+        &self.waypoints
+    }
+
+    /// The euclidean length of all lines
+    pub fn length(&self) -> f64 {
+        self.length
+    }
+
+    /// The number of blocks the search expanded
+    pub fn expanded(&self) -> usize {
+        self.expanded
+    }
+
+    /// A copy of the map with every block the lines cross marked as [BlockType::Solution]
+    pub fn to_solution_map(&self, map: &Map) -> Map {
+        let mut solution_map = map.clone();
+        let first = self
+            .waypoints
+            .iter()
+            .take(1)
+            .map(|block| (block.x, block.y));
+        let crossed = self
+            .waypoints
+            .iter()
+            .tuple_windows()
+            .flat_map(|(from, to)| crossed_blocks((from.x, from.y), (to.x, to.y)));
+        for (x, y) in first.chain(crossed) {
+            solution_map
+                .set_block_type(x, y, BlockType::Solution)
+                .expect("Lines stay within the map");
+        }
+        solution_map
+    }
+}
+
+impl Display for AnyAnglePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "The any-angle path is {:.2} long and has {} waypoints:",
+            self.length,
+            self.waypoints.len()
+        )?;
+        write!(
+            f,
+            "{}",
+            self.waypoints
+                .iter()
+                .map(|block| format!("{} {}", block.x, block.y))
+                .join(" -> ")
+        )
+    }
+}
+
+/// Theta*: like A* on all eight neighbors, but every block may take the parent of its predecessor
+/// as its own parent if there is a line of sight, so the path isn't bound to the grid.
+///
+/// Terrain costs are ignored, every line costs its euclidean length.
+/// Lines only cross plain terrain, keys, doors, portals, one-way blocks, stairs and crossings are obstacles.
+///
+/// http://idm-lab.org/bib/abstracts/papers/jair10b.pdf
+pub fn theta_star(map: &Map, start: Block, goal: Block) -> anyhow::Result<AnyAnglePath> {
+    if map.get_block(start.x, start.y).is_none() || map.get_block(goal.x, goal.y).is_none() {
+        return Err(anyhow!("Please specify coordinates within the map"));
+    }
+    let start = (start.x, start.y);
+    let goal = (goal.x, goal.y);
+    let passable = |(x, y): Position| {
+        (x, y) == goal || map.get_block(x, y).is_some_and(|block| is_plain(&block))
+    };
+    let line_of_sight =
+        |from: Position, to: Position| crossed_blocks(from, to).into_iter().all(passable);
+
+    let mut frontier: PriorityQueue<Position, Reverse<Priority>> = PriorityQueue::new();
+    // The shortest known length and the parent of every reached block
+    let mut reached: HashMap<Position, (f64, Position)> = HashMap::from([(start, (0.0, start))]);
+    frontier.push(start, Reverse(priority(0.0, start, goal)));
+    let mut expanded = 0;
+
+    while let Some((position, _)) = frontier.pop() {
+        expanded += 1;
+        if position == goal {
+            return Ok(reconstruct(map, &reached, goal, expanded));
+        }
+        let (length, parent) = reached[&position];
+        for next in neighbors(map, position) {
+            if !line_of_sight(position, next) {
+                continue;
+            }
+            // Skipping the current block is what makes the path any-angle
+            let (next_length, next_parent) = if line_of_sight(parent, next) {
+                (reached[&parent].0 + distance(parent, next), parent)
+            } else {
+                (length + distance(position, next), position)
+            };
+            if reached
+                .get(&next)
+                .is_none_or(|(known_length, _)| next_length < *known_length)
+            {
+                reached.insert(next, (next_length, next_parent));
+                frontier.push(next, Reverse(priority(next_length, next, goal)));
+            }
+        }
+    }
+
+    Err(MazeError::NoPath.into())
+}
+
+fn distance(from: Position, to: Position) -> f64 {
+    (from.0 as f64 - to.0 as f64).hypot(from.1 as f64 - to.1 as f64)
+}
+
+fn priority(length: f64, position: Position, goal: Position) -> Priority {
+    Priority(length + distance(position, goal))
+}
+
+/// All eight neighbors within the map
+fn neighbors(map: &Map, (x, y): Position) -> impl Iterator<Item = Position> + '_ {
+    (-1..=1_isize)
+        .cartesian_product(-1..=1_isize)
+        .filter(|&(dx, dy)| (dx, dy) != (0, 0))
+        .filter_map(move |(dx, dy)| Some((x.checked_add_signed(dx)?, y.checked_add_signed(dy)?)))
+        .filter(|&(x, y)| map.get_block(x, y).is_some())
+}
+
+fn reconstruct(
+    map: &Map,
+    reached: &HashMap<Position, (f64, Position)>,
+    goal: Position,
+    expanded: usize,
+) -> AnyAnglePath {
+    let mut positions = vec![goal];
+    while let Some(&(_, parent)) = positions.last().and_then(|position| reached.get(position)) {
+        if Some(&parent) == positions.last() {
+            break;
+        }
+        positions.push(parent);
+    }
+    positions.reverse();
+    AnyAnglePath {
+        waypoints: positions
+            .into_iter()
+            .map(|(x, y)| {
+                map.get_block(x, y)
+                    .expect("Reached blocks are within the map")
+            })
+            .collect(),
+        length: reached[&goal].0,
+        expanded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_rooms_are_crossed_in_a_straight_line() {
+        let map = Map::from_rows(&[".....", ".....", ".....", "....."]);
+
+        let path = theta_star(
+            &map,
+            map.get_block(0, 0).unwrap(),
+            map.get_block(4, 3).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(path.waypoints().len(), 2);
+        assert!((path.length() - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn paths_bend_around_walls() {
+        let map = Map::from_rows(&["......", "..###.", "..#...", "..#..."]);
+        let start = map.get_block(0, 3).unwrap();
+        let goal = map.get_block(4, 3).unwrap();
+
+        let path = theta_star(&map, start, goal).unwrap();
+
+        assert!(path.waypoints().len() > 2);
+        assert!(path.length() > 4.0);
+        for (from, to) in path.waypoints().iter().tuple_windows() {
+            assert!(crossed_blocks((from.x, from.y), (to.x, to.y))
+                .into_iter()
+                .all(|(x, y)| map.get_block(x, y).unwrap().is_walkable()));
+        }
+        let walled_in = Map::from_rows(&["..#..", "..#.."]);
+        assert!(theta_star(
+            &walled_in,
+            walled_in.get_block(0, 0).unwrap(),
+            walled_in.get_block(4, 0).unwrap()
+        )
+        .is_err());
+    }
+}
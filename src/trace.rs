@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use itertools::Itertools;
+
+use crate::{
+    search::Observer, search_grid, Block, Map, SearchOptions, Solution, SolveAlgorithm, State,
+};
+
+/// Every state a search reached and expanded in order, see [SolveAlgorithm::solve_traced].
+/// A step is one expansion, so step `n` is the search after it expanded `n` states.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchTrace {
+    /// The block of every state, by the order the states were first reached
+    positions: Vec<(usize, usize)>,
+    /// After how many expansions each state was first reached
+    reached_at: Vec<usize>,
+    /// The state each state was reached from when it was last expanded
+    parents: Vec<Option<usize>>,
+    /// The states in the order they were expanded, states that were reopened appear several times
+    expansions: Vec<usize>,
+}
+
+impl SearchTrace {
+    pub fn expansion_count(&self) -> usize {
+        self.expansions.len()
+    }
+
+    /// The blocks expanded within the first `step` expansions, in the order they were first expanded
+    pub fn explored(&self, step: usize) -> Vec<(usize, usize)> {
+        self.expansions[..step.min(self.expansion_count())]
+            .iter()
+            .map(|id| self.positions[*id])
+            .unique()
+            .collect()
+    }
+
+    /// The blocks of the states that were reached but not yet expanded after `step` expansions
+    pub fn frontier(&self, step: usize) -> Vec<(usize, usize)> {
+        let step = step.min(self.expansion_count());
+        let mut expanded = vec![false; self.positions.len()];
+        for id in &self.expansions[..step] {
+            expanded[*id] = true;
+        }
+        (0..self.positions.len())
+            .filter(|id| self.reached_at[*id] <= step && !expanded[*id])
+            .map(|id| self.positions[id])
+            .unique()
+            .collect()
+    }
+
+    /// The blocks from the start to the state expanded last within the first `step` expansions,
+    /// the path the search was following at that moment. Empty before the first expansion.
+    pub fn path_at(&self, step: usize) -> Vec<(usize, usize)> {
+        let Some(last) = step
+            .min(self.expansion_count())
+            .checked_sub(1)
+            .map(|i| self.expansions[i])
+        else {
+            return vec![];
+        };
+        let mut path = vec![self.positions[last]];
+        let mut current = last;
+        // Reopened states can't make the parents circular for more steps than there are states
+        while let Some(parent) =
+            self.parents[current].filter(|_| path.len() <= self.positions.len())
+        {
+            path.push(self.positions[parent]);
+            current = parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// Records the search into a [SearchTrace]
+#[derive(Default)]
+struct TraceRecorder {
+    ids: HashMap<State, usize>,
+    /// The state each state was last reached from
+    latest_parents: Vec<Option<usize>>,
+    trace: SearchTrace,
+}
+
+impl TraceRecorder {
+    fn id(&mut self, state: &State) -> usize {
+        *self.ids.entry(*state).or_insert_with(|| {
+            self.trace
+                .positions
+                .push((state.location.x, state.location.y));
+            self.trace.reached_at.push(self.trace.expansions.len());
+            self.trace.parents.push(None);
+            self.latest_parents.push(None);
+            self.trace.positions.len() - 1
+        })
+    }
+}
+
+impl Observer<State> for TraceRecorder {
+    fn expanded(&mut self, state: &State) {
+        let id = self.id(state);
+        self.trace.expansions.push(id);
+        self.trace.parents[id] = self.latest_parents[id];
+    }
+
+    fn reached(&mut self, state: &State, from: &State) {
+        let from = self.id(from);
+        let id = self.id(state);
+        self.latest_parents[id] = Some(from);
+    }
+}
+
+impl SolveAlgorithm {
+    /// Like [solve](Self::solve), but also records how the search explored the map, e.g. to draw it with
+    /// [Layers::from_trace](crate::Layers::from_trace). IDA* forgets the states it expanded, so it can't be traced.
+    pub fn solve_traced(
+        self,
+        map: &Map,
+        start_block: Block,
+        destination_block: Block,
+        options: &SearchOptions,
+    ) -> anyhow::Result<(Solution, SearchTrace)> {
+        if self == SolveAlgorithm::IdaStar {
+            return Err(anyhow!("IDA* searches can't be traced, use A* or Dijkstra"));
+        }
+        let mut recorder = TraceRecorder::default();
+        let (path, report) = search_grid(
+            map,
+            self,
+            start_block,
+            destination_block,
+            options,
+            &mut recorder,
+        )?;
+        let solution = Solution::new(
+            path.states,
+            path.cost,
+            map.clone(),
+            report,
+            options.turn_penalty,
+        )
+        .with_region_penalties(&options.penalties);
+        Ok((solution, recorder.trace))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_trace_follows_the_search() {
+        let map = Map::from_rows(&["....", ".##.", "...."]);
+        let (start, goal) = (map.get_block(0, 0).unwrap(), map.get_block(3, 2).unwrap());
+
+        let (solution, trace) = SolveAlgorithm::AStar
+            .solve_traced(&map, start, goal, &SearchOptions::default())
+            .unwrap();
+
+        assert_eq!(solution.cost(), 5);
+        assert_eq!(trace.frontier(0), [(0, 0)]);
+        assert!(trace.explored(0).is_empty());
+        assert!(trace.path_at(0).is_empty());
+        assert_eq!(trace.explored(1), [(0, 0)]);
+        assert_eq!(trace.frontier(1).len(), 2);
+        let n = trace.expansion_count();
+        assert_eq!(*trace.explored(n).last().unwrap(), (3, 2));
+        let path: Vec<_> = solution.path().iter().map(|b| (b.x, b.y)).collect();
+        assert_eq!(trace.path_at(n), path);
+        assert!(trace
+            .explored(n)
+            .iter()
+            .all(|explored| !trace.frontier(n).contains(explored)));
+    }
+
+    #[test]
+    fn dijkstra_explores_more_than_a_star() {
+        let map = Map::from_rows(&["..........", "..........", ".........."]);
+        let (start, goal) = (map.get_block(0, 1).unwrap(), map.get_block(9, 1).unwrap());
+        let trace = |algorithm: SolveAlgorithm| {
+            algorithm
+                .solve_traced(&map, start, goal, &SearchOptions::default())
+                .unwrap()
+                .1
+        };
+
+        assert!(
+            trace(SolveAlgorithm::Dijkstra).expansion_count()
+                > trace(SolveAlgorithm::AStar).expansion_count()
+        );
+        assert!(SolveAlgorithm::IdaStar
+            .solve_traced(&map, start, goal, &SearchOptions::default())
+            .is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{region_penalty, Direction, Map, PathError, Solution, State};
+
+impl Solution {
+    /// Walks the path on `map` without searching and checks that every step is allowed
+    /// and that the steps cost as much as the solution reports, including turn and region penalties.
+    ///
+    /// Only relies on the rules of the map, so it can check solvers against each other.
+    pub fn verify(&self, map: &Map) -> Result<(), PathError> {
+        let block_at = |x: usize, y: usize| {
+            map.get_block(x, y)
+                .filter(|block| block.is_walkable())
+                .ok_or(PathError::NotWalkable { x, y })
+        };
+        let first = self.path.first().ok_or(PathError::Empty)?;
+        let mut state = State::new(block_at(first.x, first.y)?);
+        let mut heading: Option<Direction> = None;
+        let mut cost = 0;
+
+        for next in self.path.iter().skip(1) {
+            let from = state.location;
+            let next = block_at(next.x, next.y)?;
+            if !map.get_reachable(from.x, from.y).contains(&next) {
+                return Err(PathError::InvalidStep {
+                    from: (from.x, from.y),
+                    to: (next.x, next.y),
+                });
+            }
+            if next.door().is_some_and(|color| !state.has_key(color)) {
+                return Err(PathError::LockedDoor {
+                    x: next.x,
+                    y: next.y,
+                });
+            }
+            if !state.can_move_to(next) {
+                return Err(PathError::TurnOnCrossing {
+                    x: from.x,
+                    y: from.y,
+                });
+            }
+
+            // Teleporting through a portal has no direction, like in the search
+            let direction = Direction::between(from, next);
+            cost += next.speed() as u32 + region_penalty(&self.region_penalties, next);
+            if heading.is_some() && direction.is_some() && heading != direction {
+                cost += self.turn_penalty;
+            }
+            heading = direction;
+            state = state.moved_to(next);
+        }
+
+        if cost != self.cost {
+            return Err(PathError::WrongCost {
+                reported: self.cost,
+                actual: cost,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{a_star, a_star_with, SearchOptions};
+
+    use super::*;
+
+    #[test]
+    fn solutions_of_a_star_are_valid() {
+        let map = Map::from_rows(&["1.#..", "..A.>", "#P#.#", "..P.."]);
+        let start = map.get_block(0, 3).unwrap();
+        let goal = map.get_block(4, 1).unwrap();
+
+        let solution = a_star(&map, start, goal).unwrap();
+        let with_turns =
+            a_star_with(&map, start, goal, &SearchOptions::default().turn_penalty(3)).unwrap();
+
+        assert_eq!(solution.verify(&map), Ok(()));
+        assert_eq!(with_turns.verify(&map), Ok(()));
+    }
+
+    #[test]
+    fn broken_paths_are_rejected() {
+        let map = Map::from_rows(&["...", ".#."]);
+        let mut solution = a_star(
+            &map,
+            map.get_block(0, 1).unwrap(),
+            map.get_block(2, 1).unwrap(),
+        )
+        .unwrap();
+        let path = solution.path.clone();
+
+        solution.cost += 1;
+        assert_eq!(
+            solution.verify(&map),
+            Err(PathError::WrongCost {
+                reported: 5,
+                actual: 4
+            })
+        );
+        solution.path.remove(1);
+        assert_eq!(
+            solution.verify(&map),
+            Err(PathError::InvalidStep {
+                from: (0, 1),
+                to: (1, 0)
+            })
+        );
+        solution.path = vec![path[0], map.get_block(1, 1).unwrap()];
+        assert_eq!(
+            solution.verify(&map),
+            Err(PathError::NotWalkable { x: 1, y: 1 })
+        );
+    }
+}
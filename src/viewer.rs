@@ -0,0 +1,223 @@
+#[cfg(feature = "gui")]
+use anyhow::anyhow;
+use image::RgbaImage;
+#[cfg(feature = "gui")]
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+
+use crate::{
+    layers::MARKERS, Block, Layers, Map, RenderOptions, SearchOptions, SearchTrace, Solution,
+    SolveAlgorithm,
+};
+
+/// The state of an interactive view of a map, independent of the window it is shown in:
+/// clicks set the start and the destination, every change solves again and [frame](Self::frame)
+/// draws the exploration and the path of the last search. With the `gui` feature [show](Self::show)
+/// opens a window for it.
+pub struct Viewer {
+    map: Map,
+    options: RenderOptions,
+    algorithm: SolveAlgorithm,
+    start: Option<Block>,
+    destination: Option<Block>,
+    /// The next click sets the destination instead of the start
+    picks_destination: bool,
+    /// The last search, or why it failed
+    result: Option<Result<(Solution, SearchTrace), String>>,
+}
+
+impl Viewer {
+    pub fn new(map: Map, options: RenderOptions) -> Self {
+        Self {
+            map,
+            options,
+            algorithm: SolveAlgorithm::default(),
+            start: None,
+            destination: None,
+            picks_destination: false,
+            result: None,
+        }
+    }
+
+    /// The block under the pixel of the frame, `None` for borders and pixels outside of the map
+    pub fn block_at(&self, pixel_x: u32, pixel_y: u32) -> Option<Block> {
+        let stride = self.options.block_width + self.options.border_width;
+        let coordinate = |pixel: u32| {
+            let pixel = pixel as usize;
+            (pixel % stride < self.options.block_width).then_some(pixel / stride)
+        };
+        self.map
+            .get_block(coordinate(pixel_x)?, coordinate(pixel_y)?)
+    }
+
+    /// Sets the start and the destination in turns to the walkable block under the pixel and solves again.
+    /// Returns whether the click hit such a block.
+    pub fn click(&mut self, pixel_x: u32, pixel_y: u32) -> bool {
+        let Some(block) = self.block_at(pixel_x, pixel_y).filter(Block::is_walkable) else {
+            return false;
+        };
+        if self.picks_destination {
+            self.destination = Some(block);
+        } else {
+            self.start = Some(block);
+        }
+        self.picks_destination = !self.picks_destination;
+        self.solve();
+        true
+    }
+
+    /// Switches to the next algorithm that can be traced and solves again
+    pub fn next_algorithm(&mut self) {
+        self.algorithm = match self.algorithm {
+            SolveAlgorithm::AStar => SolveAlgorithm::Dijkstra,
+            _ => SolveAlgorithm::AStar,
+        };
+        self.solve();
+    }
+
+    pub fn algorithm(&self) -> SolveAlgorithm {
+        self.algorithm
+    }
+
+    fn solve(&mut self) {
+        self.result = self
+            .start
+            .zip(self.destination)
+            .map(|(start, destination)| {
+                self.algorithm
+                    .solve_traced(&self.map, start, destination, &SearchOptions::default())
+                    .map_err(|error| error.to_string())
+            });
+    }
+
+    /// A line about what is shown, e.g. for the title of the window
+    pub fn status(&self) -> String {
+        let position = |block: Option<Block>| {
+            block.map_or("-".to_string(), |block| format!("{} {}", block.x, block.y))
+        };
+        let result = match &self.result {
+            None => "click to set the start and the destination".to_string(),
+            Some(Ok((solution, trace))) => format!(
+                "cost {}, expanded {} states",
+                solution.cost(),
+                trace.expansion_count()
+            ),
+            Some(Err(error)) => error.clone(),
+        };
+        format!(
+            "{}: from {} to {}, {result}",
+            self.algorithm,
+            position(self.start),
+            position(self.destination)
+        )
+    }
+
+    /// The map with the exploration and the path of the last search, or with only the start and destination marked
+    pub fn frame(&self) -> Option<RgbaImage> {
+        let layers = match &self.result {
+            Some(Ok((solution, trace))) => Layers::from_trace(trace, solution),
+            _ => Layers::default().markers(
+                [self.start, self.destination]
+                    .into_iter()
+                    .flatten()
+                    .map(|block| (block.x, block.y)),
+                MARKERS,
+            ),
+        };
+        self.map.layered_image(&layers, &self.options)
+    }
+
+    /// Shows the frame in a window until it is closed or Escape is pressed. Left clicks set the start
+    /// and the destination, Tab switches the algorithm and the title shows the [status](Self::status).
+    #[cfg(feature = "gui")]
+    pub fn show(mut self) -> anyhow::Result<()> {
+        let frame = self.frame().ok_or(anyhow!("Failed to create image"))?;
+        let (width, height) = (frame.width() as usize, frame.height() as usize);
+        let mut window = Window::new(&self.status(), width, height, WindowOptions::default())
+            .map_err(|e| anyhow!("Failed to open a window: {e}"))?;
+        window.set_target_fps(30);
+        let mut buffer = window_buffer(&frame);
+        let mut was_down = false;
+
+        while window.is_open() && !window.is_key_down(Key::Escape) {
+            // A click is the moment the button goes down
+            let down = window.get_mouse_down(MouseButton::Left);
+            let mut changed = false;
+            if down && !was_down {
+                if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
+                    changed = self.click(x as u32, y as u32);
+                }
+            }
+            was_down = down;
+            if window.is_key_pressed(Key::Tab, KeyRepeat::No) {
+                self.next_algorithm();
+                changed = true;
+            }
+            if changed {
+                let frame = self.frame().ok_or(anyhow!("Failed to create image"))?;
+                buffer = window_buffer(&frame);
+                window.set_title(&self.status());
+            }
+            window
+                .update_with_buffer(&buffer, width, height)
+                .map_err(|e| anyhow!("Failed to draw the window: {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// The pixels as `0RGB` words, the format windows are drawn from
+#[cfg(feature = "gui")]
+fn window_buffer(image: &RgbaImage) -> Vec<u32> {
+    image
+        .pixels()
+        .map(|pixel| {
+            let [red, green, blue, _] = pixel.0;
+            u32::from_be_bytes([0, red, green, blue])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewer() -> Viewer {
+        let map = Map::from_rows(&["...", ".#.", "..."]);
+        let options = RenderOptions {
+            block_width: 4,
+            border_width: 1,
+            ..Default::default()
+        };
+        Viewer::new(map, options)
+    }
+
+    #[test]
+    fn pixels_map_to_blocks() {
+        let viewer = viewer();
+
+        assert_eq!(viewer.block_at(0, 0).map(|b| (b.x, b.y)), Some((0, 0)));
+        assert_eq!(viewer.block_at(3, 5).map(|b| (b.x, b.y)), Some((0, 1)));
+        assert_eq!(viewer.block_at(4, 0), None);
+        assert_eq!(viewer.block_at(10, 13).map(|b| (b.x, b.y)), Some((2, 2)));
+        assert_eq!(viewer.block_at(15, 0), None);
+    }
+
+    #[test]
+    fn clicks_set_start_and_destination_and_solve() {
+        let mut viewer = viewer();
+
+        assert!(!viewer.click(6, 6));
+        assert!(viewer.click(0, 0));
+        assert!(viewer
+            .status()
+            .ends_with("click to set the start and the destination"));
+        assert!(viewer.click(12, 12));
+        assert!(viewer
+            .status()
+            .starts_with("astar: from 0 0 to 2 2, cost 4, expanded"));
+        viewer.next_algorithm();
+        assert_eq!(viewer.algorithm(), SolveAlgorithm::Dijkstra);
+        assert!(viewer.status().contains("cost 4"));
+        assert_eq!(viewer.frame().unwrap().dimensions(), (14, 14));
+    }
+}
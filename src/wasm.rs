@@ -0,0 +1,145 @@
+use anyhow::anyhow;
+use serde_json::{json, Value};
+use wasm_bindgen::{prelude::*, Clamped};
+
+use crate::{a_star, generate as generate_maze, GenOptions, Map, MazeAlgorithm, RenderOptions};
+
+/// Generates a maze of `width` × `height` cells, which is a map of `2 * width + 1` × `2 * height + 1` blocks.
+/// `algorithm` is named like on the command line, e.g. `backtracker`.
+///
+/// Returns the map as `{"width": .., "height": .., "rows": [..]}`
+/// with one string of [ASCII](crate::TextTheme::Ascii) blocks per row, which [solve] and [render] take as `mapJson`.
+#[wasm_bindgen]
+pub fn generate(
+    width: usize,
+    height: usize,
+    seed: Option<u64>,
+    algorithm: &str,
+) -> Result<JsValue, JsError> {
+    to_js(generate_json(width, height, seed, algorithm))
+}
+
+/// Finds the cheapest path from `start` to `dest`, both given as `[x, y]`.
+///
+/// Returns `{"cost": .., "path": [{"x": .., "y": .., "cost_so_far": ..}, ..], "map": ..}`,
+/// where `map` has the path drawn into it.
+#[wasm_bindgen]
+pub fn solve(map_json: &str, start: &[u32], dest: &[u32]) -> Result<JsValue, JsError> {
+    to_js(solve_json(map_json, start, dest))
+}
+
+/// The map as RGBA pixels, e.g. for `new ImageData(pixels.rgba, pixels.width)` on a canvas
+#[wasm_bindgen]
+pub fn render(map_json: &str) -> Result<RgbaBuffer, JsError> {
+    rgba_buffer(map_json).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// The pixels of a rendered map, row by row with four bytes per pixel
+#[wasm_bindgen]
+pub struct RgbaBuffer {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl RgbaBuffer {
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// A `Uint8ClampedArray` like the one of `ImageData`
+    #[wasm_bindgen(getter)]
+    pub fn rgba(&self) -> Clamped<Vec<u8>> {
+        Clamped(self.rgba.clone())
+    }
+}
+
+fn to_js(value: anyhow::Result<Value>) -> Result<JsValue, JsError> {
+    let value = value.map_err(|e| JsError::new(&e.to_string()))?;
+    js_sys::JSON::parse(&value.to_string()).map_err(|_| JsError::new("Failed to convert to JSON"))
+}
+
+fn map_from_json(map_json: &str) -> anyhow::Result<Map> {
+    Map::from_json_value(&serde_json::from_str(map_json)?)
+}
+
+fn generate_json(
+    width: usize,
+    height: usize,
+    seed: Option<u64>,
+    algorithm: &str,
+) -> anyhow::Result<Value> {
+    let options = GenOptions {
+        seed,
+        ..Default::default()
+    };
+    let maze = generate_maze(width, height, algorithm.parse::<MazeAlgorithm>()?, &options)?;
+    Ok(Map::from(maze).to_json_value())
+}
+
+fn solve_json(map_json: &str, start: &[u32], dest: &[u32]) -> anyhow::Result<Value> {
+    let map = map_from_json(map_json)?;
+    let block = |coordinates: &[u32]| match coordinates {
+        [x, y] => map.get_block(*x as usize, *y as usize),
+        _ => None,
+    };
+    let (Some(start), Some(dest)) = (block(start), block(dest)) else {
+        return Err(anyhow!("Please specify coordinates within the map"));
+    };
+    let solution = a_star(&map, start, dest)?;
+    let exported: Value = serde_json::from_str(&solution.to_json())?;
+    Ok(json!({
+        "cost": solution.cost(),
+        "cost_breakdown": exported["cost_breakdown"],
+        "path": exported["path"],
+        "map": solution.to_solution_map().to_json_value(),
+    }))
+}
+
+fn rgba_buffer(map_json: &str) -> anyhow::Result<RgbaBuffer> {
+    let image = map_from_json(map_json)?
+        .to_image_with(&RenderOptions::default())
+        .ok_or(anyhow!("Failed to create image"))?;
+    Ok(RgbaBuffer {
+        width: image.width(),
+        height: image.height(),
+        rgba: image.into_raw(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_maps_can_be_solved_and_rendered() {
+        let map = generate_json(4, 3, Some(1), "hunt-and-kill").unwrap();
+        assert_eq!(map["width"], 9);
+        assert_eq!(map["rows"].as_array().unwrap().len(), 7);
+
+        let solution = solve_json(&map.to_string(), &[1, 1], &[7, 5]).unwrap();
+        let path = solution["path"].as_array().unwrap();
+        assert_eq!(path.first().unwrap()["x"], 1);
+        assert_eq!(path.last().unwrap()["cost_so_far"], solution["cost"]);
+
+        let pixels = rgba_buffer(&solution["map"].to_string()).unwrap();
+        assert_eq!(
+            pixels.rgba.len(),
+            (pixels.width * pixels.height * 4) as usize
+        );
+    }
+
+    #[test]
+    fn invalid_input_is_reported() {
+        assert!(generate_json(4, 3, None, "maze").is_err());
+        assert!(solve_json(r#"{"rows": ["..", ".."]}"#, &[0, 0], &[5, 0]).is_err());
+        assert!(solve_json(r#"{"rows": 3}"#, &[0, 0], &[1, 0]).is_err());
+    }
+}